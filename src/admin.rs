@@ -0,0 +1,251 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Perform administrative operations on the server
+///
+/// Checkpoints the database, rotates the journal, or stops/restarts the
+/// server.
+///
+/// Requires super access (operator access for checkpoint and journal).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let confirmations = p4
+///     .admin(p4_cmd::admin::Operation::Checkpoint)
+///     .compress(true)
+///     .run()
+///     .unwrap();
+/// for confirmation in confirmations {
+///     println!("{:?}", confirmation);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdminCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    operation: Operation,
+
+    compress: bool,
+    prefix: Option<&'f str>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> AdminCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, operation: Operation) -> Self {
+        Self {
+            connection,
+            operation,
+            compress: false,
+            prefix: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -z flag saves the checkpoint and/or rotated journal in
+    /// compressed (gzip) format.  Ignored for `Stop` and `Restart`.
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Use `prefix` for the checkpoint and/or rotated journal file names.
+    /// Ignored for `Stop` and `Restart`.
+    pub fn prefix(mut self, prefix: &'f str) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("admin");
+        match self.operation {
+            Operation::Checkpoint | Operation::Journal => {
+                let operation = if self.operation == Operation::Checkpoint {
+                    "checkpoint"
+                } else {
+                    "journal"
+                };
+                cmd.arg(operation);
+                if self.compress {
+                    cmd.arg("-z");
+                }
+                if let Some(prefix) = self.prefix {
+                    cmd.arg(prefix);
+                }
+            }
+            Operation::Stop => {
+                cmd.arg("stop");
+            }
+            Operation::Restart => {
+                cmd.arg("restart");
+            }
+            Operation::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+        cmd
+    }
+
+    /// Run the `admin` command.
+    pub fn run(self) -> Result<Confirmations, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Confirmations(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            admin_parser::item,
+        )?))
+    }
+
+    /// Run the `admin` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Confirmation>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+/// Administrative operation to perform.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    #[doc(hidden)]
+    __Nonexhaustive,
+
+    /// 'p4 admin checkpoint' takes a checkpoint and rotates the journal.
+    Checkpoint,
+    /// 'p4 admin journal' rotates the journal.
+    Journal,
+    /// 'p4 admin stop' stops the server.
+    Stop,
+    /// 'p4 admin restart' restarts the server.
+    Restart,
+}
+
+pub type ConfirmationItem = error::Item<Confirmation>;
+
+pub struct Confirmations(exec::Items<Confirmation>);
+
+impl IntoIterator for Confirmations {
+    type Item = ConfirmationItem;
+    type IntoIter = ConfirmationsIntoIter;
+
+    fn into_iter(self) -> ConfirmationsIntoIter {
+        ConfirmationsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfirmationsIntoIter(exec::Items<Confirmation>);
+
+impl Iterator for ConfirmationsIntoIter {
+    type Item = ConfirmationItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ConfirmationItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A step reported by the server while performing the operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Confirmation {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    /// The checkpoint file written.
+    Checkpoint(String),
+    /// The file the journal was rotated to.
+    Journal(String),
+    /// The MD5 of the checkpoint.
+    Md5(String),
+    /// The server is stopping.
+    Stopped,
+}
+
+mod admin_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn strip<'a>(msg: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+        if msg.starts_with(prefix)
+            && msg.ends_with(suffix)
+            && msg.len() > prefix.len() + suffix.len()
+        {
+            Some(&msg[prefix.len()..msg.len() - suffix.len()])
+        } else {
+            None
+        }
+    }
+
+    /// e.g. `info: Checkpointing to checkpoint.5...`
+    fn to_confirmation(i: Info) -> Result<Confirmation, ()> {
+        let msg = i.msg.trim_end();
+        if let Some(file) = strip(msg, "Checkpointing to ", "...") {
+            Ok(Confirmation::Checkpoint(file.to_owned()))
+        } else if let Some(file) = strip(msg, "Rotating journal to ", "...") {
+            Ok(Confirmation::Journal(file.to_owned()))
+        } else if let Some(md5) = strip(msg, "MD5 = ", "") {
+            Ok(Confirmation::Md5(md5.to_owned()))
+        } else if msg.starts_with("Server stopped") || msg.starts_with("Stopping server") {
+            Ok(Confirmation::Stopped)
+        } else {
+            Err(())
+        }
+    }
+
+    named!(pub item<&[u8], ConfirmationItem>,
+        alt!(
+            map!(map_res!(info, to_confirmation), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn admin_checkpoint() {
+        let output: &[u8] = br#"info: Checkpointing to checkpoint.5.gz...
+info: MD5 = 2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C
+info: Rotating journal to journal.4.gz...
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, admin_parser::item);
+        assert_eq!(
+            items[0].as_data(),
+            Some(&Confirmation::Checkpoint("checkpoint.5.gz".to_owned()))
+        );
+        assert_eq!(
+            items[1].as_data(),
+            Some(&Confirmation::Md5(
+                "2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C".to_owned()
+            ))
+        );
+        assert_eq!(
+            items[2].as_data(),
+            Some(&Confirmation::Journal("journal.4.gz".to_owned()))
+        );
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
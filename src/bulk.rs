@@ -0,0 +1,220 @@
+//! Run many commands concurrently, e.g. hundreds of `files` queries.
+//!
+//! Each job runs on one of a bounded pool of worker threads and results are yielded in the order
+//! they complete, tagged with the job's index.  Command output is streamed, so a job should
+//! consume it before returning for the work to actually happen on the worker.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let p4 = p4_cmd::P4::new();
+//! let mut bulk = p4_cmd::bulk::BulkRunner::new(8);
+//! for &dir in &["//depot/a/*", "//depot/b/*", "//depot/c/*"] {
+//!     let p4 = p4.clone();
+//!     bulk.push(move || {
+//!         p4.files(dir)
+//!             .run()
+//!             .map(|files| files.into_iter().collect::<Vec<_>>())
+//!     });
+//! }
+//! for (index, files) in bulk.run() {
+//!     println!("{}: {:?}", index, files);
+//! }
+//! ```
+
+use std::collections;
+use std::fmt;
+use std::sync;
+use std::sync::mpsc;
+use std::thread;
+
+use error;
+use p4;
+
+type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+type Queue<T> = sync::Arc<sync::Mutex<collections::VecDeque<(usize, Job<T>)>>>;
+
+/// Run jobs across a bounded pool of worker threads.
+pub struct BulkRunner<T> {
+    workers: usize,
+    jobs: Vec<Job<T>>,
+}
+
+impl<T: Send + 'static> BulkRunner<T> {
+    /// Run at most `workers` jobs at a time.
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            jobs: vec![],
+        }
+    }
+
+    /// Queue a job, to be identified by its position among the queued jobs.
+    pub fn push<F>(&mut self, job: F) -> usize
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.jobs.push(Box::new(job));
+        self.jobs.len() - 1
+    }
+
+    /// Start the workers.
+    ///
+    /// Dropping the results before they are all yielded stops the workers once their current job
+    /// finishes.
+    pub fn run(self) -> BulkResults<T> {
+        let total = self.jobs.len();
+        let queue: Queue<T> = sync::Arc::new(sync::Mutex::new(
+            self.jobs.into_iter().enumerate().collect(),
+        ));
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..self.workers.min(total) {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let next = queue.lock().ok().and_then(|mut queue| queue.pop_front());
+                let (index, job) = match next {
+                    Some(next) => next,
+                    None => return,
+                };
+                if sender.send((index, job())).is_err() {
+                    // The results were dropped.
+                    return;
+                }
+            });
+        }
+        BulkResults {
+            receiver,
+            queue,
+            remaining: total,
+        }
+    }
+}
+
+impl<T> fmt::Debug for BulkRunner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BulkRunner")
+            .field("workers", &self.workers)
+            .field("jobs", &self.jobs.len())
+            .finish()
+    }
+}
+
+/// Job results, as `(index, result)` in the order they complete.
+///
+/// See [`BulkRunner::run`](struct.BulkRunner.html#method.run).
+pub struct BulkResults<T> {
+    receiver: mpsc::Receiver<(usize, T)>,
+    queue: Queue<T>,
+    remaining: usize,
+}
+
+impl<T> Iterator for BulkResults<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        // A worker only hangs up early if its job panicked.
+        let result = self.receiver.recv().ok()?;
+        self.remaining -= 1;
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+impl<T> Drop for BulkResults<T> {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.clear();
+        }
+    }
+}
+
+impl<T> fmt::Debug for BulkResults<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BulkResults")
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+/// Run `list` over `args` split between up to `workers` concurrent runs, keeping their order.
+pub(crate) fn chunked<T, F>(
+    connection: &p4::P4,
+    args: Vec<String>,
+    workers: usize,
+    list: F,
+) -> Result<Vec<T>, error::P4Error>
+where
+    T: Send + 'static,
+    F: Fn(&p4::P4, &[String]) -> Result<Vec<T>, error::P4Error> + Clone + Send + 'static,
+{
+    let workers = workers.min(args.len());
+    if workers <= 1 {
+        return list(connection, &args);
+    }
+    let per_worker = args.len().div_ceil(workers);
+    let mut bulk = BulkRunner::new(workers);
+    let mut args = args.into_iter();
+    loop {
+        let chunk: Vec<_> = args.by_ref().take(per_worker).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        let connection = connection.clone();
+        let list = list.clone();
+        bulk.push(move || list(&connection, &chunk));
+    }
+    let mut results: Vec<_> = bulk.run().collect();
+    results.sort_by_key(|&(index, _)| index);
+    let mut listed = Vec::new();
+    for (_, result) in results {
+        listed.extend(result?);
+    }
+    Ok(listed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::atomic;
+    use std::time;
+
+    #[test]
+    fn bulk_runs_all_jobs() {
+        let mut bulk = BulkRunner::new(3);
+        for i in 0..20 {
+            assert_eq!(bulk.push(move || i * 2), i);
+        }
+        let mut results: Vec<_> = bulk.run().collect();
+        results.sort();
+        let expected: Vec<_> = (0..20).map(|i| (i, i * 2)).collect();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn bulk_bounds_workers() {
+        let running = sync::Arc::new(atomic::AtomicUsize::new(0));
+        let peak = sync::Arc::new(atomic::AtomicUsize::new(0));
+        let mut bulk = BulkRunner::new(2);
+        for _ in 0..8 {
+            let running = running.clone();
+            let peak = peak.clone();
+            bulk.push(move || {
+                let now = running.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+                peak.fetch_max(now, atomic::Ordering::SeqCst);
+                thread::sleep(time::Duration::from_millis(10));
+                running.fetch_sub(1, atomic::Ordering::SeqCst);
+            });
+        }
+        assert_eq!(bulk.run().count(), 8);
+        assert!(peak.load(atomic::Ordering::SeqCst) <= 2);
+    }
+}
@@ -0,0 +1,217 @@
+//! Re-use recent results of read-only commands, e.g. for GUIs re-querying the same paths.
+//!
+//! Only commands that succeed are cached, keyed by their full invocation.  Output is still
+//! streamed on a miss and only cached once it has all been read.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::time;
+//!
+//! let cache = p4_cmd::cache::CommandCache::new(time::Duration::from_secs(30));
+//! let p4 = p4_cmd::P4::new().set_cache(Some(cache));
+//! let first = p4.dirs("//depot/*").run_checked().unwrap();
+//! // Answered from the cache.
+//! let second = p4.dirs("//depot/*").run_checked().unwrap();
+//! assert_eq!(first, second);
+//! ```
+
+use std::collections;
+use std::ffi;
+use std::io;
+use std::io::Read;
+use std::path;
+use std::process;
+use std::sync;
+use std::time;
+
+use runner;
+use trace;
+
+/// Commands cached by default, as they only query the server.
+const DEFAULT_COMMANDS: &[&str] = &["clients", "dirs", "info", "where"];
+
+/// Output ending a successful command.
+const SUCCESS: &[u8] = b"exit: 0\n";
+
+/// Everything that affects a command's output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    program: ffi::OsString,
+    args: Vec<ffi::OsString>,
+    dir: Option<path::PathBuf>,
+    envs: Vec<(ffi::OsString, Option<ffi::OsString>)>,
+    input: Option<Vec<u8>>,
+}
+
+impl Key {
+    fn new(cmd: &process::Command, input: Option<&Vec<u8>>) -> Self {
+        Self {
+            program: cmd.get_program().to_owned(),
+            args: cmd.get_args().map(ffi::OsStr::to_owned).collect(),
+            dir: cmd.get_current_dir().map(path::Path::to_owned),
+            envs: cmd
+                .get_envs()
+                .map(|(k, v)| (k.to_owned(), v.map(ffi::OsStr::to_owned)))
+                .collect(),
+            input: input.cloned(),
+        }
+    }
+}
+
+type Entries = collections::HashMap<Key, (time::Instant, Vec<u8>)>;
+
+/// Caches the output of read-only commands for a while.
+///
+/// Clones share their entries, so one cache can serve several connections.
+#[derive(Debug, Clone)]
+pub struct CommandCache {
+    ttl: time::Duration,
+    commands: Vec<String>,
+    entries: sync::Arc<sync::Mutex<Entries>>,
+}
+
+impl CommandCache {
+    /// Cache `clients`, `dirs`, `info` and `where` for `ttl`.
+    pub fn new(ttl: time::Duration) -> Self {
+        Self {
+            ttl,
+            commands: DEFAULT_COMMANDS.iter().map(|&c| c.to_owned()).collect(),
+            entries: sync::Arc::new(sync::Mutex::new(collections::HashMap::new())),
+        }
+    }
+
+    /// Also cache `command`, which should only query the server.
+    pub fn cache_command(mut self, command: &str) -> Self {
+        self.commands.push(command.to_owned());
+        self
+    }
+
+    /// Forget everything cached, e.g. after changing the depot.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> sync::MutexGuard<'_, Entries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(sync::PoisonError::into_inner)
+    }
+}
+
+/// Answers cached commands from the cache, recording the output of misses.
+#[derive(Debug)]
+pub(crate) struct Cached {
+    pub(crate) runner: sync::Arc<dyn runner::P4Runner>,
+    pub(crate) cache: CommandCache,
+}
+
+impl runner::P4Runner for Cached {
+    fn run(
+        &self,
+        cmd: process::Command,
+        options: runner::RunOptions,
+    ) -> io::Result<Box<dyn Read + Send>> {
+        let (command, _) = trace::describe(&cmd, None);
+        if !self.cache.commands.contains(&command) {
+            return self.runner.run(cmd, options);
+        }
+        let key = Key::new(&cmd, options.input.as_ref());
+        if let Some((stored, output)) = self.cache.lock().get(&key) {
+            if stored.elapsed() < self.cache.ttl {
+                return Ok(Box::new(io::Cursor::new(output.clone())));
+            }
+        }
+        let source = self.runner.run(cmd, options)?;
+        Ok(Box::new(Recording {
+            source,
+            key: Some(key),
+            output: Vec::new(),
+            cache: self.cache.clone(),
+        }))
+    }
+}
+
+/// Streams a command's output, caching it once it's all been read.
+struct Recording {
+    source: Box<dyn Read + Send>,
+    key: Option<Key>,
+    output: Vec<u8>,
+    cache: CommandCache,
+}
+
+impl Read for Recording {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = match self.source.read(buf) {
+            Ok(read) => read,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::Interrupted {
+                    // Partial output mustn't be cached.
+                    self.key = None;
+                }
+                return Err(e);
+            }
+        };
+        if self.key.is_none() {
+            return Ok(read);
+        }
+        self.output.extend_from_slice(&buf[..read]);
+        if read == 0 && self.output.ends_with(SUCCESS) {
+            let key = self.key.take().expect("checked above");
+            let output = ::std::mem::take(&mut self.output);
+            let mut entries = self.cache.lock();
+            let ttl = self.cache.ttl;
+            entries.retain(|_, (stored, _)| stored.elapsed() < ttl);
+            entries.insert(key, (time::Instant::now(), output));
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::atomic;
+
+    use p4;
+
+    /// Counts invocations, always succeeding.
+    #[derive(Debug, Default)]
+    struct Counting(atomic::AtomicUsize);
+
+    impl runner::P4Runner for Counting {
+        fn run(
+            &self,
+            _cmd: process::Command,
+            _options: runner::RunOptions,
+        ) -> io::Result<Box<dyn Read + Send>> {
+            self.0.fetch_add(1, atomic::Ordering::SeqCst);
+            Ok(Box::new(&b"info1: dir //depot/a\nexit: 0\n"[..]))
+        }
+    }
+
+    #[test]
+    fn cache_reuses_output() {
+        let counting = sync::Arc::new(Counting::default());
+        let cache = CommandCache::new(time::Duration::from_secs(60));
+        let p4 = p4::P4::new()
+            .set_runner(Some(counting.clone()))
+            .set_cache(Some(cache.clone()));
+        let first = p4.dirs("//depot/*").run_checked().unwrap();
+        let second = p4.dirs("//depot/*").run_checked().unwrap();
+        assert_eq!(first, second);
+        assert_eq!(counting.0.load(atomic::Ordering::SeqCst), 1);
+
+        p4.dirs("//other/*").run_checked().unwrap();
+        assert_eq!(counting.0.load(atomic::Ordering::SeqCst), 2);
+
+        p4.files("//depot/*").run().unwrap();
+        p4.files("//depot/*").run().unwrap();
+        assert_eq!(counting.0.load(atomic::Ordering::SeqCst), 4);
+
+        cache.clear();
+        p4.dirs("//depot/*").run_checked().unwrap();
+        assert_eq!(counting.0.load(atomic::Ordering::SeqCst), 5);
+    }
+}
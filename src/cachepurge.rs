@@ -0,0 +1,248 @@
+use std::borrow;
+use std::fmt;
+use std::process;
+
+use error;
+use exec;
+use p4;
+use paths;
+
+/// Purge a replica or proxy cache of file archives
+///
+/// Removes archive files from a replica or proxy cache to reclaim disk
+/// space.  The files are re-fetched from the master on demand.
+///
+/// Requires super access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let purge = p4_cmd::cachepurge::Purge::Size(1 << 30);
+/// let purged = p4.cachepurge(purge).file("//depot/...").run().unwrap();
+/// for file in purged {
+///     println!("{:?}", file);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachepurgeCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    purge: Purge,
+    file: Vec<borrow::Cow<'f, str>>,
+
+    preview: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> CachepurgeCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, purge: Purge) -> Self {
+        Self {
+            connection,
+            purge,
+            file: vec![],
+            preview: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Restrict purging to files matching this file argument.  Can be
+    /// repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// The -n flag displays which files would be purged without removing
+    /// them.
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("cachepurge");
+        match self.purge {
+            Purge::All => {
+                cmd.arg("-a");
+            }
+            Purge::FreeSpace(n) => {
+                cmd.args(&["-f", &n.to_string()]);
+            }
+            Purge::Max(n) => {
+                cmd.args(&["-m", &n.to_string()]);
+            }
+            Purge::Size(n) => {
+                cmd.args(&["-s", &n.to_string()]);
+            }
+            Purge::FreePercent(n) => {
+                cmd.args(&["-i", &n.to_string()]);
+            }
+            Purge::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+        if self.preview {
+            cmd.arg("-n");
+        }
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `cachepurge` command.
+    pub fn run(self) -> Result<PurgedFiles, error::P4Error> {
+        let cmd = self.to_command();
+        let files = self.file.len();
+        Ok(PurgedFiles(exec::spawn_files(
+            self.connection,
+            self.settings,
+            cmd,
+            files,
+            cachepurge_parser::item,
+        )?))
+    }
+
+    /// Run the `cachepurge` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<PurgedFile>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+/// How much of the cache to purge.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Purge {
+    #[doc(hidden)]
+    __Nonexhaustive,
+
+    /// -a: purge all files.
+    All,
+    /// -f N: purge until N bytes of the filesystem are free.
+    FreeSpace(u64),
+    /// -m N: purge at most N bytes.
+    Max(u64),
+    /// -s N: purge until the cache is at most N bytes.
+    Size(u64),
+    /// -i N: purge until N percent of the filesystem is free.
+    FreePercent(u8),
+}
+
+pub type PurgedFileItem = error::Item<PurgedFile>;
+
+pub struct PurgedFiles(exec::Items<PurgedFile>);
+
+impl IntoIterator for PurgedFiles {
+    type Item = PurgedFileItem;
+    type IntoIter = PurgedFilesIntoIter;
+
+    fn into_iter(self) -> PurgedFilesIntoIter {
+        PurgedFilesIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct PurgedFilesIntoIter(exec::Items<PurgedFile>);
+
+impl Iterator for PurgedFilesIntoIter {
+    type Item = PurgedFileItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<PurgedFileItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A file archive removed from the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PurgedFile {
+    pub depot_file: paths::DepotPath,
+    pub rev: Option<usize>,
+    /// Bytes reclaimed.
+    pub file_size: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod cachepurge_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_purged(fields: Vec<Field>) -> Result<PurgedFile, fmt::Error> {
+        let mut depot_file = None;
+        let mut rev = None;
+        let mut file_size = None;
+        for field in fields {
+            match field.key {
+                "depotFile" => {
+                    depot_file = Some(paths::DepotPath::from_bytes(field.value.as_bytes()))
+                }
+                "rev" => rev = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                "fileSize" => file_size = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                _ => (),
+            }
+        }
+        let purged = PurgedFile {
+            depot_file: depot_file.ok_or(fmt::Error)?,
+            rev,
+            file_size,
+            non_exhaustive: (),
+        };
+        Ok(purged)
+    }
+
+    named!(pub item<&[u8], PurgedFileItem>,
+        alt!(
+            map!(map_res!(record, to_purged), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cachepurge_files() {
+        let output: &[u8] = br#"info1: depotFile //depot/big.bin
+info1: rev 3
+info1: fileSize 1048576
+info1: depotFile //depot/small.txt
+info1: rev 1
+info1: fileSize 12
+info: Purged 2 files (1048588 bytes).
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, cachepurge_parser::item);
+        let first = items[0].as_data().unwrap();
+        assert_eq!(first.depot_file, "//depot/big.bin");
+        assert_eq!(first.rev, Some(3));
+        assert_eq!(first.file_size, Some(1048576));
+        assert_eq!(items[1].as_data().unwrap().depot_file, "//depot/small.txt");
+        assert!(items[2].as_message().is_some());
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,42 @@
+//! Abort in-flight commands, e.g. from a GUI's "cancel" button.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::thread;
+//!
+//! let token = p4_cmd::cancel::CancellationToken::new();
+//! let worker = token.clone();
+//! let sync = thread::spawn(move || {
+//!     let p4 = p4_cmd::P4::new();
+//!     let files = p4.sync("//depot/...").cancel_token(worker).run().unwrap();
+//!     files.into_iter().count()
+//! });
+//! token.cancel();
+//! sync.join().unwrap();
+//! ```
+
+use std::sync;
+use std::sync::atomic;
+
+/// Shared flag for aborting commands.
+///
+/// Once cancelled, watching commands kill `p4` and end their output with an
+/// `ErrorKind::Cancelled` error.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(sync::Arc<atomic::AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort all commands watching this token.
+    pub fn cancel(&self) {
+        self.0.store(true, atomic::Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(atomic::Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,177 @@
+use std::process;
+
+use error;
+use exec;
+use fetch;
+use p4;
+
+/// Clone a new personal server from a shared server
+///
+/// Initializes a personal server in the current directory and fetches
+/// the files matching `file` (or the remote spec's DepotMap) into it.
+///
+/// Remote specs are read and written like other specs, with `p4.spec("remote")` and
+/// [`Spec::remote_depot_map`](../spec/struct.Spec.html#method.remote_depot_map).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let changes = p4
+///     .clone_server("perforce:1666")
+///     .file("//depot/main/...")
+///     .run()
+///     .unwrap();
+/// for change in changes {
+///     println!("{:?}", change);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CloneCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    port: &'f str,
+
+    file: Option<&'f str>,
+    remote: Option<&'f str>,
+    depth: Option<usize>,
+    verbose: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> CloneCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, port: &'f str) -> Self {
+        Self {
+            connection,
+            port,
+            file: None,
+            remote: None,
+            depth: None,
+            verbose: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -f flag clones the files matching the file argument.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// The -r flag clones using the DepotMap of an existing remote spec on
+    /// the shared server.
+    pub fn remote(mut self, remote: &'f str) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// The -m flag clones only the last 'depth' changes.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// The -v flag reports each change as it is fetched.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&["clone", "-p", self.port]);
+        if let Some(file) = self.file {
+            cmd.args(&["-f", file]);
+        }
+        if let Some(remote) = self.remote {
+            cmd.args(&["-r", remote]);
+        }
+        if let Some(depth) = self.depth {
+            let depth = format!("{}", depth);
+            cmd.args(&["-m", &depth]);
+        }
+        if self.verbose {
+            cmd.arg("-v");
+        }
+        cmd
+    }
+
+    /// Run the `clone` command.
+    pub fn run(self) -> Result<fetch::Transfers, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(fetch::Transfers(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            fetch::fetch_parser::item,
+        )?))
+    }
+
+    /// Run the `clone` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<fetch::Transfer>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clone_to_command() {
+        let p4 = p4::P4::new();
+        let cmd = p4
+            .clone_server("perforce:1666")
+            .file("//depot/main/...")
+            .remote("origin")
+            .depth(5)
+            .verbose(true)
+            .to_command();
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_str().unwrap()).collect();
+        let clone = args.iter().position(|&a| a == "clone").unwrap();
+        assert_eq!(
+            &args[clone..],
+            [
+                "clone",
+                "-p",
+                "perforce:1666",
+                "-f",
+                "//depot/main/...",
+                "-r",
+                "origin",
+                "-m",
+                "5",
+                "-v"
+            ]
+        );
+    }
+
+    #[test]
+    fn clone_output() {
+        let output: &[u8] = br#"info1: change 1
+info1: newChange 1
+info1: files 12
+info1: desc Initial import
+info1: change 7
+info1: newChange 2
+info1: files 3
+info: 2 change(s) containing a total of 15 file revision(s) were successfully fetched.
+exit: 0
+"#;
+        let (runner, p4) = exec::recorder(output);
+        let transfers = p4
+            .clone_server("perforce:1666")
+            .remote("origin")
+            .run_checked()
+            .unwrap();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].description, Some("Initial import".to_owned()));
+        assert_eq!(transfers[1].change, 7);
+        assert_eq!(transfers[1].new_change, Some(2));
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::ffi;
+use std::fs;
+use std::path;
+
+use error;
+
+/// P4CONFIG file name `p4` looks for when `P4CONFIG` isn't set.
+const DEFAULT_CONFIG: &str = ".p4config";
+
+/// P4ENVIRO file, relative to `$HOME`, `p4` reads when `P4ENVIRO` isn't set.
+const DEFAULT_ENVIRO: &str = ".p4enviro";
+
+/// The P4ENVIRO file `p4` reads, if there is one.
+pub(crate) fn enviro() -> Option<path::PathBuf> {
+    let path = match env::var_os("P4ENVIRO") {
+        Some(path) => path::PathBuf::from(path),
+        None => path::PathBuf::from(env::var_os("HOME")?).join(DEFAULT_ENVIRO),
+    };
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Find the closest config file in `dir` or its ancestors.
+pub(crate) fn find(dir: &path::Path) -> Option<path::PathBuf> {
+    let name = env::var_os("P4CONFIG").unwrap_or_else(|| ffi::OsString::from(DEFAULT_CONFIG));
+    dir.ancestors()
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+}
+
+/// Read the `NAME=value` settings from a P4CONFIG or P4ENVIRO file.
+pub(crate) fn read(path: &path::Path) -> Result<Vec<(String, String)>, error::P4Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        error::ErrorKind::ConfigFailed
+            .error()
+            .set_cause(e)
+            .set_context(format!("File: {}", path.display()))
+    })?;
+    Ok(parse(&content))
+}
+
+fn parse(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            Some((name.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_settings() {
+        let settings = parse("# Workspace\nP4PORT=ssl:perforce:1666\n\nP4CLIENT = ws\nbogus\n");
+        assert_eq!(
+            settings,
+            vec![
+                ("P4PORT".to_owned(), "ssl:perforce:1666".to_owned()),
+                ("P4CLIENT".to_owned(), "ws".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Set, unset, or display server configuration variables
+///
+/// By default, displays the configuration variables set on the server.
+/// Use `set`, `unset`, or `history` to change or audit a variable.
+///
+/// Requires super access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let configurables = p4.configure().all_servers(true).run().unwrap();
+/// for configurable in configurables {
+///     println!("{:?}", configurable);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigureCommand<'p, 'c> {
+    connection: &'p p4::P4,
+    name: Option<&'c str>,
+
+    server: Option<&'c str>,
+    value: Option<&'c str>,
+    unset: bool,
+    history: bool,
+    all_servers: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'c> ConfigureCommand<'p, 'c> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            name: None,
+            server: None,
+            value: None,
+            unset: false,
+            history: false,
+            all_servers: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Restrict the operation to the specified configuration variable.
+    pub fn name(mut self, name: &'c str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Apply the variable to the named server (`serverid#name`) rather than
+    /// all servers.
+    pub fn server(mut self, server: &'c str) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// 'p4 configure set' sets the variable specified by `name` to
+    /// `value`.
+    pub fn set(mut self, value: &'c str) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// 'p4 configure unset' removes the variable specified by `name`,
+    /// returning it to its default value.
+    pub fn unset(mut self, unset: bool) -> Self {
+        self.unset = unset;
+        self
+    }
+
+    /// 'p4 configure history' displays the history of changes to
+    /// configuration variables.
+    pub fn history(mut self, history: bool) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// 'p4 configure show allservers' displays the variables set for all
+    /// servers, rather than the current one.
+    pub fn all_servers(mut self, all_servers: bool) -> Self {
+        self.all_servers = all_servers;
+        self
+    }
+
+    fn variable(&self) -> Option<String> {
+        self.name.map(|name| match self.server {
+            Some(server) => format!("{}#{}", server, name),
+            None => name.to_owned(),
+        })
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("configure");
+        let variable = self.variable();
+        if let Some(value) = self.value {
+            cmd.arg("set");
+            cmd.arg(format!("{}={}", variable.unwrap_or_default(), value));
+        } else if self.unset {
+            cmd.arg("unset");
+            if let Some(variable) = variable {
+                cmd.arg(variable);
+            }
+        } else if self.history {
+            cmd.arg("history");
+            if let Some(variable) = variable {
+                cmd.arg(variable);
+            }
+        } else {
+            cmd.arg("show");
+            if self.all_servers {
+                cmd.arg("allservers");
+            } else if let Some(server) = self.server {
+                cmd.arg(server);
+            }
+            if let Some(name) = self.name {
+                cmd.arg(name);
+            }
+        }
+        cmd
+    }
+
+    /// Run the `configure` command.
+    pub fn run(self) -> Result<Configurables, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Configurables(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            configure_parser::item,
+        )?))
+    }
+
+    /// Run the `configure` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Configurable>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ConfigurableItem = error::Item<Configurable>;
+
+pub struct Configurables(exec::Items<Configurable>);
+
+impl IntoIterator for Configurables {
+    type Item = ConfigurableItem;
+    type IntoIter = ConfigurablesIntoIter;
+
+    fn into_iter(self) -> ConfigurablesIntoIter {
+        ConfigurablesIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigurablesIntoIter(exec::Items<Configurable>);
+
+impl Iterator for ConfigurablesIntoIter {
+    type Item = ConfigurableItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ConfigurableItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A server configuration variable, or a change to one when listing
+/// `history`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Configurable {
+    pub name: String,
+    pub value: Option<String>,
+    /// The server the variable applies to (`any` for all servers).
+    pub server_id: Option<String>,
+    pub config_type: Option<ConfigType>,
+    /// The value before the change, when listing `history`.
+    pub old_value: Option<String>,
+    /// Who made the change, when listing `history`.
+    pub user: Option<String>,
+    /// When the change was made, when listing `history`.
+    pub date: Option<p4::ServerDate>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// How a configuration variable got its value.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::configure::ConfigType::Tunable.to_string(), "tunable");
+/// assert_eq!(
+///     "configure".parse::<p4_cmd::configure::ConfigType>().unwrap(),
+///     p4_cmd::configure::ConfigType::Configure
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConfigType {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    /// Set with `p4 configure`.
+    Configure,
+    /// Set with `-v` on the server command line.
+    Tunable,
+    /// The server's default.
+    Default,
+    /// Set from the environment (P4JOURNAL, etc).
+    Environment,
+    /// A `set` entry in `history`.
+    Set,
+    /// An `unset` entry in `history`.
+    Unset,
+
+    Unknown(String),
+}
+
+impl str::FromStr for ConfigType {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config_type = match s {
+            "configure" => ConfigType::Configure,
+            "tunable" => ConfigType::Tunable,
+            "default" => ConfigType::Default,
+            "env" => ConfigType::Environment,
+            "set" => ConfigType::Set,
+            "unset" => ConfigType::Unset,
+            s => ConfigType::Unknown(s.to_owned()),
+        };
+        Ok(config_type)
+    }
+}
+
+impl fmt::Display for ConfigType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            ConfigType::Configure => "configure",
+            ConfigType::Tunable => "tunable",
+            ConfigType::Default => "default",
+            ConfigType::Environment => "env",
+            ConfigType::Set => "set",
+            ConfigType::Unset => "unset",
+            ConfigType::Unknown(ref s) => s.as_str(),
+            ConfigType::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+mod configure_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_configurable(fields: Vec<Field>) -> Result<Configurable, fmt::Error> {
+        let mut name = None;
+        let mut value = None;
+        let mut server_id = None;
+        let mut config_type = None;
+        let mut old_value = None;
+        let mut user = None;
+        let mut date = None;
+        for field in fields {
+            match field.key {
+                "Name" => name = Some(field.value.to_owned()),
+                "Value" => value = Some(field.value.to_owned()),
+                "ServerName" => server_id = Some(field.value.to_owned()),
+                "Type" => config_type = Some(field.value.parse()?),
+                "OldValue" => old_value = Some(field.value.to_owned()),
+                "User" => user = Some(field.value.to_owned()),
+                "Date" => date = Some(field.value.parse()?),
+                _ => (),
+            }
+        }
+        let configurable = Configurable {
+            name: name.ok_or(fmt::Error)?,
+            value,
+            server_id,
+            config_type,
+            old_value,
+            user,
+            date,
+            non_exhaustive: (),
+        };
+        Ok(configurable)
+    }
+
+    named!(pub item<&[u8], ConfigurableItem>,
+        alt!(
+            map!(map_res!(record, to_configurable), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn configure_show() {
+        let output: &[u8] = br#"info1: ServerName any
+info1: Name monitor
+info1: Value 1
+info1: Type configure
+info1: ServerName edge1
+info1: Name net.tcpsize
+info1: Value 524288
+info1: Type tunable
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, configure_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.name, "monitor");
+        assert_eq!(first.value, Some("1".to_owned()));
+        assert_eq!(first.server_id, Some("any".to_owned()));
+        assert_eq!(first.config_type, Some(ConfigType::Configure));
+        assert_eq!(last.server_id, Some("edge1".to_owned()));
+        assert_eq!(last.config_type, Some(ConfigType::Tunable));
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn configure_set() {
+        let output: &[u8] = br#"info: For server 'any', configuration variable 'monitor' set to '1'
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, configure_parser::item);
+        assert!(items[0].as_message().is_some());
+    }
+}
@@ -1,7 +1,11 @@
-use std::vec;
+use std::borrow;
+use std::process;
 
 use error;
+use exec;
 use p4;
+use paths;
+use walk;
 
 /// List depot subdirectories
 ///
@@ -30,30 +34,38 @@ use p4;
 #[derive(Debug, Clone)]
 pub struct DirsCommand<'p, 'f, 's> {
     connection: &'p p4::P4,
-    dir: Vec<&'f str>,
+    dir: Vec<borrow::Cow<'f, str>>,
 
     client_only: bool,
     stream: Option<&'s str>,
     include_deleted: bool,
     include_synced: bool,
     ignore_case: bool,
+    settings: exec::Settings,
 }
 
 impl<'p, 'f, 's> DirsCommand<'p, 'f, 's> {
     pub fn new(connection: &'p p4::P4, dir: &'f str) -> Self {
         Self {
             connection,
-            dir: vec![dir],
+            dir: vec![dir.into()],
             client_only: false,
             stream: None,
             include_deleted: false,
             include_synced: false,
             ignore_case: false,
+            settings: exec::Settings::default(),
         }
     }
 
     pub fn dir(mut self, dir: &'f str) -> Self {
-        self.dir.push(dir);
+        self.dir.push(dir.into());
+        self
+    }
+
+    /// `dir` at `rev`, escaping any `@`, `#`, `%` or `*` in `dir`.  Can be repeated.
+    pub fn dir_at(mut self, dir: &str, rev: p4::RevSpec) -> Self {
+        self.dir.push(p4::file_at(dir, &rev).into());
         self
     }
 
@@ -92,9 +104,11 @@ impl<'p, 'f, 's> DirsCommand<'p, 'f, 's> {
         self
     }
 
-    /// Run the `dirs` command.
-    pub fn run(self) -> Result<Dirs, error::P4Error> {
-        let mut cmd = self.connection.connect_with_retries(None);
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
         cmd.arg("dirs");
         if self.client_only {
             cmd.arg("-C");
@@ -111,40 +125,48 @@ impl<'p, 'f, 's> DirsCommand<'p, 'f, 's> {
         if self.ignore_case {
             cmd.arg("-i");
         }
-        for dir in self.dir {
-            cmd.arg(dir);
+        for dir in &self.dir {
+            cmd.arg(dir.as_ref());
         }
-        let data = cmd.output().map_err(|e| {
-            error::ErrorKind::SpawnFailed
-                .error()
-                .set_cause(e)
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        let (_remains, (mut items, exit)) = dirs_parser::dirs(&data.stdout).map_err(|_| {
-            error::ErrorKind::ParseFailed
-                .error()
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        items.push(exit);
-        Ok(Dirs(items))
+        cmd
+    }
+
+    /// Run the `dirs` command.
+    pub fn run(self) -> Result<Dirs, error::P4Error> {
+        let cmd = self.to_command();
+        let files = self.dir.len();
+        Ok(Dirs(exec::spawn_files(
+            self.connection,
+            self.settings,
+            cmd,
+            files,
+            dirs_parser::item,
+        )?))
+    }
+
+    /// Run the `dirs` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Dir>, error::P4Error> {
+        exec::collect(self.run()?)
     }
 }
 
 pub type DirItem = error::Item<Dir>;
 
-pub struct Dirs(Vec<DirItem>);
+pub struct Dirs(exec::Items<Dir>);
 
 impl IntoIterator for Dirs {
     type Item = DirItem;
     type IntoIter = DirsIntoIter;
 
     fn into_iter(self) -> DirsIntoIter {
-        DirsIntoIter(self.0.into_iter())
+        DirsIntoIter(self.0)
     }
 }
 
 #[derive(Debug)]
-pub struct DirsIntoIter(vec::IntoIter<DirItem>);
+pub struct DirsIntoIter(exec::Items<Dir>);
 
 impl Iterator for DirsIntoIter {
     type Item = DirItem;
@@ -166,11 +188,98 @@ impl Iterator for DirsIntoIter {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Dir {
-    pub dir: String,
+    pub dir: paths::DepotPath,
+    #[cfg_attr(feature = "serde", serde(skip))]
     non_exhaustive: (),
 }
 
+/// List every directory under a depot path, since `p4 dirs` doesn't support `...`.
+///
+/// Directories are listed a level at a time, each level with a `dirs //path/*` per directory
+/// found in the level above.  The root itself isn't listed.  This is a
+/// [`walk::DepotWalk`](../walk/struct.DepotWalk.html) that skips files.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let dirs = p4.dirs_walk("//depot/dir").max_depth(3).parallel(4).run();
+/// for dir in dirs {
+///     println!("{}", dir.unwrap().dir);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirsWalk<'p>(walk::DepotWalk<'p>);
+
+impl<'p> DirsWalk<'p> {
+    pub fn new(connection: &'p p4::P4, root: &str) -> Self {
+        DirsWalk(walk::DepotWalk::new(connection, root).dirs_only())
+    }
+
+    /// List no more than `max_depth` levels below the root, `1` being the root's directories.
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        DirsWalk(self.0.max_depth(max_depth))
+    }
+
+    /// List each level with up to `workers` concurrent `dirs` commands.
+    pub fn parallel(self, workers: usize) -> Self {
+        DirsWalk(self.0.parallel(workers))
+    }
+
+    /// Include directories containing only deleted files, see `DirsCommand::include_deleted`.
+    pub fn include_deleted(self, include_deleted: bool) -> Self {
+        DirsWalk(self.0.include_deleted(include_deleted))
+    }
+
+    /// Start walking, a level being listed once the one above has been yielded.
+    pub fn run(self) -> DirsWalkIter<'p> {
+        DirsWalkIter(self.0.run())
+    }
+
+    /// Walk the whole tree, collecting every directory.
+    ///
+    /// Fails on the first level whose `dirs` reports an error.
+    pub fn run_checked(self) -> Result<Vec<Dir>, error::P4Error> {
+        self.run().collect()
+    }
+}
+
+/// Run `dirs` for `patterns`, a directory without subdirectories not being a failure.
+pub(crate) fn list(
+    connection: &p4::P4,
+    patterns: &[String],
+    include_deleted: bool,
+) -> Result<Vec<Dir>, error::P4Error> {
+    let mut cmd = DirsCommand::new(connection, &patterns[0]).include_deleted(include_deleted);
+    for pattern in &patterns[1..] {
+        cmd = cmd.dir(pattern);
+    }
+    exec::collect_found(cmd.run()?)
+}
+
+/// Directories from a [`DirsWalk`](struct.DirsWalk.html), level by level.
+#[derive(Debug)]
+pub struct DirsWalkIter<'p>(walk::DepotWalkIter<'p>);
+
+impl<'p> Iterator for DirsWalkIter<'p> {
+    type Item = Result<Dir, error::P4Error>;
+
+    fn next(&mut self) -> Option<Result<Dir, error::P4Error>> {
+        loop {
+            match self.0.next()? {
+                Ok(walk::Entry {
+                    kind: walk::EntryKind::Directory(dir),
+                    ..
+                }) => return Some(Ok(dir)),
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
 mod dirs_parser {
     use super::super::parser::*;
 
@@ -179,14 +288,14 @@ mod dirs_parser {
             dir: dir >>
             (
                 super::Dir {
-                    dir: dir.dir.to_owned(),
+                    dir: super::paths::DepotPath::from_bytes(dir.dir),
                     non_exhaustive: (),
                 }
             )
         )
     );
 
-    named!(item<&[u8], super::DirItem>,
+    named!(pub item<&[u8], super::DirItem>,
         alt!(
             map!(dir_, data_to_item) |
             map!(error, error_to_item) |
@@ -201,3 +310,59 @@ mod dirs_parser {
         )
     );
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync;
+
+    use walk::test::Tree;
+
+    const TREE: &[&str] = &[
+        "//depot/a/",
+        "//depot/b/",
+        "//depot/a/x/",
+        "//depot/a/y/",
+        "//depot/b/z/",
+        "//depot/a/x/deep/",
+    ];
+
+    fn walk(walk: DirsWalk) -> Vec<String> {
+        walk.run_checked()
+            .unwrap()
+            .into_iter()
+            .map(|d| format!("{}/", d.dir))
+            .collect()
+    }
+
+    #[test]
+    fn dirs_walk_levels() {
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(Tree(TREE))));
+        assert_eq!(walk(p4.dirs_walk("//depot/...")), TREE);
+        assert_eq!(walk(p4.dirs_walk("//depot").parallel(2)), TREE);
+        assert_eq!(walk(p4.dirs_walk("//depot/").max_depth(2)), &TREE[..5]);
+        assert_eq!(walk(p4.dirs_walk("//depot/a/x")), ["//depot/a/x/deep/"]);
+        assert!(walk(p4.dirs_walk("//depot/b/z")).is_empty());
+    }
+
+    #[test]
+    fn dirs_walk_depot_root() {
+        const ROOT: &[&str] = &["//depot/", "//spec/", "//depot/a/"];
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(Tree(ROOT))));
+        assert_eq!(walk(p4.dirs_walk("//...")), ROOT);
+        assert_eq!(walk(p4.dirs_walk("//").max_depth(1)), &ROOT[..2]);
+    }
+
+    #[test]
+    fn dirs_walk_failure() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"error: Perforce password (P4PASSWD) invalid or unset.\nexit: 1\n".to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut dirs = p4.dirs_walk("//depot").run();
+        assert!(dirs.next().unwrap().is_err());
+        assert!(dirs.next().is_none());
+    }
+}
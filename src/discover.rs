@@ -0,0 +1,108 @@
+use std::env;
+use std::ffi;
+use std::path;
+use std::process;
+use std::sync;
+
+use error;
+
+#[cfg(windows)]
+const BINARY: &str = "p4.exe";
+#[cfg(not(windows))]
+const BINARY: &str = "p4";
+
+/// The `p4` found by an earlier search.
+static FOUND: sync::Mutex<Option<path::PathBuf>> = sync::Mutex::new(None);
+
+/// Find a working `p4`, searching only the first time.
+pub(crate) fn p4() -> Result<path::PathBuf, error::P4Error> {
+    let mut found = FOUND.lock().unwrap_or_else(sync::PoisonError::into_inner);
+    if let Some(ref p4) = *found {
+        return Ok(p4.clone());
+    }
+    let path = env::var_os("PATH").unwrap_or_default();
+    let mut last = None;
+    for candidate in candidates(&path) {
+        match verify(&candidate) {
+            Ok(()) => {
+                *found = Some(candidate.clone());
+                return Ok(candidate);
+            }
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| {
+        error::ErrorKind::SpawnFailed.error().set_context(format!(
+            "No {} on PATH or in the usual install locations",
+            BINARY
+        ))
+    }))
+}
+
+/// Where `p4` is, in the order to try them.
+fn candidates(path: &ffi::OsStr) -> Vec<path::PathBuf> {
+    env::split_paths(path)
+        .chain(install_dirs())
+        .map(|dir| dir.join(BINARY))
+        .filter(|p4| p4.is_file())
+        .collect()
+}
+
+#[cfg(windows)]
+fn install_dirs() -> Vec<path::PathBuf> {
+    ["ProgramFiles", "ProgramFiles(x86)"]
+        .iter()
+        .filter_map(env::var_os)
+        .map(|dir| path::PathBuf::from(dir).join("Perforce"))
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn install_dirs() -> Vec<path::PathBuf> {
+    ["/opt/perforce/bin", "/usr/local/bin", "/opt/homebrew/bin"]
+        .iter()
+        .map(path::PathBuf::from)
+        .collect()
+}
+
+/// Check `p4` runs, with `p4 -V`.
+fn verify(p4: &path::Path) -> Result<(), error::P4Error> {
+    let context = format!("Command: {} -V", p4.display());
+    let output = process::Command::new(p4)
+        .arg("-V")
+        .stdin(process::Stdio::null())
+        .output()
+        .map_err(|e| {
+            error::ErrorKind::SpawnFailed
+                .error()
+                .set_cause(e)
+                .set_context(context.clone())
+        })?;
+    if !output.status.success() {
+        return Err(error::ErrorKind::SpawnFailed.error().set_context(context));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn candidates_follow_path() {
+        let root = env::temp_dir().join(format!("p4-cmd-discover-{}", process::id()));
+        let (first, second, empty) = (root.join("first"), root.join("second"), root.join("empty"));
+        for dir in &[&first, &second, &empty] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(first.join(BINARY), "").unwrap();
+        fs::write(second.join(BINARY), "").unwrap();
+        let path = env::join_paths([&empty, &second, &first]).unwrap();
+        let found = candidates(&path);
+        assert_eq!(found[0], second.join(BINARY));
+        assert_eq!(found[1], first.join(BINARY));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
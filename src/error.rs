@@ -1,21 +1,75 @@
 use std::error::Error;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct OperationError {
     code: i32,
+    kind: Option<ErrorKind>,
+    diagnostics: Option<String>,
+    stderr: Option<String>,
 }
 
 impl OperationError {
     pub(crate) fn new(code: i32) -> Self {
-        Self { code }
+        Self {
+            code,
+            kind: None,
+            diagnostics: None,
+            stderr: None,
+        }
+    }
+
+    /// `p4` was stopped before it could report an exit code.
+    pub(crate) fn stopped(kind: ErrorKind) -> Self {
+        Self {
+            code: -1,
+            kind: Some(kind),
+            diagnostics: None,
+            stderr: None,
+        }
     }
 
-    // Keeping around for future use.
-    #[allow(dead_code)]
-    pub(crate) fn code(&self) -> i32 {
+    pub(crate) fn set_diagnostics(mut self, diagnostics: Option<String>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Keep what `p4` wrote to stderr, if it wrote anything.
+    pub(crate) fn set_stderr(mut self, stderr: String) -> Self {
+        if !stderr.trim().is_empty() {
+            self.stderr = Some(stderr);
+        }
+        self
+    }
+
+    /// Recognize why a command failed from the messages it reported.
+    pub(crate) fn set_kind(mut self, kind: Option<ErrorKind>) -> Self {
+        if self.code != 0 && self.kind.is_none() {
+            self.kind = kind;
+        }
+        self
+    }
+
+    /// Why `p4` was stopped, or why it failed when the reason is recognizable.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        self.kind
+    }
+
+    /// The exit code `p4` reported, `-1` if it was stopped first.
+    pub fn code(&self) -> i32 {
         self.code
     }
+
+    /// The trace `p4` wrote when run with `P4::set_verbosity`.
+    pub fn diagnostics(&self) -> Option<&str> {
+        self.diagnostics.as_deref()
+    }
+
+    /// What `p4` wrote to stderr when it failed.
+    pub fn stderr(&self) -> Option<&str> {
+        self.stderr.as_deref()
+    }
 }
 
 impl Error for OperationError {
@@ -30,39 +84,203 @@ impl Error for OperationError {
 
 impl fmt::Display for OperationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Operation failed")
+        match self.kind {
+            Some(kind) => writeln!(f, "Operation failed: {}", kind),
+            None => writeln!(f, "Operation failed"),
+        }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MessageLevel {
+    /// `p4` couldn't go on, e.g. it ran out of memory.
+    Fatal,
     Error,
     Warning,
     Info,
 
     #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __Nonexhaustive,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Message {
-    level: MessageLevel,
-    msg: String,
+    pub(crate) level: MessageLevel,
+    pub(crate) msg: String,
+    code: Option<MessageCode>,
+}
+
+impl MessageLevel {
+    /// Whether the message means the command failed.
+    pub fn is_failure(self) -> bool {
+        matches!(self, MessageLevel::Fatal | MessageLevel::Error)
+    }
 }
 
 impl Message {
     pub(crate) fn new(level: MessageLevel, msg: String) -> Self {
-        Self { level, msg }
+        Self {
+            level,
+            msg,
+            code: None,
+        }
+    }
+
+    pub(crate) fn set_code(mut self, code: Option<MessageCode>) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn level(&self) -> MessageLevel {
+        self.level
+    }
+
+    pub fn text(&self) -> &str {
+        &self.msg
+    }
+
+    /// Identifies the message regardless of its wording, when `P4::set_message_codes` is enabled.
+    pub fn code(&self) -> Option<MessageCode> {
+        self.code
+    }
+}
+
+/// The code identifying a server message, stable across releases and languages.
+///
+/// # Example
+///
+/// ```rust
+/// let code = p4_cmd::error::MessageCode::new(823203963);
+/// assert_eq!(code.generic(), p4_cmd::error::GenericCode::Empty);
+/// assert_eq!(code.level(), p4_cmd::error::MessageLevel::Error);
+/// assert_eq!(code.subsystem(), 6);
+/// assert_eq!(code.unique(), 123);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MessageCode(u32);
+
+impl MessageCode {
+    pub fn new(code: u32) -> Self {
+        MessageCode(code)
+    }
+
+    /// The raw code, as reported by `p4 -e`.
+    pub fn code(self) -> u32 {
+        self.0
+    }
+
+    /// The broad category of the message, e.g. a file not existing.
+    pub fn generic(self) -> GenericCode {
+        GenericCode::from_code(((self.0 >> 16) & 0xff) as u8)
+    }
+
+    pub fn level(self) -> MessageLevel {
+        match self.0 >> 28 {
+            0 | 1 => MessageLevel::Info,
+            2 => MessageLevel::Warning,
+            3 => MessageLevel::Error,
+            _ => MessageLevel::Fatal,
+        }
+    }
+
+    /// The part of Perforce the message comes from.
+    pub fn subsystem(self) -> u8 {
+        ((self.0 >> 10) & 0x3f) as u8
+    }
+
+    /// Identifies the message within its subsystem.
+    pub fn unique(self) -> u16 {
+        (self.0 & 0x3ff) as u16
+    }
+
+    /// How many arguments the message's format takes.
+    pub fn args(self) -> u8 {
+        ((self.0 >> 24) & 0xf) as u8
+    }
+}
+
+/// The broad category of a server message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GenericCode {
+    None,
+    /// The command was used incorrectly.
+    Usage,
+    /// Something, like a client or label, doesn't exist.
+    Unknown,
+    /// Not allowed in this context.
+    Context,
+    /// Not allowed, e.g. a file is locked.
+    Illegal,
+    /// Something else needs to happen first.
+    NotYet,
+    /// The user doesn't have permission.
+    Protect,
+    /// No files or records matched, e.g. `no such file(s)`.
+    Empty,
+    /// A server error.
+    Fault,
+    /// A client error.
+    Client,
+    /// A server configuration error.
+    Admin,
+    /// A client configuration error.
+    Config,
+    /// Needs a newer client or server.
+    Upgrade,
+    /// Communication failed.
+    Comm,
+    /// Too big to handle, e.g. over a server limit.
+    TooBig,
+    Other(u8),
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+impl GenericCode {
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x00 => GenericCode::None,
+            0x01 => GenericCode::Usage,
+            0x02 => GenericCode::Unknown,
+            0x03 => GenericCode::Context,
+            0x04 => GenericCode::Illegal,
+            0x05 => GenericCode::NotYet,
+            0x06 => GenericCode::Protect,
+            0x11 => GenericCode::Empty,
+            0x20 => GenericCode::Fault,
+            0x21 => GenericCode::Client,
+            0x22 => GenericCode::Admin,
+            0x23 => GenericCode::Config,
+            0x24 => GenericCode::Upgrade,
+            0x25 => GenericCode::Comm,
+            0x26 => GenericCode::TooBig,
+            code => GenericCode::Other(code),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Item<T> {
     Data(T),
     Message(Message),
     Error(OperationError),
 
     #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __Nonexhaustive,
 }
 
@@ -91,11 +309,123 @@ impl<T> Item<T> {
 
 type ErrorCause = Error + Send + Sync + 'static;
 
+/// Messages `p4` reports when it couldn't reach the server.
+const CONNECTION_FAILURES: &[&str] = &[
+    "Connect to server failed",
+    "TCP connect to ",
+    "TCP receive failed",
+    "TCP send failed",
+    "Partner exited unexpectedly",
+];
+
+/// Reasons the OS gives, within a message, for a connection failing.
+const CONNECTION_REASONS: &[&str] = &["Network is unreachable", "Connection reset by peer"];
+
+/// Messages `p4` reports when the user isn't logged in.
+const AUTH_FAILURES: &[&str] = &[
+    "Perforce password (P4PASSWD) invalid or unset.",
+    "Your session has expired, please login again.",
+    "Password invalid.",
+];
+
+/// Messages `p4` reports when single sign-on fails.
+const SSO_FAILURES: &[&str] = &[
+    "Single sign-on on client failed",
+    "Invalid response from single sign-on on client",
+];
+
+/// Messages `p4` reports when it doesn't trust an `ssl:` server's fingerprint.
+const TRUST_FAILURES: &[&str] = &[
+    "The authenticity of",
+    "IDENTIFICATION HAS CHANGED",
+    "p4 trust",
+];
+
+/// Messages `p4` reports when it and the server disagree on whether to use unicode.
+const UNICODE_FAILURES: &[&str] = &[
+    "Unicode server permits only unicode enabled clients",
+    "Unicode clients require a unicode enabled server",
+];
+
+/// Recognize a server resource limit being hit, e.g.
+/// `Request too large (over 50000); see 'p4 help maxresults'.`
+fn resource_limit(msg: &str) -> Option<ResourceLimit> {
+    let start = msg.find("(over ")? + "(over ".len();
+    let end = start + msg[start..].find(')')?;
+    let value = msg[start..end].split_whitespace().next()?;
+    let msg = msg.to_lowercase();
+    let limit = if msg.contains("maxresults") {
+        ResourceLimit::MaxResults(value.parse().ok()?)
+    } else if msg.contains("maxscanrows") {
+        ResourceLimit::MaxScanRows(value.parse().ok()?)
+    } else if msg.contains("maxlocktime") {
+        let seconds: f64 = value.parse().ok()?;
+        ResourceLimit::MaxLockTime((seconds * 1000.0).round() as u32)
+    } else if msg.contains("maxopenfiles") {
+        ResourceLimit::MaxOpenFiles(value.parse().ok()?)
+    } else {
+        return None;
+    };
+    Some(limit)
+}
+
+/// Recognize the failure a message from `p4` describes.
+///
+/// A message's code is recognized whatever language it's in; otherwise only the start of the
+/// English message is.
+pub(crate) fn recognize(msg: &str, code: Option<MessageCode>) -> Option<ErrorKind> {
+    if code.map(MessageCode::generic) == Some(GenericCode::Comm) {
+        return Some(ErrorKind::ConnectFailed);
+    }
+    let msg = msg.trim_start();
+    let starts = |failures: &[&str]| failures.iter().any(|f| msg.starts_with(f));
+    if starts(CONNECTION_FAILURES) || CONNECTION_REASONS.iter().any(|r| msg.contains(r)) {
+        Some(ErrorKind::ConnectFailed)
+    } else if starts(SSO_FAILURES) {
+        Some(ErrorKind::SsoFailed)
+    } else if starts(AUTH_FAILURES) {
+        Some(ErrorKind::AuthFailed)
+    } else if TRUST_FAILURES.iter().any(|f| msg.contains(f)) {
+        Some(ErrorKind::TrustRequired)
+    } else if UNICODE_FAILURES.iter().any(|f| msg.contains(f)) {
+        Some(ErrorKind::UnicodeMismatch)
+    } else {
+        resource_limit(msg).map(ErrorKind::ResultLimitExceeded)
+    }
+}
+
 /// For programmatically processing failures.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ErrorKind {
     SpawnFailed,
     ParseFailed,
+    /// The command exceeded its timeout and was killed.
+    TimedOut,
+    /// The command was cancelled and killed.
+    Cancelled,
+    /// The command reported errors.
+    CommandFailed,
+    /// `p4` couldn't reach the server.
+    ConnectFailed,
+    /// The server rejected the user's password or ticket.
+    AuthFailed,
+    /// Single sign-on, through the P4LOGINSSO agent, failed.
+    SsoFailed,
+    /// The `ssl:` server's fingerprint isn't trusted.
+    TrustRequired,
+    /// A settings file, like P4CONFIG, couldn't be read.
+    ConfigFailed,
+    /// An option isn't supported by the server's version.
+    UnsupportedByServer,
+    /// `p4` used unicode with a non-unicode server, or the other way around.
+    UnicodeMismatch,
+    /// The command hit one of the server's resource limits; narrow it down, e.g. to fewer files.
+    ResultLimitExceeded(ResourceLimit),
+    /// The command's output couldn't be written where it was asked to go.
+    WriteFailed,
+    /// An argument can't be passed to `p4`, e.g. a path that isn't UTF-8.
+    InvalidArgument,
 }
 
 impl ErrorKind {
@@ -109,6 +439,58 @@ impl fmt::Display for ErrorKind {
         match *self {
             ErrorKind::SpawnFailed => write!(f, "Failed to launch P4 command."),
             ErrorKind::ParseFailed => write!(f, "Failed to parse P4 output."),
+            ErrorKind::TimedOut => write!(f, "P4 command timed out."),
+            ErrorKind::Cancelled => write!(f, "P4 command was cancelled."),
+            ErrorKind::CommandFailed => write!(f, "P4 command reported errors."),
+            ErrorKind::ConnectFailed => write!(f, "Failed to connect to the P4 server."),
+            ErrorKind::AuthFailed => write!(f, "P4 login is required."),
+            ErrorKind::SsoFailed => write!(f, "P4 single sign-on failed."),
+            ErrorKind::TrustRequired => write!(f, "P4 server is not trusted."),
+            ErrorKind::ConfigFailed => write!(f, "Failed to read P4 settings."),
+            ErrorKind::UnsupportedByServer => write!(f, "P4 server is too old for this option."),
+            ErrorKind::UnicodeMismatch => write!(f, "P4 server's unicode mode doesn't match."),
+            ErrorKind::ResultLimitExceeded(limit) => {
+                write!(f, "P4 command exceeded the server's {}.", limit)
+            }
+            ErrorKind::WriteFailed => write!(f, "Failed to write P4 output."),
+            ErrorKind::InvalidArgument => write!(f, "Invalid P4 command argument."),
+        }
+    }
+}
+
+/// A server limit on how much a command may do, as configured for the user's groups.
+///
+/// # Example
+///
+/// ```rust
+/// let limit = p4_cmd::error::ResourceLimit::MaxResults(50000);
+/// assert_eq!(limit.to_string(), "MaxResults of 50000");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResourceLimit {
+    /// Rows of data a command may return.
+    MaxResults(u32),
+    /// Rows a command may scan.
+    MaxScanRows(u32),
+    /// Milliseconds a command may lock the database.
+    MaxLockTime(u32),
+    /// Files a command may open.
+    MaxOpenFiles(u32),
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResourceLimit::MaxResults(n) => write!(f, "MaxResults of {}", n),
+            ResourceLimit::MaxScanRows(n) => write!(f, "MaxScanRows of {}", n),
+            ResourceLimit::MaxLockTime(n) => write!(f, "MaxLockTime of {} ms", n),
+            ResourceLimit::MaxOpenFiles(n) => write!(f, "MaxOpenFiles of {}", n),
+            ResourceLimit::__Nonexhaustive => unreachable!("This is a private variant"),
         }
     }
 }
@@ -117,6 +499,9 @@ impl fmt::Display for ErrorKind {
 pub struct P4Error {
     kind: ErrorKind,
     context: Option<String>,
+    stderr: Option<String>,
+    diagnostics: Option<String>,
+    messages: Vec<Message>,
     cause: Option<Box<ErrorCause>>,
 }
 
@@ -125,6 +510,9 @@ impl P4Error {
         Self {
             kind,
             context: None,
+            stderr: None,
+            diagnostics: None,
+            messages: Vec::new(),
             cause: None,
         }
     }
@@ -138,6 +526,22 @@ impl P4Error {
         self
     }
 
+    /// Attach what `p4` wrote to stderr.
+    pub(crate) fn set_stderr(mut self, stderr: Option<String>) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    pub(crate) fn set_diagnostics(mut self, diagnostics: Option<String>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    pub(crate) fn set_messages(mut self, messages: Vec<Message>) -> Self {
+        self.messages = messages;
+        self
+    }
+
     pub(crate) fn set_cause<E>(mut self, cause: E) -> Self
     where
         E: Error + Send + Sync + 'static,
@@ -151,6 +555,21 @@ impl P4Error {
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
+
+    /// Messages the command reported before failing.
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// What `p4` wrote to stderr, if it was captured.
+    pub fn stderr(&self) -> Option<&str> {
+        self.stderr.as_deref()
+    }
+
+    /// The trace `p4` wrote when run with `P4::set_verbosity`.
+    pub fn diagnostics(&self) -> Option<&str> {
+        self.diagnostics.as_deref()
+    }
 }
 
 impl Error for P4Error {
@@ -172,9 +591,112 @@ impl fmt::Display for P4Error {
         if let Some(ref context) = self.context {
             writeln!(f, "{}", context)?;
         }
+        for message in &self.messages {
+            writeln!(f, "{}", message.msg)?;
+        }
+        if let Some(ref stderr) = self.stderr {
+            writeln!(f, "{}", stderr.trim_end())?;
+        }
         if let Some(ref cause) = self.cause {
             writeln!(f, "Cause: {}", cause)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recognize_connection_failures() {
+        assert_eq!(
+            recognize("Connect to server failed; check $P4PORT.", None),
+            Some(ErrorKind::ConnectFailed)
+        );
+        assert_eq!(
+            recognize("TCP connect to perforce:1666 failed.", None),
+            Some(ErrorKind::ConnectFailed)
+        );
+        assert_eq!(
+            recognize("connect: 10.0.0.1:1666: Network is unreachable", None),
+            Some(ErrorKind::ConnectFailed)
+        );
+        assert_eq!(
+            recognize(
+                "Path '/ws/x' is not under client's root; check $P4PORT.",
+                None
+            ),
+            None
+        );
+
+        // `gen 37`, communication, in any language.
+        let comm = MessageCode::new(807732225);
+        assert_eq!(comm.generic(), GenericCode::Comm);
+        assert_eq!(
+            recognize("Echec de la connexion au serveur.", Some(comm)),
+            Some(ErrorKind::ConnectFailed)
+        );
+        assert_eq!(recognize("Echec de la connexion au serveur.", None), None);
+    }
+
+    #[test]
+    fn recognize_auth_failures() {
+        assert_eq!(
+            recognize("Perforce password (P4PASSWD) invalid or unset.", None),
+            Some(ErrorKind::AuthFailed)
+        );
+        assert_eq!(
+            recognize("Your session has expired, please login again.", None),
+            Some(ErrorKind::AuthFailed)
+        );
+        assert_eq!(
+            recognize(
+                "Can't read P4CONFIG file; set (P4PASSWD) or P4TICKETS.",
+                None
+            ),
+            None
+        );
+        assert_eq!(recognize("Change 12 submitted.", None), None);
+    }
+
+    #[test]
+    fn recognize_sso_failures() {
+        assert_eq!(
+            recognize(
+                "Single sign-on on client failed: 'sso' trigger exited.",
+                None
+            ),
+            Some(ErrorKind::SsoFailed)
+        );
+        assert_eq!(
+            recognize(
+                "Invalid response from single sign-on on client: bad token",
+                None
+            ),
+            Some(ErrorKind::SsoFailed)
+        );
+        assert_eq!(
+            recognize("Job job000012 - fix single sign-on timeout.", None),
+            None
+        );
+        assert_eq!(recognize("P4LOGINSSO=/usr/bin/sso (set)", None), None);
+    }
+
+    #[test]
+    fn recognize_other_failures() {
+        assert_eq!(
+            recognize("Unicode server permits only unicode enabled clients.", None),
+            Some(ErrorKind::UnicodeMismatch)
+        );
+        assert_eq!(
+            recognize(
+                "Request too large (over 50000); see 'p4 help maxresults'.",
+                None
+            ),
+            Some(ErrorKind::ResultLimitExceeded(ResourceLimit::MaxResults(
+                50000
+            )))
+        );
+    }
+}
@@ -0,0 +1,1637 @@
+use std::cell;
+use std::collections;
+use std::ffi;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::ops;
+use std::process;
+use std::sync;
+use std::thread;
+use std::time;
+
+use nom;
+
+use cancel;
+use error;
+use login;
+use observer;
+use p4;
+use parser;
+use progress;
+use redact;
+use retry;
+use runner;
+use trace;
+
+/// Parses a single record (data, error, or info) from the start of `p4 -s` output.
+pub(crate) type ItemParser<T> = fn(&[u8]) -> nom::IResult<&[u8], error::Item<T>>;
+
+/// A record's length, and the record unless it was a progress indicator.
+type Parsed<'a, T> = Result<(usize, Option<error::Item<T>>), nom::Err<&'a [u8]>>;
+
+const MIN_READ: usize = 8 * 1024;
+
+/// Bytes of arguments to stay under, within `CreateProcess`' 32767 character command line.
+#[cfg(windows)]
+const MAX_ARGS: usize = 30 * 1024;
+
+/// Bytes of arguments to stay under, well within `ARG_MAX` while leaving room for the environment.
+#[cfg(not(windows))]
+const MAX_ARGS: usize = 512 * 1024;
+
+/// How `p4` is asked to format its records.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub(crate) enum Format {
+    /// `-s` output: `info1: <name> <value>` fields, ending with an `exit:` record.
+    #[default]
+    Tagged,
+    /// `-ztag` output: `... <name> <value>` fields, with records separated by blank lines.
+    ///
+    /// There is no `exit:` record, so failures are only known from what `p4` writes to stderr.
+    Ztag,
+}
+
+/// Builder methods overriding a command's `settings: exec::Settings` field, shared by every
+/// command so they only need their own flags.
+macro_rules! settings_methods {
+    () => {
+        /// Kill `p4` and report `ErrorKind::TimedOut` if the command takes longer
+        /// than `timeout`, overriding the connection's default.
+        pub fn timeout(mut self, timeout: ::std::time::Duration) -> Self {
+            self.settings.timeout = Some(timeout);
+            self
+        }
+
+        /// Kill `p4` and report `ErrorKind::Cancelled` once `token` is
+        /// cancelled.
+        pub fn cancel_token(mut self, token: $crate::cancel::CancellationToken) -> Self {
+            self.settings.cancel = Some(token);
+            self
+        }
+
+        /// Run as `user`, overriding the connection's user.
+        pub fn as_user(mut self, user: &str) -> Self {
+            self.settings.user = Some(user.to_owned());
+            self
+        }
+
+        /// Run in the `client` workspace, overriding the connection's client.
+        pub fn in_client(mut self, client: &str) -> Self {
+            self.settings.client = Some(client.to_owned());
+            self
+        }
+
+        /// Connect to the server at `port`, overriding the connection's port.
+        pub fn on_port(mut self, port: &str) -> Self {
+            self.settings.port = Some(port.to_owned());
+            self
+        }
+
+        /// Authenticate with `password`, overriding the connection's password.
+        pub fn with_password(mut self, password: &str) -> Self {
+            self.settings.password = Some(password.to_owned());
+            self
+        }
+    };
+}
+
+/// Settings a command builder can override for a single run.
+#[derive(Clone, Default)]
+pub(crate) struct Settings {
+    pub(crate) timeout: Option<time::Duration>,
+    pub(crate) cancel: Option<cancel::CancellationToken>,
+    pub(crate) progress: Option<progress::Reporter>,
+    pub(crate) user: Option<String>,
+    pub(crate) client: Option<String>,
+    pub(crate) port: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) format: Format,
+}
+
+impl fmt::Debug for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Settings")
+            .field("timeout", &self.timeout)
+            .field("cancel", &self.cancel)
+            .field("progress", &self.progress)
+            .field("user", &self.user)
+            .field("client", &self.client)
+            .field("port", &self.port)
+            .field("password", &self.password.as_ref().map(redact::Secret))
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Run `cmd` through the connection's runner, lazily parsing its output as it is produced.
+pub(crate) fn spawn<T>(
+    connection: &p4::P4,
+    settings: Settings,
+    cmd: process::Command,
+    parser: ItemParser<T>,
+) -> Result<Items<T>, error::P4Error> {
+    spawn_with_input(connection, settings, cmd, None, parser)
+}
+
+/// Like `spawn`, feeding `input` to the command's stdin.
+pub(crate) fn spawn_with_input<T>(
+    connection: &p4::P4,
+    settings: Settings,
+    cmd: process::Command,
+    input: Option<Vec<u8>>,
+    parser: ItemParser<T>,
+) -> Result<Items<T>, error::P4Error> {
+    launch(connection, settings, vec![cmd], input, parser)
+}
+
+/// Like `spawn`, where the last `files` arguments of `cmd` are files.
+///
+/// When there are too many files for one command line, they are split across several runs of
+/// `p4` whose output is reported in order, ending with the first failing exit status.  Limits
+/// like `-m` then apply to each run.
+pub(crate) fn spawn_files<T>(
+    connection: &p4::P4,
+    settings: Settings,
+    cmd: process::Command,
+    files: usize,
+    parser: ItemParser<T>,
+) -> Result<Items<T>, error::P4Error> {
+    let cmds = split(cmd, files, MAX_ARGS);
+    launch(connection, settings, cmds, None, parser)
+}
+
+/// Split `cmd`'s last `files` arguments across commands whose arguments fit in `limit` bytes.
+fn split(cmd: process::Command, files: usize, limit: usize) -> Vec<process::Command> {
+    fn len(arg: &ffi::OsStr) -> usize {
+        // Each argument is also separated or terminated.
+        arg.len() + 1
+    }
+
+    let total: usize = cmd.get_args().map(len).sum();
+    if total <= limit {
+        return vec![cmd];
+    }
+    let args: Vec<_> = cmd.get_args().collect();
+    let (fixed, files) = args.split_at(args.len() - files);
+    let fixed_len: usize = fixed.iter().map(|a| len(a)).sum();
+    let mut chunks = vec![vec![]];
+    let mut chunk_len = fixed_len;
+    for file in files {
+        let last = chunks.last_mut().expect("always a chunk");
+        if !last.is_empty() && chunk_len + len(file) > limit {
+            chunks.push(vec![]);
+            chunk_len = fixed_len;
+        }
+        chunk_len += len(file);
+        chunks.last_mut().expect("always a chunk").push(*file);
+    }
+    chunks
+        .into_iter()
+        .map(|chunk| rebuild(&cmd, fixed.iter().chain(chunk.iter())))
+        .collect()
+}
+
+/// Start running `cmds` in turn.
+fn launch<T>(
+    connection: &p4::P4,
+    settings: Settings,
+    cmds: Vec<process::Command>,
+    input: Option<Vec<u8>>,
+    parser: ItemParser<T>,
+) -> Result<Items<T>, error::P4Error> {
+    let mut cmds = collections::VecDeque::from(cmds);
+    let cmd = cmds.pop_front().expect("at least one command");
+    let context = format!("Command: {}", redact::command(&cmd));
+    let cancel = settings.cancel.clone();
+    let options = runner::RunOptions {
+        input,
+        timeout: settings.timeout.or_else(|| connection.timeout()),
+        cancel: cancel.clone(),
+        stderr: runner::Stderr::default(),
+        non_exhaustive: (),
+    };
+    let stderr = options.stderr.clone();
+    let runner = connection.runner();
+    let relaunch = |cmd: &process::Command| relaunch(connection, &settings, &runner, cmd, &options);
+    let first_relaunch = relaunch(&cmd);
+    let pending: collections::VecDeque<_> = cmds
+        .into_iter()
+        .map(|cmd| {
+            let relaunch = relaunch(&cmd);
+            (cmd, relaunch)
+        })
+        .collect();
+    let (command, files) = trace::describe(&cmd, options.input.as_deref());
+    let server = settings.port.as_deref().or_else(|| connection.port());
+    let mut trace = trace::Trace::new(&command, files, server);
+    let mut observation = connection
+        .observer()
+        .map(|observer| observer::Observation::new(observer.clone(), &command));
+    if let Some(ref mut observation) = observation {
+        observation.start();
+    }
+    let chunks = if pending.is_empty() {
+        None
+    } else {
+        Some(Chunks {
+            runner: runner.clone(),
+            options: options.clone(),
+            pending,
+            exit: None,
+        })
+    };
+    let source = match runner.run(cmd, options) {
+        Ok(source) => source,
+        Err(e) => {
+            if let Some(ref observation) = observation {
+                observation.complete(&error::OperationError::stopped(
+                    error::ErrorKind::SpawnFailed,
+                ));
+            }
+            return Err(error::ErrorKind::SpawnFailed
+                .error()
+                .set_cause(e)
+                .set_context(context));
+        }
+    };
+    trace.spawned(1);
+    let mut items = Items::new(source, parser, context);
+    items.trace = trace;
+    items.observation = observation;
+    items.relaunch = first_relaunch;
+    items.chunks = chunks;
+    items.cancel = cancel;
+    items.stderr = stderr;
+    items.progress = settings.progress;
+    items.format = settings.format;
+    items.diagnostics = connection.verbosity().map(|_| cell::RefCell::default());
+    Ok(items)
+}
+
+/// How to re-run `cmd` if it fails, if the connection wants it re-run.
+fn relaunch(
+    connection: &p4::P4,
+    settings: &Settings,
+    runner: &sync::Arc<dyn runner::P4Runner>,
+    cmd: &process::Command,
+    options: &runner::RunOptions,
+) -> Option<Relaunch> {
+    // Logging in and trusting are only understood through `-s` output.
+    let tagged = Settings {
+        format: Format::Tagged,
+        ..settings.clone()
+    };
+    let logs_in = connection.password_mode() == p4::PasswordMode::Login;
+    let relogin = if connection.auto_login() || logs_in {
+        let mut cmd = connection.connect_login(&tagged);
+        cmd.arg("login");
+        Some(Relogin {
+            cmd,
+            password: settings
+                .password
+                .clone()
+                .or_else(|| connection.password().map(str::to_owned)),
+            credentials: connection.credentials().cloned(),
+        })
+    } else {
+        None
+    };
+    let retrust = connection.trusted_fingerprint().map(|fingerprint| {
+        let mut cmd = connection.connect(&tagged);
+        cmd.args(&["trust", "-f", "-i", fingerprint]);
+        cmd
+    });
+    let policy = connection.retry_policy().cloned();
+    let unicode = connection.detected_unicode().cloned();
+    if policy.is_none() && relogin.is_none() && retrust.is_none() && unicode.is_none() {
+        return None;
+    }
+    Some(Relaunch {
+        runner: runner.clone(),
+        cmd: clone_command(cmd),
+        options: options.clone(),
+        policy,
+        relogin,
+        retrust,
+        unicode,
+        attempt: 1,
+        held: Vec::new(),
+    })
+}
+
+/// Raw bytes following a record, like `print`'s binary content, read a piece at a time.
+///
+/// Only the piece at hand is held in memory, however large the content is.
+pub(crate) struct Payload<T> {
+    /// How many raw bytes follow `record`, if any.
+    pub(crate) len: fn(&T) -> Option<usize>,
+    /// Records starting with this follow instead when the content isn't raw, e.g. `text: `.
+    pub(crate) unless: &'static [u8],
+    /// Wrap a piece of the raw bytes as a record.
+    pub(crate) piece: fn(&[u8]) -> T,
+}
+
+/// Commands still to run once the current one is done, for file lists too long for one run.
+struct Chunks {
+    runner: sync::Arc<dyn runner::P4Runner>,
+    options: runner::RunOptions,
+    pending: collections::VecDeque<(process::Command, Option<Relaunch>)>,
+    /// The first failing exit status, or the last successful one.
+    exit: Option<error::OperationError>,
+}
+
+/// `process::Command` isn't `Clone`, so rebuild it for another attempt.
+fn clone_command(cmd: &process::Command) -> process::Command {
+    rebuild(cmd, cmd.get_args())
+}
+
+/// Rebuild `cmd` with different arguments.
+fn rebuild<I, S>(cmd: &process::Command, args: I) -> process::Command
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<ffi::OsStr>,
+{
+    let mut clone = process::Command::new(cmd.get_program());
+    clone.args(args);
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => clone.env(key, value),
+            None => clone.env_remove(key),
+        };
+    }
+    if let Some(dir) = cmd.get_current_dir() {
+        clone.current_dir(dir);
+    }
+    clone
+}
+
+/// What's needed to re-run a command that failed before reporting data.
+struct Relaunch {
+    runner: sync::Arc<dyn runner::P4Runner>,
+    cmd: process::Command,
+    options: runner::RunOptions,
+    policy: Option<retry::RetryPolicy>,
+    /// Taken once it's been used.
+    relogin: Option<Relogin>,
+    /// `p4 trust` for the pinned fingerprint, taken once it's been used.
+    retrust: Option<process::Command>,
+    /// Where to record the server's unicode mode, taken once it's been switched.
+    unicode: Option<sync::Arc<sync::Mutex<Option<bool>>>>,
+    attempt: usize,
+    /// Messages from the current attempt, reported only if it isn't retried.
+    held: Vec<error::Message>,
+}
+
+impl Relaunch {
+    /// Log in again, reporting whether it succeeded.
+    fn login(&self, relogin: &Relogin) -> bool {
+        let password = match relogin.password() {
+            Some(password) => password,
+            None => return false,
+        };
+        let input = format!("{}\n", password).into_bytes();
+        self.repair(&relogin.cmd, Some(input))
+    }
+
+    /// Switch whether `-C utf8` is passed, as the server is in the other mode.
+    fn switch_unicode(&mut self, detected: &sync::Mutex<Option<bool>>) {
+        let mut args: Vec<_> = self.cmd.get_args().map(ffi::OsStr::to_owned).collect();
+        let at = unicode_at(&args);
+        let unicode = at.is_some();
+        match at {
+            Some(at) => {
+                args.drain(at..at + p4::UNICODE.len());
+            }
+            None => {
+                let flag = p4::UNICODE.iter().map(ffi::OsString::from);
+                args.splice(1..1, flag);
+            }
+        }
+        self.cmd = rebuild(&self.cmd, args);
+        *detected
+            .lock()
+            .unwrap_or_else(sync::PoisonError::into_inner) = Some(!unicode);
+    }
+
+    /// Run a command fixing why the last attempt failed, reporting whether it succeeded.
+    fn repair(&self, cmd: &process::Command, input: Option<Vec<u8>>) -> bool {
+        let options = runner::RunOptions {
+            input,
+            timeout: self.options.timeout,
+            cancel: self.options.cancel.clone(),
+            stderr: runner::Stderr::default(),
+            non_exhaustive: (),
+        };
+        let context = format!("Command: {}", redact::command(cmd));
+        match self.runner.run(clone_command(cmd), options) {
+            Ok(source) => {
+                let mut items = Items::new(source, any_item, context);
+                // Skip any `-v` trace.
+                items.diagnostics = Some(cell::RefCell::default());
+                collect(items).is_ok()
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Where `-C utf8` is in `args`, if it's there.
+fn unicode_at(args: &[ffi::OsString]) -> Option<usize> {
+    args.windows(p4::UNICODE.len())
+        .position(|window| window.iter().zip(&p4::UNICODE).all(|(a, u)| a == u))
+}
+
+/// How to log in again once a command's session expired.
+struct Relogin {
+    cmd: process::Command,
+    password: Option<String>,
+    credentials: Option<login::Credentials>,
+}
+
+impl Relogin {
+    fn password(&self) -> Option<String> {
+        self.credentials
+            .as_ref()
+            .and_then(login::Credentials::password)
+            .or_else(|| self.password.clone())
+    }
+}
+
+named!(any_item<&[u8], error::Item<()>>,
+    alt!(
+        map!(parser::record, |_| parser::data_to_item(())) |
+        map!(parser::error, parser::error_to_item) |
+        map!(parser::info, parser::info_to_item)
+    )
+);
+
+/// Arguments for `p4 -x -`, one per line.
+pub(crate) fn arg_file<S: AsRef<str>>(args: &[S]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for arg in args {
+        input.extend_from_slice(arg.as_ref().as_bytes());
+        input.push(b'\n');
+    }
+    input
+}
+
+/// Hand each data record to `visit` while it is still in the output buffer, failing like
+/// `collect`.
+///
+/// `items` only needs to recognize records; `visit` parses them without copying out of the
+/// buffer.
+pub(crate) fn visit<F>(mut items: Items<()>, mut visit: F) -> Result<(), error::P4Error>
+where
+    F: FnMut(&[u8]),
+{
+    let mut rest = Vec::new();
+    while let Some(item) = items.next() {
+        match item {
+            error::Item::Data(()) => visit(items.record()),
+            item => rest.push(item),
+        }
+    }
+    collect(rest).map(|_| ())
+}
+
+/// Like `collect`, where a file pattern matching nothing, e.g. a directory without
+/// subdirectories, isn't a failure.
+pub(crate) fn collect_found<T, I>(items: I) -> Result<Vec<T>, error::P4Error>
+where
+    I: IntoIterator<Item = error::Item<T>>,
+{
+    collect_unmatched(items, " - no such file(s).")
+}
+
+/// Like `collect`, dropping messages ending with `suffix` and the failure they caused.
+pub(crate) fn collect_unmatched<T, I>(items: I, suffix: &str) -> Result<Vec<T>, error::P4Error>
+where
+    I: IntoIterator<Item = error::Item<T>>,
+{
+    let mut unmatched = false;
+    let mut found = Vec::new();
+    for item in items {
+        match item {
+            error::Item::Message(ref m) if m.text().ends_with(suffix) => {
+                unmatched = true;
+            }
+            error::Item::Error(ref e) if unmatched && e.code() == 1 => {
+                found.push(error::Item::Error(error::OperationError::new(0)));
+            }
+            item => found.push(item),
+        }
+    }
+    collect(found)
+}
+
+/// Collect a command's data, failing with everything it reported if it reported an error.
+pub(crate) fn collect<T, I>(items: I) -> Result<Vec<T>, error::P4Error>
+where
+    I: IntoIterator<Item = error::Item<T>>,
+{
+    let mut data = Vec::new();
+    let mut messages = Vec::new();
+    let mut failed = false;
+    let mut exit = None;
+    for item in items {
+        match item {
+            error::Item::Data(d) => data.push(d),
+            error::Item::Message(m) => {
+                failed |= m.level.is_failure();
+                messages.push(m);
+            }
+            error::Item::Error(e) => exit = Some(e),
+            error::Item::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+    match exit {
+        Some(e) if e.code() == 0 && !failed => Ok(data),
+        Some(e) => {
+            let diagnostics = e.diagnostics().map(str::to_owned);
+            let stderr = e.stderr().map(str::to_owned);
+            Err(e
+                .kind()
+                .unwrap_or(error::ErrorKind::CommandFailed)
+                .error()
+                .set_diagnostics(diagnostics)
+                .set_stderr(stderr)
+                .set_cause(e)
+                .set_messages(messages))
+        }
+        None => Err(error::ErrorKind::ParseFailed
+            .error()
+            .set_context("Output ended without an exit status")
+            .set_messages(messages)),
+    }
+}
+
+/// Records parsed incrementally from `p4 -s` output.
+///
+/// Only as much output as is needed for the next record is held in memory.
+pub(crate) struct Items<T> {
+    source: Box<dyn Read + Send>,
+    parser: ItemParser<T>,
+    context: String,
+    buffer: Vec<u8>,
+    consumed: usize,
+    /// Where the last data record is in `buffer`, until more output is read.
+    record: ops::Range<usize>,
+    eof: bool,
+    done: bool,
+    relaunch: Option<Relaunch>,
+    chunks: Option<Chunks>,
+    payload: Option<Payload<T>>,
+    /// Raw bytes of the current payload still to be read.
+    raw: usize,
+    /// Whether the current payload might be records after all.
+    undecided: bool,
+    released: collections::VecDeque<error::Item<T>>,
+    cancel: Option<cancel::CancellationToken>,
+    stderr: runner::Stderr,
+    /// Items ending the current attempt.
+    ending: collections::VecDeque<error::Item<T>>,
+    /// The failure the current attempt's error messages describe.
+    recognized: Option<error::ErrorKind>,
+    progress: Option<progress::Reporter>,
+    format: Format,
+    /// The `-v` trace, when requested.
+    diagnostics: Option<cell::RefCell<String>>,
+    trace: trace::Trace,
+    observation: Option<observer::Observation>,
+}
+
+impl<T> Items<T> {
+    pub(crate) fn new(
+        source: Box<dyn Read + Send>,
+        parser: ItemParser<T>,
+        context: String,
+    ) -> Self {
+        Self {
+            source,
+            parser,
+            context,
+            buffer: Vec::new(),
+            consumed: 0,
+            record: 0..0,
+            eof: false,
+            done: false,
+            relaunch: None,
+            chunks: None,
+            payload: None,
+            raw: 0,
+            undecided: false,
+            released: collections::VecDeque::new(),
+            cancel: None,
+            stderr: runner::Stderr::default(),
+            ending: collections::VecDeque::new(),
+            recognized: None,
+            progress: None,
+            format: Format::Tagged,
+            diagnostics: None,
+            trace: trace::Trace::none(),
+            observation: None,
+        }
+    }
+
+    /// Read the raw bytes described by `payload` a piece at a time, rather than parsing them.
+    pub(crate) fn set_payload(&mut self, payload: Payload<T>) {
+        self.payload = Some(payload);
+    }
+
+    /// Start parsing a new attempt's output.
+    fn restart(&mut self, source: Box<dyn Read + Send>) {
+        self.source = source;
+        self.buffer.clear();
+        self.consumed = 0;
+        self.record = 0..0;
+        self.raw = 0;
+        self.eof = false;
+        self.done = false;
+        self.stderr.take();
+        self.ending.clear();
+        self.recognized = None;
+    }
+
+    /// Stop retrying, reporting the held messages ahead of `item`.
+    fn release(&mut self, item: Option<error::Item<T>>) -> Option<error::Item<T>> {
+        if let Some(relaunch) = self.relaunch.take() {
+            self.released
+                .extend(relaunch.held.into_iter().map(error::Item::Message));
+        }
+        self.released.extend(item);
+        self.released.pop_front()
+    }
+
+    /// The bytes of the data record last returned, borrowed from the output buffer.
+    ///
+    /// Only valid until `next` is called again.
+    pub(crate) fn record(&self) -> &[u8] {
+        &self.buffer[self.record.clone()]
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        if self.consumed != 0 {
+            self.buffer.drain(0..self.consumed);
+            self.consumed = 0;
+            self.record = 0..0;
+        }
+        // Grow geometrically so re-parsing a large record stays linear.
+        let len = self.buffer.len();
+        let read = MIN_READ.max(len);
+        self.buffer.resize(len + read, 0);
+        let result = loop {
+            match self.source.read(&mut self.buffer[len..]) {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let read = *result.as_ref().unwrap_or(&0);
+        self.buffer.truncate(len + read);
+        if read == 0 {
+            self.eof = true;
+        } else {
+            self.trace.received();
+            if let Some(ref mut observation) = self.observation {
+                observation.read(read);
+            }
+        }
+        result.map(|_| ())
+    }
+
+    /// Parse the next record, `None` if it was a progress indicator.
+    fn parse(&self) -> Parsed<'_, T> {
+        let input = &self.buffer[self.consumed..];
+        let (remaining, item) = match (self.parser)(input) {
+            Err(nom::Err::Error(e)) => match parser::exit(input) {
+                Err(nom::Err::Error(_)) => return self.parse_untagged(input, nom::Err::Error(e)),
+                exit => {
+                    let (remaining, exit) = exit?;
+                    (remaining, Some(parser::exit_to_item(exit)))
+                }
+            },
+            result => {
+                let (remaining, item) = result?;
+                (remaining, Some(item))
+            }
+        };
+        Ok((input.len() - remaining.len(), item))
+    }
+
+    /// Parse output that isn't a record: progress indicators, the `-v` trace and, for `-ztag`,
+    /// plain text.
+    fn parse_untagged<'a>(&self, input: &'a [u8], error: nom::Err<&'a [u8]>) -> Parsed<'a, T> {
+        if let Some(ref reporter) = self.progress {
+            match parser::progress(input) {
+                Ok((remaining, progress)) => {
+                    reporter.report(&progress);
+                    return Ok((input.len() - remaining.len(), None));
+                }
+                Err(nom::Err::Error(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if self.format == Format::Tagged {
+            let diagnostics = match self.diagnostics {
+                Some(ref diagnostics) => diagnostics,
+                None => return Err(error),
+            };
+            let (remaining, line) = parser::untagged(input)?;
+            let mut diagnostics = diagnostics.borrow_mut();
+            diagnostics.push_str(line.msg);
+            diagnostics.push('\n');
+            return Ok((input.len() - remaining.len(), None));
+        }
+        // Records are separated by blank lines.
+        if let Ok((remaining, _)) = parser::newline(input) {
+            return Ok((input.len() - remaining.len(), None));
+        }
+        let (remaining, info) = parser::untagged(input)?;
+        Ok((
+            input.len() - remaining.len(),
+            Some(parser::info_to_item(info)),
+        ))
+    }
+
+    /// End the attempt with `error`, reporting what `p4` wrote to stderr ahead of it.
+    fn end(&mut self, error: error::OperationError) -> Option<error::Item<T>> {
+        self.done = true;
+        let error = error.set_diagnostics(self.take_diagnostics());
+        self.complete(&error);
+        let stderr = self.stderr.take();
+        if error.code() == 0 {
+            return Some(error::Item::Error(error));
+        }
+        self.report(&stderr);
+        let error = error.set_kind(self.recognized).set_stderr(stderr);
+        self.ending.push_back(error::Item::Error(error));
+        self.ending.pop_front()
+    }
+
+    /// Report how the attempt went.
+    fn complete(&self, error: &error::OperationError) {
+        self.trace.completed(error);
+        if let Some(ref observation) = self.observation {
+            observation.complete(error);
+        }
+    }
+
+    fn take_diagnostics(&mut self) -> Option<String> {
+        self.diagnostics.as_ref().map(|d| d.replace(String::new()))
+    }
+
+    /// Queue each line of `stderr` as an error message.
+    fn report(&mut self, stderr: &str) {
+        for line in stderr.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            self.recognized = self.recognized.or_else(|| error::recognize(line, None));
+            self.ending
+                .push_back(error::Item::Message(error::Message::new(
+                    error::MessageLevel::Error,
+                    line.to_owned(),
+                )));
+        }
+    }
+
+    fn failure(&mut self, kind: error::ErrorKind, msg: &str) -> Option<error::Item<T>> {
+        self.ending
+            .push_back(error::Item::Message(error::Message::new(
+                error::MessageLevel::Error,
+                format!("{}\n{}", msg, self.context),
+            )));
+        let stderr = self.stderr.take();
+        self.report(&stderr);
+        let kind = self.recognized.unwrap_or(kind);
+        self.end(error::OperationError::stopped(kind).set_stderr(stderr))
+    }
+}
+
+impl<T> Iterator for Items<T> {
+    type Item = error::Item<T>;
+
+    fn next(&mut self) -> Option<error::Item<T>> {
+        loop {
+            let exit = match self.next_command_item() {
+                Some(error::Item::Error(e)) if self.chunks.is_some() && e.code() >= 0 => e,
+                item => return item,
+            };
+            let (next, runner, options) = {
+                let chunks = self.chunks.as_mut().expect("checked above");
+                chunks.exit = match chunks.exit.take() {
+                    Some(first) if first.code() != 0 => Some(first),
+                    _ => Some(exit),
+                };
+                (
+                    chunks.pending.pop_front(),
+                    chunks.runner.clone(),
+                    chunks.options.clone(),
+                )
+            };
+            let (cmd, relaunch) = match next {
+                Some(next) => next,
+                None => {
+                    let chunks = self.chunks.take().expect("checked above");
+                    return chunks.exit.map(error::Item::Error);
+                }
+            };
+            self.context = format!("Command: {}", redact::command(&cmd));
+            if let Some(ref mut observation) = self.observation {
+                observation.start();
+            }
+            match runner.run(cmd, options) {
+                Ok(source) => {
+                    self.restart(source);
+                    self.trace.spawned(1);
+                    self.relaunch = relaunch;
+                }
+                Err(e) => {
+                    self.chunks = None;
+                    let msg = format!("{}: {}", error::ErrorKind::SpawnFailed, e);
+                    self.restart(Box::new(io::empty()));
+                    return self.failure(error::ErrorKind::SpawnFailed, &msg);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Items<T> {
+    /// The next record from the current command, retrying it as needed.
+    fn next_command_item(&mut self) -> Option<error::Item<T>> {
+        if let Some(item) = self.released.pop_front() {
+            return Some(item);
+        }
+        loop {
+            let item = self.next_attempt_item();
+            let mut relaunch = match (item, self.relaunch.take()) {
+                (Some(error::Item::Message(msg)), Some(mut relaunch)) => {
+                    relaunch.held.push(msg);
+                    self.relaunch = Some(relaunch);
+                    continue;
+                }
+                (Some(error::Item::Error(e)), Some(mut relaunch)) if e.code() != 0 => {
+                    let retry = match e.kind() {
+                        Some(error::ErrorKind::AuthFailed) if relaunch.relogin.is_some() => {
+                            let relogin = relaunch.relogin.take().expect("checked above");
+                            relaunch.login(&relogin)
+                        }
+                        Some(error::ErrorKind::TrustRequired) if relaunch.retrust.is_some() => {
+                            let retrust = relaunch.retrust.take().expect("checked above");
+                            relaunch.repair(&retrust, None)
+                        }
+                        Some(error::ErrorKind::UnicodeMismatch) if relaunch.unicode.is_some() => {
+                            let detected = relaunch.unicode.take().expect("checked above");
+                            relaunch.switch_unicode(&detected);
+                            true
+                        }
+                        _ => match relaunch.policy {
+                            Some(ref policy) => {
+                                let retry =
+                                    policy.should_retry(relaunch.attempt, &e, &relaunch.held);
+                                if retry {
+                                    thread::sleep(policy.delay(relaunch.attempt));
+                                }
+                                retry
+                            }
+                            None => false,
+                        },
+                    };
+                    if !retry {
+                        self.relaunch = Some(relaunch);
+                        return self.release(Some(error::Item::Error(e)));
+                    }
+                    relaunch
+                }
+                (item, relaunch) => {
+                    self.relaunch = relaunch;
+                    return self.release(item);
+                }
+            };
+            relaunch.attempt += 1;
+            relaunch.held.clear();
+            if let Some(ref mut observation) = self.observation {
+                observation.start();
+            }
+            match relaunch
+                .runner
+                .run(clone_command(&relaunch.cmd), relaunch.options.clone())
+            {
+                Ok(source) => {
+                    self.restart(source);
+                    self.trace.spawned(relaunch.attempt);
+                    self.relaunch = Some(relaunch);
+                }
+                Err(e) => {
+                    let msg = format!("{}: {}", error::ErrorKind::SpawnFailed, e);
+                    self.restart(Box::new(io::empty()));
+                    return self.failure(error::ErrorKind::SpawnFailed, &msg);
+                }
+            }
+        }
+    }
+
+    /// The next record from the current attempt.
+    fn next_attempt_item(&mut self) -> Option<error::Item<T>> {
+        if let Some(item) = self.ending.pop_front() {
+            return Some(item);
+        }
+        if self.done {
+            return None;
+        }
+        loop {
+            if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+                // Dropping the output stops `p4`.
+                self.source = Box::new(io::empty());
+                self.done = true;
+                let e = error::OperationError::stopped(error::ErrorKind::Cancelled)
+                    .set_diagnostics(self.take_diagnostics());
+                self.complete(&e);
+                return Some(error::Item::Error(e));
+            }
+            if self.raw != 0 {
+                match self.next_piece() {
+                    Ok(Some(item)) => return Some(item),
+                    Ok(None) => continue,
+                    Err(e) => return self.read_failed(e),
+                }
+            }
+            let parsed = self.parse();
+            match parsed {
+                Ok((consumed, None)) => {
+                    self.consumed += consumed;
+                }
+                Ok((consumed, Some(item))) => {
+                    let start = self.consumed;
+                    self.consumed += consumed;
+                    match item {
+                        error::Item::Data(ref data) => {
+                            self.record = start..self.consumed;
+                            if let Some(ref payload) = self.payload {
+                                self.raw = (payload.len)(data).unwrap_or(0);
+                                self.undecided = true;
+                            }
+                        }
+                        error::Item::Error(e) => {
+                            // Run the source to completion so the process can be reaped.
+                            let _ = io::copy(&mut self.source, &mut io::sink());
+                            return self.end(e);
+                        }
+                        error::Item::Message(ref m) if m.level.is_failure() => {
+                            self.recognized = self
+                                .recognized
+                                .or_else(|| error::recognize(&m.msg, m.code()));
+                        }
+                        _ => {}
+                    }
+                    return Some(item);
+                }
+                Err(nom::Err::Incomplete(_)) if !self.eof => {
+                    if let Err(e) = self.fill() {
+                        return self.read_failed(e);
+                    }
+                }
+                Err(_) if self.eof && self.consumed == self.buffer.len() => {
+                    // `p4` died without reporting how, or `-ztag` output ended; stderr may say why.
+                    self.done = true;
+                    let stderr = self.stderr.take();
+                    if stderr.trim().is_empty() {
+                        if self.format == Format::Ztag {
+                            return self.end(error::OperationError::new(0));
+                        }
+                        self.complete(&error::OperationError::stopped(
+                            error::ErrorKind::ParseFailed,
+                        ));
+                        return None;
+                    }
+                    self.report(&stderr);
+                    let kind = match self.format {
+                        Format::Tagged => error::ErrorKind::ParseFailed,
+                        Format::Ztag => error::ErrorKind::CommandFailed,
+                    };
+                    let kind = self.recognized.unwrap_or(kind);
+                    return self.end(error::OperationError::stopped(kind).set_stderr(stderr));
+                }
+                Err(_) => {
+                    let msg = error::ErrorKind::ParseFailed.to_string();
+                    return self.failure(error::ErrorKind::ParseFailed, &msg);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Items<T> {
+    /// The next piece of the current payload, `None` if more output is needed first.
+    ///
+    /// A payload cut short by the end of the output is left for parsing to report.
+    fn next_piece(&mut self) -> io::Result<Option<error::Item<T>>> {
+        let available = self.buffer.len() - self.consumed;
+        if self.undecided {
+            let unless = self
+                .payload
+                .as_ref()
+                .expect("only raw with a payload")
+                .unless;
+            let input = &self.buffer[self.consumed..];
+            if available < unless.len() && unless.starts_with(input) && !self.eof {
+                self.fill()?;
+                return Ok(None);
+            }
+            self.undecided = false;
+            if input.starts_with(unless) {
+                self.raw = 0;
+                return Ok(None);
+            }
+        }
+        if available == 0 {
+            if self.eof {
+                self.raw = 0;
+            } else {
+                self.fill()?;
+            }
+            return Ok(None);
+        }
+        let start = self.consumed;
+        let len = available.min(self.raw);
+        self.consumed += len;
+        self.raw -= len;
+        self.record = start..self.consumed;
+        let piece = self
+            .payload
+            .as_ref()
+            .expect("only raw with a payload")
+            .piece;
+        Ok(Some(error::Item::Data(piece(
+            &self.buffer[start..self.consumed],
+        ))))
+    }
+
+    /// End the attempt after failing to read `p4`'s output.
+    fn read_failed(&mut self, e: io::Error) -> Option<error::Item<T>> {
+        if e.kind() == io::ErrorKind::TimedOut {
+            let e = error::OperationError::stopped(error::ErrorKind::TimedOut);
+            return self.end(e);
+        }
+        let msg = format!("Failed to read P4 output: {}", e);
+        self.failure(error::ErrorKind::ParseFailed, &msg)
+    }
+}
+
+impl<T> fmt::Debug for Items<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Items")
+            .field("context", &self.context)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+/// Parse canned output, splitting off the trailing `exit:` record.
+#[cfg(test)]
+pub(crate) fn parse<T>(
+    output: &[u8],
+    parser: ItemParser<T>,
+) -> (Vec<error::Item<T>>, error::Item<T>) {
+    let source = Box::new(io::Cursor::new(output.to_vec()));
+    let mut items: Vec<_> = Items::new(source, parser, "test".to_owned()).collect();
+    let exit = items.pop().expect("output to include an exit record");
+    (items, exit)
+}
+
+/// Runner replaying canned output, recording each invocation.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct Recorder {
+    pub(crate) output: Vec<u8>,
+    /// Written to the command's stderr.
+    pub(crate) stderr: Vec<u8>,
+    pub(crate) calls: ::std::sync::Mutex<Vec<(Vec<String>, Option<Vec<u8>>)>>,
+}
+
+/// A connection replaying `output` for every command, and the `Recorder` behind it.
+#[cfg(test)]
+pub(crate) fn recorder(output: &[u8]) -> (sync::Arc<Recorder>, p4::P4) {
+    let runner = sync::Arc::new(Recorder {
+        output: output.to_vec(),
+        ..Default::default()
+    });
+    let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+    (runner, p4)
+}
+
+#[cfg(test)]
+impl runner::P4Runner for Recorder {
+    fn run(
+        &self,
+        cmd: process::Command,
+        options: runner::RunOptions,
+    ) -> io::Result<Box<dyn Read + Send>> {
+        let args = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        io::Write::write_all(&mut options.stderr.clone(), &self.stderr)?;
+        self.calls.lock().unwrap().push((args, options.input));
+        Ok(Box::new(io::Cursor::new(self.output.clone())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Write;
+
+    /// Hands out one byte at a time to exercise resuming a partial record.
+    struct Trickle(io::Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[0..len])
+        }
+    }
+
+    named!(key<&[u8], error::Item<String>>,
+        alt!(
+            map!(call!(parser::field, "key"), |k| parser::data_to_item(k.to_owned())) |
+            map!(parser::error, parser::error_to_item)
+        )
+    );
+
+    #[test]
+    fn items_resume_partial_records() {
+        let output = b"info1: key one\ninfo1: key two\nexit: 0\n".to_vec();
+        let source = Box::new(Trickle(io::Cursor::new(output)));
+        let items: Vec<_> = Items::new(source, key, "test".to_owned()).collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items[1].as_data(), Some(&"two".to_owned()));
+        assert_eq!(items[2].as_error(), Some(&error::OperationError::new(0)));
+        assert_eq!(items.len(), 3);
+    }
+
+    named!(sized<&[u8], error::Item<Vec<u8>>>,
+        alt!(
+            map!(call!(parser::field, "size"), |k| parser::data_to_item(k.as_bytes().to_vec())) |
+            map!(parser::error, parser::error_to_item)
+        )
+    );
+
+    fn sized_payload() -> Payload<Vec<u8>> {
+        Payload {
+            len: |d| str::from_utf8(d).ok().and_then(|d| d.parse().ok()),
+            unless: b"info1: ",
+            piece: |p| p.to_vec(),
+        }
+    }
+
+    #[test]
+    fn items_stream_payload() {
+        let mut output = b"info1: size 100000\n".to_vec();
+        output.extend((0..100_000).map(|i| i as u8));
+        output.extend_from_slice(b"info1: size 3\ninfo1: size 0\nexit: 0\n");
+        let source = Box::new(io::Cursor::new(output.clone()));
+        let mut items = Items::new(source, sized, "test".to_owned());
+        items.set_payload(sized_payload());
+        assert_eq!(items.next().unwrap().as_data(), Some(&b"100000".to_vec()));
+        let mut content = Vec::new();
+        while content.len() < 100_000 {
+            let piece = items.next().unwrap().as_data().unwrap().clone();
+            assert!(piece.len() <= MIN_READ);
+            content.extend(piece);
+        }
+        assert_eq!(&content[..], &output[19..100_019]);
+        assert!(items.buffer.capacity() < 4 * MIN_READ);
+        // Records take the place of raw content that starts like one.
+        assert_eq!(items.next().unwrap().as_data(), Some(&b"3".to_vec()));
+        assert_eq!(items.next().unwrap().as_data(), Some(&b"0".to_vec()));
+        assert_eq!(
+            items.next().unwrap().as_error(),
+            Some(&error::OperationError::new(0))
+        );
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn items_stream_payload_trickle() {
+        let output = b"info1: size 5\n1\x002\n3info1: size 1\ni\nexit: 0\n".to_vec();
+        let source = Box::new(Trickle(io::Cursor::new(output)));
+        let mut items = Items::new(source, sized, "test".to_owned());
+        items.set_payload(sized_payload());
+        let data: Vec<_> = items.filter_map(|i| i.as_data().cloned()).collect();
+        let content: Vec<u8> = data[1..6].concat();
+        assert_eq!(&content[..], b"1\x002\n3");
+        assert_eq!(data[7], b"i".to_vec());
+        assert_eq!(data.len(), 8);
+    }
+
+    /// Fails as a runner does once it has killed `p4` for running too long.
+    struct Expired;
+
+    impl Read for Expired {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "expired"))
+        }
+    }
+
+    #[test]
+    fn items_report_timeout() {
+        let items: Vec<_> = Items::new(Box::new(Expired), key, "test".to_owned()).collect();
+        let error = items[0].as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::TimedOut));
+        assert_eq!(items.len(), 1);
+    }
+
+    /// Replays a different output for each attempt.
+    #[derive(Debug)]
+    struct Attempts(sync::Mutex<collections::VecDeque<&'static [u8]>>);
+
+    impl runner::P4Runner for Attempts {
+        fn run(
+            &self,
+            _cmd: process::Command,
+            _options: runner::RunOptions,
+        ) -> io::Result<Box<dyn Read + Send>> {
+            let output = self.0.lock().unwrap().pop_front().unwrap();
+            Ok(Box::new(output))
+        }
+    }
+
+    fn retrying(attempts: Vec<&'static [u8]>) -> p4::P4 {
+        let policy = retry::RetryPolicy::new(3)
+            .set_backoff(time::Duration::from_millis(1))
+            .set_predicate(|e, _| e.code() == 1);
+        let runner = Attempts(sync::Mutex::new(attempts.into_iter().collect()));
+        p4::P4::new()
+            .set_retry_policy(Some(policy))
+            .set_runner(Some(sync::Arc::new(runner)))
+    }
+
+    #[test]
+    fn items_retry_until_success() {
+        let p4 = retrying(vec![
+            b"error: Connect to server failed\nexit: 1\n",
+            b"info1: key one\nexit: 0\n",
+        ]);
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items[1].as_error(), Some(&error::OperationError::new(0)));
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn split_long_file_lists() {
+        let mut cmd = process::Command::new("p4");
+        cmd.args(&["files", "-a", "//a", "//b", "//c"]);
+        let args = |cmd: &process::Command| -> Vec<String> {
+            cmd.get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(split(clone_command(&cmd), 3, 1024).len(), 1);
+        let cmds = split(cmd, 3, 18);
+        assert_eq!(args(&cmds[0]), vec!["files", "-a", "//a", "//b"]);
+        assert_eq!(args(&cmds[1]), vec!["files", "-a", "//c"]);
+        assert_eq!(cmds.len(), 2);
+    }
+
+    #[test]
+    fn items_merge_chunks() {
+        let runner = Attempts(sync::Mutex::new(
+            vec![
+                &b"info1: key one\nexit: 0\n"[..],
+                &b"error: //b - no such file(s).\nexit: 1\n"[..],
+                &b"info1: key three\nexit: 0\n"[..],
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(runner)));
+        let cmds = ["//a", "//b", "//c"]
+            .iter()
+            .map(|file| {
+                let mut cmd = process::Command::new("p4");
+                cmd.arg(file);
+                cmd
+            })
+            .collect();
+        let items: Vec<_> = launch(&p4, Settings::default(), cmds, None, key)
+            .unwrap()
+            .collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert!(items[1].as_message().is_some());
+        assert_eq!(items[2].as_data(), Some(&"three".to_owned()));
+        assert_eq!(items[3].as_error().unwrap().code(), 1);
+        assert_eq!(items.len(), 4);
+    }
+
+    #[test]
+    fn items_switch_unicode() {
+        let runner = Attempts(sync::Mutex::new(
+            vec![
+                &b"error: Unicode clients require a unicode enabled server.\nexit: 1\n"[..],
+                &b"info1: key one\nexit: 0\n"[..],
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(runner)));
+        let has_unicode = |cmd: &process::Command| {
+            let args: Vec<_> = cmd.get_args().map(ffi::OsStr::to_owned).collect();
+            unicode_at(&args).is_some()
+        };
+        let cmd = p4.connect(&Settings::default());
+        assert!(has_unicode(&cmd));
+        assert_eq!(
+            &cmd.get_args().collect::<Vec<_>>()[..3],
+            ["-Gs", "-C", "utf8"]
+        );
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items[1].as_error(), Some(&error::OperationError::new(0)));
+        let switched = p4.connect(&Settings::default());
+        assert!(!has_unicode(&switched));
+        assert!(!switched.get_args().any(|a| a == "-C" || a == "utf8"));
+
+        let p4 = p4.set_unicode(Some(true));
+        assert!(has_unicode(&p4.connect(&Settings::default())));
+    }
+
+    #[test]
+    fn items_report_last_attempt() {
+        let p4 = retrying(vec![
+            b"error: first\nexit: 1\n",
+            b"error: second\nexit: 1\n",
+            b"error: third\nexit: 1\n",
+        ]);
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items[0].as_message().unwrap().msg, "third");
+        assert_eq!(items[1].as_error(), Some(&error::OperationError::new(1)));
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn items_retry_not_after_data() {
+        let p4 = retrying(vec![
+            b"info1: key one\nexit: 1\n",
+            b"info1: key two\nexit: 0\n",
+        ]);
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items[1].as_error(), Some(&error::OperationError::new(1)));
+        assert_eq!(items.len(), 2);
+    }
+
+    fn relogging(password: Option<&str>, attempts: Vec<&'static [u8]>) -> p4::P4 {
+        let runner = Attempts(sync::Mutex::new(attempts.into_iter().collect()));
+        p4::P4::new()
+            .set_password(password.map(str::to_owned))
+            .set_auto_login(true)
+            .set_runner(Some(sync::Arc::new(runner)))
+    }
+
+    #[test]
+    fn items_relogin_once() {
+        let expired = b"error: Your session has expired, please login again.\nexit: 1\n";
+        let p4 = relogging(
+            Some("secret"),
+            vec![expired, b"info: User alice logged in.\nexit: 0\n", expired],
+        );
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert!(items[0].as_message().is_some());
+        let error = items[1].as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::AuthFailed));
+        assert_eq!(items.len(), 2);
+
+        let p4 = relogging(
+            Some("secret"),
+            vec![
+                expired,
+                b"info: User alice logged in.\nexit: 0\n",
+                b"info1: key one\nexit: 0\n",
+            ],
+        );
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn items_login_over_stdin() {
+        let runner = sync::Arc::new(Recorder {
+            output: b"error: Perforce password (P4PASSWD) invalid or unset.\nexit: 1\n".to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new()
+            .set_password(Some("secret".to_owned()))
+            .set_password_mode(p4::PasswordMode::Login)
+            .set_runner(Some(runner.clone()));
+        let cmd = p4.connect(&Settings::default());
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items.last().unwrap().as_error().unwrap().code(), 1);
+
+        let calls = runner.calls.lock().unwrap();
+        assert!(calls
+            .iter()
+            .all(|(args, _)| !args.iter().any(|a| a == "secret")));
+        let (ref args, ref input) = calls[1];
+        assert_eq!(args.last().map(String::as_str), Some("login"));
+        assert_eq!(input.as_ref().unwrap(), b"secret\n");
+    }
+
+    #[test]
+    fn items_relogin_needs_password() {
+        let expired = b"error: Your session has expired, please login again.\nexit: 1\n";
+        let p4 = relogging(None, vec![expired]);
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        let error = items[1].as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::AuthFailed));
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn items_trust_pinned_fingerprint() {
+        let runner = Attempts(sync::Mutex::new(
+            vec![
+                &b"error: The authenticity of '10.0.0.2:1666' can't be established,\nexit: 1\n"[..],
+                &b"info: Added trust for P4PORT 'ssl:10.0.0.2:1666'\nexit: 0\n"[..],
+                &b"info1: key one\nexit: 0\n"[..],
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let p4 = p4::P4::new()
+            .set_trusted_fingerprint(Some("AB:CD".to_owned()))
+            .set_runner(Some(sync::Arc::new(runner)));
+        let cmd = process::Command::new("p4");
+        let items: Vec<_> = spawn(&p4, Settings::default(), cmd, key).unwrap().collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn items_report_cancelled() {
+        let p4 = retrying(vec![b"info1: key one\ninfo1: key two\nexit: 0\n"]);
+        let token = cancel::CancellationToken::new();
+        let settings = Settings {
+            cancel: Some(token.clone()),
+            ..Settings::default()
+        };
+        let mut items = spawn(&p4, settings, process::Command::new("p4"), key).unwrap();
+        assert_eq!(items.next().unwrap().as_data(), Some(&"one".to_owned()));
+        token.cancel();
+        let error = items.next().unwrap();
+        let error = error.as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::Cancelled));
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn items_report_garbage() {
+        let (items, last) = parse(b"info1: key one\nbogus\n", key);
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert!(items[1].as_message().is_some());
+        let error = last.as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::ParseFailed));
+    }
+
+    #[test]
+    fn items_recognize_messages() {
+        let output = b"error: Perforce password (P4PASSWD) invalid or unset.\nexit: 1\n";
+        let (items, exit) = parse(output, key);
+        assert!(items[0].as_message().is_some());
+        let error = exit.as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::AuthFailed));
+
+        let output = b"error: Single sign-on on client failed: 'sso' trigger exited.\nexit: 1\n";
+        let (_, exit) = parse(output, key);
+        let error = exit.as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::SsoFailed));
+
+        let output = "error: code0 807732225 (sub 1 sysid 1 gen 37 args 0 sev 3 uniq 1)
+error: Echec de la connexion au serveur.
+exit: 1
+";
+        let (_, exit) = parse(output.as_bytes(), key);
+        let error = exit.as_error().unwrap();
+        assert_eq!(error.kind(), Some(error::ErrorKind::ConnectFailed));
+
+        let output = b"error: Request too large (over 50000); see 'p4 help maxresults'.\nexit: 1\n";
+        let (_, exit) = parse(output, key);
+        let error = exit.as_error().unwrap();
+        let limit = error::ResourceLimit::MaxResults(50000);
+        assert_eq!(
+            error.kind(),
+            Some(error::ErrorKind::ResultLimitExceeded(limit))
+        );
+
+        let output = b"error: Operation took too long (over 30.00 seconds); see 'p4 help maxlocktime'.\nexit: 1\n";
+        let (_, exit) = parse(output, key);
+        let error = exit.as_error().unwrap();
+        let limit = error::ResourceLimit::MaxLockTime(30000);
+        assert_eq!(
+            error.kind(),
+            Some(error::ErrorKind::ResultLimitExceeded(limit))
+        );
+    }
+
+    fn canned(output: &[u8]) -> Items<String> {
+        let source = Box::new(io::Cursor::new(output.to_vec()));
+        Items::new(source, key, "test".to_owned())
+    }
+
+    #[test]
+    fn items_report_progress() {
+        let reported = sync::Arc::new(sync::Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let mut items = canned(b"Syncing 1/2 files\rSyncing 2/2 files\ninfo1: key one\nexit: 0\n");
+        items.progress = Some(progress::Reporter::new(move |p| {
+            sink.lock().unwrap().push(p.done);
+        }));
+        let items: Vec<_> = items.collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        assert_eq!(items.len(), 2);
+        assert_eq!(*reported.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn items_capture_diagnostics() {
+        let output =
+            b"RpcRecvBuffer clientFoo\ninfo1: key one\n\nNetTcpTransport closed\nexit: 0\n";
+        let mut items = canned(output);
+        items.diagnostics = Some(cell::RefCell::default());
+        let items: Vec<_> = items.collect();
+        assert_eq!(items[0].as_data(), Some(&"one".to_owned()));
+        let exit = items[1].as_error().unwrap();
+        assert_eq!(
+            exit.diagnostics(),
+            Some("RpcRecvBuffer clientFoo\n\nNetTcpTransport closed\n")
+        );
+        assert_eq!(items.len(), 2);
+
+        let mut items = canned(b"rpc trace\nerror: bad\nexit: 1\n");
+        items.diagnostics = Some(cell::RefCell::default());
+        let error = collect(items).unwrap_err();
+        assert_eq!(error.diagnostics(), Some("rpc trace\n"));
+    }
+
+    #[test]
+    fn collect_data() {
+        let keys = collect(canned(b"info1: key one\ninfo1: key two\nexit: 0\n")).unwrap();
+        assert_eq!(keys, vec!["one".to_owned(), "two".to_owned()]);
+    }
+
+    #[test]
+    fn collect_failure() {
+        let error = collect(canned(b"info1: key one\nerror: bad\nexit: 1\n")).unwrap_err();
+        assert_eq!(error.kind(), error::ErrorKind::CommandFailed);
+        assert_eq!(error.messages()[0].msg, "bad");
+        assert_eq!(error.messages().len(), 1);
+
+        let error = collect(canned(b"info1: key one\n")).unwrap_err();
+        assert_eq!(error.kind(), error::ErrorKind::ParseFailed);
+    }
+
+    #[test]
+    fn items_report_stderr() {
+        let source = Box::new(io::Cursor::new(b"exit: 1\n".to_vec()));
+        let mut items = Items::new(source, key, "test".to_owned());
+        let mut stderr = runner::Stderr::default();
+        stderr
+            .write_all(b"Perforce client error:\n\tConnect to server failed; check $P4PORT.\n")
+            .unwrap();
+        items.stderr = stderr;
+        let items: Vec<_> = items.collect();
+        assert_eq!(items[0].as_message().unwrap().msg, "Perforce client error:");
+        assert_eq!(
+            items[1].as_message().unwrap().msg,
+            "Connect to server failed; check $P4PORT."
+        );
+        let error = items[2].as_error().unwrap();
+        assert_eq!(error.code(), 1);
+        assert_eq!(error.kind(), Some(error::ErrorKind::ConnectFailed));
+        assert_eq!(items.len(), 3);
+    }
+
+    #[test]
+    fn collect_keeps_stderr() {
+        let stderr: &[u8] = b"Perforce client error:\n\tConnect to server failed; check $P4PORT.\n";
+        let runner = sync::Arc::new(Recorder {
+            output: b"exit: 1\n".to_vec(),
+            stderr: stderr.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let error = p4.files("//depot/...").run_checked().unwrap_err();
+        assert_eq!(error.kind(), error::ErrorKind::ConnectFailed);
+        assert_eq!(error.stderr().map(str::as_bytes), Some(stderr));
+
+        let error = collect(canned(b"error: Oops\nexit: 1\n")).unwrap_err();
+        assert_eq!(error.stderr(), None);
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt;
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Extract journal or checkpoint records
+///
+/// Exports journal records starting at `token` (a journal number and
+/// optional offset, e.g. `12/3456`), for replication or auditing.  Pass the
+/// `Exported::Token` from the end of one export into the next to continue
+/// incrementally.
+///
+/// Requires super access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let records = p4.export("12").set_max_lines(Some(1000)).run().unwrap();
+/// for record in records {
+///     println!("{:?}", record);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExportCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    token: &'f str,
+
+    checkpoint: bool,
+    formatted: bool,
+    filter: Option<&'f str>,
+    max_lines: Option<usize>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> ExportCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, token: &'f str) -> Self {
+        Self {
+            connection,
+            token,
+            checkpoint: false,
+            formatted: false,
+            filter: None,
+            max_lines: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -c flag exports a checkpoint rather than a journal (-j); `token`
+    /// is then the checkpoint number.
+    pub fn checkpoint(mut self, checkpoint: bool) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// The -f flag formats non-textual datatypes (e.g. dates) for
+    /// readability.
+    pub fn formatted(mut self, formatted: bool) -> Self {
+        self.formatted = formatted;
+        self
+    }
+
+    /// The -F filter flag limits the output to records matching the
+    /// filter expression, e.g. `table=db.rev`.
+    pub fn filter(mut self, filter: &'f str) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// The -l flag limits the output to approximately 'lines' journal
+    /// lines, ending with a token to continue from.
+    pub fn set_max_lines(mut self, max_lines: Option<usize>) -> Self {
+        self.max_lines = max_lines;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("export");
+        if self.checkpoint {
+            cmd.args(&["-c", self.token]);
+        } else {
+            cmd.args(&["-j", self.token]);
+        }
+        if self.formatted {
+            cmd.arg("-f");
+        }
+        if let Some(filter) = self.filter {
+            cmd.args(&["-F", filter]);
+        }
+        if let Some(max_lines) = self.max_lines {
+            let max_lines = format!("{}", max_lines);
+            cmd.args(&["-l", &max_lines]);
+        }
+        cmd
+    }
+
+    /// Run the `export` command.
+    pub fn run(self) -> Result<Exports, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Exports(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            export_parser::item,
+        )?))
+    }
+
+    /// Run the `export` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Exported>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ExportItem = error::Item<Exported>;
+
+pub struct Exports(exec::Items<Exported>);
+
+impl IntoIterator for Exports {
+    type Item = ExportItem;
+    type IntoIter = ExportsIntoIter;
+
+    fn into_iter(self) -> ExportsIntoIter {
+        ExportsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportsIntoIter(exec::Items<Exported>);
+
+impl Iterator for ExportsIntoIter {
+    type Item = ExportItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ExportItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// Output of `export`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Exported {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Record(Record),
+    /// Where to continue the next export from.
+    Token(String),
+}
+
+/// A raw journal record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Record {
+    /// The table, e.g. `db.rev`.
+    pub table: Option<String>,
+    /// The journal operation, e.g. `pv` (put value) or `dv` (delete value).
+    pub op: String,
+    /// The remaining columns, in order.
+    pub fields: Vec<(String, String)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod export_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_record((op, fields): (&str, Vec<Field>)) -> Result<Record, fmt::Error> {
+        let mut record = Record {
+            table: None,
+            op: op.to_owned(),
+            fields: vec![],
+            non_exhaustive: (),
+        };
+        for field in fields {
+            match field.key {
+                "table" => record.table = Some(field.value.to_owned()),
+                key => record.fields.push((key.to_owned(), field.value.to_owned())),
+            }
+        }
+        Ok(record)
+    }
+
+    named!(record_field<&[u8], Field>,
+        verify!(any_field, |f: Field| f.key != "op" && f.key != "token")
+    );
+
+    named!(export_record<&[u8], Record>,
+        map_res!(
+            pair!(
+                call!(field, "op"),
+                many0!(record_field)
+            ),
+            to_record
+        )
+    );
+
+    named!(pub item<&[u8], ExportItem>,
+        alt!(
+            map!(export_record, |r| data_to_item(Exported::Record(r))) |
+            map!(call!(field, "token"), |t| data_to_item(Exported::Token(t.to_owned()))) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn export_records() {
+        let output: &[u8] = br#"info1: op pv
+info1: table db.counters
+info1: name change
+info1: value 42
+info1: op pv
+info1: table db.counters
+info1: name journal
+info1: value 12
+info1: token 12/3456
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, export_parser::item);
+        match items[0].as_data().unwrap() {
+            Exported::Record(record) => {
+                assert_eq!(record.table, Some("db.counters".to_owned()));
+                assert_eq!(record.op, "pv");
+                assert_eq!(
+                    record.fields,
+                    vec![
+                        ("name".to_owned(), "change".to_owned()),
+                        ("value".to_owned(), "42".to_owned()),
+                    ]
+                );
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert!(items[1].as_data().is_some());
+        assert_eq!(
+            items[2].as_data(),
+            Some(&Exported::Token("12/3456".to_owned()))
+        );
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
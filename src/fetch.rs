@@ -0,0 +1,249 @@
+use std::borrow;
+use std::fmt;
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Copy files and history from another server into this one
+///
+/// Fetches the changes matching the remote spec's DepotMap (or the file
+/// argument) from the remote server into the personal server.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let changes = p4.fetch().remote("origin").run().unwrap();
+/// for change in changes {
+///     println!("{:?}", change);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FetchCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    file: Vec<borrow::Cow<'f, str>>,
+
+    remote: Option<&'f str>,
+    depth: Option<usize>,
+    preview: bool,
+    verbose: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> FetchCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            file: vec![],
+            remote: None,
+            depth: None,
+            preview: false,
+            verbose: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Restrict fetching to files matching this file argument.  Can be
+    /// repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// The -r flag specifies the remote spec to fetch from, instead of
+    /// 'origin'.
+    pub fn remote(mut self, remote: &'f str) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// The -m flag fetches only the last 'depth' changes.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// The -n flag displays what would be fetched without fetching it.
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// The -v flag reports each change as it is fetched.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("fetch");
+        if let Some(remote) = self.remote {
+            cmd.args(&["-r", remote]);
+        }
+        if let Some(depth) = self.depth {
+            let depth = format!("{}", depth);
+            cmd.args(&["-m", &depth]);
+        }
+        if self.preview {
+            cmd.arg("-n");
+        }
+        if self.verbose {
+            cmd.arg("-v");
+        }
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `fetch` command.
+    pub fn run(self) -> Result<Transfers, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Transfers(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            fetch_parser::item,
+        )?))
+    }
+
+    /// Run the `fetch` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Transfer>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type TransferItem = error::Item<Transfer>;
+
+pub struct Transfers(pub(crate) exec::Items<Transfer>);
+
+impl IntoIterator for Transfers {
+    type Item = TransferItem;
+    type IntoIter = TransfersIntoIter;
+
+    fn into_iter(self) -> TransfersIntoIter {
+        TransfersIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct TransfersIntoIter(exec::Items<Transfer>);
+
+impl Iterator for TransfersIntoIter {
+    type Item = TransferItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<TransferItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A change copied between servers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transfer {
+    /// The change number on the server that sent it.
+    pub change: usize,
+    /// The change number on the server that received it, when renumbered.
+    pub new_change: Option<usize>,
+    /// Number of file revisions in the change.
+    pub files: Option<usize>,
+    pub description: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+pub(crate) mod fetch_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_usize(value: &str) -> Result<usize, fmt::Error> {
+        value.parse().map_err(|_| fmt::Error)
+    }
+
+    fn to_transfer(fields: Vec<Field>) -> Result<Transfer, fmt::Error> {
+        let mut change = None;
+        let mut new_change = None;
+        let mut files = None;
+        let mut description = None;
+        for field in fields {
+            match field.key {
+                "change" => change = Some(to_usize(field.value)?),
+                "newChange" => new_change = Some(to_usize(field.value)?),
+                "files" => files = Some(to_usize(field.value)?),
+                "desc" => description = Some(field.value.to_owned()),
+                _ => (),
+            }
+        }
+        let transfer = Transfer {
+            change: change.ok_or(fmt::Error)?,
+            new_change,
+            files,
+            description,
+            non_exhaustive: (),
+        };
+        Ok(transfer)
+    }
+
+    named!(pub item<&[u8], TransferItem>,
+        alt!(
+            map!(map_res!(record, to_transfer), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fetch_changes() {
+        let output: &[u8] = br#"info1: change 42
+info1: newChange 3
+info1: files 2
+info1: desc Fix crash
+info1: change 43
+info1: files 1
+info: 2 change(s) containing a total of 3 file revision(s) were successfully fetched.
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, fetch_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.change, 42);
+        assert_eq!(first.new_change, Some(3));
+        assert_eq!(first.files, Some(2));
+        assert_eq!(first.description, Some("Fix crash".to_owned()));
+        assert_eq!(last.change, 43);
+        assert_eq!(last.new_change, None);
+        assert!(items[2].as_message().is_some());
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
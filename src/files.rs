@@ -1,7 +1,10 @@
-use std::vec;
+use std::borrow;
+use std::process;
 
 use error;
+use exec;
 use p4;
+use paths;
 
 /// List files in the depot.
 ///
@@ -29,28 +32,49 @@ use p4;
 #[derive(Debug, Clone)]
 pub struct FilesCommand<'p, 'f> {
     connection: &'p p4::P4,
-    file: Vec<&'f str>,
+    file: Vec<borrow::Cow<'f, str>>,
+    batch_files: bool,
 
     list_revisions: bool,
     syncable_only: bool,
     ignore_case: bool,
     max: Option<usize>,
+    unloaded: bool,
+    archived: bool,
+    settings: exec::Settings,
 }
 
 impl<'p, 'f> FilesCommand<'p, 'f> {
     pub fn new(connection: &'p p4::P4, file: &'f str) -> Self {
         Self {
             connection,
-            file: vec![file],
+            file: vec![file.into()],
+            batch_files: false,
             list_revisions: false,
             syncable_only: false,
             ignore_case: false,
             max: None,
+            unloaded: false,
+            archived: false,
+            settings: exec::Settings::default(),
         }
     }
 
     pub fn file(mut self, file: &'f str) -> Self {
-        self.file.push(file);
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// Pass the file arguments on stdin (the global `-x -` option) rather
+    /// than the command line, avoiding OS limits on very long file lists.
+    pub fn batch_files(mut self, batch_files: bool) -> Self {
+        self.batch_files = batch_files;
         self
     }
 
@@ -82,9 +106,29 @@ impl<'p, 'f> FilesCommand<'p, 'f> {
         self
     }
 
-    /// Run the `files` command.
-    pub fn run(self) -> Result<Files, error::P4Error> {
-        let mut cmd = self.connection.connect_with_retries(None);
+    /// The -U flag displays files in the unload depot (see 'p4 help unload'
+    /// for more information about the unload depot).
+    pub fn unloaded(mut self, unloaded: bool) -> Self {
+        self.unloaded = unloaded;
+        self
+    }
+
+    /// The -A flag displays files in archive depots (see 'p4 help archive').
+    pub fn archived(mut self, archived: bool) -> Self {
+        self.archived = archived;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    ///
+    /// With `batch_files`, the files are fed over stdin rather than being arguments.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        if self.batch_files {
+            cmd.args(&["-x", "-"]);
+        }
         cmd.arg("files");
         if self.list_revisions {
             cmd.arg("-a");
@@ -98,40 +142,95 @@ impl<'p, 'f> FilesCommand<'p, 'f> {
         if let Some(max) = self.max {
             cmd.arg(format!("-m {}", max));
         }
-        for file in self.file {
-            cmd.arg(file);
+        if self.unloaded {
+            cmd.arg("-U");
+        }
+        if self.archived {
+            cmd.arg("-A");
+        }
+        if !self.batch_files {
+            for file in &self.file {
+                cmd.arg(file.as_ref());
+            }
+        }
+        cmd
+    }
+
+    /// Run the `files` command.
+    pub fn run(self) -> Result<Files, error::P4Error> {
+        Ok(Files(self.spawn(files_parser::item)?))
+    }
+
+    /// Run the `files` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<File>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+
+    /// Run the `files` command, handing each file to `visit` without copying it out of `p4`'s
+    /// output.
+    ///
+    /// For listings too large to hold an owned `File` per revision.  Fails like `run_checked`,
+    /// after every file has been visited.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let mut text = 0;
+    /// p4.files("//depot/...")
+    ///     .run_visit(|file| {
+    ///         if file.file_type.base == p4_cmd::BaseFileType::Text {
+    ///             text += 1;
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// println!("{} text files", text);
+    /// ```
+    pub fn run_visit<F>(self, mut visit: F) -> Result<(), error::P4Error>
+    where
+        F: FnMut(FileRef),
+    {
+        let items = self.spawn(files_parser::item_ref)?;
+        exec::visit(items, |record| {
+            let (_, file) = files_parser::file_ref(record).expect("recognized while reading");
+            visit(file)
+        })
+    }
+
+    fn spawn<T>(self, parser: exec::ItemParser<T>) -> Result<exec::Items<T>, error::P4Error> {
+        let cmd = self.to_command();
+        if self.batch_files {
+            let input = exec::arg_file(&self.file);
+            return exec::spawn_with_input(
+                self.connection,
+                self.settings,
+                cmd,
+                Some(input),
+                parser,
+            );
         }
-        let data = cmd.output().map_err(|e| {
-            error::ErrorKind::SpawnFailed
-                .error()
-                .set_cause(e)
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        let (_remains, (mut items, exit)) = files_parser::files(&data.stdout).map_err(|_| {
-            error::ErrorKind::ParseFailed
-                .error()
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        items.push(exit);
-        Ok(Files(items))
+        let files = self.file.len();
+        exec::spawn_files(self.connection, self.settings, cmd, files, parser)
     }
 }
 
 pub type FileItem = error::Item<File>;
 
-pub struct Files(Vec<FileItem>);
+pub struct Files(exec::Items<File>);
 
 impl IntoIterator for Files {
     type Item = FileItem;
     type IntoIter = FilesIntoIter;
 
     fn into_iter(self) -> FilesIntoIter {
-        FilesIntoIter(self.0.into_iter())
+        FilesIntoIter(self.0)
     }
 }
 
 #[derive(Debug)]
-pub struct FilesIntoIter(vec::IntoIter<FileItem>);
+pub struct FilesIntoIter(exec::Items<File>);
 
 impl Iterator for FilesIntoIter {
     type Item = FileItem;
@@ -153,8 +252,25 @@ impl Iterator for FilesIntoIter {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct File {
-    pub depot_file: String,
+    pub depot_file: paths::DepotPath,
+    pub rev: usize,
+    pub change: usize,
+    pub action: p4::Action,
+    pub file_type: p4::FileType,
+    pub time: p4::Time,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// A `File` borrowing its depot path from `p4`'s output, see `FilesCommand::run_visit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRef<'a> {
+    /// The depot path, with anything that isn't UTF-8 replaced.
+    pub depot_file: borrow::Cow<'a, str>,
+    /// The depot path exactly as reported.
+    pub depot_file_bytes: &'a [u8],
     pub rev: usize,
     pub change: usize,
     pub action: p4::Action,
@@ -163,7 +279,22 @@ pub struct File {
     non_exhaustive: (),
 }
 
-mod files_parser {
+impl<'a> FileRef<'a> {
+    /// Copy out of the output, e.g. to keep only some files.
+    pub fn to_file(&self) -> File {
+        File {
+            depot_file: paths::DepotPath::from_bytes(self.depot_file_bytes),
+            rev: self.rev,
+            change: self.change,
+            action: self.action.clone(),
+            file_type: self.file_type.clone(),
+            time: self.time,
+            non_exhaustive: (),
+        }
+    }
+}
+
+pub(crate) mod files_parser {
     use super::*;
 
     use super::super::parser::*;
@@ -178,7 +309,7 @@ mod files_parser {
             time: time >>
             (
                 File {
-                    depot_file: depot_file.path.to_owned(),
+                    depot_file: paths::DepotPath::from_bytes(depot_file.path),
                     rev: rev.rev,
                     change: change.change,
                     action: action.action.parse().expect("Unknown to capture all"),
@@ -190,18 +321,200 @@ mod files_parser {
         )
     );
 
-    named!(item<&[u8], FileItem>,
+    named!(pub file_ref<&[u8], FileRef<'_>>,
+        do_parse!(
+            depot_file: depot_file >>
+            rev: rev >>
+            change: change >>
+            action: action >>
+            file_type: file_type >>
+            time: time >>
+            (
+                FileRef {
+                    depot_file: String::from_utf8_lossy(depot_file.path),
+                    depot_file_bytes: depot_file.path,
+                    rev: rev.rev,
+                    change: change.change,
+                    action: action.action.parse().expect("Unknown to capture all"),
+                    file_type: file_type.ft.parse().expect("Unknown to capture all"),
+                    time: p4::from_timestamp(time.time),
+                    non_exhaustive: (),
+                }
+            )
+        )
+    );
+
+    named!(pub item_ref<&[u8], error::Item<()>>,
         alt!(
-            map!(file, data_to_item) |
+            map!(file_ref, |_| data_to_item(())) |
             map!(error, error_to_item) |
             map!(info, info_to_item)
         )
     );
 
-    named!(pub files<&[u8], (Vec<FileItem>, FileItem)>,
-        pair!(
-            many0!(item),
-            map!(exit, exit_to_item)
+    named!(pub item<&[u8], FileItem>,
+        alt!(
+            map!(file, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
         )
     );
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync;
+
+    #[test]
+    fn files_batch_files() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"exit: 0\n".to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+        let files = p4
+            .files("//depot/a")
+            .file("//depot/b")
+            .batch_files(true)
+            .run()
+            .unwrap();
+        assert_eq!(files.into_iter().count(), 1);
+
+        let calls = runner.calls.lock().unwrap();
+        let (ref args, ref input) = calls[0];
+        let batch = args.iter().position(|a| a == "-x").unwrap();
+        assert_eq!(&args[batch..], &["-x", "-", "files"]);
+        assert_eq!(input.as_ref().unwrap(), b"//depot/a\n//depot/b\n");
+    }
+
+    #[test]
+    fn files_to_command() {
+        let p4 = p4::P4::new().set_port(Some("perforce:1666".to_owned()));
+        let cmd = p4
+            .files("//depot/...")
+            .list_revisions(true)
+            .unloaded(true)
+            .archived(true)
+            .to_command();
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(
+            args,
+            [
+                "-Gs",
+                "-C",
+                "utf8",
+                "-p",
+                "perforce:1666",
+                "files",
+                "-a",
+                "-U",
+                "-A",
+                "//depot/..."
+            ]
+        );
+    }
+
+    #[test]
+    fn files_overrides() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"exit: 0\n".to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new()
+            .set_user(Some("alice".to_owned()))
+            .set_client(Some("alice-ws".to_owned()))
+            .set_runner(Some(runner.clone()));
+        let files = p4.files("//depot/a").as_user("buildbot").run().unwrap();
+        assert_eq!(files.into_iter().count(), 1);
+
+        let calls = runner.calls.lock().unwrap();
+        let (ref args, _) = calls[0];
+        let user = args.iter().position(|a| a == "-u").unwrap();
+        assert_eq!(args[user + 1], "buildbot");
+        let client = args.iter().position(|a| a == "-c").unwrap();
+        assert_eq!(args[client + 1], "alice-ws");
+    }
+
+    #[test]
+    fn files_run_visit() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"info1: depotFile //depot/caf\xe9\ninfo1: rev 3\ninfo1: change 42\n\
+info1: action edit\ninfo1: type text+x\ninfo1: time 1527128318\n\
+warning: //depot/b - no such file(s).\nexit: 0\n"
+                .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut visited = Vec::new();
+        p4.files("//depot/...")
+            .run_visit(|file| {
+                assert_eq!(file.depot_file, "//depot/caf\u{fffd}");
+                assert!(match file.depot_file {
+                    borrow::Cow::Owned(_) => true,
+                    borrow::Cow::Borrowed(_) => false,
+                });
+                visited.push(file.to_file());
+            })
+            .unwrap();
+        assert_eq!(visited.len(), 1);
+        assert_eq!(visited[0].depot_file.as_bytes(), b"//depot/caf\xe9");
+        assert_eq!(visited[0].rev, 3);
+        assert_eq!(visited[0].action, p4::Action::Edit);
+    }
+
+    #[test]
+    fn files_lists_purged_and_archived() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"info1: depotFile //depot/a\ninfo1: rev 2\ninfo1: change 7\n\
+info1: action purge\ninfo1: type binary+S\ninfo1: time 1527128318\n\
+info1: depotFile //depot/b\ninfo1: rev 1\ninfo1: change 5\n\
+info1: action archive\ninfo1: type binary\ninfo1: time 1527128318\nexit: 0\n"
+                .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+        let files = p4
+            .files("//depot/...")
+            .list_revisions(true)
+            .run_checked()
+            .unwrap();
+        let actions: Vec<_> = files.iter().map(|f| f.action.clone()).collect();
+        assert_eq!(actions, [p4::Action::Purge, p4::Action::Archive]);
+
+        let calls = runner.calls.lock().unwrap();
+        assert!(!calls[0].0.iter().any(|a| a == "-e"));
+    }
+
+    #[test]
+    fn files_run_visit_failure() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"error: //depot/a - protected\nexit: 1\n".to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let err = p4.files("//depot/a").run_visit(|_| panic!()).unwrap_err();
+        assert_eq!(err.messages()[0].text(), "//depot/a - protected");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn files_serde_round_trip() {
+        let output: &[u8] = br#"info1: depotFile //depot/dir/file
+info1: rev 3
+info1: change 42
+info1: action edit
+info1: type text+x
+info1: time 1527128318
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, files_parser::item);
+        let json = ::serde_json::to_string(&items[0]).unwrap();
+        let file: FileItem = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(file, items[0]);
+        let json = ::serde_json::to_string(&exit).unwrap();
+        let exit_back: FileItem = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(exit_back, exit);
+    }
+}
@@ -0,0 +1,339 @@
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// List groups (of users)
+///
+/// The default form lists all user groups defined in the server, or just
+/// the specified group.
+///
+/// When a name is given with `include_indirect`, subgroup relationships are
+/// displayed. If a user argument is specified, only groups containing that
+/// user are displayed. If a group argument is specified, only groups
+/// containing the group are displayed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let groups = p4.groups().name("alice").run().unwrap();
+/// for group in groups {
+///     println!("{:?}", group);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GroupsCommand<'p, 'n> {
+    connection: &'p p4::P4,
+    name: Option<&'n str>,
+
+    name_type: NameType,
+    include_indirect: bool,
+    include_limits: bool,
+    max: Option<usize>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'n> GroupsCommand<'p, 'n> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            name: None,
+            name_type: NameType::Any,
+            include_indirect: false,
+            include_limits: false,
+            max: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Restrict the operation to the specified user or group.
+    pub fn name(mut self, name: &'n str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// The -g, -u, and -o flags indicate that the 'name' argument is a
+    /// group, user, or owner.  This is useful when there are groups and
+    /// users with the same name, or when requesting all groups owned by a
+    /// certain user.
+    pub fn name_type(mut self, name_type: NameType) -> Self {
+        self.name_type = name_type;
+        self
+    }
+
+    /// The -i flag also displays groups that the user or group belongs to
+    /// indirectly by means of membership in subgroups.
+    pub fn include_indirect(mut self, include_indirect: bool) -> Self {
+        self.include_indirect = include_indirect;
+        self
+    }
+
+    /// The -v flag displays the MaxResults, MaxScanRows, MaxLockTime, and
+    /// Timeout values for each group that is displayed.
+    pub fn include_limits(mut self, include_limits: bool) -> Self {
+        self.include_limits = include_limits;
+        self
+    }
+
+    /// The -m max flag limits output to the specified number of groups.
+    pub fn set_max(mut self, max: Option<usize>) -> Self {
+        self.max = max;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("groups");
+        match self.name_type {
+            NameType::Group => {
+                cmd.arg("-g");
+            }
+            NameType::User => {
+                cmd.arg("-u");
+            }
+            NameType::Owner => {
+                cmd.arg("-o");
+            }
+            NameType::Any | NameType::__Nonexhaustive => (),
+        }
+        if self.include_indirect {
+            cmd.arg("-i");
+        }
+        if self.include_limits {
+            cmd.arg("-v");
+        }
+        if let Some(max) = self.max {
+            let max = format!("{}", max);
+            cmd.args(&["-m", &max]);
+        }
+        if let Some(name) = self.name {
+            cmd.arg(name);
+        }
+        cmd
+    }
+
+    /// Run the `groups` command.
+    pub fn run(self) -> Result<Groups, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Groups(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            groups_parser::item,
+        )?))
+    }
+
+    /// Run the `groups` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Group>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+/// How to interpret the name passed to `GroupsCommand`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NameType {
+    /// Either a user or a group.
+    Any,
+    Group,
+    User,
+    Owner,
+
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+pub type GroupItem = error::Item<Group>;
+
+pub struct Groups(exec::Items<Group>);
+
+impl IntoIterator for Groups {
+    type Item = GroupItem;
+    type IntoIter = GroupsIntoIter;
+
+    fn into_iter(self) -> GroupsIntoIter {
+        GroupsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct GroupsIntoIter(exec::Items<Group>);
+
+impl Iterator for GroupsIntoIter {
+    type Item = GroupItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<GroupItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A group membership.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Group {
+    pub group: String,
+    /// The member (user or subgroup) this record is about.
+    pub user: String,
+    pub is_sub_group: bool,
+    pub is_owner: bool,
+    pub is_user: bool,
+    pub max_results: Option<Limit>,
+    pub max_scan_rows: Option<Limit>,
+    pub max_lock_time: Option<Limit>,
+    pub max_open_files: Option<Limit>,
+    /// Ticket timeout, in seconds.
+    pub timeout: Option<Limit>,
+    /// Password timeout, in seconds.
+    pub pass_timeout: Option<Limit>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// A resource limit applied to a group.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::groups::Limit::Value(42).to_string(), "42");
+/// assert_eq!("unset".parse::<p4_cmd::groups::Limit>().unwrap(), p4_cmd::groups::Limit::Unset);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Limit {
+    /// No limit is set for this group, defer to other groups.
+    Unset,
+    /// Explicitly unlimited.
+    Unlimited,
+    Value(usize),
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+impl str::FromStr for Limit {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let limit = match s {
+            "unset" => Limit::Unset,
+            "unlimited" => Limit::Unlimited,
+            s => Limit::Value(s.parse().map_err(|_| fmt::Error)?),
+        };
+        Ok(limit)
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Unset => write!(f, "unset"),
+            Limit::Unlimited => write!(f, "unlimited"),
+            Limit::Value(v) => write!(f, "{}", v),
+            Limit::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+}
+
+mod groups_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    named!(group<&[u8], Group>,
+        do_parse!(
+            user: call!(field, "user") >>
+            group: call!(field, "group") >>
+            is_sub_group: call!(bool_field, "isSubGroup") >>
+            is_owner: call!(bool_field, "isOwner") >>
+            is_user: call!(bool_field, "isUser") >>
+            max_results: opt!(map_res!(call!(field, "maxResults"), str::parse)) >>
+            max_scan_rows: opt!(map_res!(call!(field, "maxScanRows"), str::parse)) >>
+            max_lock_time: opt!(map_res!(call!(field, "maxLockTime"), str::parse)) >>
+            max_open_files: opt!(map_res!(call!(field, "maxOpenFiles"), str::parse)) >>
+            timeout: opt!(map_res!(call!(field, "timeout"), str::parse)) >>
+            pass_timeout: opt!(map_res!(call!(field, "passTimeout"), str::parse)) >>
+            (
+                Group {
+                    group: group.to_owned(),
+                    user: user.to_owned(),
+                    is_sub_group,
+                    is_owner,
+                    is_user,
+                    max_results,
+                    max_scan_rows,
+                    max_lock_time,
+                    max_open_files,
+                    timeout,
+                    pass_timeout,
+                    non_exhaustive: (),
+                }
+            )
+        )
+    );
+
+    named!(pub item<&[u8], GroupItem>,
+        alt!(
+            map!(group, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn groups_multi() {
+        let output: &[u8] = br#"info1: user alice
+info1: group dev
+info1: isSubGroup 0
+info1: isOwner 1
+info1: isUser 1
+info1: maxResults unset
+info1: maxScanRows unlimited
+info1: maxLockTime unset
+info1: maxOpenFiles unset
+info1: timeout 43200
+info1: passTimeout unset
+info1: user qa
+info1: group dev
+info1: isSubGroup 1
+info1: isOwner 0
+info1: isUser 0
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, groups_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.user, "alice");
+        assert!(first.is_owner);
+        assert_eq!(first.max_scan_rows, Some(Limit::Unlimited));
+        assert_eq!(first.timeout, Some(Limit::Value(43200)));
+        assert_eq!(last.user, "qa");
+        assert!(last.is_sub_group);
+        assert_eq!(last.timeout, None);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
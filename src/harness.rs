@@ -0,0 +1,288 @@
+//! Throwaway Helix Core server for integration testing.
+//!
+//! This is gated behind the `it-harness` feature.  It bootstraps a fresh server, either from a
+//! local `p4d` binary or a docker image, with a depot and client ready to use so the crate's
+//! commands can be run against real server behavior rather than canned output.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let server = p4_cmd::harness::Server::from_env().unwrap().expect("P4D or P4D_IMAGE to be set");
+//! server.submit("dir/file", b"Hello World\n").unwrap();
+//! let p4 = server.connection();
+//! let files = p4.files("//depot/dir/*").run().unwrap();
+//! for file in files {
+//!     println!("{:?}", file);
+//! }
+//! ```
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::net;
+use std::path;
+use std::process;
+use std::sync::atomic;
+use std::thread;
+use std::time;
+
+use error;
+use p4;
+
+/// Name of the user created on the throwaway server.
+pub const USER: &str = "p4cmd";
+/// Name of the client created on the throwaway server.
+pub const CLIENT: &str = "p4cmd_ws";
+
+static NEXT_ID: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+
+/// How long to wait for the server to start accepting connections.
+const STARTUP_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+
+/// A running, throwaway Helix Core server.
+///
+/// The server and its files are torn down on drop.
+#[derive(Debug)]
+pub struct Server {
+    backend: Backend,
+    port: String,
+    root: path::PathBuf,
+    p4_cmd: Option<path::PathBuf>,
+}
+
+#[derive(Debug)]
+enum Backend {
+    P4d(process::Child),
+    Docker(String),
+}
+
+impl Server {
+    /// Start a server based on the environment.
+    ///
+    /// - `P4D`: Path to a `p4d` binary to run.
+    /// - `P4D_IMAGE`: Docker image running `p4d` on port 1666.
+    /// - `P4_CMD`: Path to the `p4` binary, if not on `PATH`.
+    ///
+    /// Returns `None` when neither `P4D` nor `P4D_IMAGE` is set, so tests can be skipped.
+    pub fn from_env() -> Result<Option<Self>, error::P4Error> {
+        let p4_cmd = env::var_os("P4_CMD").map(path::PathBuf::from);
+        if let Some(p4d) = env::var_os("P4D") {
+            Self::with_p4d(path::Path::new(&p4d), p4_cmd).map(Some)
+        } else if let Ok(image) = env::var("P4D_IMAGE") {
+            Self::with_docker(&image, p4_cmd).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Start a server by running a local `p4d` binary.
+    pub fn with_p4d(
+        p4d: &path::Path,
+        p4_cmd: Option<path::PathBuf>,
+    ) -> Result<Self, error::P4Error> {
+        let root = scratch_dir()?;
+        let server_root = root.join("server");
+        fs::create_dir_all(&server_root).map_err(|e| spawn_failed(e, "Creating server root"))?;
+        let port = format!("localhost:{}", free_port()?);
+
+        let mut cmd = process::Command::new(p4d);
+        cmd.arg("-r")
+            .arg(&server_root)
+            .args(&["-p", &port])
+            .args(&["-L", "log", "-J", "journal"])
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null());
+        let child = cmd
+            .spawn()
+            .map_err(|e| spawn_failed(e, format!("Command: {:?}", cmd)))?;
+
+        let server = Self {
+            backend: Backend::P4d(child),
+            port,
+            root,
+            p4_cmd,
+        };
+        server.bootstrap()?;
+        Ok(server)
+    }
+
+    /// Start a server from a docker image that runs `p4d` on port 1666.
+    pub fn with_docker(image: &str, p4_cmd: Option<path::PathBuf>) -> Result<Self, error::P4Error> {
+        let root = scratch_dir()?;
+        let host_port = free_port()?;
+        let publish = format!("{}:1666", host_port);
+
+        let mut cmd = process::Command::new("docker");
+        cmd.args(&["run", "--detach", "--rm", "--publish", &publish, image]);
+        let output = cmd
+            .output()
+            .map_err(|e| spawn_failed(e, format!("Command: {:?}", cmd)))?;
+        if !output.status.success() {
+            return Err(error::ErrorKind::SpawnFailed.error().set_context(format!(
+                "Command: {:?}\n{}",
+                cmd,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        let container = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+
+        let server = Self {
+            backend: Backend::Docker(container),
+            port: format!("localhost:{}", host_port),
+            root,
+            p4_cmd,
+        };
+        server.bootstrap()?;
+        Ok(server)
+    }
+
+    /// The P4PORT the server is listening on.
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    /// The local root of `CLIENT`.
+    pub fn client_root(&self) -> path::PathBuf {
+        self.root.join("client")
+    }
+
+    /// A connection to the server, as `USER` in `CLIENT`.
+    pub fn connection(&self) -> p4::P4 {
+        p4::P4::new()
+            .set_p4_cmd(self.p4_cmd.clone())
+            .set_port(Some(self.port.clone()))
+            .set_user(Some(USER.to_owned()))
+            .set_client(Some(CLIENT.to_owned()))
+    }
+
+    /// Add or update a file in the client and submit it.
+    ///
+    /// `path` is relative to the client root.
+    pub fn submit(&self, path: &str, content: &[u8]) -> Result<(), error::P4Error> {
+        let local = self.client_root().join(path);
+        let existed = local.exists();
+        if let Some(parent) = local.parent() {
+            fs::create_dir_all(parent).map_err(|e| spawn_failed(e, "Creating client dir"))?;
+        }
+        if existed {
+            self.p4(&["edit", &local.to_string_lossy()], None)?;
+        }
+        fs::write(&local, content).map_err(|e| spawn_failed(e, "Writing client file"))?;
+        if !existed {
+            self.p4(&["add", &local.to_string_lossy()], None)?;
+        }
+        self.p4(&["submit", "-d", "p4-cmd harness"], None)?;
+        Ok(())
+    }
+
+    fn bootstrap(&self) -> Result<(), error::P4Error> {
+        let start = time::Instant::now();
+        while self.p4(&["info"], None).is_err() {
+            if STARTUP_TIMEOUT < start.elapsed() {
+                return Err(error::ErrorKind::SpawnFailed
+                    .error()
+                    .set_context(format!("Server at {} never came up", self.port)));
+            }
+            thread::sleep(time::Duration::from_millis(100));
+        }
+
+        let client_root = self.client_root();
+        fs::create_dir_all(&client_root).map_err(|e| spawn_failed(e, "Creating client root"))?;
+        let spec = format!(
+            "Client: {client}\n\
+             Owner: {user}\n\
+             Root: {root}\n\
+             Options: allwrite noclobber nocompress unlocked nomodtime normdir\n\
+             LineEnd: local\n\
+             View:\n\
+             \t//depot/... //{client}/...\n",
+            client = CLIENT,
+            user = USER,
+            root = client_root.display()
+        );
+        self.p4(&["client", "-i"], Some(spec.as_bytes()))?;
+        Ok(())
+    }
+
+    fn p4(&self, args: &[&str], input: Option<&[u8]>) -> Result<(), error::P4Error> {
+        let p4_cmd = self
+            .p4_cmd
+            .as_ref()
+            .map(path::PathBuf::as_path)
+            .unwrap_or_else(|| path::Path::new("p4"));
+        let mut cmd = process::Command::new(p4_cmd);
+        cmd.args(&["-p", &self.port, "-u", USER, "-c", CLIENT])
+            .args(args)
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped());
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| spawn_failed(e, format!("Command: {:?}", cmd)))?;
+        {
+            let stdin = child.stdin.as_mut().expect("stdin to be piped");
+            if let Some(input) = input {
+                stdin
+                    .write_all(input)
+                    .map_err(|e| spawn_failed(e, format!("Command: {:?}", cmd)))?;
+            }
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| spawn_failed(e, format!("Command: {:?}", cmd)))?;
+        if !output.status.success() {
+            return Err(error::ErrorKind::SpawnFailed
+                .error()
+                .set_context(format!("Command: {:?}", cmd))
+                .set_stderr(Some(String::from_utf8_lossy(&output.stderr).into_owned())));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        match self.backend {
+            Backend::P4d(ref mut child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            Backend::Docker(ref container) => {
+                let _ = process::Command::new("docker")
+                    .args(&["rm", "--force", container])
+                    .stdout(process::Stdio::null())
+                    .stderr(process::Stdio::null())
+                    .status();
+            }
+        }
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn spawn_failed<E, S>(cause: E, context: S) -> error::P4Error
+where
+    E: ::std::error::Error + Send + Sync + 'static,
+    S: Into<String>,
+{
+    error::ErrorKind::SpawnFailed
+        .error()
+        .set_cause(cause)
+        .set_context(context)
+}
+
+fn scratch_dir() -> Result<path::PathBuf, error::P4Error> {
+    let id = NEXT_ID.fetch_add(1, atomic::Ordering::SeqCst);
+    let root = env::temp_dir().join(format!("p4-cmd-it-{}-{}", process::id(), id));
+    fs::create_dir_all(&root).map_err(|e| spawn_failed(e, "Creating scratch dir"))?;
+    Ok(root)
+}
+
+fn free_port() -> Result<u16, error::P4Error> {
+    let listener = net::TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| spawn_failed(e, "Looking for a free port"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| spawn_failed(e, "Looking for a free port"))?
+        .port();
+    Ok(port)
+}
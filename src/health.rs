@@ -0,0 +1,116 @@
+use error;
+use p4;
+
+/// Whether commands can be run, as reported by `P4::check_connection`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConnectionStatus {
+    /// Commands can be run.
+    Ok,
+    /// The user needs to log in, e.g. their ticket expired.
+    NeedsLogin,
+    /// The server's fingerprint needs to be trusted, see `P4::trust`.
+    NeedsTrust,
+    /// The server couldn't be reached.
+    Unreachable,
+    /// The client workspace doesn't exist on the server.
+    UnknownClient,
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+pub(crate) fn check(connection: &p4::P4) -> Result<ConnectionStatus, error::P4Error> {
+    let infos = match connection.info().run_checked() {
+        Ok(infos) => infos,
+        Err(e) => return classify(e),
+    };
+    // `info` doesn't require a login.
+    if let Err(e) = connection.login().status(true).run_checked() {
+        return classify(e);
+    }
+    match infos.first() {
+        Some(info) if info.client_name.is_none() => Ok(ConnectionStatus::UnknownClient),
+        _ => Ok(ConnectionStatus::Ok),
+    }
+}
+
+fn classify(e: error::P4Error) -> Result<ConnectionStatus, error::P4Error> {
+    match e.kind() {
+        error::ErrorKind::ConnectFailed => Ok(ConnectionStatus::Unreachable),
+        error::ErrorKind::AuthFailed | error::ErrorKind::SsoFailed => {
+            Ok(ConnectionStatus::NeedsLogin)
+        }
+        error::ErrorKind::TrustRequired => Ok(ConnectionStatus::NeedsTrust),
+        _ => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections;
+    use std::io;
+    use std::process;
+    use std::sync;
+
+    use runner;
+
+    /// Replays a different output for each command.
+    #[derive(Debug)]
+    struct Replay(sync::Mutex<collections::VecDeque<&'static [u8]>>);
+
+    impl runner::P4Runner for Replay {
+        fn run(
+            &self,
+            _cmd: process::Command,
+            _options: runner::RunOptions,
+        ) -> io::Result<Box<dyn io::Read + Send>> {
+            Ok(Box::new(self.0.lock().unwrap().pop_front().unwrap()))
+        }
+    }
+
+    fn check_replay(outputs: Vec<&'static [u8]>) -> ConnectionStatus {
+        let runner = Replay(sync::Mutex::new(outputs.into_iter().collect()));
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(runner)));
+        check(&p4).unwrap()
+    }
+
+    const INFO: &[u8] = b"info1: userName alice
+info1: clientName alice_ws
+info1: serverAddress perforce:1666
+info1: serverDate 2018/08/13 10:11:12 -0700 PDT
+info1: serverVersion P4D/LINUX26X86_64/2018.1/1660568 (2018/05/03)
+info1: caseHandling sensitive
+exit: 0
+";
+
+    #[test]
+    fn check_ok() {
+        let login = b"info1: User alice\ninfo1: TicketExpiration 43162\nexit: 0\n";
+        assert_eq!(check_replay(vec![INFO, login]), ConnectionStatus::Ok);
+    }
+
+    #[test]
+    fn check_needs_login() {
+        let login = b"error: Perforce password (P4PASSWD) invalid or unset.\nexit: 1\n";
+        assert_eq!(
+            check_replay(vec![INFO, login]),
+            ConnectionStatus::NeedsLogin
+        );
+    }
+
+    #[test]
+    fn check_unreachable() {
+        let info = b"error: Perforce client error:\nerror: \tConnect to server failed; check $P4PORT.\nexit: 1\n";
+        assert_eq!(check_replay(vec![info]), ConnectionStatus::Unreachable);
+    }
+
+    #[test]
+    fn check_needs_trust() {
+        let info = b"error: The authenticity of '10.0.0.2:1666' can't be established,\nexit: 1\n";
+        assert_eq!(check_replay(vec![info]), ConnectionStatus::NeedsTrust);
+    }
+}
@@ -0,0 +1,449 @@
+use std::fmt;
+use std::path;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Display client/server information
+///
+/// Info lists information about the current client (user name,
+/// client name, applicable client root, client current directory,
+/// and the client IP address) and some server information (server
+/// IP address, server root, date, uptime, version and license data).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let info = p4.info().run().unwrap();
+/// for item in info {
+///     println!("{:?}", item);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InfoCommand<'p> {
+    connection: &'p p4::P4,
+
+    short: bool,
+    settings: exec::Settings,
+}
+
+impl<'p> InfoCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            short: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -s option produces 'short' output that omits any information
+    /// that requires a database lookup such as the client root).
+    pub fn short(mut self, short: bool) -> Self {
+        self.short = short;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("info");
+        if self.short {
+            cmd.arg("-s");
+        }
+        cmd
+    }
+
+    /// Run the `info` command.
+    pub fn run(self) -> Result<Infos, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Infos(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            info_parser::item,
+        )?))
+    }
+
+    /// Run the `info` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<ServerInfo>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type InfoItem = error::Item<ServerInfo>;
+
+pub struct Infos(exec::Items<ServerInfo>);
+
+impl IntoIterator for Infos {
+    type Item = InfoItem;
+    type IntoIter = InfosIntoIter;
+
+    fn into_iter(self) -> InfosIntoIter {
+        InfosIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct InfosIntoIter(exec::Items<ServerInfo>);
+
+impl Iterator for InfosIntoIter {
+    type Item = InfoItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<InfoItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// Client and server information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServerInfo {
+    pub user_name: String,
+    /// `None` when the client is unknown to the server.
+    pub client_name: Option<String>,
+    pub client_root: Option<path::PathBuf>,
+    pub client_host: Option<String>,
+    pub server_address: String,
+    pub server_root: Option<path::PathBuf>,
+    pub server_date: p4::Time,
+    /// The server's offset from UTC, in seconds.
+    pub server_utc_offset: i64,
+    pub server_version: String,
+    pub server_id: Option<String>,
+    pub server_services: Option<String>,
+    pub case_handling: CaseHandling,
+    /// Whether the server is running in unicode mode.
+    pub unicode: bool,
+    /// The server's `security` level, when reported.
+    pub security: Option<usize>,
+    /// The proxy relaying commands to the server, if any.
+    pub proxy_address: Option<String>,
+    pub proxy_version: Option<String>,
+    pub proxy_root: Option<path::PathBuf>,
+    /// The broker relaying commands to the server, if any.
+    pub broker_address: Option<String>,
+    pub broker_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl ServerInfo {
+    /// The server's release, parsed from `server_version`.
+    pub fn version(&self) -> Option<ServerVersion> {
+        self.server_version.parse().ok()
+    }
+
+    /// Whether commands go through a proxy (`P4P`).
+    pub fn is_via_proxy(&self) -> bool {
+        self.proxy_address.is_some() || self.proxy_version.is_some()
+    }
+
+    /// Whether commands go through a broker (`P4Broker`).
+    pub fn is_via_broker(&self) -> bool {
+        self.broker_address.is_some() || self.broker_version.is_some()
+    }
+}
+
+/// A server release, e.g. `2018.1`, ordered from oldest to newest.
+///
+/// # Example
+///
+/// ```rust
+/// let version: p4_cmd::info::ServerVersion = "P4D/LINUX26X86_64/2018.1/1660568 (2018/05/03)"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(version, p4_cmd::info::ServerVersion::new(2018, 1));
+/// assert!(version > p4_cmd::info::ServerVersion::new(2017, 2));
+/// assert_eq!(version.to_string(), "2018.1");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServerVersion {
+    pub year: u16,
+    pub release: u8,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl ServerVersion {
+    pub fn new(year: u16, release: u8) -> Self {
+        Self {
+            year,
+            release,
+            non_exhaustive: (),
+        }
+    }
+}
+
+impl str::FromStr for ServerVersion {
+    type Err = fmt::Error;
+
+    /// Parse a release like `2018.1`, or a full `serverVersion` like
+    /// `P4D/LINUX26X86_64/2018.1/1660568 (2018/05/03)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let release = if s.contains('/') {
+            s.split('/').nth(2).ok_or(fmt::Error)?
+        } else {
+            s
+        };
+        let mut parts = release.splitn(2, '.');
+        let year = parts.next().ok_or(fmt::Error)?;
+        let year = year.parse().map_err(|_| fmt::Error)?;
+        let release = parts.next().ok_or(fmt::Error)?;
+        let digits = release.find(|c: char| !c.is_ascii_digit());
+        let release = &release[..digits.unwrap_or(release.len())];
+        let release = release.parse().map_err(|_| fmt::Error)?;
+        Ok(Self::new(year, release))
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.year, self.release)
+    }
+}
+
+/// How the server treats the case of file names.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::info::CaseHandling::Insensitive.to_string(), "insensitive");
+/// assert_eq!(
+///     "insensitive".parse::<p4_cmd::info::CaseHandling>().unwrap(),
+///     p4_cmd::info::CaseHandling::Insensitive
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CaseHandling {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Sensitive,
+    Insensitive,
+    Hybrid,
+
+    Unknown(String),
+}
+
+impl str::FromStr for CaseHandling {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ch = match s {
+            "sensitive" => CaseHandling::Sensitive,
+            "insensitive" => CaseHandling::Insensitive,
+            "hybrid" => CaseHandling::Hybrid,
+            s => CaseHandling::Unknown(s.to_owned()),
+        };
+        Ok(ch)
+    }
+}
+
+impl fmt::Display for CaseHandling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            CaseHandling::Sensitive => "sensitive",
+            CaseHandling::Insensitive => "insensitive",
+            CaseHandling::Hybrid => "hybrid",
+            CaseHandling::Unknown(ref s) => s.as_str(),
+            CaseHandling::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+mod info_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_server_info(fields: Vec<Field>) -> Result<ServerInfo, fmt::Error> {
+        let mut user_name = None;
+        let mut client_name = None;
+        let mut client_root = None;
+        let mut client_host = None;
+        let mut server_address = None;
+        let mut server_root = None;
+        let mut server_date = None;
+        let mut server_version = None;
+        let mut server_id = None;
+        let mut server_services = None;
+        let mut case_handling = None;
+        let mut unicode = false;
+        let mut security = None;
+        let mut proxy_address = None;
+        let mut proxy_version = None;
+        let mut proxy_root = None;
+        let mut broker_address = None;
+        let mut broker_version = None;
+        for field in fields {
+            match field.key {
+                "userName" => user_name = Some(field.value.to_owned()),
+                "clientName" if field.value != "*unknown*" => {
+                    client_name = Some(field.value.to_owned())
+                }
+                "clientRoot" => client_root = Some(path::PathBuf::from(field.value)),
+                "clientHost" => client_host = Some(field.value.to_owned()),
+                "serverAddress" => server_address = Some(field.value.to_owned()),
+                "serverRoot" => server_root = Some(path::PathBuf::from(field.value)),
+                "serverDate" => server_date = Some(field.value),
+                "serverVersion" => server_version = Some(field.value.to_owned()),
+                "ServerID" | "serverID" => server_id = Some(field.value.to_owned()),
+                "serverServices" => server_services = Some(field.value.to_owned()),
+                "caseHandling" => case_handling = Some(field.value.parse()?),
+                "unicode" => unicode = field.value == "enabled",
+                "security" => security = field.value.parse().ok(),
+                "proxyAddress" => proxy_address = Some(field.value.to_owned()),
+                "proxyVersion" => proxy_version = Some(field.value.to_owned()),
+                "proxyRoot" => proxy_root = Some(path::PathBuf::from(field.value)),
+                "brokerAddress" => broker_address = Some(field.value.to_owned()),
+                "brokerVersion" => broker_version = Some(field.value.to_owned()),
+                _ => (),
+            }
+        }
+
+        // `serverDate` is `YYYY/MM/DD HH:MM:SS +ZZZZ TZ`
+        let server_date = server_date.ok_or(fmt::Error)?;
+        let mut parts = server_date.splitn(4, ' ');
+        let date = parts.next().ok_or(fmt::Error)?;
+        let time = parts.next().ok_or(fmt::Error)?;
+        let server_utc_offset = parts
+            .next()
+            .and_then(p4::from_utc_offset)
+            .ok_or(fmt::Error)?;
+        let server_date =
+            p4::from_date(&format!("{} {}", date, time), server_utc_offset).ok_or(fmt::Error)?;
+
+        let info = ServerInfo {
+            user_name: user_name.ok_or(fmt::Error)?,
+            client_name,
+            client_root,
+            client_host,
+            server_address: server_address.ok_or(fmt::Error)?,
+            server_root,
+            server_date,
+            server_utc_offset,
+            server_version: server_version.ok_or(fmt::Error)?,
+            server_id,
+            server_services,
+            case_handling: case_handling.unwrap_or(CaseHandling::Sensitive),
+            unicode,
+            security,
+            proxy_address,
+            proxy_version,
+            proxy_root,
+            broker_address,
+            broker_version,
+            non_exhaustive: (),
+        };
+        Ok(info)
+    }
+
+    named!(pub item<&[u8], InfoItem>,
+        alt!(
+            map!(map_res!(many1!(any_field), to_server_info), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn info_single() {
+        let output: &[u8] = br#"info1: userName alice
+info1: clientName alice_ws
+info1: clientRoot /home/alice/ws
+info1: clientCwd /home/alice/ws/dir
+info1: clientHost build01
+info1: peerAddress 10.0.0.2:52918
+info1: clientAddress 10.0.0.2
+info1: serverAddress perforce:1666
+info1: serverRoot /p4/root
+info1: serverDate 2018/08/13 10:11:12 -0700 PDT
+info1: tzoffset -25200
+info1: serverUptime 126:38:07
+info1: serverVersion P4D/LINUX26X86_64/2018.1/1660568 (2018/05/03)
+info1: ServerID master
+info1: serverServices standard
+info1: serverLicense none
+info1: caseHandling insensitive
+info1: unicode enabled
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, info_parser::item);
+        let info = items[0].as_data().unwrap();
+        assert_eq!(info.user_name, "alice");
+        assert_eq!(info.client_name, Some("alice_ws".to_owned()));
+        assert_eq!(
+            info.client_root,
+            Some(path::PathBuf::from("/home/alice/ws"))
+        );
+        assert_eq!(info.server_address, "perforce:1666");
+        assert_eq!(info.server_utc_offset, -25200);
+        assert_eq!(info.server_date, p4::from_timestamp(1534180272));
+        assert_eq!(info.case_handling, CaseHandling::Insensitive);
+        assert_eq!(info.version(), Some(ServerVersion::new(2018, 1)));
+        assert!(info.unicode);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn info_unknown_client() {
+        let output: &[u8] = br#"info1: userName alice
+info1: clientName *unknown*
+info1: serverAddress perforce:1666
+info1: serverDate 2018/08/13 10:11:12 +0000 UTC
+info1: serverVersion P4D/LINUX26X86_64/2018.1/1660568 (2018/05/03)
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, info_parser::item);
+        let info = items[0].as_data().unwrap();
+        assert_eq!(info.client_name, None);
+        assert!(!info.unicode);
+        assert!(!info.is_via_proxy());
+    }
+
+    #[test]
+    fn info_via_proxy() {
+        let output: &[u8] = br#"info1: userName alice
+info1: serverAddress perforce:1666
+info1: serverDate 2018/08/13 10:11:12 +0000 UTC
+info1: serverVersion P4D/LINUX26X86_64/2018.1/1660568 (2018/05/03)
+info1: proxyAddress proxy.remote:1666
+info1: proxyRoot /p4/proxy
+info1: proxyVersion P4P/LINUX26X86_64/2018.1/1637407 (2018/04/05)
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, info_parser::item);
+        let info = items[0].as_data().unwrap();
+        assert!(info.is_via_proxy());
+        assert!(!info.is_via_broker());
+        assert_eq!(info.proxy_address, Some("proxy.remote:1666".to_owned()));
+    }
+}
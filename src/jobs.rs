@@ -0,0 +1,414 @@
+use std::borrow;
+use std::fmt;
+use std::process;
+
+use error;
+use exec;
+use p4;
+use spec;
+
+/// Display list of jobs
+///
+/// Lists jobs in the server. If a file specification is included, fixes
+/// for submitted changelists affecting those files are listed.  The file
+/// specification can include wildcards and revision specifiers.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let jobs = p4.jobs().jobview("status=open").run().unwrap();
+/// for job in jobs {
+///     println!("{:?}", job);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct JobsCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    file: Vec<borrow::Cow<'f, str>>,
+
+    jobview: Option<&'f str>,
+    long: bool,
+    reverse: bool,
+    max: Option<usize>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> JobsCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            file: vec![],
+            jobview: None,
+            long: false,
+            reverse: false,
+            max: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Only list jobs fixed by changelists affecting this file.  Can be
+    /// repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// The -e flag lists only those jobs that match the jobview
+    /// expression.  For details, see 'p4 help jobview'.
+    pub fn jobview(mut self, jobview: &'f str) -> Self {
+        self.jobview = Some(jobview);
+        self
+    }
+
+    /// The -l flag produces long output with the full text of the job
+    /// descriptions.
+    pub fn long(mut self, long: bool) -> Self {
+        self.long = long;
+        self
+    }
+
+    /// The -r flag sorts the jobs in reverse order (by job name).
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// The -m flag limits the output to the first 'max' jobs, ordered
+    /// by their job name.
+    pub fn set_max(mut self, max: Option<usize>) -> Self {
+        self.max = max;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("jobs");
+        if let Some(jobview) = self.jobview {
+            cmd.args(&["-e", jobview]);
+        }
+        if self.long {
+            cmd.arg("-l");
+        }
+        if self.reverse {
+            cmd.arg("-r");
+        }
+        if let Some(max) = self.max {
+            let max = format!("{}", max);
+            cmd.args(&["-m", &max]);
+        }
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `jobs` command.
+    pub fn run(self) -> Result<Jobs, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Jobs(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            jobs_parser::item,
+        )?))
+    }
+
+    /// Run the `jobs` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Job>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type JobItem = error::Item<Job>;
+
+pub struct Jobs(exec::Items<Job>);
+
+impl IntoIterator for Jobs {
+    type Item = JobItem;
+    type IntoIter = JobsIntoIter;
+
+    fn into_iter(self) -> JobsIntoIter {
+        JobsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct JobsIntoIter(exec::Items<Job>);
+
+impl Iterator for JobsIntoIter {
+    type Item = JobItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<JobItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A job record.
+///
+/// The fields every jobspec has are typed, the rest are kept in order so a job read with
+/// `p4 job -o` can be written back with `p4 job -i` without losing any.
+///
+/// # Example
+///
+/// ```rust
+/// use p4_cmd::jobs::Job;
+///
+/// let form = "Job:\tjob000001\n\nStatus:\topen\n\nSeverity:\tB\n";
+/// let mut job = Job::from_spec(&form.parse().unwrap()).unwrap();
+/// assert_eq!(job.field("Severity"), Some("B"));
+/// job.status = Some("closed".to_owned());
+/// assert_eq!(
+///     job.to_spec().to_string(),
+///     "Job:\tjob000001\n\nStatus:\tclosed\n\nSeverity:\tB\n\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Job {
+    pub job: String,
+    pub status: Option<String>,
+    pub user: Option<String>,
+    pub date: Option<p4::ServerDate>,
+    pub description: Option<String>,
+    /// Site-defined fields from the jobspec, in order.
+    pub fields: Vec<(String, String)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Job {
+    pub fn new(job: &str) -> Self {
+        Self {
+            job: job.to_owned(),
+            status: None,
+            user: None,
+            date: None,
+            description: None,
+            fields: vec![],
+            non_exhaustive: (),
+        }
+    }
+
+    /// Look up the site-defined field named `name`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.0 == name)
+            .map(|f| f.1.as_str())
+    }
+
+    /// Replace the site-defined field named `name`, adding it after the others if it's missing.
+    pub fn set_field(&mut self, name: &str, value: String) {
+        match self.fields.iter_mut().find(|f| f.0 == name) {
+            Some(field) => field.1 = value,
+            None => self.fields.push((name.to_owned(), value)),
+        }
+    }
+
+    /// Set a field, typed or site-defined, from its text.
+    ///
+    /// Fails if `Date` isn't a date.
+    fn set(&mut self, name: &str, value: String) -> Result<(), fmt::Error> {
+        match name {
+            "Job" => self.job = value,
+            "Status" => self.status = Some(value),
+            "User" => self.user = Some(value),
+            "Date" => self.date = Some(value.parse()?),
+            "Description" => self.description = Some(value),
+            name => self.set_field(name, value),
+        }
+        Ok(())
+    }
+
+    /// Read a job from its spec form, like `p4 job -o` writes.
+    ///
+    /// `None` without a `Job:` field or if `Date:` isn't a date.
+    pub fn from_spec(spec: &spec::Spec) -> Option<Self> {
+        let mut job = Job::new(&spec.get("Job")?.to_text());
+        for (name, value) in &spec.fields {
+            job.set(name, value.to_text()).ok()?;
+        }
+        Some(job)
+    }
+
+    /// The job's spec form, for `p4 job -i`.
+    pub fn to_spec(&self) -> spec::Spec {
+        let typed = vec![
+            ("Job", Some(self.job.clone())),
+            ("Status", self.status.clone()),
+            ("User", self.user.clone()),
+            ("Date", self.date.as_ref().map(ToString::to_string)),
+            ("Description", self.description.clone()),
+        ];
+        let typed = typed
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|v| (name.to_owned(), v)));
+        let mut spec = spec::Spec::new();
+        for (name, value) in typed.chain(self.fields.iter().cloned()) {
+            let value = if value.contains('\n') {
+                spec::SpecValue::Lines(value.lines().map(str::to_owned).collect())
+            } else {
+                spec::SpecValue::Line(value)
+            };
+            spec.fields.push((name, value));
+        }
+        spec
+    }
+}
+
+mod jobs_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_job((job, fields): (&str, Vec<Field>)) -> Result<Job, fmt::Error> {
+        let mut job = Job::new(job);
+        for field in fields {
+            job.set(field.key, field.value.to_owned())?;
+        }
+        Ok(job)
+    }
+
+    named!(job_field<&[u8], Field>,
+        verify!(any_field, |f: Field| f.key != "Job")
+    );
+
+    named!(job<&[u8], Job>,
+        map_res!(
+            pair!(
+                call!(field, "Job"),
+                many0!(job_field)
+            ),
+            to_job
+        )
+    );
+
+    named!(pub item<&[u8], JobItem>,
+        alt!(
+            map!(job, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn jobs_custom_fields() {
+        let output: &[u8] = br#"info1: Job job000001
+info1: Status open
+info1: User alice
+info1: Date 2018/08/13 10:11:12
+info1: Description Crash on startup
+info1: Severity A
+info1: Job job000002
+info1: Status closed
+info1: User bob
+info1: Date 2018/08/14 10:11:12
+info1: Description Typo in docs
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, jobs_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.job, "job000001");
+        assert_eq!(first.status, Some("open".to_owned()));
+        assert_eq!(first.date, Some("2018/08/13 10:11:12".parse().unwrap()));
+        assert_eq!(first.field("Severity"), Some("A"));
+        assert_eq!(last.job, "job000002");
+        assert_eq!(last.user, Some("bob".to_owned()));
+        assert!(last.fields.is_empty());
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn jobs_long_description() {
+        let output: &[u8] = br#"info1: Job job000001
+info1: Status open
+info1: Description Crash on startup.
+
+Seen on Windows.
+info1: Severity A
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, jobs_parser::item);
+        let job = items[0].as_data().unwrap();
+        assert_eq!(
+            job.description,
+            Some("Crash on startup.\n\nSeen on Windows.".to_owned())
+        );
+        assert_eq!(job.field("Severity"), Some("A"));
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn job_spec_round_trip() {
+        let form = "Job:\tjob000001
+
+Status:\topen
+
+Owner:\talice
+
+Date:\t2018/08/13 10:11:12
+
+Description:
+\tCrash on startup.
+
+\tSeen on Windows.
+
+Severity:\tA
+
+";
+        let spec: spec::Spec = form.parse().unwrap();
+        let mut job = Job::from_spec(&spec).unwrap();
+        assert_eq!(job.date, Some("2018/08/13 10:11:12".parse().unwrap()));
+        assert_eq!(
+            job.description,
+            Some("Crash on startup.\n\nSeen on Windows.".to_owned())
+        );
+        assert_eq!(
+            job.fields,
+            vec![
+                ("Owner".to_owned(), "alice".to_owned()),
+                ("Severity".to_owned(), "A".to_owned()),
+            ]
+        );
+
+        job.set_field("Severity", "B".to_owned());
+        let updated = job.to_spec();
+        let names: Vec<_> = updated.fields.iter().map(|f| f.0.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Job", "Status", "Date", "Description", "Owner", "Severity"]
+        );
+        let updated = updated.to_string();
+        assert_eq!(Job::from_spec(&updated.parse().unwrap()), Some(job));
+        assert_eq!(Job::from_spec(&spec::Spec::new()), None);
+    }
+}
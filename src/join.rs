@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::iter;
+
+use error;
+use exec;
+use files;
+use p4;
+use paths;
+use where_;
+
+/// Number of `files` records read ahead and joined by a single `where`.
+///
+/// `where` splits its arguments across several runs if they don't fit on one command line.
+const BATCH_SIZE: usize = 128;
+
+/// Join depot files to their location in the client workspace.
+///
+/// Records are read `BATCH_SIZE` at a time, looked up with one `where` and yielded before
+/// more are read, so a large listing is never held in memory.  Files that are not mapped by
+/// the client view, or only by a line excluding them, are yielded with no `path`.
+///
+/// Depot paths are passed to `where` as `files` reported them, already escaped.  A depot path
+/// that isn't UTF-8 can't be passed at all, so it's yielded with no `path` too.
+///
+/// Non-data items (messages and the exit status) of `files` are passed through unchanged.  If
+/// a `where` fails, its messages and exit status are yielded and nothing more is read.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let files = p4.files("//depot/dir/*").run().unwrap();
+/// for file in p4_cmd::join::resolve_local_paths(&p4, files) {
+///     println!("{:?}", file);
+/// }
+/// ```
+pub fn resolve_local_paths<I>(connection: &p4::P4, files: I) -> LocalFiles<'_, I::IntoIter>
+where
+    I: IntoIterator<Item = files::FileItem>,
+{
+    LocalFiles {
+        connection,
+        files: files.into_iter(),
+    }
+}
+
+pub type LocalFileItem = error::Item<LocalFile>;
+
+pub struct LocalFiles<'p, I> {
+    connection: &'p p4::P4,
+    files: I,
+}
+
+impl<'p, I> IntoIterator for LocalFiles<'p, I>
+where
+    I: Iterator<Item = files::FileItem>,
+{
+    type Item = LocalFileItem;
+    type IntoIter = LocalFilesIntoIter<'p, I>;
+
+    fn into_iter(self) -> LocalFilesIntoIter<'p, I> {
+        LocalFilesIntoIter {
+            connection: self.connection,
+            files: self.files.fuse(),
+            joined: VecDeque::new(),
+            failed: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalFilesIntoIter<'p, I> {
+    connection: &'p p4::P4,
+    files: iter::Fuse<I>,
+    /// The current batch, joined.
+    joined: VecDeque<LocalFileItem>,
+    /// A `where` failed, so nothing more is read.
+    failed: bool,
+}
+
+impl<'p, I> LocalFilesIntoIter<'p, I>
+where
+    I: Iterator<Item = files::FileItem>,
+{
+    /// Look up the next batch, or report why that failed.
+    fn join_batch(&mut self) {
+        let batch: Vec<_> = self.files.by_ref().take(BATCH_SIZE).collect();
+        let mapping = match self.mapping(&batch) {
+            Ok(mapping) => mapping,
+            Err(failure) => {
+                self.failed = true;
+                self.joined.extend(failure);
+                return;
+            }
+        };
+        self.joined.extend(batch.into_iter().map(|item| match item {
+            error::Item::Data(file) => {
+                let local = mapping.get(&file.depot_file);
+                let client_file = local.map(|l| l.client_file.clone());
+                let path = local.map(|l| l.path.clone());
+                error::Item::Data(LocalFile {
+                    file,
+                    client_file,
+                    path,
+                    non_exhaustive: (),
+                })
+            }
+            error::Item::Message(m) => error::Item::Message(m),
+            error::Item::Error(e) => error::Item::Error(e),
+            error::Item::__Nonexhaustive => error::Item::__Nonexhaustive,
+        }));
+    }
+
+    /// Where each file of `batch` is mapped, or the failure of `where` as items.
+    fn mapping(
+        &self,
+        batch: &[files::FileItem],
+    ) -> Result<HashMap<paths::DepotPath, where_::File>, Vec<LocalFileItem>> {
+        let mut mapping = HashMap::new();
+        let depot_files: Vec<&str> = batch
+            .iter()
+            .filter_map(|f| f.as_data())
+            .map(|f| &f.depot_file)
+            .filter(|d| d.as_bytes() == d.as_str().as_bytes())
+            .map(|d| d.as_str())
+            .collect();
+        if depot_files.is_empty() {
+            return Ok(mapping);
+        }
+        let mut cmd = self.connection.where_();
+        for depot_file in depot_files {
+            cmd = cmd.file(depot_file);
+        }
+        let items: Vec<_> = match cmd.run() {
+            Ok(items) => items.into_iter().collect(),
+            Err(e) => {
+                let failure = error::Message::new(error::MessageLevel::Error, e.to_string());
+                return Err(vec![
+                    error::Item::Message(failure),
+                    error::Item::Error(error::OperationError::stopped(e.kind())),
+                ]);
+            }
+        };
+        let found = match exec::collect_unmatched(items.clone(), " - file(s) not in client view.") {
+            Ok(found) => found,
+            Err(_) => {
+                return Err(items
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        error::Item::Message(m) => Some(error::Item::Message(m)),
+                        error::Item::Error(e) => Some(error::Item::Error(e)),
+                        _ => None,
+                    })
+                    .collect())
+            }
+        };
+        // Later mappings take precedence, so let them overwrite or exclude earlier ones.
+        for file in found {
+            if file.unmapped {
+                mapping.remove(&file.depot_file);
+            } else {
+                mapping.insert(file.depot_file.clone(), file);
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+impl<'p, I> Iterator for LocalFilesIntoIter<'p, I>
+where
+    I: Iterator<Item = files::FileItem>,
+{
+    type Item = LocalFileItem;
+
+    fn next(&mut self) -> Option<LocalFileItem> {
+        if self.joined.is_empty() && !self.failed {
+            self.join_batch();
+        }
+        self.joined.pop_front()
+    }
+}
+
+/// A depot file combined with its location in the client workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LocalFile {
+    pub file: files::File,
+    /// The name on the client in Perforce syntax, if mapped.
+    pub client_file: Option<paths::ClientPath>,
+    /// The name on the client in local syntax, if mapped.
+    pub path: Option<paths::LocalPath>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn files(output: &[u8]) -> Vec<files::FileItem> {
+        exec::parse(output, files::files_parser::item).0
+    }
+
+    #[test]
+    fn join_mapped_and_excluded() {
+        let depot: &[u8] = b"info1: depotFile //depot/a%40b
+info1: rev 1
+info1: change 1
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: depotFile //depot/c
+info1: rev 1
+info1: change 1
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: depotFile //depot/d
+info1: rev 1
+info1: change 1
+info1: action add
+info1: type text
+info1: time 1527128624
+exit: 0
+";
+        let output: &[u8] = b"info1: depotFile //depot/a%40b
+info1: clientFile //ws/a%40b
+info1: path /home/user/ws/a@b
+info1: depotFile //depot/c
+info1: clientFile //ws/c
+info1: path /home/user/ws/c
+info1: unmap
+info1: depotFile //depot/c
+info1: clientFile //ws/c
+info1: path /home/user/ws/c
+exit: 0
+";
+        let (runner, p4) = exec::recorder(output);
+        let joined: Vec<_> = resolve_local_paths(&p4, files(depot))
+            .into_iter()
+            .filter_map(|i| i.as_data().cloned())
+            .collect();
+        assert_eq!(joined.len(), 3);
+        assert_eq!(joined[0].client_file.as_ref().unwrap(), "//ws/a%40b");
+        assert_eq!(
+            joined[0].path.as_ref().unwrap(),
+            ::std::path::Path::new("/home/user/ws/a@b")
+        );
+        assert_eq!(joined[1].path, None);
+        assert_eq!(joined[2].client_file, None);
+
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        let args = &calls[0].0;
+        let start = args.iter().position(|a| a == "where").unwrap() + 1;
+        assert_eq!(&args[start..], &["//depot/a%40b", "//depot/c", "//depot/d"]);
+    }
+
+    #[test]
+    fn join_not_in_view() {
+        let depot: &[u8] = b"info1: depotFile //depot/a
+info1: rev 1
+info1: change 1
+info1: action add
+info1: type text
+info1: time 1527128624
+exit: 0
+";
+        let output: &[u8] = b"error: //depot/a - file(s) not in client view.
+exit: 1
+";
+        let (_, p4) = exec::recorder(output);
+        let joined: Vec<_> = resolve_local_paths(&p4, files(depot))
+            .into_iter()
+            .filter_map(|i| i.as_data().cloned())
+            .collect();
+        assert_eq!(joined[0].path, None);
+
+        let output: &[u8] = b"error: Client 'ws' unknown - use 'client' command to create it.
+exit: 1
+";
+        let (_, p4) = exec::recorder(output);
+        let err = exec::collect(resolve_local_paths(&p4, files(depot))).unwrap_err();
+        assert_eq!(
+            err.messages()[0].msg,
+            "Client 'ws' unknown - use 'client' command to create it."
+        );
+    }
+
+    #[test]
+    fn join_non_utf8() {
+        let depot: &[u8] = b"info1: depotFile //depot/caf\xe9
+info1: rev 1
+info1: change 1
+info1: action add
+info1: type text
+info1: time 1527128624
+exit: 0
+";
+        let (runner, p4) = exec::recorder(b"exit: 0\n");
+        let joined: Vec<_> = resolve_local_paths(&p4, files(depot))
+            .into_iter()
+            .filter_map(|i| i.as_data().cloned())
+            .collect();
+        assert_eq!(joined[0].path, None);
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn join_streams_batches() {
+        let file: &[u8] = b"info1: depotFile //depot/a
+info1: rev 1
+info1: change 1
+info1: action add
+info1: type text
+info1: time 1527128624
+";
+        let mut depot = file.repeat(BATCH_SIZE + 1);
+        depot.extend_from_slice(b"exit: 0\n");
+        let read = ::std::cell::Cell::new(0);
+        let files = files(&depot)
+            .into_iter()
+            .inspect(|_| read.set(read.get() + 1));
+
+        let (runner, p4) = exec::recorder(b"exit: 0\n");
+        let mut joined = resolve_local_paths(&p4, files).into_iter();
+        assert!(joined.next().unwrap().as_data().is_some());
+        assert_eq!(read.get(), BATCH_SIZE);
+        assert_eq!(runner.calls.lock().unwrap().len(), 1);
+
+        assert_eq!(joined.count(), BATCH_SIZE);
+        assert_eq!(runner.calls.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Display journal or checkpoint history
+///
+/// Lists the history of checkpoints and journal rotations recorded by the
+/// server, e.g. to verify backups are being taken.
+///
+/// Requires super access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let journals = p4.journals().set_max(Some(10)).run().unwrap();
+/// for journal in journals {
+///     println!("{:?}", journal);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct JournalsCommand<'p, 'f> {
+    connection: &'p p4::P4,
+
+    filter: Option<&'f str>,
+    max: Option<usize>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> JournalsCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            filter: None,
+            max: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -F filter flag limits the output to records matching the
+    /// filter expression, e.g. `type=checkpoint`.
+    pub fn filter(mut self, filter: &'f str) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// The -m flag limits the output to the first 'max' records.
+    pub fn set_max(mut self, max: Option<usize>) -> Self {
+        self.max = max;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("journals");
+        if let Some(filter) = self.filter {
+            cmd.args(&["-F", filter]);
+        }
+        if let Some(max) = self.max {
+            let max = format!("{}", max);
+            cmd.args(&["-m", &max]);
+        }
+        cmd
+    }
+
+    /// Run the `journals` command.
+    pub fn run(self) -> Result<Journals, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Journals(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            journals_parser::item,
+        )?))
+    }
+
+    /// Run the `journals` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Journal>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type JournalItem = error::Item<Journal>;
+
+pub struct Journals(exec::Items<Journal>);
+
+impl IntoIterator for Journals {
+    type Item = JournalItem;
+    type IntoIter = JournalsIntoIter;
+
+    fn into_iter(self) -> JournalsIntoIter {
+        JournalsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct JournalsIntoIter(exec::Items<Journal>);
+
+impl Iterator for JournalsIntoIter {
+    type Item = JournalItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<JournalItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A checkpoint or journal rotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Journal {
+    pub journal_type: JournalType,
+    /// The journal/checkpoint sequence number.
+    pub sequence: usize,
+    pub start_date: Option<p4::Time>,
+    pub end_date: Option<p4::Time>,
+    /// Size in bytes.
+    pub size: Option<u64>,
+    pub file: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// Kind of journal history record.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::journals::JournalType::Checkpoint.to_string(), "checkpoint");
+/// assert_eq!(
+///     "journal".parse::<p4_cmd::journals::JournalType>().unwrap(),
+///     p4_cmd::journals::JournalType::Journal
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JournalType {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Checkpoint,
+    Journal,
+
+    Unknown(String),
+}
+
+impl str::FromStr for JournalType {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let journal_type = match s {
+            "checkpoint" => JournalType::Checkpoint,
+            "journal" => JournalType::Journal,
+            s => JournalType::Unknown(s.to_owned()),
+        };
+        Ok(journal_type)
+    }
+}
+
+impl fmt::Display for JournalType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            JournalType::Checkpoint => "checkpoint",
+            JournalType::Journal => "journal",
+            JournalType::Unknown(ref s) => s.as_str(),
+            JournalType::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+mod journals_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// Either seconds since the epoch or `2018/08/13 10:11:12`.
+    fn to_date(value: &str) -> Result<p4::Time, fmt::Error> {
+        match value.parse() {
+            Ok(timestamp) => Ok(p4::from_timestamp(timestamp)),
+            Err(_) => p4::from_date(value, 0).ok_or(fmt::Error),
+        }
+    }
+
+    fn to_journal(fields: Vec<Field>) -> Result<Journal, fmt::Error> {
+        let mut journal_type = None;
+        let mut sequence = None;
+        let mut start_date = None;
+        let mut end_date = None;
+        let mut size = None;
+        let mut file = None;
+        for field in fields {
+            match field.key {
+                "type" => journal_type = Some(field.value.parse()?),
+                "num" => sequence = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                "startDate" => start_date = Some(to_date(field.value)?),
+                "endDate" => end_date = Some(to_date(field.value)?),
+                "size" => size = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                "file" => file = Some(field.value.to_owned()),
+                _ => (),
+            }
+        }
+        let journal = Journal {
+            journal_type: journal_type.ok_or(fmt::Error)?,
+            sequence: sequence.ok_or(fmt::Error)?,
+            start_date,
+            end_date,
+            size,
+            file,
+            non_exhaustive: (),
+        };
+        Ok(journal)
+    }
+
+    named!(pub item<&[u8], JournalItem>,
+        alt!(
+            map!(map_res!(record, to_journal), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn journals_history() {
+        let output: &[u8] = br#"info1: type checkpoint
+info1: num 5
+info1: startDate 1534180272
+info1: endDate 1534180290
+info1: size 1048576
+info1: file checkpoint.5.gz
+info1: type journal
+info1: num 4
+info1: startDate 2018/08/13 10:11:12
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, journals_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.journal_type, JournalType::Checkpoint);
+        assert_eq!(first.sequence, 5);
+        assert_eq!(first.start_date, Some(p4::from_timestamp(1534180272)));
+        assert_eq!(first.size, Some(1048576));
+        assert_eq!(last.journal_type, JournalType::Journal);
+        assert_eq!(last.start_date, Some(p4::from_timestamp(1534155072)));
+        assert_eq!(last.end_date, None);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,151 @@
+use std::process;
+
+use error;
+use exec;
+use keys;
+use p4;
+
+/// Display, set, or delete a key/value
+///
+/// The first form displays the value of the specified key.
+///
+/// The second form sets the key to the specified value.
+///
+/// The -d flag deletes the specified key.
+///
+/// The -i flag increments a key by 1 and returns the new value.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let keys = p4.key("build-last").increment(true).run().unwrap();
+/// for key in keys {
+///     println!("{:?}", key);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyCommand<'p, 'k> {
+    connection: &'p p4::P4,
+    name: &'k str,
+
+    value: Option<&'k str>,
+    delete: bool,
+    increment: bool,
+    compare: Option<&'k str>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'k> KeyCommand<'p, 'k> {
+    pub fn new(connection: &'p p4::P4, name: &'k str) -> Self {
+        Self {
+            connection,
+            name,
+            value: None,
+            delete: false,
+            increment: false,
+            compare: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Set the key to the specified value.
+    pub fn set(mut self, value: &'k str) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// The -d flag deletes the specified key.
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// The -i flag increments a key by 1 and returns the new value.  This
+    /// option is used instead of a value argument and can only be used with
+    /// numeric key values.
+    pub fn increment(mut self, increment: bool) -> Self {
+        self.increment = increment;
+        self
+    }
+
+    /// The --from and --to flags set the key to the new value only if its
+    /// current value is 'from'.  Use with `set` to provide the new value.
+    pub fn compare(mut self, from: &'k str) -> Self {
+        self.compare = Some(from);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("key");
+        if self.delete {
+            cmd.arg("-d");
+        }
+        if self.increment {
+            cmd.arg("-i");
+        }
+        match (self.compare, self.value) {
+            (Some(from), Some(to)) => {
+                cmd.arg(format!("--from={}", from));
+                cmd.arg(format!("--to={}", to));
+                cmd.arg(self.name);
+            }
+            (_, value) => {
+                cmd.arg(self.name);
+                if let Some(value) = value {
+                    cmd.arg(value);
+                }
+            }
+        }
+        cmd
+    }
+
+    /// Run the `key` command.
+    pub fn run(self) -> Result<keys::Keys, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(keys::Keys(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            keys::keys_parser::item,
+        )?))
+    }
+
+    /// Run the `key` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<keys::Key>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn key_increment() {
+        let output: &[u8] = br#"info1: key build-last
+info1: value 43
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, keys::keys_parser::item);
+        let key = items[0].as_data().unwrap();
+        assert_eq!(key.key, "build-last");
+        assert_eq!(key.as_number(), Some(43));
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn key_delete() {
+        let output: &[u8] = br#"info: Key build-last deleted.
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, keys::keys_parser::item);
+        assert!(items[0].as_message().is_some());
+    }
+}
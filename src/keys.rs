@@ -0,0 +1,191 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Display list of known keys and their values
+///
+/// Lists keys and their values.  Keys are a key/value store in the
+/// server, a variant of counters without the special meaning some
+/// counters have.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let keys = p4.keys().filter("build-*").run().unwrap();
+/// for key in keys {
+///     println!("{:?}", key);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeysCommand<'p, 'f> {
+    connection: &'p p4::P4,
+
+    filter: Vec<&'f str>,
+    max: Option<usize>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> KeysCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            filter: vec![],
+            max: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -e nameFilter flag lists keys with a name that matches the
+    /// nameFilter pattern, for example: -e 'mycounter-*'.  Can be
+    /// repeated.
+    pub fn filter(mut self, filter: &'f str) -> Self {
+        self.filter.push(filter);
+        self
+    }
+
+    /// The -m flag limits the output to the first 'max' keys.
+    pub fn set_max(mut self, max: Option<usize>) -> Self {
+        self.max = max;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("keys");
+        for filter in &self.filter {
+            cmd.args(&["-e", filter]);
+        }
+        if let Some(max) = self.max {
+            let max = format!("{}", max);
+            cmd.args(&["-m", &max]);
+        }
+        cmd
+    }
+
+    /// Run the `keys` command.
+    pub fn run(self) -> Result<Keys, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Keys(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            keys_parser::item,
+        )?))
+    }
+
+    /// Run the `keys` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Key>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type KeyItem = error::Item<Key>;
+
+pub struct Keys(pub(crate) exec::Items<Key>);
+
+impl IntoIterator for Keys {
+    type Item = KeyItem;
+    type IntoIter = KeysIntoIter;
+
+    fn into_iter(self) -> KeysIntoIter {
+        KeysIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct KeysIntoIter(exec::Items<Key>);
+
+impl Iterator for KeysIntoIter {
+    type Item = KeyItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<KeyItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A key and its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Key {
+    pub key: String,
+    pub value: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Key {
+    /// The value, if it is numeric (e.g. after an increment).
+    pub fn as_number(&self) -> Option<u64> {
+        self.value.parse().ok()
+    }
+}
+
+pub(crate) mod keys_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    named!(key<&[u8], Key>,
+        do_parse!(
+            key: call!(field, "key") >>
+            value: call!(field, "value") >>
+            (
+                Key {
+                    key: key.to_owned(),
+                    value: value.to_owned(),
+                    non_exhaustive: (),
+                }
+            )
+        )
+    );
+
+    named!(pub item<&[u8], KeyItem>,
+        alt!(
+            map!(key, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keys_multi() {
+        let output: &[u8] = br#"info1: key build-last
+info1: value 42
+info1: key build-owner
+info1: value alice
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, keys_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.key, "build-last");
+        assert_eq!(first.as_number(), Some(42));
+        assert_eq!(last.value, "alice");
+        assert_eq!(last.as_number(), None);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,228 @@
+//! Strip or expand RCS keywords, like `$Id$`, in the content of `+k` files.
+//!
+//! The server expands keywords in `+k` files when they're printed or synced, so content from
+//! different revisions differs in its keywords even when nothing else changed.
+//! [`strip`](fn.strip.html) reverts them to their unexpanded `$Name$` form for comparing
+//! content, and [`expand`](fn.expand.html) expands them as the server would.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use p4_cmd::keywords;
+//!
+//! let file_type: p4_cmd::FileType = "text+k".parse().unwrap();
+//! let mut values = keywords::Keywords::new("//depot/dir/file.c", 3, 42);
+//! values.date = Some("2018/08/13 10:11:12".parse().unwrap());
+//! let expanded = keywords::expand("// $Id$ $Date$\n", &file_type, &values);
+//! assert_eq!(
+//!     expanded,
+//!     "// $Id: //depot/dir/file.c#3 $ $Date: 2018/08/13 $\n"
+//! );
+//! assert_eq!(keywords::strip(&expanded), "// $Id$ $Date$\n");
+//! ```
+
+use std::borrow;
+
+use p4;
+use print;
+
+/// The keywords the server expands.
+const KEYWORDS: &[&str] = &[
+    "Id",
+    "Header",
+    "Date",
+    "DateUTC",
+    "DateTime",
+    "DateTimeUTC",
+    "DateTimeTZ",
+    "Change",
+    "File",
+    "Revision",
+    "Author",
+];
+
+/// The keywords `+ko` files expand.
+const OLD_STYLE_KEYWORDS: &[&str] = &["Id", "Header"];
+
+/// What keywords expand to, for a file revision.
+///
+/// Keywords without a value, like `$Author$` when `author` is `None`, are left as they are.
+/// `$DateTimeTZ$` is never expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keywords {
+    pub depot_file: String,
+    pub rev: usize,
+    pub change: usize,
+    /// When the revision was submitted, in the server's time zone.
+    pub date: Option<p4::ServerDate>,
+    /// When the revision was submitted, in UTC.
+    pub date_utc: Option<p4::ServerDate>,
+    /// Who submitted the revision.
+    pub author: Option<String>,
+    non_exhaustive: (),
+}
+
+impl Keywords {
+    pub fn new(depot_file: &str, rev: usize, change: usize) -> Self {
+        Self {
+            depot_file: depot_file.to_owned(),
+            rev,
+            change,
+            date: None,
+            date_utc: None,
+            author: None,
+            non_exhaustive: (),
+        }
+    }
+
+    /// The values for a printed file.
+    ///
+    /// `print` reports when the revision was submitted without the server's time zone, so only
+    /// `date_utc` is filled in.
+    pub fn for_file(file: &print::File) -> Self {
+        let mut keywords = Self::new(file.depot_file.as_str(), file.rev, file.change);
+        keywords.date_utc = Some(p4::ServerDate::from_time(&file.time, 0));
+        keywords
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        let day = |d: &p4::ServerDate| format!("{:04}/{:02}/{:02}", d.year, d.month, d.day);
+        match name {
+            "Id" | "Header" => Some(format!("{}#{}", self.depot_file, self.rev)),
+            "Date" => self.date.as_ref().map(day),
+            "DateUTC" => self.date_utc.as_ref().map(day),
+            "DateTime" => self.date.as_ref().map(ToString::to_string),
+            "DateTimeUTC" => self.date_utc.as_ref().map(ToString::to_string),
+            "Change" => Some(self.change.to_string()),
+            "File" => Some(self.depot_file.clone()),
+            "Revision" => Some(format!("#{}", self.rev)),
+            "Author" => self.author.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// Revert expanded keywords, like `$Id: //depot/file#3 $`, to `$Id$`.
+pub fn strip(text: &str) -> borrow::Cow<'_, str> {
+    rewrite(text, |name| {
+        if KEYWORDS.contains(&name) {
+            Some(format!("${}$", name))
+        } else {
+            None
+        }
+    })
+}
+
+/// Expand keywords as the server does for `file_type`, re-expanding any already expanded.
+///
+/// Only `+k` types are expanded, and only `$Id$` and `$Header$` for `+ko`.
+pub fn expand<'t>(
+    text: &'t str,
+    file_type: &p4::FileType,
+    keywords: &Keywords,
+) -> borrow::Cow<'t, str> {
+    let modifiers = match file_type.modifiers {
+        Some(ref modifiers) if modifiers.rcs_expansion => modifiers,
+        _ => return borrow::Cow::Borrowed(text),
+    };
+    let expanded = if modifiers.old_style_expansion {
+        OLD_STYLE_KEYWORDS
+    } else {
+        KEYWORDS
+    };
+    rewrite(text, |name| {
+        if !expanded.contains(&name) {
+            return None;
+        }
+        keywords
+            .value(name)
+            .map(|value| format!("${}: {} $", name, value))
+    })
+}
+
+/// Replace each `$Name$` or `$Name: value $` in `text` that `replace` has a replacement for.
+fn rewrite<F>(text: &str, mut replace: F) -> borrow::Cow<'_, str>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut rewritten = String::new();
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(i) = text[pos..].find('$') {
+        let start = pos + i;
+        pos = start + 1;
+        let after = &text[pos..];
+        let name_len = after
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(after.len());
+        let (name, tail) = after.split_at(name_len);
+        let len = if tail.starts_with('$') {
+            name_len + 1
+        } else if tail.starts_with(':') {
+            match tail.find(['$', '\n']) {
+                Some(end) if tail[end..].starts_with('$') => name_len + end + 1,
+                _ => continue,
+            }
+        } else {
+            continue;
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let end = pos + len;
+        if let Some(replacement) = replace(name) {
+            if replacement != text[start..end] {
+                rewritten.push_str(&text[copied..start]);
+                rewritten.push_str(&replacement);
+                copied = end;
+            }
+            pos = end;
+        }
+    }
+    if copied == 0 {
+        return borrow::Cow::Borrowed(text);
+    }
+    rewritten.push_str(&text[copied..]);
+    borrow::Cow::Owned(rewritten)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strip_keywords() {
+        assert_eq!(
+            strip("$Id: //depot/a#1 $ $Change: 42 $ $Author$"),
+            "$Id$ $Change$ $Author$"
+        );
+        assert_eq!(strip("cost: $5, $Other: x $"), "cost: $5, $Other: x $");
+        assert_eq!(strip("$Id: unterminated\n$"), "$Id: unterminated\n$");
+        assert!(match strip("$Id$ $Change$") {
+            borrow::Cow::Borrowed(_) => true,
+            borrow::Cow::Owned(_) => false,
+        });
+    }
+
+    #[test]
+    fn expand_keywords() {
+        let mut keywords = Keywords::new("//depot/a", 3, 42);
+        keywords.date_utc = Some("2018/08/13 10:11:12".parse().unwrap());
+        let text = "$Revision$ $Change: 41 $ $DateTimeUTC$ $Author$ $DateTime$";
+
+        let file_type: p4::FileType = "text+k".parse().unwrap();
+        assert_eq!(
+            expand(text, &file_type, &keywords),
+            "$Revision: #3 $ $Change: 42 $ $DateTimeUTC: 2018/08/13 10:11:12 $ $Author$ $DateTime$"
+        );
+
+        let file_type: p4::FileType = "text+ko".parse().unwrap();
+        assert_eq!(
+            expand("$Header$ $Change$", &file_type, &keywords),
+            "$Header: //depot/a#3 $ $Change$"
+        );
+
+        let file_type: p4::FileType = "text".parse().unwrap();
+        assert_eq!(expand(text, &file_type, &keywords), text);
+    }
+}
@@ -1,14 +1,79 @@
+#[cfg(feature = "chrono")]
 extern crate chrono;
+#[cfg(feature = "futures")]
+extern crate futures;
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
+mod config;
+mod discover;
+#[macro_use]
+mod exec;
 mod p4;
 mod parser;
+mod redact;
+mod trace;
 
 pub use p4::*;
+pub mod admin;
+pub mod bulk;
+pub mod cache;
+pub mod cachepurge;
+pub mod cancel;
+pub mod clone;
+pub mod configure;
 pub mod dirs;
 pub mod error;
+pub mod export;
+pub mod fetch;
 pub mod files;
+pub mod groups;
+pub mod health;
+#[cfg(feature = "it-harness")]
+pub mod harness;
+pub mod info;
+pub mod jobs;
+pub mod join;
+pub mod journals;
+pub mod key;
+pub mod keys;
+pub mod keywords;
+pub mod list;
+pub mod login;
+pub mod logout;
+pub mod logstat;
+pub mod logtail;
+pub mod monitor;
+pub mod observer;
+pub mod paths;
 pub mod print;
+pub mod progress;
+pub mod protects;
+pub mod push;
+pub mod rate_limit;
+pub mod raw;
+pub mod reload;
+pub mod retry;
+pub mod runner;
+pub mod servers;
+pub mod set;
+pub mod spec;
+#[cfg(feature = "futures")]
+pub mod stream;
 pub mod sync;
+pub mod tickets;
+pub mod triggers;
+pub mod trust;
+pub mod unload;
+pub mod unzip;
+pub mod view;
+pub mod walk;
 pub mod where_;
+pub mod zip;
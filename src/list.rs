@@ -0,0 +1,233 @@
+use std::borrow;
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Create a temporary list of files that can be used as a label
+///
+/// Creates an in-memory label containing the specified files.  The label
+/// only lasts for the connection's session unless `global` is used, and
+/// can be passed as a revision specifier (`@label`) to other commands
+/// such as sync.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let labels = p4.list("build-42").file("//depot/main/...@42").run().unwrap();
+/// for label in labels {
+///     println!("{:?}", label);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ListCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    label: &'f str,
+    file: Vec<borrow::Cow<'f, str>>,
+
+    delete: bool,
+    client_only: bool,
+    global: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> ListCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, label: &'f str) -> Self {
+        Self {
+            connection,
+            label,
+            file: vec![],
+            delete: false,
+            client_only: false,
+            global: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Files to include in the label.  Can be repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// The -d flag deletes the in-memory label.
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// The -C flag limits the files to those in the current client
+    /// workspace view.
+    pub fn client_only(mut self, client_only: bool) -> Self {
+        self.client_only = client_only;
+        self
+    }
+
+    /// The -M flag makes the label available to all servers in a
+    /// commit/edge installation, rather than just the current one.
+    pub fn global(mut self, global: bool) -> Self {
+        self.global = global;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&["list", "-l", self.label]);
+        if self.delete {
+            cmd.arg("-d");
+        }
+        if self.client_only {
+            cmd.arg("-C");
+        }
+        if self.global {
+            cmd.arg("-M");
+        }
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `list` command.
+    pub fn run(self) -> Result<ListLabels, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(ListLabels(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            list_parser::item,
+        )?))
+    }
+
+    /// Run the `list` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<ListLabel>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ListLabelItem = error::Item<ListLabel>;
+
+pub struct ListLabels(exec::Items<ListLabel>);
+
+impl IntoIterator for ListLabels {
+    type Item = ListLabelItem;
+    type IntoIter = ListLabelsIntoIter;
+
+    fn into_iter(self) -> ListLabelsIntoIter {
+        ListLabelsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ListLabelsIntoIter(exec::Items<ListLabel>);
+
+impl Iterator for ListLabelsIntoIter {
+    type Item = ListLabelItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ListLabelItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// An in-memory label that was created or deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ListLabel {
+    pub label: String,
+    /// Number of files in the label, when created.
+    pub file_count: Option<usize>,
+    pub deleted: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod list_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// e.g. `info: Label build-42 created with 1234 files.` or
+    /// `info: Label build-42 deleted.`
+    fn to_label(i: Info) -> Result<ListLabel, ()> {
+        let words: Vec<_> = i.msg.trim_end().trim_end_matches('.').split(' ').collect();
+        if words.len() < 3 || words[0] != "Label" {
+            return Err(());
+        }
+        let file_count = words
+            .windows(2)
+            .find(|w| w[1] == "files" || w[1] == "file")
+            .and_then(|w| w[0].parse().ok());
+        let deleted = words.contains(&"deleted");
+        if file_count.is_none() && !deleted {
+            return Err(());
+        }
+        let label = ListLabel {
+            label: words[1].to_owned(),
+            file_count,
+            deleted,
+            non_exhaustive: (),
+        };
+        Ok(label)
+    }
+
+    named!(pub item<&[u8], ListLabelItem>,
+        alt!(
+            map!(map_res!(info, to_label), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn list_create() {
+        let output: &[u8] = br#"info: Label build-42 created with 1234 files.
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, list_parser::item);
+        let label = items[0].as_data().unwrap();
+        assert_eq!(label.label, "build-42");
+        assert_eq!(label.file_count, Some(1234));
+        assert!(!label.deleted);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn list_delete() {
+        let output: &[u8] = br#"info: Label build-42 deleted.
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, list_parser::item);
+        let label = items[0].as_data().unwrap();
+        assert_eq!(label.file_count, None);
+        assert!(label.deleted);
+    }
+}
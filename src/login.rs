@@ -0,0 +1,342 @@
+use std::fmt;
+use std::process;
+use std::sync;
+use std::time;
+
+use error;
+use exec;
+use p4;
+use redact;
+
+/// Log in to a Perforce server
+///
+/// Login sets up a login ticket on the server that is valid for a limited
+/// time (set by the server's timeout), so that the password does not have to
+/// be passed on every command.  The password is piped to `p4` over stdin so it
+/// never shows up in the process list.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let logins = p4.login().password("secret").run().unwrap();
+/// for login in logins {
+///     println!("{:?}", login);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoginCommand<'p, 's> {
+    connection: &'p p4::P4,
+    user: Option<&'s str>,
+    password: Option<redact::Secret<&'s str>>,
+
+    all_hosts: bool,
+    print_ticket: bool,
+    status: bool,
+    host: Option<&'s str>,
+    settings: exec::Settings,
+}
+
+impl<'p, 's> LoginCommand<'p, 's> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            user: None,
+            password: None,
+            all_hosts: false,
+            print_ticket: false,
+            status: false,
+            host: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Log in as another user.  Only superusers can do this without a
+    /// password.
+    pub fn user(mut self, user: &'s str) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// The password to pipe over stdin, defaulting to `with_password`'s, then the connection's
+    /// password.
+    pub fn password(mut self, password: &'s str) -> Self {
+        self.password = Some(redact::Secret(password));
+        self
+    }
+
+    /// The -a flag causes the server to issue a ticket that is valid on all
+    /// host machines.
+    pub fn all_hosts(mut self, all_hosts: bool) -> Self {
+        self.all_hosts = all_hosts;
+        self
+    }
+
+    /// The -p flag displays the ticket, but does not store it on the client
+    /// machine.
+    pub fn print_ticket(mut self, print_ticket: bool) -> Self {
+        self.print_ticket = print_ticket;
+        self
+    }
+
+    /// The -s flag displays the status of the current ticket (if there is
+    /// one), including when it expires.
+    pub fn status(mut self, status: bool) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// The -h flag causes the server to issue a ticket that is valid on the
+    /// specified host (IP address).
+    pub fn host(mut self, host: &'s str) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    ///
+    /// The password is fed over stdin rather than being an argument, whatever the connection's
+    /// `PasswordMode`.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect_login(&self.settings);
+        cmd.arg("login");
+        if self.status {
+            cmd.arg("-s");
+        }
+        if self.all_hosts {
+            cmd.arg("-a");
+        }
+        if self.print_ticket {
+            cmd.arg("-p");
+        }
+        if let Some(host) = self.host {
+            cmd.args(&["-h", host]);
+        }
+        if let Some(user) = self.user {
+            cmd.arg(user);
+        }
+        cmd
+    }
+
+    /// Run the `login` command.
+    pub fn run(self) -> Result<Logins, error::P4Error> {
+        let cmd = self.to_command();
+        let password = self
+            .password
+            .map(|p| p.0)
+            .or_else(|| self.settings.password.as_ref().map(String::as_str))
+            .or_else(|| self.connection.password());
+        let input = password.map(|p| format!("{}\n", p).into_bytes());
+        let items = exec::spawn_with_input(
+            self.connection,
+            self.settings,
+            cmd,
+            input,
+            login_parser::item,
+        )?;
+        Ok(Logins(items))
+    }
+
+    /// Run the `login` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Login>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+/// Supplies the password for logging in again, see `P4::set_credentials`.
+#[derive(Clone)]
+pub(crate) struct Credentials(sync::Arc<dyn Fn() -> Option<String> + Send + Sync>);
+
+impl Credentials {
+    pub(crate) fn new<F>(credentials: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        Credentials(sync::Arc::new(credentials))
+    }
+
+    pub(crate) fn password(&self) -> Option<String> {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Credentials(<fn>)")
+    }
+}
+
+pub type LoginItem = error::Item<Login>;
+
+pub struct Logins(exec::Items<Login>);
+
+impl IntoIterator for Logins {
+    type Item = LoginItem;
+    type IntoIter = LoginsIntoIter;
+
+    fn into_iter(self) -> LoginsIntoIter {
+        LoginsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct LoginsIntoIter(exec::Items<Login>);
+
+impl Iterator for LoginsIntoIter {
+    type Item = LoginItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<LoginItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A login session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Login {
+    pub user: Option<String>,
+    /// The ticket, when requested with `print_ticket`.
+    pub ticket: Option<String>,
+    /// Time remaining until the ticket expires.
+    pub expiration: Option<time::Duration>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod login_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_login(fields: Vec<Field>) -> Result<Login, ::std::num::ParseIntError> {
+        let mut user = None;
+        let mut ticket = None;
+        let mut expiration = None;
+        for field in fields {
+            match field.key {
+                "User" | "user" => user = Some(field.value.to_owned()),
+                "ticket" | "Ticket" => ticket = Some(field.value.to_owned()),
+                "TicketExpiration" | "Expiration" | "expiration" => {
+                    expiration = Some(time::Duration::from_secs(field.value.parse()?))
+                }
+                _ => (),
+            }
+        }
+        let login = Login {
+            user,
+            ticket,
+            expiration,
+            non_exhaustive: (),
+        };
+        Ok(login)
+    }
+
+    /// `-p` prints the bare ticket, e.g. `info: 2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C`
+    fn to_ticket(i: Info) -> Result<Login, ()> {
+        let is_ticket = i.msg.len() == 32 && i.msg.bytes().all(|c| c.is_ascii_hexdigit());
+        if !is_ticket {
+            return Err(());
+        }
+        let login = Login {
+            user: None,
+            ticket: Some(i.msg.to_owned()),
+            expiration: None,
+            non_exhaustive: (),
+        };
+        Ok(login)
+    }
+
+    named!(pub item<&[u8], LoginItem>,
+        alt!(
+            map!(map_res!(many1!(any_field), to_login), data_to_item) |
+            map!(map_res!(info, to_ticket), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn login_status() {
+        let output: &[u8] = br#"info1: User alice
+info1: TicketExpiration 43162
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, login_parser::item);
+        let login = items[0].as_data().unwrap();
+        assert_eq!(login.user, Some("alice".to_owned()));
+        assert_eq!(login.expiration, Some(time::Duration::from_secs(43162)));
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn login_print_ticket() {
+        let output: &[u8] = br#"info: Enter password:
+info: 2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, login_parser::item);
+        assert!(items[0].as_message().is_some());
+        let login = items[1].as_data().unwrap();
+        assert_eq!(
+            login.ticket,
+            Some("2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C".to_owned())
+        );
+    }
+
+    #[test]
+    fn login_password_over_stdin() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"exit: 0\n".to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new()
+            .set_password(Some("secret".to_owned()))
+            .set_runner(Some(runner.clone()));
+        p4.login().run_checked().unwrap();
+        p4.login().with_password("override").run_checked().unwrap();
+        p4.login()
+            .with_password("override")
+            .password("explicit")
+            .run_checked()
+            .unwrap();
+
+        let calls = runner.calls.lock().unwrap();
+        let inputs: Vec<_> = calls
+            .iter()
+            .map(|&(ref args, ref input)| {
+                assert!(!args.iter().any(|a| a == "-P"), "{:?}", args);
+                assert!(!args.iter().any(|a| a.contains("secret")), "{:?}", args);
+                assert!(!args.iter().any(|a| a == "override"), "{:?}", args);
+                input.clone().unwrap()
+            })
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                b"secret\n".to_vec(),
+                b"override\n".to_vec(),
+                b"explicit\n".to_vec()
+            ]
+        );
+    }
+}
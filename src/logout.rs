@@ -0,0 +1,174 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Log out from a Perforce server
+///
+/// Logout removes any login ticket for the current user from the client
+/// machine and invalidates it on the server.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let logouts = p4.logout().all_hosts(true).run().unwrap();
+/// for logout in logouts {
+///     println!("{:?}", logout);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogoutCommand<'p, 'u> {
+    connection: &'p p4::P4,
+    user: Option<&'u str>,
+
+    all_hosts: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'u> LogoutCommand<'p, 'u> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            user: None,
+            all_hosts: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Log out another user.  Only superusers can log out other users.
+    pub fn user(mut self, user: &'u str) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// The -a flag invalidates the ticket on the server for all hosts, not
+    /// just the current one.
+    pub fn all_hosts(mut self, all_hosts: bool) -> Self {
+        self.all_hosts = all_hosts;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("logout");
+        if self.all_hosts {
+            cmd.arg("-a");
+        }
+        if let Some(user) = self.user {
+            cmd.arg(user);
+        }
+        cmd
+    }
+
+    /// Run the `logout` command.
+    pub fn run(self) -> Result<Logouts, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Logouts(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            logout_parser::item,
+        )?))
+    }
+
+    /// Run the `logout` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Logout>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type LogoutItem = error::Item<Logout>;
+
+pub struct Logouts(exec::Items<Logout>);
+
+impl IntoIterator for Logouts {
+    type Item = LogoutItem;
+    type IntoIter = LogoutsIntoIter;
+
+    fn into_iter(self) -> LogoutsIntoIter {
+        LogoutsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct LogoutsIntoIter(exec::Items<Logout>);
+
+impl Iterator for LogoutsIntoIter {
+    type Item = LogoutItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<LogoutItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A user that was logged out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Logout {
+    pub user: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod logout_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// e.g. `info: User alice logged out.`
+    fn to_logout(i: Info) -> Result<Logout, ()> {
+        let user = i
+            .msg
+            .trim_start_matches("User ")
+            .trim_end_matches(" logged out.");
+        if user.len() == i.msg.len() || user.contains(' ') {
+            return Err(());
+        }
+        let logout = Logout {
+            user: user.to_owned(),
+            non_exhaustive: (),
+        };
+        Ok(logout)
+    }
+
+    named!(pub item<&[u8], LogoutItem>,
+        alt!(
+            map!(map_res!(info, to_logout), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn logout_single() {
+        let output: &[u8] = br#"info: User alice logged out.
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, logout_parser::item);
+        assert_eq!(items[0].as_data().unwrap().user, "alice");
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Report size of journal, error log, and audit log files
+///
+/// Reports the size of the server's journal, error log, and audit log
+/// files, e.g. to decide when to rotate them.
+///
+/// Requires super access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let stats = p4.logstat().run().unwrap();
+/// for stat in stats {
+///     println!("{:?}", stat);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogstatCommand<'p> {
+    connection: &'p p4::P4,
+
+    include_structured: bool,
+    settings: exec::Settings,
+}
+
+impl<'p> LogstatCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            include_structured: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -s flag also reports the structured logs.
+    pub fn include_structured(mut self, include_structured: bool) -> Self {
+        self.include_structured = include_structured;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("logstat");
+        if self.include_structured {
+            cmd.arg("-s");
+        }
+        cmd
+    }
+
+    /// Run the `logstat` command.
+    pub fn run(self) -> Result<LogStats, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(LogStats(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            logstat_parser::item,
+        )?))
+    }
+
+    /// Run the `logstat` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<LogStat>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type LogStatItem = error::Item<LogStat>;
+
+pub struct LogStats(exec::Items<LogStat>);
+
+impl IntoIterator for LogStats {
+    type Item = LogStatItem;
+    type IntoIter = LogStatsIntoIter;
+
+    fn into_iter(self) -> LogStatsIntoIter {
+        LogStatsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct LogStatsIntoIter(exec::Items<LogStat>);
+
+impl Iterator for LogStatsIntoIter {
+    type Item = LogStatItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<LogStatItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// Sizes of the server's log files, in bytes.
+///
+/// Each file is `None` when the server doesn't have it enabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LogStat {
+    pub journal_file: Option<String>,
+    pub journal_size: Option<u64>,
+    pub log_file: Option<String>,
+    pub log_size: Option<u64>,
+    pub audit_file: Option<String>,
+    pub audit_size: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod logstat_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_size(value: &str) -> Result<u64, fmt::Error> {
+        value.parse().map_err(|_| fmt::Error)
+    }
+
+    fn to_stat(fields: Vec<Field>) -> Result<LogStat, fmt::Error> {
+        let mut stat = LogStat {
+            journal_file: None,
+            journal_size: None,
+            log_file: None,
+            log_size: None,
+            audit_file: None,
+            audit_size: None,
+            non_exhaustive: (),
+        };
+        for field in fields {
+            match field.key {
+                "journalFile" => stat.journal_file = Some(field.value.to_owned()),
+                "journalSize" => stat.journal_size = Some(to_size(field.value)?),
+                "logFile" => stat.log_file = Some(field.value.to_owned()),
+                "logSize" => stat.log_size = Some(to_size(field.value)?),
+                "auditFile" => stat.audit_file = Some(field.value.to_owned()),
+                "auditSize" => stat.audit_size = Some(to_size(field.value)?),
+                _ => (),
+            }
+        }
+        Ok(stat)
+    }
+
+    named!(pub item<&[u8], LogStatItem>,
+        alt!(
+            map!(map_res!(record, to_stat), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn logstat_sizes() {
+        let output: &[u8] = br#"info1: journalFile journal
+info1: journalSize 4096
+info1: logFile log
+info1: logSize 123456
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, logstat_parser::item);
+        let stat = items[0].as_data().unwrap();
+        assert_eq!(stat.journal_file, Some("journal".to_owned()));
+        assert_eq!(stat.journal_size, Some(4096));
+        assert_eq!(stat.log_size, Some(123456));
+        assert_eq!(stat.audit_file, None);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,219 @@
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Display the last block(s) of the errorLog
+///
+/// Bypasses the need for external access to the server's log file.  To
+/// poll incrementally, pass the `offset` of the last block back in as the
+/// next `start_offset`.
+///
+/// Requires super access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let blocks = p4.logtail().start_offset(1024).run().unwrap();
+/// for block in blocks {
+///     println!("{:?}", block);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogtailCommand<'p> {
+    connection: &'p p4::P4,
+
+    block_size: Option<usize>,
+    start_offset: Option<u64>,
+    max_blocks: Option<usize>,
+    settings: exec::Settings,
+}
+
+impl<'p> LogtailCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            block_size: None,
+            start_offset: None,
+            max_blocks: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -b flag specifies the block size in bytes (default 8192).
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    /// The -s flag specifies the offset from which to start displaying
+    /// log data, typically the `offset` from a previous call.
+    pub fn start_offset(mut self, start_offset: u64) -> Self {
+        self.start_offset = Some(start_offset);
+        self
+    }
+
+    /// The -m flag specifies the maximum number of blocks to output.
+    pub fn set_max(mut self, max_blocks: Option<usize>) -> Self {
+        self.max_blocks = max_blocks;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("logtail");
+        if let Some(block_size) = self.block_size {
+            let block_size = format!("{}", block_size);
+            cmd.args(&["-b", &block_size]);
+        }
+        if let Some(start_offset) = self.start_offset {
+            let start_offset = format!("{}", start_offset);
+            cmd.args(&["-s", &start_offset]);
+        }
+        if let Some(max_blocks) = self.max_blocks {
+            let max_blocks = format!("{}", max_blocks);
+            cmd.args(&["-m", &max_blocks]);
+        }
+        cmd
+    }
+
+    /// Run the `logtail` command.
+    pub fn run(self) -> Result<LogBlocks, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(LogBlocks(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            logtail_parser::item,
+        )?))
+    }
+
+    /// Run the `logtail` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<LogBlock>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type LogBlockItem = error::Item<LogBlock>;
+
+pub struct LogBlocks(exec::Items<LogBlock>);
+
+impl IntoIterator for LogBlocks {
+    type Item = LogBlockItem;
+    type IntoIter = LogBlocksIntoIter;
+
+    fn into_iter(self) -> LogBlocksIntoIter {
+        LogBlocksIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct LogBlocksIntoIter(exec::Items<LogBlock>);
+
+impl Iterator for LogBlocksIntoIter {
+    type Item = LogBlockItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<LogBlockItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A block of the server log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LogBlock {
+    pub file: String,
+    pub data: String,
+    /// Where the next block starts, for passing to `start_offset`.
+    pub offset: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod logtail_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_data(input: &[u8]) -> Result<&str, str::Utf8Error> {
+        str::from_utf8(input)
+    }
+
+    named!(data<&[u8], &str>,
+        map_res!(
+            delimited!(
+                tag!(b"info1: data "),
+                take_until!("\ninfo1: offset "),
+                tag!(b"\n")
+            ),
+            to_data
+        )
+    );
+
+    named!(block<&[u8], LogBlock>,
+        do_parse!(
+            file: call!(field, "file") >>
+            data: data >>
+            offset: map_res!(call!(field, "offset"), str::parse::<u64>) >>
+            (
+                LogBlock {
+                    file: file.to_owned(),
+                    data: data.to_owned(),
+                    offset,
+                    non_exhaustive: (),
+                }
+            )
+        )
+    );
+
+    named!(pub item<&[u8], LogBlockItem>,
+        alt!(
+            map!(block, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn logtail_multi_line() {
+        let output: &[u8] = br#"info1: file log
+info1: data Perforce server info:
+	2018/08/13 10:11:12 pid 1234 alice@alice_ws 127.0.0.1 [p4/2018.1] 'user-sync //...'
+info1: offset 2048
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, logtail_parser::item);
+        let block = items[0].as_data().unwrap();
+        assert_eq!(block.file, "log");
+        assert!(block
+            .data
+            .starts_with("Perforce server info:\n\t2018/08/13"));
+        assert!(block.data.ends_with("'user-sync //...'"));
+        assert_eq!(block.offset, 2048);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt;
+use std::process;
+use std::str;
+use std::time;
+
+use error;
+use exec;
+use p4;
+
+/// Display current running commands
+///
+/// By default, lists the processes running on the server.  Use
+/// `terminate` or `clear` to manage them.
+///
+/// Requires that the server's `monitor` configurable is set, and admin or
+/// super access for most options.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let processes = p4
+///     .monitor()
+///     .all_args(true)
+///     .long(true)
+///     .extended(true)
+///     .run()
+///     .unwrap();
+/// for process in processes {
+///     println!("{:?}", process);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MonitorCommand<'p, 'i> {
+    connection: &'p p4::P4,
+
+    all_args: bool,
+    long: bool,
+    extended: bool,
+    terminate: Option<&'i str>,
+    clear: Option<&'i str>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'i> MonitorCommand<'p, 'i> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            all_args: false,
+            long: false,
+            extended: false,
+            terminate: None,
+            clear: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -a flag includes the command args.
+    pub fn all_args(mut self, all_args: bool) -> Self {
+        self.all_args = all_args;
+        self
+    }
+
+    /// The -l flag displays long output, including the full username and
+    /// argument list.
+    pub fn long(mut self, long: bool) -> Self {
+        self.long = long;
+        self
+    }
+
+    /// The -e flag includes the client application, host address and
+    /// client name.
+    pub fn extended(mut self, extended: bool) -> Self {
+        self.extended = extended;
+        self
+    }
+
+    /// 'p4 monitor terminate' marks the specified process for termination.
+    /// The process is cancelled at the next point where it checks for
+    /// termination.
+    pub fn terminate(mut self, id: &'i str) -> Self {
+        self.terminate = Some(id);
+        self
+    }
+
+    /// 'p4 monitor clear' removes the specified process record from the
+    /// monitor table.  Use "all" to clear all records.
+    pub fn clear(mut self, id: &'i str) -> Self {
+        self.clear = Some(id);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("monitor");
+        if let Some(id) = self.terminate {
+            cmd.args(&["terminate", id]);
+        } else if let Some(id) = self.clear {
+            cmd.args(&["clear", id]);
+        } else {
+            cmd.arg("show");
+            if self.all_args {
+                cmd.arg("-a");
+            }
+            if self.long {
+                cmd.arg("-l");
+            }
+            if self.extended {
+                cmd.arg("-e");
+            }
+        }
+        cmd
+    }
+
+    /// Run the `monitor` command.
+    pub fn run(self) -> Result<Processes, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Processes(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            monitor_parser::item,
+        )?))
+    }
+
+    /// Run the `monitor` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Process>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ProcessItem = error::Item<Process>;
+
+pub struct Processes(exec::Items<Process>);
+
+impl IntoIterator for Processes {
+    type Item = ProcessItem;
+    type IntoIter = ProcessesIntoIter;
+
+    fn into_iter(self) -> ProcessesIntoIter {
+        ProcessesIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ProcessesIntoIter(exec::Items<Process>);
+
+impl Iterator for ProcessesIntoIter {
+    type Item = ProcessItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ProcessItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A process running on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Process {
+    pub id: usize,
+    pub status: Status,
+    pub user: String,
+    pub elapsed: time::Duration,
+    pub command: String,
+    /// Command arguments, with `all_args`.
+    pub args: Option<String>,
+    /// Client application, with `extended`.
+    pub application: Option<String>,
+    /// Host address, with `extended`.
+    pub host: Option<String>,
+    /// Client name, with `extended`.
+    pub client: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// State of a server process.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::monitor::Status::Running.to_string(), "R");
+/// assert_eq!(
+///     "T".parse::<p4_cmd::monitor::Status>().unwrap(),
+///     p4_cmd::monitor::Status::Terminated
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Status {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Running,
+    Terminated,
+    Paused,
+    Idle,
+    Background,
+    Finished,
+
+    Unknown(String),
+}
+
+impl str::FromStr for Status {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let status = match s {
+            "R" => Status::Running,
+            "T" => Status::Terminated,
+            "P" => Status::Paused,
+            "I" => Status::Idle,
+            "B" => Status::Background,
+            "F" => Status::Finished,
+            s => Status::Unknown(s.to_owned()),
+        };
+        Ok(status)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            Status::Running => "R",
+            Status::Terminated => "T",
+            Status::Paused => "P",
+            Status::Idle => "I",
+            Status::Background => "B",
+            Status::Finished => "F",
+            Status::Unknown(ref s) => s.as_str(),
+            Status::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+mod monitor_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// e.g. `01:02:03`
+    fn to_elapsed(s: &str) -> Result<time::Duration, fmt::Error> {
+        let mut seconds = 0;
+        for part in s.split(':') {
+            let part: u64 = part.parse().map_err(|_| fmt::Error)?;
+            seconds = seconds * 60 + part;
+        }
+        Ok(time::Duration::from_secs(seconds))
+    }
+
+    fn to_process(fields: Vec<Field>) -> Result<Process, fmt::Error> {
+        let mut id = None;
+        let mut status = None;
+        let mut user = None;
+        let mut elapsed = None;
+        let mut command = None;
+        let mut args = None;
+        let mut application = None;
+        let mut host = None;
+        let mut client = None;
+        for field in fields {
+            match field.key {
+                "id" => id = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                "status" => status = Some(field.value.parse()?),
+                "owner" => user = Some(field.value.to_owned()),
+                "time" => elapsed = Some(to_elapsed(field.value)?),
+                "command" => command = Some(field.value.to_owned()),
+                "args" => args = Some(field.value.to_owned()),
+                "prog" => application = Some(field.value.to_owned()),
+                "host" => host = Some(field.value.to_owned()),
+                "client" => client = Some(field.value.to_owned()),
+                _ => (),
+            }
+        }
+        let process = Process {
+            id: id.ok_or(fmt::Error)?,
+            status: status.ok_or(fmt::Error)?,
+            user: user.ok_or(fmt::Error)?,
+            elapsed: elapsed.ok_or(fmt::Error)?,
+            command: command.ok_or(fmt::Error)?,
+            args,
+            application,
+            host,
+            client,
+            non_exhaustive: (),
+        };
+        Ok(process)
+    }
+
+    named!(pub item<&[u8], ProcessItem>,
+        alt!(
+            map!(map_res!(record, to_process), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn monitor_show() {
+        let output: &[u8] = br#"info1: id 1234
+info1: status R
+info1: owner alice
+info1: time 01:02:03
+info1: command sync
+info1: args //depot/...
+info1: prog p4/2018.1/LINUX26X86_64/1637071
+info1: host 10.0.0.1
+info1: client alice_ws
+info1: id 1235
+info1: status I
+info1: owner bob
+info1: time 00:00:05
+info1: command monitor
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, monitor_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.id, 1234);
+        assert_eq!(first.status, Status::Running);
+        assert_eq!(first.elapsed, time::Duration::from_secs(3723));
+        assert_eq!(first.args, Some("//depot/...".to_owned()));
+        assert_eq!(first.client, Some("alice_ws".to_owned()));
+        assert_eq!(last.user, "bob");
+        assert_eq!(last.args, None);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,133 @@
+//! Watch commands as they run, e.g. to collect metrics.
+//!
+//! An application registers a [`P4Observer`](trait.P4Observer.html) with
+//! [`P4::set_observer`](../struct.P4.html#method.set_observer) to be told when each `p4`
+//! invocation starts and how it went, without this crate depending on any metrics library.
+//! Commands that are retried are observed once per attempt.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::io;
+//! use std::process;
+//! use std::sync;
+//! use std::sync::atomic;
+//!
+//! #[derive(Debug, Default)]
+//! struct Counter {
+//!     started: atomic::AtomicUsize,
+//!     failed: atomic::AtomicUsize,
+//! }
+//!
+//! impl p4_cmd::observer::P4Observer for Counter {
+//!     fn on_start(&self, _command: &str) {
+//!         self.started.fetch_add(1, atomic::Ordering::Relaxed);
+//!     }
+//!
+//!     fn on_complete(&self, completion: &p4_cmd::observer::Completion) {
+//!         if completion.exit_code != 0 {
+//!             self.failed.fetch_add(1, atomic::Ordering::Relaxed);
+//!         }
+//!     }
+//! }
+//!
+//! #[derive(Debug)]
+//! struct Canned(&'static [u8]);
+//!
+//! impl p4_cmd::runner::P4Runner for Canned {
+//!     fn run(
+//!         &self,
+//!         _cmd: process::Command,
+//!         _options: p4_cmd::runner::RunOptions,
+//!     ) -> io::Result<Box<dyn io::Read + Send>> {
+//!         Ok(Box::new(self.0))
+//!     }
+//! }
+//!
+//! let counter = sync::Arc::new(Counter::default());
+//! let p4 = p4_cmd::P4::new()
+//!     .set_runner(Some(sync::Arc::new(Canned(b"error: bad\nexit: 1\n"))))
+//!     .set_observer(Some(counter.clone()));
+//! assert!(p4.keys().run_checked().is_err());
+//! assert_eq!(counter.started.load(atomic::Ordering::Relaxed), 1);
+//! assert_eq!(counter.failed.load(atomic::Ordering::Relaxed), 1);
+//! ```
+
+use std::fmt;
+use std::sync;
+use std::time;
+
+use error;
+
+/// Told about each `p4` invocation.
+pub trait P4Observer: fmt::Debug + Send + Sync {
+    /// `p4` is about to be run for `command`, e.g. `sync`.
+    fn on_start(&self, command: &str) {
+        let _ = command;
+    }
+
+    /// `p4` finished, or was stopped.
+    fn on_complete(&self, completion: &Completion) {
+        let _ = completion;
+    }
+}
+
+/// How a `p4` invocation went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Completion {
+    /// The command, e.g. `sync`.
+    pub command: String,
+    /// How long `p4` ran, from being started until its output was done being read.
+    pub duration: time::Duration,
+    /// How much output was read.
+    pub bytes_read: u64,
+    /// The exit code `p4` reported, `-1` if it was stopped first.
+    pub exit_code: i32,
+    /// Why `p4` was stopped, or why it failed when the reason is recognizable.
+    pub error_kind: Option<error::ErrorKind>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// Tracks one attempt at a command for an observer.
+#[derive(Debug)]
+pub(crate) struct Observation {
+    observer: sync::Arc<dyn P4Observer>,
+    command: String,
+    started: time::Instant,
+    bytes_read: u64,
+}
+
+impl Observation {
+    pub(crate) fn new(observer: sync::Arc<dyn P4Observer>, command: &str) -> Self {
+        Self {
+            observer,
+            command: command.to_owned(),
+            started: time::Instant::now(),
+            bytes_read: 0,
+        }
+    }
+
+    pub(crate) fn start(&mut self) {
+        self.started = time::Instant::now();
+        self.bytes_read = 0;
+        self.observer.on_start(&self.command);
+    }
+
+    pub(crate) fn read(&mut self, bytes: usize) {
+        self.bytes_read += bytes as u64;
+    }
+
+    pub(crate) fn complete(&self, error: &error::OperationError) {
+        let completion = Completion {
+            command: self.command.clone(),
+            duration: self.started.elapsed(),
+            bytes_read: self.bytes_read,
+            exit_code: error.code(),
+            error_kind: error.kind(),
+            non_exhaustive: (),
+        };
+        self.observer.on_complete(&completion);
+    }
+}
@@ -1,25 +1,101 @@
+use std::env;
+use std::ffi;
 use std::fmt;
 use std::path;
 use std::process;
 use std::str;
+use std::time;
 
+#[cfg(feature = "chrono")]
 use chrono;
-use chrono::TimeZone;
 
+use admin;
+use cache;
+use cachepurge;
+use clone;
+use config;
+use configure;
 use dirs;
+use discover;
+use error;
+use exec;
+use export;
+use fetch;
 use files;
+use groups;
+use health;
+use info;
+use jobs;
+use journals;
+use key;
+use keys;
+use list;
+use login;
+use logout;
+use logstat;
+use logtail;
+use monitor;
+use observer;
+use paths;
 use print;
+use protects;
+use push;
+use rate_limit;
+use raw;
+use redact;
+use reload;
+use retry;
+use runner;
+use servers;
+use set;
+use spec;
 use sync;
+use tickets;
+use triggers;
+use trust;
+use unload;
+use unzip;
+use walk;
 use where_;
+use zip;
+
+/// Has `p4` talk to a unicode server in UTF-8, as the option and its value.
+pub(crate) const UNICODE: [&str; 2] = ["-C", "utf8"];
 
 #[derive(Clone, Debug)]
 pub struct P4 {
     custom_p4: Option<path::PathBuf>,
     port: Option<String>,
     user: Option<String>,
-    password: Option<String>,
+    password: Option<redact::Secret<String>>,
+    password_mode: PasswordMode,
     client: Option<String>,
+    directory: Option<path::PathBuf>,
+    host: Option<String>,
+    command_charset: Option<String>,
+    language: Option<String>,
+    tickets_file: Option<path::PathBuf>,
+    enviro_file: Option<path::PathBuf>,
+    login_sso: Option<path::PathBuf>,
+    env_clear: bool,
+    envs: Vec<(ffi::OsString, Option<ffi::OsString>)>,
     retries: Option<usize>,
+    timeout: Option<time::Duration>,
+    retry_policy: Option<retry::RetryPolicy>,
+    auto_login: bool,
+    credentials: Option<login::Credentials>,
+    trusted_fingerprint: Option<String>,
+    verbosity: Option<String>,
+    z_tags: Vec<String>,
+    message_codes: bool,
+    observer: Option<::std::sync::Arc<dyn observer::P4Observer>>,
+    rate_limiter: Option<rate_limit::RateLimiter>,
+    cache: Option<cache::CommandCache>,
+    server_version: Option<info::ServerVersion>,
+    unicode: Option<bool>,
+    /// Whether the server turned out to be in unicode mode, when `unicode` isn't set.
+    detected_unicode: ::std::sync::Arc<::std::sync::Mutex<Option<bool>>>,
+    runner: Option<::std::sync::Arc<dyn runner::P4Runner>>,
 }
 
 impl P4 {
@@ -29,15 +105,111 @@ impl P4 {
             port: None,
             user: None,
             password: None,
+            password_mode: PasswordMode::Argument,
             client: None,
+            directory: None,
+            host: None,
+            command_charset: None,
+            language: None,
+            tickets_file: None,
+            enviro_file: None,
+            login_sso: None,
+            env_clear: false,
+            envs: Vec::new(),
             retries: None,
+            timeout: None,
+            retry_policy: None,
+            auto_login: false,
+            credentials: None,
+            trusted_fingerprint: None,
+            verbosity: None,
+            z_tags: Vec::new(),
+            message_codes: false,
+            observer: None,
+            rate_limiter: None,
+            cache: None,
+            server_version: None,
+            unicode: None,
+            detected_unicode: Default::default(),
+            runner: None,
+        }
+    }
+
+    /// Use the first working `p4` on `PATH` or in the usual install locations.
+    ///
+    /// The search, which checks `p4 -V` runs, is only done once per process.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::discover().unwrap();
+    /// let files = p4.files("//depot/dir/*").run().unwrap();
+    /// for file in files {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn discover() -> Result<Self, error::P4Error> {
+        Ok(Self::new().set_p4_cmd(Some(discover::p4()?)))
+    }
+
+    /// Connect using the settings of the P4CONFIG file in `dir` or its closest ancestor.
+    ///
+    /// The file is named by the `P4CONFIG` environment variable, defaulting to `.p4config`.
+    ///
+    /// Like `p4`, this also honors the P4ENVIRO file (`$P4ENVIRO`, defaulting to
+    /// `~/.p4enviro`), for settings that neither the P4CONFIG file nor environment variables
+    /// set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::from_config(std::env::current_dir().unwrap()).unwrap();
+    /// let files = p4.files("//depot/dir/*").run().unwrap();
+    /// for file in files {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn from_config<P>(dir: P) -> Result<Self, error::P4Error>
+    where
+        P: AsRef<path::Path>,
+    {
+        let mut p4 = Self::new();
+        if let Some(path) = config::enviro() {
+            let settings = config::read(&path)?
+                .into_iter()
+                .filter(|(name, _)| env::var_os(name).is_none())
+                .collect();
+            p4 = p4.apply_settings(settings);
+        }
+        if let Some(path) = config::find(dir.as_ref()) {
+            p4 = p4.apply_settings(config::read(&path)?);
+        }
+        Ok(p4)
+    }
+
+    /// Apply `P4PORT`-style settings, like those in P4CONFIG files.
+    fn apply_settings(mut self, settings: Vec<(String, String)>) -> Self {
+        for (name, value) in settings {
+            match name.as_str() {
+                "P4PORT" => self.port = Some(value),
+                "P4USER" => self.user = Some(value),
+                "P4PASSWD" => self.password = Some(redact::Secret(value)),
+                "P4CLIENT" => self.client = Some(value),
+                "P4HOST" => self.host = Some(value),
+                "P4COMMANDCHARSET" => self.command_charset = Some(value),
+                "P4LANGUAGE" => self.language = Some(value),
+                "P4TICKETS" => self.tickets_file = Some(value.into()),
+                // `p4` applies the rest itself.
+                _ => self.envs.push((name.into(), Some(value.into()))),
+            }
         }
+        self
     }
 
     /// Overrides the `p4` command used.
     ///
     /// This is useful for "portable" installs (not in system path) and performance (caching the
-    /// `PATH` lookup via `where` crate).
+    /// `PATH` lookup, see `discover`).
     pub fn set_p4_cmd(mut self, custom_p4: Option<path::PathBuf>) -> Self {
         self.custom_p4 = custom_p4;
         self
@@ -57,7 +229,13 @@ impl P4 {
 
     /// Overrides any P4PASSWD setting with the specified passwo
     pub fn set_password(mut self, password: Option<String>) -> Self {
-        self.password = password;
+        self.password = password.map(redact::Secret);
+        self
+    }
+
+    /// Controls how the password is handed to `p4`, defaulting to `PasswordMode::Argument`.
+    pub fn set_password_mode(mut self, password_mode: PasswordMode) -> Self {
+        self.password_mode = password_mode;
         self
     }
 
@@ -67,6 +245,99 @@ impl P4 {
         self
     }
 
+    /// Overrides any PWD setting (i.e. current working directory) and replaces it with the
+    /// specified directory.
+    ///
+    /// Relative and client-syntax paths, as well as P4CONFIG files, are resolved as if commands
+    /// were run from `directory`.
+    pub fn set_directory(mut self, directory: Option<path::PathBuf>) -> Self {
+        self.directory = directory;
+        self
+    }
+
+    /// Overrides any P4HOST setting with the specified hostname.
+    ///
+    /// This is needed to use clients whose Host field names a different machine, like shared
+    /// build workspaces.
+    pub fn set_host(mut self, host: Option<String>) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Overrides any P4COMMANDCHARSET setting with the specified charset.
+    ///
+    /// This controls how `p4` interprets its arguments, like file names with non-ASCII
+    /// characters, when the OS locale differs from the server's charset.
+    pub fn set_command_charset(mut self, command_charset: Option<String>) -> Self {
+        self.command_charset = command_charset;
+        self
+    }
+
+    /// Overrides any P4LANGUAGE setting with the specified language, for servers with localized
+    /// message catalogs.
+    ///
+    /// Only message text is localized, so output still parses.  With `set_message_codes`,
+    /// connection failures are still recognized from the messages' codes; other failures are
+    /// only recognized from English messages.
+    pub fn set_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Overrides any P4TICKETS setting with the specified file.
+    ///
+    /// This lets each connection keep its own logins, e.g. for services acting as several users.
+    pub fn set_tickets_file(mut self, tickets_file: Option<path::PathBuf>) -> Self {
+        self.tickets_file = tickets_file;
+        self
+    }
+
+    /// Overrides any P4ENVIRO setting with the specified file.
+    ///
+    /// `p4` reads settings that aren't set elsewhere from this file, and on Linux and macOS,
+    /// `p4 set` writes to it.
+    pub fn set_enviro_file(mut self, enviro_file: Option<path::PathBuf>) -> Self {
+        self.enviro_file = enviro_file;
+        self
+    }
+
+    /// Overrides any P4LOGINSSO setting with the specified single sign-on agent.
+    ///
+    /// `p4 login` runs the agent to authenticate the user.  Any variables it needs can be set with
+    /// `env`.  Failures are reported as `ErrorKind::SsoFailed`.
+    pub fn set_login_sso(mut self, login_sso: Option<path::PathBuf>) -> Self {
+        self.login_sso = login_sso;
+        self
+    }
+
+    /// Set an environment variable for `p4`, e.g. `P4TRUST`.
+    pub fn env<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<ffi::OsString>,
+        V: Into<ffi::OsString>,
+    {
+        self.envs.push((key.into(), Some(value.into())));
+        self
+    }
+
+    /// Remove an environment variable for `p4`, e.g. `P4CONFIG`.
+    pub fn env_remove<K>(mut self, key: K) -> Self
+    where
+        K: Into<ffi::OsString>,
+    {
+        self.envs.push((key.into(), None));
+        self
+    }
+
+    /// Don't pass this process's environment on to `p4`, only variables set with `env`.
+    ///
+    /// This is useful for reproducible runs, unaffected by the user's settings.
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self.envs.clear();
+        self
+    }
+
     /// Number of times a command should be retried if the network times out (takes longer than N
     /// seconds to respond to a single I/O operation) during command execution.
     pub fn set_retries(mut self, retries: Option<usize>) -> Self {
@@ -74,6 +345,175 @@ impl P4 {
         self
     }
 
+    /// Kill commands that take longer than `timeout`, reporting `ErrorKind::TimedOut`.
+    ///
+    /// Commands can override this with their own `timeout`.
+    pub fn set_timeout(mut self, timeout: Option<time::Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Re-run commands that fail before reporting any data.
+    ///
+    /// See the [`retry`](retry/index.html) module.
+    pub fn set_retry_policy(mut self, retry_policy: Option<retry::RetryPolicy>) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Log in again and re-run commands that fail because the user's session expired.
+    ///
+    /// The password comes from `set_credentials`, falling back to the connection's password.
+    /// Each command is re-run at most once.
+    pub fn set_auto_login(mut self, auto_login: bool) -> Self {
+        self.auto_login = auto_login;
+        self
+    }
+
+    /// Supply the password for logging in again, e.g. by prompting the user.
+    ///
+    /// See `set_auto_login`.
+    pub fn set_credentials<F>(mut self, credentials: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.credentials = Some(login::Credentials::new(credentials));
+        self
+    }
+
+    /// Trust an `ssl:` server with this fingerprint when `p4` can't establish its authenticity.
+    ///
+    /// Commands failing with `ErrorKind::TrustRequired` install the fingerprint with
+    /// `p4 trust -f -i` and are re-run once.  This replaces any fingerprint previously trusted
+    /// for the server.
+    pub fn set_trusted_fingerprint(mut self, fingerprint: Option<String>) -> Self {
+        self.trusted_fingerprint = fingerprint;
+        self
+    }
+
+    /// Trace what `p4` does (`-v`) at the specified level, e.g. `3` or `net=3,rpc=5`.
+    ///
+    /// The trace is kept apart from the parsed records and is available from the command's final
+    /// `OperationError::diagnostics`, or `P4Error::diagnostics` if it failed.
+    pub fn set_verbosity(mut self, verbosity: Option<String>) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Pass a protocol tag with `-Z`, e.g. `proxyload` to have a proxy cache files without sending
+    /// them, or `proxyverbose` to report which files a proxy delivered.
+    ///
+    /// Together with `ServerInfo::is_via_proxy`, this lets build farms check they're using the
+    /// intended proxy.
+    pub fn z_tag<S>(mut self, tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.z_tags.push(tag.into());
+        self
+    }
+
+    /// Report each message's code (`-e`), available from `Message::code`.
+    ///
+    /// Codes identify messages regardless of wording or language, e.g. to tell a file not
+    /// existing apart from the user not having permission to see it.
+    pub fn set_message_codes(mut self, message_codes: bool) -> Self {
+        self.message_codes = message_codes;
+        self
+    }
+
+    /// Tell `observer` about each `p4` invocation, e.g. to collect metrics.
+    ///
+    /// See the [`observer`](observer/index.html) module.
+    pub fn set_observer(
+        mut self,
+        observer: Option<::std::sync::Arc<dyn observer::P4Observer>>,
+    ) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Limit how often `p4` is run, e.g. to stay under a server's command limits.
+    ///
+    /// See the [`rate_limit`](rate_limit/index.html) module.
+    pub fn set_rate_limiter(mut self, rate_limiter: Option<rate_limit::RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Re-use recent results of read-only commands, like `where` and `dirs`.
+    ///
+    /// See the [`cache`](cache/index.html) module.
+    pub fn set_cache(mut self, cache: Option<cache::CommandCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// The server's release, so commands can reject options it doesn't support with
+    /// `ErrorKind::UnsupportedByServer`.
+    ///
+    /// When unknown, options are passed through for the server to judge.
+    pub fn set_server_version(mut self, server_version: Option<info::ServerVersion>) -> Self {
+        self.server_version = server_version;
+        self
+    }
+
+    /// Whether the server is in unicode mode, deciding whether `p4` is run with `-C utf8`.
+    ///
+    /// When unknown, `-C utf8` is passed until the server rejects it, after which commands are
+    /// retried and run without it.
+    pub fn set_unicode(mut self, unicode: Option<bool>) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Run `info` to learn about the server, like its release (see `set_server_version`) and
+    /// whether it's in unicode mode (see `set_unicode`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new().detect_server().unwrap();
+    /// let files = p4.sync("//depot/dir/...").parallel(4).run_checked();
+    /// if let Err(e) = files {
+    ///     if e.kind() == p4_cmd::error::ErrorKind::UnsupportedByServer {
+    ///         println!("Falling back to a serial sync");
+    ///     }
+    /// }
+    /// ```
+    pub fn detect_server(self) -> Result<Self, error::P4Error> {
+        let infos = self.info().run_checked()?;
+        let version = infos.first().and_then(info::ServerInfo::version);
+        let unicode = infos.first().map(|info| info.unicode);
+        Ok(self.set_server_version(version).set_unicode(unicode))
+    }
+
+    /// Overrides how `p4` is run, defaulting to a `p4` child process.
+    ///
+    /// This is useful for testing code using this crate without a live Perforce server.  See the
+    /// [`runner`](runner/index.html) module.
+    pub fn set_runner(mut self, runner: Option<::std::sync::Arc<dyn runner::P4Runner>>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Check whether commands can be run, e.g. before starting a long operation.
+    ///
+    /// This runs the quick `info` and `login -s` commands, classifying common failures.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// match p4.check_connection().unwrap() {
+    ///     p4_cmd::health::ConnectionStatus::Ok => println!("Ready"),
+    ///     status => println!("Not ready: {:?}", status),
+    /// }
+    /// ```
+    pub fn check_connection(&self) -> Result<health::ConnectionStatus, error::P4Error> {
+        health::check(self)
+    }
+
     /// Write a depot file to standard output
     ///
     /// Retrieve the contents of a depot file to the client's standard output.
@@ -140,134 +580,1300 @@ impl P4 {
     ///     println!("{:?}", dir);
     /// }
     /// ```
-    pub fn sync<'p, 'f>(&'p self, file: &'f str) -> sync::SyncCommand<'p, 'f> {
-        sync::SyncCommand::new(self, file)
+    pub fn sync<'p, 'f>(&'p self, file: &'f str) -> sync::SyncCommand<'p, 'f> {
+        sync::SyncCommand::new(self, file)
+    }
+
+    /// List files in the depot.
+    ///
+    /// List details about specified files: depot file name, revision,
+    /// file, type, change action and changelist number of the current
+    /// head revision. If client syntax is used to specify the file
+    /// argument, the client view mapping is used to determine the
+    /// corresponding depot files.
+    ///
+    /// By default, the head revision is listed.  If the file argument
+    /// specifies a revision, then all files at that revision are listed.
+    /// If the file argument specifies a revision range, the highest revision
+    /// in the range is used for each file. For details about specifying
+    /// revisions, see 'p4 help revisions'.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let files = p4.files("//depot/dir/*").run().unwrap();
+    /// for file in files {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn files<'p, 'f>(&'p self, file: &'f str) -> files::FilesCommand<'p, 'f> {
+        files::FilesCommand::new(self, file)
+    }
+
+    /// List depot subdirectories
+    ///
+    /// List directories that match the specified file pattern (dir).
+    /// This command does not support the recursive wildcard (...).
+    /// Use the * wildcard instead.
+    ///
+    /// Perforce does not track directories individually. A path is treated
+    /// as a directory if there are any undeleted files with that path as a
+    /// prefix.
+    ///
+    /// By default, all directories containing files are listed. If the dir
+    /// argument includes a revision range, only directories containing files
+    /// in the range are listed. For details about specifying file revisions,
+    /// see 'p4 help revisions'.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let dirs = p4.dirs("//depot/dir/*").run().unwrap();
+    /// for dir in dirs {
+    ///     println!("{:?}", dir);
+    /// }
+    /// ```
+    pub fn dirs<'p, 'f, 's>(&'p self, dir: &'f str) -> dirs::DirsCommand<'p, 'f, 's> {
+        dirs::DirsCommand::new(self, dir)
+    }
+
+    /// List every depot directory under `root`, a level at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let dirs = p4.dirs_walk("//depot/dir").run_checked().unwrap();
+    /// for dir in dirs {
+    ///     println!("{}", dir.dir);
+    /// }
+    /// ```
+    pub fn dirs_walk<'p>(&'p self, root: &str) -> dirs::DirsWalk<'p> {
+        dirs::DirsWalk::new(self, root)
+    }
+
+    /// Walk the directories and files under `root`, a level at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// for entry in p4.walk("//depot/dir").max_depth(2).run() {
+    ///     let entry = entry.unwrap();
+    ///     println!("{} {}", entry.depth, entry.path());
+    /// }
+    /// ```
+    pub fn walk<'p>(&'p self, root: &str) -> walk::DepotWalk<'p> {
+        walk::DepotWalk::new(self, root)
+    }
+
+    /// Show how file names are mapped by the client view
+    ///
+    /// Where shows how the specified files are mapped by the client view.
+    /// For each argument, three names are produced: the name in the depot,
+    /// the name on the client in Perforce syntax, and the name on the client
+    /// in local syntax.
+    ///
+    /// If the file parameter is omitted, the mapping for all files in the
+    /// current directory and below) is returned.
+    ///
+    /// Note that 'p4 where' does not determine where any real files reside.
+    /// It only displays the locations that are mapped by the client view.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let files = p4.where_().file("//depot/dir/*").run().unwrap();
+    /// for file in files {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn where_<'p, 'f>(&'p self) -> where_::WhereCommand<'p, 'f> {
+        where_::WhereCommand::new(self)
+    }
+
+    /// Where the client view maps the depot file `depot_file` on the client's machine.
+    ///
+    /// `depot_file` is taken literally, e.g. `@` isn't a revision.  `None` if the view doesn't
+    /// include the file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// match p4.depot_to_local("//depot/dir/file").unwrap() {
+    ///     Some(path) => println!("{}", path.display()),
+    ///     None => println!("Not in the client view"),
+    /// }
+    /// ```
+    pub fn depot_to_local(
+        &self,
+        depot_file: &str,
+    ) -> Result<Option<path::PathBuf>, error::P4Error> {
+        let file = where_::mapping(self, &paths::escape(depot_file))?;
+        Ok(file.map(|f| f.path.into_path_buf()))
+    }
+
+    /// The depot file the client view maps `path`, on the client's machine, to.
+    ///
+    /// `path` is taken literally, and fails with `ErrorKind::InvalidArgument` if it isn't UTF-8.
+    /// The depot file is escaped, as the server reports it.  `None` if the view doesn't include
+    /// the file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// if let Some(depot_file) = p4.local_to_depot("/home/user/ws/dir/file").unwrap() {
+    ///     println!("{}", depot_file);
+    /// }
+    /// ```
+    pub fn local_to_depot<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<Option<paths::DepotPath>, error::P4Error> {
+        let path = path.as_ref();
+        let path = path.to_str().ok_or_else(|| {
+            error::ErrorKind::InvalidArgument
+                .error()
+                .set_context(format!("{} isn't UTF-8", path.display()))
+        })?;
+        let file = where_::mapping(self, &paths::escape(path))?;
+        Ok(file.map(|f| f.depot_file))
+    }
+
+    /// List groups (of users)
+    ///
+    /// The default form lists all user groups defined in the server, or
+    /// just the specified group.
+    ///
+    /// When a name is given with `include_indirect`, subgroup relationships
+    /// are displayed. If a user argument is specified, only groups
+    /// containing that user are displayed. If a group argument is
+    /// specified, only groups containing the group are displayed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let groups = p4.groups().name("alice").run().unwrap();
+    /// for group in groups {
+    ///     println!("{:?}", group);
+    /// }
+    /// ```
+    pub fn groups<'p, 'n>(&'p self) -> groups::GroupsCommand<'p, 'n> {
+        groups::GroupsCommand::new(self)
+    }
+
+    /// Display client/server information
+    ///
+    /// Info lists information about the current client (user name,
+    /// client name, applicable client root, client current directory,
+    /// and the client IP address) and some server information (server
+    /// IP address, server root, date, uptime, version and license data).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let info = p4.info().run().unwrap();
+    /// for item in info {
+    ///     println!("{:?}", item);
+    /// }
+    /// ```
+    pub fn info<'p>(&'p self) -> info::InfoCommand<'p> {
+        info::InfoCommand::new(self)
+    }
+
+    /// Log in to a Perforce server
+    ///
+    /// Login sets up a login ticket on the server that is valid for a
+    /// limited time (set by the server's timeout), so that the password
+    /// does not have to be passed on every command.  The password is piped
+    /// to `p4` over stdin so it never shows up in the process list.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let logins = p4.login().password("secret").run().unwrap();
+    /// for login in logins {
+    ///     println!("{:?}", login);
+    /// }
+    /// ```
+    pub fn login<'p, 's>(&'p self) -> login::LoginCommand<'p, 's> {
+        login::LoginCommand::new(self)
+    }
+
+    /// Log out from a Perforce server
+    ///
+    /// Logout removes any login ticket for the current user from the client
+    /// machine and invalidates it on the server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let logouts = p4.logout().all_hosts(true).run().unwrap();
+    /// for logout in logouts {
+    ///     println!("{:?}", logout);
+    /// }
+    /// ```
+    pub fn logout<'p, 'u>(&'p self) -> logout::LogoutCommand<'p, 'u> {
+        logout::LogoutCommand::new(self)
+    }
+
+    /// Display list of session tickets for this user
+    ///
+    /// Lists all the tickets stored in the user's ticket file, so callers
+    /// can detect which servers they are already authenticated to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let tickets = p4.tickets().run().unwrap();
+    /// for ticket in tickets {
+    ///     println!("{:?}", ticket);
+    /// }
+    /// ```
+    pub fn tickets<'p>(&'p self) -> tickets::TicketsCommand<'p> {
+        tickets::TicketsCommand::new(self)
+    }
+
+    /// Establish trust of an SSL connection
+    ///
+    /// This client command manages the p4 trust file.  This file contains
+    /// fingerprints of the keys received on ssl connections.  Establishing
+    /// trust with a connection prevents undetected communication
+    /// interception (man-in-the-middle) attacks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let fingerprints = p4.trust().list(true).run().unwrap();
+    /// for fingerprint in fingerprints {
+    ///     println!("{:?}", fingerprint);
+    /// }
+    /// ```
+    pub fn trust<'p, 'f>(&'p self) -> trust::TrustCommand<'p, 'f> {
+        trust::TrustCommand::new(self)
+    }
+
+    /// Display Perforce variables
+    ///
+    /// Lists the Perforce variables in effect (from the environment,
+    /// P4CONFIG files, the P4ENVIRO file, or the Windows registry) along
+    /// with where each value came from.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let settings = p4.set().run().unwrap();
+    /// for setting in settings {
+    ///     println!("{:?}", setting);
+    /// }
+    /// ```
+    pub fn set<'p>(&'p self) -> set::SetCommand<'p> {
+        set::SetCommand::new(self)
+    }
+
+    /// Display list of jobs
+    ///
+    /// Lists jobs in the server. If a file specification is included, fixes
+    /// for submitted changelists affecting those files are listed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let jobs = p4.jobs().jobview("status=open").run().unwrap();
+    /// for job in jobs {
+    ///     println!("{:?}", job);
+    /// }
+    /// ```
+    pub fn jobs<'p, 'f>(&'p self) -> jobs::JobsCommand<'p, 'f> {
+        jobs::JobsCommand::new(self)
+    }
+
+    /// Display protections defined for a specified user and path
+    ///
+    /// Protects displays the lines from the protections table that apply to
+    /// the current user.  The protections table is managed using the protect
+    /// command.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let protections = p4.protects().user("alice").file("//depot/...").run().unwrap();
+    /// for protection in protections {
+    ///     println!("{:?}", protection);
+    /// }
+    /// ```
+    pub fn protects<'p, 'f>(&'p self) -> protects::ProtectsCommand<'p, 'f> {
+        protects::ProtectsCommand::new(self)
+    }
+
+    /// Write the triggers table
+    ///
+    /// Writes the lines of the triggers table, which is edited with
+    /// 'p4 triggers -i' ('p4 triggers -o').  This command requires super
+    /// access.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let triggers = p4.triggers().run().unwrap();
+    /// for trigger in triggers {
+    ///     println!("{:?}", trigger);
+    /// }
+    /// ```
+    pub fn triggers<'p>(&'p self) -> triggers::TriggersCommand<'p> {
+        triggers::TriggersCommand::new(self)
+    }
+
+    /// Set, unset, or display server configuration variables
+    ///
+    /// By default, displays the configuration variables set on the server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let configurables = p4.configure().name("monitor").set("1").run().unwrap();
+    /// for configurable in configurables {
+    ///     println!("{:?}", configurable);
+    /// }
+    /// ```
+    pub fn configure<'p, 'c>(&'p self) -> configure::ConfigureCommand<'p, 'c> {
+        configure::ConfigureCommand::new(self)
+    }
+
+    /// Display current running commands
+    ///
+    /// By default, lists the processes running on the server.  Processes
+    /// can also be terminated or cleared from the monitor table.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let processes = p4.monitor().all_args(true).extended(true).run().unwrap();
+    /// for process in processes {
+    ///     println!("{:?}", process);
+    /// }
+    /// ```
+    pub fn monitor<'p, 'i>(&'p self) -> monitor::MonitorCommand<'p, 'i> {
+        monitor::MonitorCommand::new(self)
+    }
+
+    /// Unload a client, label, or task stream to the unload depot
+    ///
+    /// Unload transfers infrequently-used metadata from the versioning engine
+    /// tables to an unload depot.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let unloaded = p4.unload().client("alice_ws").run().unwrap();
+    /// for unload in unloaded {
+    ///     println!("{:?}", unload);
+    /// }
+    /// ```
+    pub fn unload<'p, 's>(&'p self) -> unload::UnloadCommand<'p, 's> {
+        unload::UnloadCommand::new(self)
+    }
+
+    /// Reload an unloaded client, label, or task stream
+    ///
+    /// Reload restores metadata previously unloaded with 'p4 unload'.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let reloaded = p4.reload().client("alice_ws").run().unwrap();
+    /// for reload in reloaded {
+    ///     println!("{:?}", reload);
+    /// }
+    /// ```
+    pub fn reload<'p, 's>(&'p self) -> reload::ReloadCommand<'p, 's> {
+        reload::ReloadCommand::new(self)
+    }
+
+    /// Display list of server specifications
+    ///
+    /// Lists the server specs defined in a distributed (commit/edge or
+    /// replicated) installation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let servers = p4.servers().run().unwrap();
+    /// for server in servers {
+    ///     println!("{:?}", server);
+    /// }
+    /// ```
+    pub fn servers<'p>(&'p self) -> servers::ServersCommand<'p> {
+        servers::ServersCommand::new(self)
+    }
+
+    /// Display a server specification
+    ///
+    /// Writes the server spec for `server_id` ('p4 server -o').
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let servers = p4.server("edge1").run().unwrap();
+    /// for server in servers {
+    ///     println!("{:?}", server);
+    /// }
+    /// ```
+    pub fn server<'p, 's>(&'p self, server_id: &'s str) -> servers::ServerCommand<'p, 's> {
+        servers::ServerCommand::new(self, server_id)
+    }
+
+    /// Report size of journal, error log, and audit log files
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let stats = p4.logstat().run().unwrap();
+    /// for stat in stats {
+    ///     println!("{:?}", stat);
+    /// }
+    /// ```
+    pub fn logstat<'p>(&'p self) -> logstat::LogstatCommand<'p> {
+        logstat::LogstatCommand::new(self)
+    }
+
+    /// Display the last block(s) of the errorLog
+    ///
+    /// To poll incrementally, pass the `offset` of the last block back in as
+    /// the next `start_offset`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let blocks = p4.logtail().start_offset(1024).run().unwrap();
+    /// for block in blocks {
+    ///     println!("{:?}", block);
+    /// }
+    /// ```
+    pub fn logtail<'p>(&'p self) -> logtail::LogtailCommand<'p> {
+        logtail::LogtailCommand::new(self)
+    }
+
+    /// Purge a replica or proxy cache of file archives
+    ///
+    /// Removes archive files from a replica or proxy cache to reclaim disk
+    /// space.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let purge = p4_cmd::cachepurge::Purge::Size(1 << 30);
+    /// let purged = p4.cachepurge(purge).run().unwrap();
+    /// for file in purged {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn cachepurge<'p, 'f>(
+        &'p self,
+        purge: cachepurge::Purge,
+    ) -> cachepurge::CachepurgeCommand<'p, 'f> {
+        cachepurge::CachepurgeCommand::new(self, purge)
+    }
+
+    /// Perform administrative operations on the server
+    ///
+    /// Checkpoints the database, rotates the journal, or stops/restarts the
+    /// server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let confirmations = p4
+    ///     .admin(p4_cmd::admin::Operation::Journal)
+    ///     .run()
+    ///     .unwrap();
+    /// for confirmation in confirmations {
+    ///     println!("{:?}", confirmation);
+    /// }
+    /// ```
+    pub fn admin<'p, 'f>(&'p self, operation: admin::Operation) -> admin::AdminCommand<'p, 'f> {
+        admin::AdminCommand::new(self, operation)
+    }
+
+    /// Display journal or checkpoint history
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let journals = p4.journals().filter("type=checkpoint").run().unwrap();
+    /// for journal in journals {
+    ///     println!("{:?}", journal);
+    /// }
+    /// ```
+    pub fn journals<'p, 'f>(&'p self) -> journals::JournalsCommand<'p, 'f> {
+        journals::JournalsCommand::new(self)
+    }
+
+    /// Create a temporary list of files that can be used as a label
+    ///
+    /// The in-memory label can be passed as a revision specifier (`@label`)
+    /// to other commands such as sync.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let labels = p4.list("build-42").file("//depot/main/...@42").run().unwrap();
+    /// for label in labels {
+    ///     println!("{:?}", label);
+    /// }
+    /// ```
+    pub fn list<'p, 'f>(&'p self, label: &'f str) -> list::ListCommand<'p, 'f> {
+        list::ListCommand::new(self, label)
+    }
+
+    /// Extract journal or checkpoint records
+    ///
+    /// Exports journal records starting at `token`.  Pass the
+    /// `Exported::Token` from the end of one export into the next to continue
+    /// incrementally.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let records = p4.export("12/3456").run().unwrap();
+    /// for record in records {
+    ///     println!("{:?}", record);
+    /// }
+    /// ```
+    pub fn export<'p, 'f>(&'p self, token: &'f str) -> export::ExportCommand<'p, 'f> {
+        export::ExportCommand::new(self, token)
+    }
+
+    /// Package a set of files and their history for use by p4 unzip
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let files = p4.zip("release.zip").file("//depot/rel/...").run().unwrap();
+    /// for file in files {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn zip<'p, 'f>(&'p self, output: &'f str) -> zip::ZipCommand<'p, 'f> {
+        zip::ZipCommand::new(self, output)
+    }
+
+    /// Import files from a zip file created by p4 zip
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let files = p4.unzip("release.zip").archives(true).run().unwrap();
+    /// for file in files {
+    ///     println!("{:?}", file);
+    /// }
+    /// ```
+    pub fn unzip<'p, 'f>(&'p self, input: &'f str) -> unzip::UnzipCommand<'p, 'f> {
+        unzip::UnzipCommand::new(self, input)
+    }
+
+    /// Clone a new personal server from a shared server
+    ///
+    /// Initializes a personal server in the current directory and fetches
+    /// the requested files into it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let changes = p4.clone_server("perforce:1666").remote("main").run().unwrap();
+    /// for change in changes {
+    ///     println!("{:?}", change);
+    /// }
+    /// ```
+    pub fn clone_server<'p, 'f>(&'p self, port: &'f str) -> clone::CloneCommand<'p, 'f> {
+        clone::CloneCommand::new(self, port)
+    }
+
+    /// Copy files and history from another server into this one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let changes = p4.fetch().remote("origin").run().unwrap();
+    /// for change in changes {
+    ///     println!("{:?}", change);
+    /// }
+    /// ```
+    pub fn fetch<'p, 'f>(&'p self) -> fetch::FetchCommand<'p, 'f> {
+        fetch::FetchCommand::new(self)
+    }
+
+    /// Copy files and history from this server into another one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let changes = p4.push().remote("origin").run().unwrap();
+    /// for change in changes {
+    ///     println!("{:?}", change);
+    /// }
+    /// ```
+    pub fn push<'p, 'f>(&'p self) -> push::PushCommand<'p, 'f> {
+        push::PushCommand::new(self)
+    }
+
+    /// Run a command this crate doesn't wrap
+    ///
+    /// The connection's global options are applied and each tagged record is
+    /// reported as generic fields.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let records = p4.raw_command("changes").args(&["-m", "5"]).run().unwrap();
+    /// for record in records {
+    ///     println!("{:?}", record);
+    /// }
+    /// ```
+    pub fn raw_command<'p, 'c>(&'p self, command: &'c str) -> raw::RawCommand<'p, 'c> {
+        raw::RawCommand::new(self, command)
     }
 
-    /// List files in the depot.
-    ///
-    /// List details about specified files: depot file name, revision,
-    /// file, type, change action and changelist number of the current
-    /// head revision. If client syntax is used to specify the file
-    /// argument, the client view mapping is used to determine the
-    /// corresponding depot files.
+    /// Write a spec form
     ///
-    /// By default, the head revision is listed.  If the file argument
-    /// specifies a revision, then all files at that revision are listed.
-    /// If the file argument specifies a revision range, the highest revision
-    /// in the range is used for each file. For details about specifying
-    /// revisions, see 'p4 help revisions'.
+    /// Writes the `kind` spec, e.g. `client`, to be edited and written back
+    /// with 'p4 <kind> -i' ('p4 <kind> -o').
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// let p4 = p4_cmd::P4::new();
-    /// let files = p4.files("//depot/dir/*").run().unwrap();
-    /// for file in files {
-    ///     println!("{:?}", file);
+    /// let specs = p4.spec("label").name("rel-1.0").run().unwrap();
+    /// for spec in specs {
+    ///     println!("{:?}", spec);
     /// }
     /// ```
-    pub fn files<'p, 'f>(&'p self, file: &'f str) -> files::FilesCommand<'p, 'f> {
-        files::FilesCommand::new(self, file)
+    pub fn spec<'p, 's>(&'p self, kind: &'s str) -> spec::SpecCommand<'p, 's> {
+        spec::SpecCommand::new(self, kind)
     }
 
-    /// List depot subdirectories
-    ///
-    /// List directories that match the specified file pattern (dir).
-    /// This command does not support the recursive wildcard (...).
-    /// Use the * wildcard instead.
-    ///
-    /// Perforce does not track directories individually. A path is treated
-    /// as a directory if there are any undeleted files with that path as a
-    /// prefix.
+    /// Display, set, or delete a key/value
     ///
-    /// By default, all directories containing files are listed. If the dir
-    /// argument includes a revision range, only directories containing files
-    /// in the range are listed. For details about specifying file revisions,
-    /// see 'p4 help revisions'.
+    /// By default, displays the value of the specified key.  The key can
+    /// also be set, deleted, or incremented.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// let p4 = p4_cmd::P4::new();
-    /// let dirs = p4.dirs("//depot/dir/*").run().unwrap();
-    /// for dir in dirs {
-    ///     println!("{:?}", dir);
+    /// let keys = p4.key("build-last").increment(true).run().unwrap();
+    /// for key in keys {
+    ///     println!("{:?}", key);
     /// }
     /// ```
-    pub fn dirs<'p, 'f, 's>(&'p self, dir: &'f str) -> dirs::DirsCommand<'p, 'f, 's> {
-        dirs::DirsCommand::new(self, dir)
+    pub fn key<'p, 'k>(&'p self, name: &'k str) -> key::KeyCommand<'p, 'k> {
+        key::KeyCommand::new(self, name)
     }
 
-    /// Show how file names are mapped by the client view
-    ///
-    /// Where shows how the specified files are mapped by the client view.
-    /// For each argument, three names are produced: the name in the depot,
-    /// the name on the client in Perforce syntax, and the name on the client
-    /// in local syntax.
-    ///
-    /// If the file parameter is omitted, the mapping for all files in the
-    /// current directory and below) is returned.
+    /// Display list of known keys and their values
     ///
-    /// Note that 'p4 where' does not determine where any real files reside.
-    /// It only displays the locations that are mapped by the client view.
+    /// Lists keys and their values.  Keys are a key/value store in the
+    /// server, a variant of counters without the special meaning some
+    /// counters have.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// let p4 = p4_cmd::P4::new();
-    /// let files = p4.where_().file("//depot/dir/*").run().unwrap();
-    /// for file in files {
-    ///     println!("{:?}", file);
+    /// let keys = p4.keys().filter("build-*").run().unwrap();
+    /// for key in keys {
+    ///     println!("{:?}", key);
     /// }
     /// ```
-    pub fn where_<'p, 'f>(&'p self) -> where_::WhereCommand<'p, 'f> {
-        where_::WhereCommand::new(self)
+    pub fn keys<'p, 'f>(&'p self) -> keys::KeysCommand<'p, 'f> {
+        keys::KeysCommand::new(self)
+    }
+
+    pub(crate) fn port(&self) -> Option<&str> {
+        self.port.as_deref()
+    }
+
+    pub(crate) fn password(&self) -> Option<&str> {
+        self.password.as_ref().map(|p| p.0.as_str())
+    }
+
+    pub(crate) fn password_mode(&self) -> PasswordMode {
+        self.password_mode
+    }
+
+    pub(crate) fn timeout(&self) -> Option<time::Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn retry_policy(&self) -> Option<&retry::RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    pub(crate) fn auto_login(&self) -> bool {
+        self.auto_login
+    }
+
+    pub(crate) fn credentials(&self) -> Option<&login::Credentials> {
+        self.credentials.as_ref()
+    }
+
+    pub(crate) fn trusted_fingerprint(&self) -> Option<&str> {
+        self.trusted_fingerprint.as_deref()
+    }
+
+    pub(crate) fn verbosity(&self) -> Option<&str> {
+        self.verbosity.as_deref()
+    }
+
+    pub(crate) fn observer(&self) -> Option<&::std::sync::Arc<dyn observer::P4Observer>> {
+        self.observer.as_ref()
+    }
+
+    /// Fail with `ErrorKind::UnsupportedByServer` if the server is known to predate `option`.
+    pub(crate) fn require(
+        &self,
+        settings: &exec::Settings,
+        option: &str,
+        since: info::ServerVersion,
+    ) -> Result<(), error::P4Error> {
+        // A command on another server can't be judged by this one's release.
+        if settings.port.is_some() {
+            return Ok(());
+        }
+        match self.server_version {
+            Some(version) if version < since => {
+                let context = format!(
+                    "`{}` needs server {} or newer, the server is {}",
+                    option, since, version
+                );
+                Err(error::ErrorKind::UnsupportedByServer
+                    .error()
+                    .set_context(context))
+            }
+            _ => Ok(()),
+        }
     }
 
-    pub(crate) fn connect(&self) -> process::Command {
+    /// Where to record the server's unicode mode once it's discovered, `None` if it's been set.
+    pub(crate) fn detected_unicode(
+        &self,
+    ) -> Option<&::std::sync::Arc<::std::sync::Mutex<Option<bool>>>> {
+        if self.unicode.is_some() {
+            None
+        } else {
+            Some(&self.detected_unicode)
+        }
+    }
+
+    fn unicode(&self) -> bool {
+        self.unicode
+            .or_else(|| {
+                *self
+                    .detected_unicode
+                    .lock()
+                    .unwrap_or_else(::std::sync::PoisonError::into_inner)
+            })
+            .unwrap_or(true)
+    }
+
+    pub(crate) fn runner(&self) -> ::std::sync::Arc<dyn runner::P4Runner> {
+        let runner = self
+            .runner
+            .clone()
+            .unwrap_or_else(|| ::std::sync::Arc::new(runner::ProcessRunner));
+        let runner: ::std::sync::Arc<dyn runner::P4Runner> = match self.rate_limiter {
+            Some(ref limiter) => ::std::sync::Arc::new(rate_limit::Limited {
+                runner,
+                limiter: limiter.clone(),
+            }),
+            None => runner,
+        };
+        // Cache hits don't wait on the rate limiter.
+        match self.cache {
+            Some(ref cache) => ::std::sync::Arc::new(cache::Cached {
+                runner,
+                cache: cache.clone(),
+            }),
+            None => runner,
+        }
+    }
+
+    /// Start a `p4` invocation, applying a command's overrides of the connection's options.
+    pub(crate) fn connect(&self, settings: &exec::Settings) -> process::Command {
+        self.start(settings, true)
+    }
+
+    /// Like `connect`, without the password, for `p4 login` which reads it over stdin.
+    pub(crate) fn connect_login(&self, settings: &exec::Settings) -> process::Command {
+        self.start(settings, false)
+    }
+
+    fn start(&self, settings: &exec::Settings, with_password: bool) -> process::Command {
         let p4_cmd = self
             .custom_p4
             .as_ref()
             .map(path::PathBuf::as_path)
             .unwrap_or_else(|| path::Path::new("p4"));
         let mut cmd = process::Command::new(p4_cmd);
-        cmd.args(&["-Gs", "-C utf8"]);
-        if let Some(ref port) = self.port {
+        match settings.format {
+            exec::Format::Tagged => cmd.arg("-Gs"),
+            exec::Format::Ztag => cmd.arg("-ztag"),
+        };
+        if self.unicode() {
+            cmd.args(&UNICODE);
+        }
+        if let Some(port) = settings.port.as_ref().or(self.port.as_ref()) {
             cmd.args(&["-p", port.as_str()]);
         }
-        if let Some(ref user) = self.user {
+        if let Some(user) = settings.user.as_ref().or(self.user.as_ref()) {
             cmd.args(&["-u", user.as_str()]);
         }
-        if let Some(ref password) = self.password {
+        let password = settings
+            .password
+            .as_ref()
+            .or_else(|| self.password.as_ref().map(|p| &p.0))
+            .filter(|_| with_password);
+        if let (Some(password), PasswordMode::Argument) = (password, self.password_mode) {
             cmd.args(&["-P", password.as_str()]);
         }
-        if let Some(ref client) = self.client {
+        if let Some(client) = settings.client.as_ref().or(self.client.as_ref()) {
             cmd.args(&["-c", client.as_str()]);
         }
-        cmd
-    }
-
-    pub(crate) fn connect_with_retries(&self, retries: Option<usize>) -> process::Command {
-        let mut cmd = self.connect();
-        if let Some(retries) = retries.or(self.retries) {
+        if let Some(ref directory) = self.directory {
+            cmd.arg("-d").arg(directory);
+            // `p4` only looks for P4CONFIG files from its actual working directory.
+            cmd.current_dir(directory);
+        }
+        if let Some(ref host) = self.host {
+            cmd.args(&["-H", host.as_str()]);
+        }
+        if let Some(ref command_charset) = self.command_charset {
+            cmd.args(&["-Q", command_charset.as_str()]);
+        }
+        if let Some(ref language) = self.language {
+            cmd.args(&["-L", language.as_str()]);
+        }
+        if self.env_clear {
+            // Remove each variable rather than `env_clear` so retries can rebuild the command.
+            for (key, _) in env::vars_os() {
+                cmd.env_remove(key);
+            }
+        }
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        if let Some(ref tickets_file) = self.tickets_file {
+            cmd.env("P4TICKETS", tickets_file);
+        }
+        if let Some(ref enviro_file) = self.enviro_file {
+            cmd.env("P4ENVIRO", enviro_file);
+        }
+        if let Some(ref login_sso) = self.login_sso {
+            cmd.env("P4LOGINSSO", login_sso);
+        }
+        if let (Some(password), PasswordMode::Environment) = (password, self.password_mode) {
+            cmd.env("P4PASSWD", password);
+        }
+        if let Some(retries) = self.retries {
             let retries = format!("{}", retries);
             cmd.args(&["-r", &retries]);
         }
+        if let Some(ref verbosity) = self.verbosity {
+            cmd.args(&["-v", verbosity.as_str()]);
+        }
+        if self.message_codes {
+            cmd.arg("-e");
+        }
+        for tag in &self.z_tags {
+            cmd.args(&["-Z", tag.as_str()]);
+        }
         cmd
     }
 }
 
-pub type Time = chrono::DateTime<chrono::Utc>;
+/// How the password is handed to `p4`.
+///
+/// See [`P4::set_password_mode`](struct.P4.html#method.set_password_mode).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PasswordMode {
+    /// Pass `-P <password>`, which other users can see in process listings.
+    Argument,
+    /// Set `P4PASSWD` in `p4`'s environment.
+    Environment,
+    /// Don't pass the password to commands.  When one fails because the user isn't logged in,
+    /// run `p4 login` with the password fed over stdin and re-run the command once, as
+    /// `P4::set_auto_login` does.
+    Login,
+
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// Point in time reported by the server.
+///
+/// Convert it to a `std::time::SystemTime`, or with the `chrono` feature, to a
+/// `chrono::DateTime<chrono::Utc>`.
+///
+/// # Example
+///
+/// ```rust
+/// let time = p4_cmd::Time::from(std::time::UNIX_EPOCH);
+/// assert_eq!(time.as_system_time(), std::time::UNIX_EPOCH);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Time(time::SystemTime);
+
+impl Time {
+    pub fn as_system_time(&self) -> time::SystemTime {
+        self.0
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> chrono::DateTime<chrono::Utc> {
+        self.0.into()
+    }
+}
+
+impl From<time::SystemTime> for Time {
+    fn from(time: time::SystemTime) -> Self {
+        Time(time)
+    }
+}
+
+impl From<Time> for time::SystemTime {
+    fn from(time: Time) -> Self {
+        time.0
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Time {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Time(time.into())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Time> for chrono::DateTime<chrono::Utc> {
+    fn from(time: Time) -> Self {
+        time.0.into()
+    }
+}
 
-// Keeping around for future use.
-#[allow(dead_code)]
 pub(crate) fn to_timestamp(time: &Time) -> i64 {
-    time.timestamp()
+    match time.0.duration_since(time::UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
 }
 
 pub(crate) fn from_timestamp(timestamp: i64) -> Time {
-    chrono::Utc.timestamp(timestamp, 0)
+    if timestamp < 0 {
+        Time(time::UNIX_EPOCH - time::Duration::from_secs(-timestamp as u64))
+    } else {
+        Time(time::UNIX_EPOCH + time::Duration::from_secs(timestamp as u64))
+    }
+}
+
+/// Parse a `YYYY/MM/DD HH:MM:SS` server date, with the server's offset from UTC in seconds.
+pub(crate) fn from_date(date: &str, utc_offset: i64) -> Option<Time> {
+    date.parse::<ServerDate>()
+        .ok()
+        .map(|d| d.to_time(utc_offset))
+}
+
+/// A date as the server reports it, e.g. `2018/08/13 10:11:12` for a spec's `Update:`.
+///
+/// Dates are in the server's time zone, so converting them to a `Time` needs the server's
+/// offset from UTC, e.g. `info::ServerInfo::server_utc_offset`.
+///
+/// # Example
+///
+/// ```rust
+/// let date: p4_cmd::ServerDate = "2018/08/13 10:11:12".parse().unwrap();
+/// assert_eq!(date.to_string(), "2018/08/13 10:11:12");
+/// // A server at UTC-7.
+/// let time = date.to_time(-25_200);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServerDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl ServerDate {
+    /// The point in time, for a server `utc_offset` seconds from UTC.
+    pub fn to_time(&self, utc_offset: i64) -> Time {
+        let year = i64::from(self.year);
+        let month = i64::from(self.month);
+        let day = i64::from(self.day);
+
+        // Days since the epoch, from Howard Hinnant's `days_from_civil`.
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = (if 0 <= y { y } else { y - 399 }) / 400;
+        let yoe = y - era * 400;
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+
+        let local = days * 86_400
+            + i64::from(self.hour) * 3_600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second);
+        from_timestamp(local - utc_offset)
+    }
+
+    /// `time` on the clock of a server `utc_offset` seconds from UTC.
+    pub fn from_time(time: &Time, utc_offset: i64) -> Self {
+        let local = to_timestamp(time) + utc_offset;
+        let days = local.div_euclid(86_400);
+        let seconds = local.rem_euclid(86_400);
+
+        // Calendar date, from Howard Hinnant's `civil_from_days`.
+        let z = days + 719_468;
+        let era = (if 0 <= z { z } else { z - 146_096 }) / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds / 3_600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+            non_exhaustive: (),
+        }
+    }
+}
+
+impl str::FromStr for ServerDate {
+    type Err = fmt::Error;
+
+    /// Parse `YYYY/MM/DD HH:MM:SS`, `YYYY/MM/DD:HH:MM:SS` or `YYYY/MM/DD`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn number<T: str::FromStr>(part: Option<&str>) -> Result<T, fmt::Error> {
+            part.ok_or(fmt::Error)?
+                .trim()
+                .parse()
+                .map_err(|_| fmt::Error)
+        }
+
+        let s = s.trim();
+        let (date, time) = match s.find([' ', ':']) {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let mut ymd = date.splitn(3, '/');
+        let year = number(ymd.next())?;
+        let month = number(ymd.next())?;
+        let day = number(ymd.next())?;
+        let (hour, minute, second) = match time {
+            Some(hms) => {
+                let mut hms = hms.splitn(3, ':');
+                let hour = number(hms.next())?;
+                let minute = number(hms.next())?;
+                let second = number(Some(hms.next().unwrap_or("0")))?;
+                (hour, minute, second)
+            }
+            None => (0, 0, 0),
+        };
+        let valid = (1..=12).contains(&month)
+            && (1..=31).contains(&day)
+            && hour <= 23
+            && minute <= 59
+            && second <= 60;
+        if !valid {
+            return Err(fmt::Error);
+        }
+        Ok(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            non_exhaustive: (),
+        })
+    }
+}
+
+impl fmt::Display for ServerDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}/{:02}/{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Parse a `+HHMM`/`-HHMM` UTC offset into seconds.
+pub(crate) fn from_utc_offset(offset: &str) -> Option<i64> {
+    let offset = offset.trim();
+    let (sign, digits) = match offset.chars().next()? {
+        '+' => (1, &offset[1..]),
+        '-' => (-1, &offset[1..]),
+        _ => return None,
+    };
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i64 = digits[0..2].parse().ok()?;
+    let minutes: i64 = digits[2..4].parse().ok()?;
+    Some(sign * (hours * 3_600 + minutes * 60))
+}
+
+/// Which revisions of a file to operate on.
+///
+/// See 'p4 help revisions'.
+///
+/// # Example
+///
+/// ```rust
+/// use p4_cmd::RevSpec;
+///
+/// assert_eq!(RevSpec::Change(1234).to_string(), "@1234");
+/// let range = RevSpec::range(RevSpec::Rev(1), RevSpec::Head).unwrap();
+/// assert_eq!(range.to_string(), "#1,#head");
+///
+/// let p4 = p4_cmd::P4::new();
+/// let files = p4.files("//depot/a/...").file_at("//depot/b@home", RevSpec::Have);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevSpec {
+    /// The latest revision, `#head`.
+    Head,
+    /// The revision synced to the client, `#have`.
+    Have,
+    /// No revision, i.e. the file is absent, `#none`.
+    None,
+    /// A revision number, `#n`.
+    Rev(usize),
+    /// The revision as of a changelist, `@n`.
+    Change(usize),
+    /// The revision in a label, or synced to a client workspace, `@name`.
+    Label(String),
+    /// The revision as of a point in time on the server's clock, `@YYYY/MM/DD:HH:MM:SS`.
+    ///
+    /// Use `ServerDate::from_time` with the server's offset from UTC, e.g.
+    /// `info::ServerInfo::server_utc_offset`, to get one from a `Time`.
+    Date(ServerDate),
+    /// The revisions from the first through the second, `from,to`.
+    Range(RevRange),
+
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl RevSpec {
+    /// The revisions from `from` through `to`.
+    ///
+    /// Fails if either end is itself a range.
+    pub fn range(from: RevSpec, to: RevSpec) -> Result<Self, fmt::Error> {
+        let is_single =
+            |rev: &RevSpec| !matches!(rev, RevSpec::Range(_) | RevSpec::__Nonexhaustive);
+        if !is_single(&from) || !is_single(&to) {
+            return Err(fmt::Error);
+        }
+        Ok(RevSpec::Range(RevRange {
+            from: Box::new(from),
+            to: Box::new(to),
+        }))
+    }
+}
+
+impl fmt::Display for RevSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevSpec::Head => write!(f, "#head"),
+            RevSpec::Have => write!(f, "#have"),
+            RevSpec::None => write!(f, "#none"),
+            RevSpec::Rev(rev) => write!(f, "#{}", rev),
+            RevSpec::Change(change) => write!(f, "@{}", change),
+            RevSpec::Label(ref label) => write!(f, "@{}", label),
+            RevSpec::Date(ref date) => write!(
+                f,
+                "@{:04}/{:02}/{:02}:{:02}:{:02}:{:02}",
+                date.year, date.month, date.day, date.hour, date.minute, date.second
+            ),
+            RevSpec::Range(ref range) => write!(f, "{},{}", range.from, range.to),
+            RevSpec::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+}
+
+/// Ends of a `RevSpec::Range`, neither of which is a range, see `RevSpec::range`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevRange {
+    from: Box<RevSpec>,
+    to: Box<RevSpec>,
+}
+
+impl RevRange {
+    pub fn from(&self) -> &RevSpec {
+        &self.from
+    }
+
+    pub fn to(&self) -> &RevSpec {
+        &self.to
+    }
+}
+
+/// `file` at `rev`, escaping `file` so it isn't read as revisions or wildcards.
+pub(crate) fn file_at(file: &str, rev: &RevSpec) -> String {
+    format!("{}{}", paths::escape(file), rev)
 }
 
 /// Action performed on a file at a given revision.
@@ -279,8 +1885,10 @@ pub(crate) fn from_timestamp(timestamp: i64) -> Time {
 /// assert_eq!("move/delete".parse::<p4_cmd::Action>().unwrap(), p4_cmd::Action::MoveDelete);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Action {
     #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __Nonexhaustive,
 
     Add,
@@ -347,8 +1955,10 @@ impl fmt::Display for Action {
 /// assert_eq!("utf8".parse::<p4_cmd::BaseFileType>().unwrap(), p4_cmd::BaseFileType::Utf8);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum BaseFileType {
     #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __Nonexhaustive,
 
     /// Text file
@@ -403,10 +2013,48 @@ pub enum BaseFileType {
     ///
     /// Stored as: RCS deltas in UTF-8 format
     Utf16,
+    /// Mac file with a resource fork
+    ///
+    /// Stored as: full file, compressed, AppleSingle format
+    Apple,
+    /// Mac resource fork
+    ///
+    /// Obsolete, only still reported for old files.
+    ///
+    /// Stored as: full file, compressed
+    Resource,
 
     Unknown(String),
 }
 
+/// Old-style file types, still reported for some files, and their modern equivalents.
+const LEGACY_FILE_TYPES: &[(&str, &str, &str)] = &[
+    ("ctempobj", "binary", "Sw"),
+    ("ctext", "text", "C"),
+    ("cxtext", "text", "Cx"),
+    ("ktext", "text", "k"),
+    ("kxtext", "text", "kx"),
+    ("ltext", "text", "F"),
+    ("tempobj", "binary", "FSw"),
+    ("ubinary", "binary", "F"),
+    ("uresource", "resource", "F"),
+    ("uxbinary", "binary", "Fx"),
+    ("xbinary", "binary", "x"),
+    ("xltext", "text", "Fx"),
+    ("xtempobj", "binary", "Swx"),
+    ("xtext", "text", "x"),
+    ("xunicode", "unicode", "x"),
+    ("xutf16", "utf16", "x"),
+];
+
+/// The base type and modifiers an old-style file type stands for.
+fn legacy_file_type(s: &str) -> Option<(&'static str, &'static str)> {
+    LEGACY_FILE_TYPES
+        .iter()
+        .find(|(legacy, _, _)| *legacy == s)
+        .map(|&(_, base, modifiers)| (base, modifiers))
+}
+
 impl Default for BaseFileType {
     fn default() -> Self {
         BaseFileType::Text
@@ -416,7 +2064,10 @@ impl Default for BaseFileType {
 impl str::FromStr for BaseFileType {
     type Err = fmt::Error;
 
+    /// Old-style file types, like `ctext`, parse as their base type.  Parse them as a `FileType`
+    /// to also get their modifiers.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = legacy_file_type(s).map_or(s, |(base, _)| base);
         let ft = match s {
             "text" => BaseFileType::Text,
             "binary" => BaseFileType::Binary,
@@ -424,6 +2075,8 @@ impl str::FromStr for BaseFileType {
             "unicode" => BaseFileType::Unicode,
             "utf8" => BaseFileType::Utf8,
             "utf16" => BaseFileType::Utf16,
+            "apple" => BaseFileType::Apple,
+            "resource" => BaseFileType::Resource,
             s => BaseFileType::Unknown(s.to_owned()),
         };
         Ok(ft)
@@ -439,6 +2092,8 @@ impl fmt::Display for BaseFileType {
             BaseFileType::Unicode => "unicode",
             BaseFileType::Utf8 => "utf8",
             BaseFileType::Utf16 => "utf16",
+            BaseFileType::Apple => "apple",
+            BaseFileType::Resource => "resource",
             BaseFileType::Unknown(ref s) => s.as_str(),
             BaseFileType::__Nonexhaustive => unreachable!("This is a private variant"),
         };
@@ -457,6 +2112,7 @@ impl fmt::Display for BaseFileType {
 /// assert_eq!("l".parse::<p4_cmd::FileTypeModifiers>().unwrap(), modifiers);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FileTypeModifiers {
     /// File is always writable on client
     pub always_writeable: bool,
@@ -482,6 +2138,7 @@ pub struct FileTypeModifiers {
     pub modtime: bool,
     /// Archive trigger required
     pub archive: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     non_exhaustive: (),
 }
 
@@ -496,8 +2153,17 @@ impl str::FromStr for FileTypeModifiers {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut modifiers = FileTypeModifiers::default();
+        modifiers.apply(s)?;
+        Ok(modifiers)
+    }
+}
 
-        for flag in s.chars() {
+impl FileTypeModifiers {
+    /// Set the modifiers listed in `s`.
+    fn apply(&mut self, s: &str) -> Result<(), fmt::Error> {
+        let modifiers = self;
+        let mut chars = s.char_indices().peekable();
+        while let Some((i, flag)) = chars.next() {
             match flag {
                 'w' => modifiers.always_writeable = true,
                 'x' => modifiers.executable = true,
@@ -507,15 +2173,29 @@ impl str::FromStr for FileTypeModifiers {
                 'C' => modifiers.full = true,
                 'D' => modifiers.deltas = true,
                 'F' => modifiers.full_uncompressed = true,
-                'S' => modifiers.head = true,
-                // TODO: handle `revisions`.
+                'S' => {
+                    // `S<n>` keeps `n` revisions, plain `S` only the head revision.
+                    let mut end = i + 1;
+                    while let Some(&(j, c)) = chars.peek() {
+                        if !c.is_ascii_digit() {
+                            break;
+                        }
+                        end = j + 1;
+                        chars.next();
+                    }
+                    if end == i + 1 {
+                        modifiers.head = true;
+                    } else {
+                        modifiers.revisions = Some(s[i + 1..end].parse().map_err(|_| fmt::Error)?);
+                    }
+                }
                 'm' => modifiers.modtime = true,
                 'X' => modifiers.archive = true,
                 _ => return Err(fmt::Error),
             }
         }
 
-        Ok(modifiers)
+        Ok(())
     }
 }
 
@@ -543,7 +2223,7 @@ impl fmt::Display for FileTypeModifiers {
             write!(f, "D")?;
         }
         if self.full_uncompressed {
-            write!(f, "S")?;
+            write!(f, "F")?;
         }
         if self.head {
             write!(f, "S")?;
@@ -576,10 +2256,12 @@ impl fmt::Display for FileTypeModifiers {
 /// assert_eq!("binary+l".parse::<p4_cmd::FileType>().unwrap(), ft);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FileType {
     /// The base Perforce file type
     pub base: BaseFileType,
     pub modifiers: Option<FileTypeModifiers>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     non_exhaustive: (),
 }
 
@@ -605,14 +2287,15 @@ impl str::FromStr for FileType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut itr = s.splitn(2, '+');
         let base = itr.next().ok_or(fmt::Error)?;
+        let legacy = legacy_file_type(base);
         let base = base.parse().map_err(|_| fmt::Error)?;
 
-        let modifiers = itr
-            .next()
-            .map(|f| {
-                let modifiers: FileTypeModifiers = f.parse()?;
-                Ok(modifiers)
-            }).map_or(Ok(None), |r| r.map(Some))?;
+        let mut modifiers = None;
+        for flags in legacy.map(|(_, flags)| flags).into_iter().chain(itr.next()) {
+            modifiers
+                .get_or_insert_with(FileTypeModifiers::default)
+                .apply(flags)?;
+        }
 
         let ft = FileType {
             base,
@@ -633,3 +2316,111 @@ impl fmt::Display for FileType {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn modifiers_revisions() {
+        let modifiers: FileTypeModifiers = "kS10".parse().unwrap();
+        assert!(modifiers.rcs_expansion);
+        assert!(!modifiers.head);
+        assert_eq!(modifiers.revisions, Some(10));
+        assert_eq!(modifiers.to_string(), "kS10");
+
+        let modifiers: FileTypeModifiers = "Sw".parse().unwrap();
+        assert!(modifiers.head);
+        assert!(modifiers.always_writeable);
+        assert_eq!(modifiers.revisions, None);
+    }
+
+    #[test]
+    fn rev_spec_date() {
+        let time = from_timestamp(1_527_128_318);
+        let date = ServerDate::from_time(&time, 0);
+        assert_eq!(RevSpec::Date(date).to_string(), "@2018/05/24:02:18:38");
+        // A server at UTC-7 is still on the previous day.
+        let date = ServerDate::from_time(&time, -25_200);
+        assert_eq!(RevSpec::Date(date).to_string(), "@2018/05/23:19:18:38");
+        assert_eq!(date.to_time(-25_200), time);
+        let time = from_timestamp(951_782_400);
+        let date = ServerDate::from_time(&time, 0);
+        assert_eq!(RevSpec::Date(date).to_string(), "@2000/02/29:00:00:00");
+        assert_eq!(from_date("2000/02/29 00:00:00", 0), Some(time));
+        assert_eq!(from_date("2000/02/29:01:00:00", 3_600), Some(time));
+        assert_eq!(from_date("2000/02/29", 0), Some(time));
+        assert_eq!(from_date("2000/13/29", 0), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn time_chrono() {
+        let time = from_timestamp(1_527_128_318);
+        let chrono_time = time.to_chrono();
+        assert_eq!(chrono_time.timestamp(), 1_527_128_318);
+        assert_eq!(Time::from(chrono_time), time);
+    }
+
+    #[test]
+    fn rev_spec_escapes_file() {
+        let rev = RevSpec::range(RevSpec::Label("rel-1".to_owned()), RevSpec::Change(42)).unwrap();
+        assert_eq!(
+            file_at("//depot/a@b#c/...", &rev),
+            "//depot/a%40b%23c/...@rel-1,@42"
+        );
+        assert_eq!(
+            file_at("//depot/100%25*.png", &RevSpec::Head),
+            "//depot/100%2525%2A.png#head"
+        );
+    }
+
+    #[test]
+    fn rev_spec_range_not_nested() {
+        let range = RevSpec::range(RevSpec::Rev(1), RevSpec::Rev(2)).unwrap();
+        assert!(RevSpec::range(range.clone(), RevSpec::Head).is_err());
+        assert!(RevSpec::range(RevSpec::Head, range).is_err());
+    }
+
+    #[test]
+    fn file_type_legacy() {
+        let ft: FileType = "ctext".parse().unwrap();
+        assert_eq!(ft.to_string(), "text+C");
+        let ft: FileType = "kxtext+l".parse().unwrap();
+        assert_eq!(ft.to_string(), "text+xkl");
+        let ft: FileType = "tempobj".parse().unwrap();
+        assert_eq!(ft.to_string(), "binary+wFS");
+        let ft: FileType = "resource".parse().unwrap();
+        assert_eq!(ft.base, BaseFileType::Resource);
+        assert_eq!(ft.modifiers, None);
+        assert_eq!("xbinary".parse(), Ok(BaseFileType::Binary));
+    }
+
+    #[test]
+    fn modifiers_round_trip() {
+        let revisions = [None, Some(1), Some(16)];
+        for bits in 0..(1 << 11) {
+            for &revisions in &revisions {
+                let flag = |n: u32| bits & (1 << n) != 0;
+                let modifiers = FileTypeModifiers {
+                    always_writeable: flag(0),
+                    executable: flag(1),
+                    rcs_expansion: flag(2),
+                    old_style_expansion: flag(3),
+                    exclusive: flag(4),
+                    full: flag(5),
+                    deltas: flag(6),
+                    full_uncompressed: flag(7),
+                    head: flag(8),
+                    revisions,
+                    modtime: flag(9),
+                    archive: flag(10),
+                    non_exhaustive: (),
+                };
+                let display = modifiers.to_string();
+                let parsed: FileTypeModifiers = display.parse().unwrap();
+                assert_eq!(parsed, modifiers, "{}", display);
+            }
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use std::char;
 use std::num;
+use std::path;
 use std::str;
 
 use nom;
 
 use error;
+use progress;
 
 fn is_newline(c: u8) -> bool {
     let c = char::from_u32(u32::from(c));
@@ -41,18 +43,39 @@ fn str_from_bytes(input: &[u8]) -> Result<&str, str::Utf8Error> {
     Ok(input)
 }
 
+/// Convert a path to UTF-8, replacing anything that isn't, e.g. Latin-1 names.
+pub fn lossy(input: &[u8]) -> String {
+    String::from_utf8_lossy(input).into_owned()
+}
+
+/// The path as reported, only kept when `lossy` can't represent it.
+pub fn non_utf8(input: &[u8]) -> Option<Vec<u8>> {
+    str::from_utf8(input).err().map(|_| input.to_owned())
+}
+
+/// Convert a local path, keeping its bytes where the OS allows.
+#[cfg(unix)]
+pub fn path_buf(input: &[u8]) -> path::PathBuf {
+    use std::ffi;
+    use std::os::unix::ffi::OsStrExt;
+
+    path::PathBuf::from(ffi::OsStr::from_bytes(input))
+}
+
+/// Convert a local path, keeping its bytes where the OS allows.
+#[cfg(not(unix))]
+pub fn path_buf(input: &[u8]) -> path::PathBuf {
+    path::PathBuf::from(lossy(input))
+}
+
 pub fn error_to_item<T>(e: Error) -> error::Item<T> {
-    error::Item::Message(error::Message::new(
-        error::MessageLevel::Error,
-        e.msg.to_owned(),
-    ))
+    let msg = error::Message::new(e.level, e.msg.to_owned());
+    error::Item::Message(msg.set_code(e.code.map(error::MessageCode::new)))
 }
 
 pub fn info_to_item<T>(e: Info) -> error::Item<T> {
-    error::Item::Message(error::Message::new(
-        error::MessageLevel::Info,
-        e.msg.to_owned(),
-    ))
+    let msg = error::Message::new(error::MessageLevel::Info, e.msg.to_owned());
+    error::Item::Message(msg.set_code(e.code.map(error::MessageCode::new)))
 }
 
 pub fn exit_to_item<T>(e: Exit) -> error::Item<T> {
@@ -93,88 +116,142 @@ named!(pub exit<&[u8], Exit>,
     map_res!(terminated!(preceded!(tag!(b"exit: "), take_while!(nom::is_digit)), newline), exit_from_bytes)
 );
 
+fn progress_from_bytes(input: &[u8]) -> Option<progress::Progress> {
+    let line = str::from_utf8(input).ok()?;
+    progress::Progress::from_line(line)
+}
+
+// Progress indicators (`-I`) are untagged and may be ended by "\r" to overwrite each other.
+named!(pub progress<&[u8], progress::Progress>,
+    map_opt!(terminated!(take_till1!(is_newline), newline), progress_from_bytes)
+);
+
+/// A message reporting a problem: a warning, error or fatal error.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Error<'a> {
+    pub(crate) level: error::MessageLevel,
+    pub(crate) code: Option<u32>,
     pub(crate) msg: &'a str,
 }
 
-fn error_from_bytes(input: &[u8]) -> Result<Error, str::Utf8Error> {
-    let msg = str_from_bytes(input)?;
-    Ok(Error { msg })
-}
+named!(severity<&[u8], error::MessageLevel>,
+    alt!(
+        value!(error::MessageLevel::Error, tag!(b"error: ")) |
+        value!(error::MessageLevel::Warning, tag!(b"warning: ")) |
+        value!(error::MessageLevel::Fatal, tag!(b"fatal: "))
+    )
+);
+
+named!(message_tag<&[u8], ()>,
+    alt!(value!((), severity) | value!((), tag!(b"info: ")))
+);
+
+// With `-e`, a message is preceded by its code and format, e.g.
+// `error: code0 822483067 (sub 123 sysid 6 gen 6 args 1 sev 3 uniq 6123)`.
+named!(code<&[u8], u32>,
+    do_parse!(
+        message_tag >>
+        tag!(b"code0 ") >>
+        code: map_res!(map_res!(take_while1!(nom::is_digit), str::from_utf8), str::parse) >>
+        take_till!(is_newline) >>
+        newline >>
+        opt!(tuple!(message_tag, tag!(b"fmt0 "), take_till!(is_newline), newline)) >>
+        (code)
+    )
+);
 
 named!(pub error<&[u8], Error>,
-    map_res!(terminated!(preceded!(tag!(b"error: "), take_till!(is_newline)), newline), error_from_bytes)
+    do_parse!(
+        code: opt!(code) >>
+        level: severity >>
+        msg: map_res!(take_till!(is_newline), str_from_bytes) >>
+        newline >>
+        (Error { level, code, msg })
+    )
 );
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Info<'a> {
+    pub(crate) code: Option<u32>,
     pub(crate) msg: &'a str,
 }
 
 fn info_from_bytes(input: &[u8]) -> Result<Info, str::Utf8Error> {
     let msg = str_from_bytes(input)?;
-    Ok(Info { msg })
+    Ok(Info { code: None, msg })
 }
 
 named!(pub info<&[u8], Info>,
-    map_res!(terminated!(preceded!(tag!(b"info: "), take_till!(is_newline)), newline), info_from_bytes)
+    do_parse!(
+        code: opt!(code) >>
+        tag!(b"info: ") >>
+        msg: map_res!(take_till!(is_newline), str_from_bytes) >>
+        newline >>
+        (Info { code, msg })
+    )
+);
+
+// Without `-s`, messages aren't tagged, nor is the `-v` trace.
+named!(pub untagged<&[u8], Info>,
+    map_res!(terminated!(take_till!(is_newline), newline), info_from_bytes)
+);
+
+// Fields are tagged `info1: ` in `-s` output and `... ` in `-ztag` output, with a `... ` per level
+// of nesting.
+named!(pub field_tag<&[u8], ()>,
+    alt!(value!((), tag!(b"info1: ")) | value!((), many1!(tag!(b"... "))))
 );
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DepotFile<'a> {
-    pub(crate) path: &'a str,
+    pub(crate) path: &'a [u8],
 }
 
-fn depot_file_from_bytes(input: &[u8]) -> Result<DepotFile, str::Utf8Error> {
-    let path = str_from_bytes(input)?;
-    Ok(DepotFile { path })
+fn depot_file_from_bytes(input: &[u8]) -> DepotFile {
+    DepotFile { path: input }
 }
 
 named!(pub depot_file<&[u8], DepotFile>,
-    map_res!(terminated!(preceded!(tag!(b"info1: depotFile "), take_till!(is_newline)), newline), depot_file_from_bytes)
+    map!(terminated!(preceded!(pair!(field_tag, tag!(b"depotFile ")), take_till!(is_newline)), newline), depot_file_from_bytes)
 );
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ClientFile<'a> {
-    pub(crate) path: &'a str,
+    pub(crate) path: &'a [u8],
 }
 
-fn client_file_from_bytes(input: &[u8]) -> Result<ClientFile, str::Utf8Error> {
-    let path = str_from_bytes(input)?;
-    Ok(ClientFile { path })
+fn client_file_from_bytes(input: &[u8]) -> ClientFile {
+    ClientFile { path: input }
 }
 
 named!(pub client_file<&[u8], ClientFile>,
-    map_res!(terminated!(preceded!(tag!(b"info1: clientFile "), take_till!(is_newline)), newline), client_file_from_bytes)
+    map!(terminated!(preceded!(pair!(field_tag, tag!(b"clientFile ")), take_till!(is_newline)), newline), client_file_from_bytes)
 );
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Path<'a> {
-    pub(crate) path: &'a str,
+    pub(crate) path: &'a [u8],
 }
 
-fn path_from_bytes(input: &[u8]) -> Result<Path, str::Utf8Error> {
-    let path = str_from_bytes(input)?;
-    Ok(Path { path })
+fn path_from_bytes(input: &[u8]) -> Path {
+    Path { path: input }
 }
 
 named!(pub path<&[u8], Path>,
-    map_res!(terminated!(preceded!(tag!(b"info1: path "), take_till!(is_newline)), newline), path_from_bytes)
+    map!(terminated!(preceded!(pair!(field_tag, tag!(b"path ")), take_till!(is_newline)), newline), path_from_bytes)
 );
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Dir<'a> {
-    pub(crate) dir: &'a str,
+    pub(crate) dir: &'a [u8],
 }
 
-fn dir_from_bytes(input: &[u8]) -> Result<Dir, str::Utf8Error> {
-    let dir = str_from_bytes(input)?;
-    Ok(Dir { dir })
+fn dir_from_bytes(input: &[u8]) -> Dir {
+    Dir { dir: input }
 }
 
 named!(pub dir<&[u8], Dir>,
-    map_res!(terminated!(preceded!(tag!(b"info1: dir "), take_till!(is_newline)), newline), dir_from_bytes)
+    map!(terminated!(preceded!(pair!(field_tag, tag!(b"dir ")), take_till!(is_newline)), newline), dir_from_bytes)
 );
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -190,7 +267,7 @@ fn rev_from_bytes(input: &[u8]) -> Result<Rev, num::ParseIntError> {
 }
 
 named!(pub rev<&[u8], Rev>,
-    map_res!(terminated!(preceded!(tag!(b"info1: rev "), take_while!(nom::is_digit)), newline), rev_from_bytes)
+    map_res!(terminated!(preceded!(pair!(field_tag, tag!(b"rev ")), take_while!(nom::is_digit)), newline), rev_from_bytes)
 );
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -206,7 +283,7 @@ fn change_from_bytes(input: &[u8]) -> Result<Change, num::ParseIntError> {
 }
 
 named!(pub change<&[u8], Change>,
-    map_res!(terminated!(preceded!(tag!(b"info1: change "), take_while!(nom::is_digit)), newline), change_from_bytes)
+    map_res!(terminated!(preceded!(pair!(field_tag, tag!(b"change ")), take_while!(nom::is_digit)), newline), change_from_bytes)
 );
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -220,7 +297,7 @@ fn action_from_bytes(input: &[u8]) -> Result<Action, str::Utf8Error> {
 }
 
 named!(pub action<&[u8], Action>,
-    map_res!(terminated!(preceded!(tag!(b"info1: action "), take_till!(is_newline)), newline), action_from_bytes)
+    map_res!(terminated!(preceded!(pair!(field_tag, tag!(b"action ")), take_till!(is_newline)), newline), action_from_bytes)
 );
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -234,7 +311,7 @@ fn file_type_from_bytes(input: &[u8]) -> Result<FileType, str::Utf8Error> {
 }
 
 named!(pub file_type<&[u8], FileType>,
-    map_res!(terminated!(preceded!(tag!(b"info1: type "), take_till!(is_newline)), newline), file_type_from_bytes)
+    map_res!(terminated!(preceded!(pair!(field_tag, tag!(b"type ")), take_till!(is_newline)), newline), file_type_from_bytes)
 );
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -250,7 +327,7 @@ fn time_from_bytes(input: &[u8]) -> Result<Time, num::ParseIntError> {
 }
 
 named!(pub time<&[u8], Time>,
-    map_res!(terminated!(preceded!(tag!(b"info1: time "), take_while!(nom::is_digit)), newline), time_from_bytes)
+    map_res!(terminated!(preceded!(pair!(field_tag, tag!(b"time ")), take_while!(nom::is_digit)), newline), time_from_bytes)
 );
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -266,25 +343,144 @@ fn file_size_from_bytes(input: &[u8]) -> Result<FileSize, num::ParseIntError> {
 }
 
 named!(pub file_size<&[u8], FileSize>,
-    map_res!(terminated!(preceded!(tag!(b"info1: fileSize "), take_while!(nom::is_digit)), newline), file_size_from_bytes)
+    map_res!(terminated!(preceded!(pair!(field_tag, tag!(b"fileSize ")), take_while!(nom::is_digit)), newline), file_size_from_bytes)
 );
 
-fn ignore_from_bytes(_input: &[u8]) -> Result<(), num::ParseIntError> {
-    Ok(())
+/// Whether `line` starts like a tagged line of `-s` or `-ztag` output.
+fn is_tagged(line: &[u8]) -> bool {
+    const TAGS: &[&[u8]] = &[
+        b"... ",
+        b"info: ",
+        b"error: ",
+        b"warning: ",
+        b"fatal: ",
+        b"exit: ",
+        b"text: ",
+        b"binary: ",
+    ];
+    if TAGS.iter().any(|t| line.starts_with(t)) {
+        return true;
+    }
+    // Fields are `info1: `, and nested ones `info2: ` and so on.
+    line.strip_prefix(b"info").is_some_and(|rest| {
+        let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+        digits > 0 && rest[digits..].starts_with(b": ")
+    })
 }
 
-named!(pub ignore_info1<&[u8], ()>,
-    map_res!(terminated!(preceded!(tag!(b"info1: "), take_till!(is_newline)), newline), ignore_from_bytes)
+// Whether fields are tagged for `-ztag` rather than `-s`.
+named!(field_format<&[u8], bool>,
+    alt!(value!(false, tag!(b"info1: ")) | value!(true, many1!(tag!(b"... "))))
 );
 
-fn text_from_bytes(input: &[u8]) -> Result<String, str::Utf8Error> {
-    let text = str_from_bytes(input)?.to_owned();
+/// Fields whose values are free text, which may span lines, e.g. change and job descriptions.
+const MULTI_LINE_FIELDS: &[&[u8]] = &[b"desc", b"Description"];
+
+/// The rest of a field's line, along with the lines continuing it for `MULTI_LINE_FIELDS`.
+///
+/// In `-s` output every other line is tagged, so untagged lines, blank ones included, continue
+/// the value.  In `-ztag` output blank lines separate records, so only the untagged lines before
+/// the next blank line do.  The value keeps its line breaks, but not the final one.
+///
+/// Other fields end with their line, leaving untagged lines like the `-v` trace to the caller.
+fn field_value<'a>(input: &'a [u8], key: &[u8], ztag: bool) -> nom::IResult<&'a [u8], &'a [u8]> {
+    if !MULTI_LINE_FIELDS.contains(&key) {
+        return terminated!(input, take_till!(is_newline), newline);
+    }
+    let line_end = |from: usize| {
+        input[from..]
+            .iter()
+            .position(|&c| is_newline(c))
+            .map(|len| from + len)
+            .ok_or(nom::Err::Incomplete(nom::Needed::Size(1)))
+    };
+    let mut end = line_end(0)?;
+    let remaining = loop {
+        let (remaining, _) = newline(&input[end..])?;
+        let start = input.len() - remaining.len();
+        let next_end = line_end(start)?;
+        let line = &input[start..next_end];
+        if is_tagged(line) || (ztag && line.is_empty()) {
+            break remaining;
+        }
+        end = next_end;
+    };
+    Ok((remaining, &input[..end]))
+}
+
+/// Parse an arbitrary `info1: <name> <value>` field.
+///
+/// For use with `call!` when a command has fields not shared with other commands.
+pub fn field<'a>(input: &'a [u8], name: &str) -> nom::IResult<&'a [u8], &'a str> {
+    do_parse!(
+        input,
+        ztag: field_format >>
+        tag!(name) >>
+        tag!(b" ") >>
+        value: map_res!(call!(field_value, name.as_bytes(), ztag), str_from_bytes) >>
+        (value)
+    )
+}
+
+/// Parse an arbitrary `info1: <name> <0|1>` field as a `bool`.
+pub fn bool_field<'a>(input: &'a [u8], name: &str) -> nom::IResult<&'a [u8], bool> {
+    map!(input, call!(field, name), |v| v != "0")
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Field<'a> {
+    pub(crate) key: &'a str,
+    pub(crate) value: &'a str,
+}
+
+fn is_key_end(c: u8) -> bool {
+    c == b' ' || is_newline(c)
+}
+
+fn any_field_from_bytes<'a>(input: (&'a [u8], &'a [u8])) -> Result<Field<'a>, str::Utf8Error> {
+    let key = str_from_bytes(input.0)?;
+    let value = str_from_bytes(input.1)?;
+    Ok(Field { key, value })
+}
+
+// Parse any `info1: <name> <value>` field, for commands whose fields vary by server.
+named!(pub any_field<&[u8], Field>,
+    map_res!(
+        do_parse!(
+            ztag: field_format >>
+            key: take_till1!(is_key_end) >>
+            opt!(tag!(b" ")) >>
+            value: call!(field_value, key, ztag) >>
+            ((key, value))
+        ),
+        any_field_from_bytes
+    )
+);
 
-    Ok(text)
+/// Parse consecutive `info1:` fields as one record, ending the record when a key repeats.
+///
+/// For commands whose records don't start with a fixed field.
+pub fn record<'a>(input: &'a [u8]) -> nom::IResult<&'a [u8], Vec<Field<'a>>> {
+    let (mut input, first) = any_field(input)?;
+    let mut fields = vec![first];
+    loop {
+        let (remaining, field) = match any_field(input) {
+            Ok(parsed) => parsed,
+            // Output may still be arriving; the record can't be ended yet.
+            Err(nom::Err::Incomplete(needed)) => return Err(nom::Err::Incomplete(needed)),
+            Err(_) => break,
+        };
+        if fields.iter().any(|f| f.key == field.key) {
+            break;
+        }
+        fields.push(field);
+        input = remaining;
+    }
+    Ok((input, fields))
 }
 
-named!(pub text<&[u8], String>,
-    map_res!(terminated!(preceded!(tag!(b"text: "), take_till!(is_newline)), newline), text_from_bytes)
+named!(pub text<&[u8], Vec<u8>>,
+    map!(terminated!(preceded!(tag!(b"text: "), take_till!(is_newline)), newline), <[u8]>::to_vec)
 );
 
 #[cfg(test)]
@@ -317,10 +513,46 @@ mod test {
             Ok((
                 expected_remaining,
                 Error {
+                    level: error::MessageLevel::Error,
+                    code: None,
                     msg: ".tags - no such file(s)."
                 }
             ))
         );
+        assert_eq!(
+            error(b"warning: //depot/... - file(s) up-to-date.\n"),
+            Ok((
+                expected_remaining,
+                Error {
+                    level: error::MessageLevel::Warning,
+                    code: None,
+                    msg: "//depot/... - file(s) up-to-date."
+                }
+            ))
+        );
+        assert_eq!(
+            error(b"fatal: Out of memory!\n").map(|(_, e)| e.level),
+            Ok(error::MessageLevel::Fatal)
+        );
+    }
+
+    #[test]
+    fn parse_error_code() {
+        let output = b"error: code0 823203963 (sub 123 sysid 6 gen 17 args 1 sev 3 uniq 6123)
+error: fmt0 [%argc% - no such file(s).|%argc% - no such %argc%.]
+error: //depot/nonexistent - no such file(s).
+";
+        let (remaining, e) = error(output).unwrap();
+        assert_eq!(remaining, b"");
+        assert_eq!(e.code, Some(823203963));
+        assert_eq!(e.msg, "//depot/nonexistent - no such file(s).");
+
+        let output = b"info: code0 285219107 (sub 291 sysid 6 gen 0 args 1 sev 1 uniq 6291)
+info: User alice logged out.
+";
+        let (_, i) = info(output).unwrap();
+        assert_eq!(i.code, Some(285219107));
+        assert_eq!(i.msg, "User alice logged out.");
     }
 
     #[test]
@@ -331,7 +563,7 @@ mod test {
             Ok((
                 expected_remaining,
                 DepotFile {
-                    path: "//depot/dir/file"
+                    path: b"//depot/dir/file"
                 }
             ))
         );
@@ -345,7 +577,7 @@ mod test {
             Ok((
                 expected_remaining,
                 ClientFile {
-                    path: "//client/depot/dir/file"
+                    path: b"//client/depot/dir/file"
                 }
             ))
         );
@@ -359,7 +591,7 @@ mod test {
             Ok((
                 expected_remaining,
                 Path {
-                    path: "/home/user/depot/dir/file"
+                    path: b"/home/user/depot/dir/file"
                 }
             ))
         );
@@ -370,7 +602,12 @@ mod test {
         let expected_remaining: &[u8] = b"";
         assert_eq!(
             dir(b"info1: dir //depot/dir\n"),
-            Ok((expected_remaining, Dir { dir: "//depot/dir" }))
+            Ok((
+                expected_remaining,
+                Dir {
+                    dir: b"//depot/dir"
+                }
+            ))
         );
     }
 
@@ -419,6 +656,103 @@ mod test {
         );
     }
 
+    #[test]
+    fn parse_ztag_field() {
+        let expected_remaining: &[u8] = b"";
+        assert_eq!(
+            field(b"... group dev\n", "group"),
+            Ok((expected_remaining, "dev"))
+        );
+        assert_eq!(
+            any_field(b"... ... otherOpen0 bob@ws\n"),
+            Ok((
+                expected_remaining,
+                Field {
+                    key: "otherOpen0",
+                    value: "bob@ws"
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_field() {
+        let expected_remaining: &[u8] = b"";
+        assert_eq!(
+            field(b"info1: group dev\n", "group"),
+            Ok((expected_remaining, "dev"))
+        );
+        assert!(field(b"info1: user dev\n", "group").is_err());
+    }
+
+    #[test]
+    fn parse_bool_field() {
+        let expected_remaining: &[u8] = b"";
+        assert_eq!(
+            bool_field(b"info1: isOwner 1\n", "isOwner"),
+            Ok((expected_remaining, true))
+        );
+    }
+
+    #[test]
+    fn parse_any_field() {
+        let expected_remaining: &[u8] = b"";
+        assert_eq!(
+            any_field(b"info1: serverDate 2018/08/13 10:11:12 -0700 PDT\n"),
+            Ok((
+                expected_remaining,
+                Field {
+                    key: "serverDate",
+                    value: "2018/08/13 10:11:12 -0700 PDT"
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_multi_line_field() {
+        let expected_remaining: &[u8] = b"info1: status submitted\nexit: 0\n";
+        assert_eq!(
+            field(
+                b"info1: desc Fix crash\n\nOn startup.\ninfo1: status submitted\nexit: 0\n",
+                "desc"
+            ),
+            Ok((expected_remaining, "Fix crash\n\nOn startup."))
+        );
+        assert_eq!(
+            any_field(b"... Description Fix crash\nOn startup.\n\nDone\n").map(|(_, f)| f.value),
+            Ok("Fix crash\nOn startup.")
+        );
+        assert!(field(b"info1: desc Fix crash\nOn startup.\n", "desc").is_err());
+
+        let expected_remaining: &[u8] = b"trace\nexit: 0\n";
+        assert_eq!(
+            field(b"info1: user alice\ntrace\nexit: 0\n", "user"),
+            Ok((expected_remaining, "alice"))
+        );
+    }
+
+    #[test]
+    fn parse_record() {
+        let expected_remaining: &[u8] = b"info1: Name security\nexit: 0\n";
+        assert_eq!(
+            record(b"info1: Name monitor\ninfo1: Value 1\ninfo1: Name security\nexit: 0\n"),
+            Ok((
+                expected_remaining,
+                vec![
+                    Field {
+                        key: "Name",
+                        value: "monitor"
+                    },
+                    Field {
+                        key: "Value",
+                        value: "1"
+                    },
+                ]
+            ))
+        );
+    }
+
     #[test]
     fn parse_windows_newline() {
         let expected_remaining: &[u8] = b"";
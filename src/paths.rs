@@ -0,0 +1,300 @@
+//! Depot, client and local paths, kept apart so one isn't passed where another is expected.
+//!
+//! Depot and client paths keep the exact bytes the server reported, for names that aren't UTF-8.
+//!
+//! File names containing `@`, `#`, `%` or `*` have to be [`escape`](fn.escape.html)d before
+//! being passed to a command, or they are read as revisions and wildcards.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use p4_cmd::paths;
+//!
+//! let depot: paths::DepotPath = "//depot/dir/file".parse().unwrap();
+//! assert_eq!(depot, "//depot/dir/file");
+//! assert!("dir/file".parse::<paths::DepotPath>().is_err());
+//! assert!(paths::LocalPath::new("dir/file").is_err());
+//!
+//! let literal = format!("//depot/dir/{}", paths::escape("100%@2x.png"));
+//! assert_eq!(literal, "//depot/dir/100%25%402x.png");
+//! assert!(!paths::has_wildcards(&literal));
+//! ```
+
+use std::borrow;
+use std::fmt;
+use std::path;
+use std::str;
+
+use parser;
+
+/// Characters with a special meaning in file arguments, and how they're escaped.
+const ESCAPES: &[(char, &str)] = &[('%', "%25"), ('@', "%40"), ('#', "%23"), ('*', "%2A")];
+
+/// Escape `@`, `#`, `%` and `*` in a file name so they're taken literally.
+///
+/// Only escape names, not patterns: wildcards are escaped too.
+pub fn escape(name: &str) -> borrow::Cow<'_, str> {
+    if !name.contains(|c| ESCAPES.iter().any(|&(special, _)| c == special)) {
+        return borrow::Cow::Borrowed(name);
+    }
+    let mut escaped = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        match ESCAPES.iter().find(|&&(special, _)| c == special) {
+            Some(&(_, escape)) => escaped.push_str(escape),
+            None => escaped.push(c),
+        }
+    }
+    borrow::Cow::Owned(escaped)
+}
+
+/// Undo [`escape`](fn.escape.html), e.g. for names reported by the server.
+///
+/// Other `%` sequences are left as they are.
+pub fn unescape(name: &str) -> borrow::Cow<'_, str> {
+    if !name.contains('%') {
+        return borrow::Cow::Borrowed(name);
+    }
+    let mut unescaped = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find('%') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let special = rest.get(..3).and_then(|code| {
+            ESCAPES
+                .iter()
+                .find(|&&(_, escape)| escape.eq_ignore_ascii_case(code))
+        });
+        match special {
+            Some(&(c, escape)) => {
+                unescaped.push(c);
+                rest = &rest[escape.len()..];
+            }
+            None => {
+                unescaped.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    borrow::Cow::Owned(unescaped)
+}
+
+/// Whether `path` has wildcards: `*`, `...` or a `%%1` positional specifier.
+pub fn has_wildcards(path: &str) -> bool {
+    path.contains('*')
+        || path.contains("...")
+        || path
+            .match_indices("%%")
+            .any(|(i, _)| path[i + 2..].starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Whether `path` is in `//name/...` syntax.
+fn is_double_slash(path: &str) -> bool {
+    let name = path.get(2..).unwrap_or("");
+    path.starts_with("//") && !name.is_empty() && !name.starts_with('/')
+}
+
+macro_rules! syntax_path {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub struct $name {
+            path: String,
+            #[cfg_attr(
+                feature = "serde",
+                serde(default, skip_serializing_if = "Option::is_none")
+            )]
+            raw: Option<Vec<u8>>,
+        }
+
+        impl $name {
+            /// Wrap a path reported by the server, which may not be UTF-8.
+            pub(crate) fn from_bytes(path: &[u8]) -> Self {
+                Self {
+                    path: parser::lossy(path),
+                    raw: parser::non_utf8(path),
+                }
+            }
+
+            /// The path, with anything that isn't UTF-8 replaced.
+            pub fn as_str(&self) -> &str {
+                &self.path
+            }
+
+            /// The path exactly as reported, for names that aren't UTF-8.
+            pub fn as_bytes(&self) -> &[u8] {
+                self.raw
+                    .as_ref()
+                    .map_or(self.path.as_bytes(), |raw| raw.as_slice())
+            }
+
+            pub fn into_string(self) -> String {
+                self.path
+            }
+        }
+
+        impl str::FromStr for $name {
+            type Err = fmt::Error;
+
+            /// Fails unless `s` is in `//name/...` syntax.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                if !is_double_slash(s) {
+                    return Err(fmt::Error);
+                }
+                Ok(Self {
+                    path: s.to_owned(),
+                    raw: None,
+                })
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.path)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.path
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(path: $name) -> Self {
+                path.path
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.path == other
+            }
+        }
+
+        impl<'a> PartialEq<&'a str> for $name {
+            fn eq(&self, other: &&'a str) -> bool {
+                self.path == *other
+            }
+        }
+    };
+}
+
+syntax_path!(
+    /// A path in depot syntax, e.g. `//depot/dir/file`.
+    DepotPath
+);
+
+syntax_path!(
+    /// A path in client syntax, e.g. `//workspace/dir/file`.
+    ClientPath
+);
+
+/// An absolute path on the client's machine, e.g. `/home/user/workspace/dir/file`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LocalPath(path::PathBuf);
+
+impl LocalPath {
+    /// Fails unless `path` is absolute.
+    pub fn new<P: Into<path::PathBuf>>(path: P) -> Result<Self, fmt::Error> {
+        let path = path.into();
+        if !path.is_absolute() {
+            return Err(fmt::Error);
+        }
+        Ok(LocalPath(path))
+    }
+
+    /// Wrap a path reported by the server, keeping its bytes where the OS allows.
+    pub(crate) fn from_bytes(path: &[u8]) -> Self {
+        LocalPath(parser::path_buf(path))
+    }
+
+    pub fn as_path(&self) -> &path::Path {
+        &self.0
+    }
+
+    pub fn into_path_buf(self) -> path::PathBuf {
+        self.0
+    }
+}
+
+impl AsRef<path::Path> for LocalPath {
+    fn as_ref(&self) -> &path::Path {
+        &self.0
+    }
+}
+
+impl From<LocalPath> for path::PathBuf {
+    fn from(path: LocalPath) -> Self {
+        path.0
+    }
+}
+
+impl PartialEq<path::Path> for LocalPath {
+    fn eq(&self, other: &path::Path) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<path::PathBuf> for LocalPath {
+    fn eq(&self, other: &path::PathBuf) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        assert_eq!(escape("a@b#c%d*e"), "a%40b%23c%25d%2Ae");
+        assert_eq!(unescape("a%40b%23c%25d%2Ae"), "a@b#c%d*e");
+        assert_eq!(unescape("%2a%41%"), "*%41%");
+        assert_eq!(unescape(&escape("%40")), "%40");
+        assert!(match escape("plain") {
+            borrow::Cow::Borrowed(_) => true,
+            borrow::Cow::Owned(_) => false,
+        });
+    }
+
+    #[test]
+    fn detect_wildcards() {
+        assert!(has_wildcards("//depot/..."));
+        assert!(has_wildcards("//depot/*.txt"));
+        assert!(has_wildcards("//depot/%%1/file"));
+        assert!(!has_wildcards("//depot/%2A.txt"));
+        assert!(!has_wildcards("//depot/100%%"));
+        assert!(!has_wildcards("//depot/a.b"));
+    }
+
+    #[test]
+    fn depot_path_syntax() {
+        assert!("//depot/dir/...".parse::<DepotPath>().is_ok());
+        assert!("//depot".parse::<DepotPath>().is_ok());
+        assert!("//".parse::<DepotPath>().is_err());
+        assert!("///dir".parse::<DepotPath>().is_err());
+        assert!("/home/user".parse::<DepotPath>().is_err());
+    }
+
+    #[test]
+    fn depot_path_non_utf8() {
+        let path = DepotPath::from_bytes(b"//depot/caf\xe9");
+        assert_eq!(path, "//depot/caf\u{fffd}");
+        assert_eq!(path.as_bytes(), b"//depot/caf\xe9");
+        let path = DepotPath::from_bytes(b"//depot/a");
+        assert_eq!(path.as_bytes(), b"//depot/a");
+        assert_eq!(path, "//depot/a".parse::<DepotPath>().unwrap());
+    }
+
+    #[test]
+    fn local_path_eq() {
+        let root = ::std::env::current_dir().unwrap();
+        let path = LocalPath::new(root.join("file")).unwrap();
+        assert_eq!(path, root.join("file"));
+        assert!(path == *root.join("file").as_path());
+        assert!(path != root);
+    }
+}
@@ -1,7 +1,14 @@
-use std::vec;
+use std::borrow;
+use std::collections;
+use std::io;
+use std::path;
+use std::process;
 
 use error;
+use exec;
 use p4;
+use parser;
+use paths;
 
 /// Write a depot file to standard output
 ///
@@ -29,26 +36,36 @@ use p4;
 #[derive(Debug, Clone)]
 pub struct PrintCommand<'p, 'f> {
     connection: &'p p4::P4,
-    file: Vec<&'f str>,
+    file: Vec<borrow::Cow<'f, str>>,
 
     all_revs: bool,
     keyword_expansion: bool,
     max_files: Option<usize>,
+    output_to: Option<path::PathBuf>,
+    settings: exec::Settings,
 }
 
 impl<'p, 'f> PrintCommand<'p, 'f> {
     pub fn new(connection: &'p p4::P4, file: &'f str) -> Self {
         Self {
             connection,
-            file: vec![file],
+            file: vec![file.into()],
             all_revs: false,
             keyword_expansion: true,
             max_files: None,
+            output_to: None,
+            settings: exec::Settings::default(),
         }
     }
 
-    pub fn file(mut self, dir: &'f str) -> Self {
-        self.file.push(dir);
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
         self
     }
 
@@ -71,12 +88,24 @@ impl<'p, 'f> PrintCommand<'p, 'f> {
         self
     }
 
-    /// Run the `print` command.
-    pub fn run(self) -> Result<Files, error::P4Error> {
-        let mut cmd = self.connection.connect_with_retries(None);
+    /// The -o flag writes the file's content to `path` rather than to the output, letting `p4`
+    /// write it directly, with its executable bit for `+x` types.
+    ///
+    /// The files reported then have no `content`.  Meant for printing a single file, as each
+    /// file overwrites the last.
+    pub fn output_to(mut self, path: path::PathBuf) -> Self {
+        self.output_to = Some(path);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
         cmd.arg("print");
         if self.all_revs {
-            cmd.arg("-s");
+            cmd.arg("-a");
         }
         if !self.keyword_expansion {
             cmd.arg("-k");
@@ -85,65 +114,389 @@ impl<'p, 'f> PrintCommand<'p, 'f> {
             let max_files = format!("{}", max_files);
             cmd.args(&["-m", &max_files]);
         }
-        for file in self.file {
-            cmd.arg(file);
+        if let Some(ref output_to) = self.output_to {
+            cmd.arg("-o").arg(output_to);
         }
-        let data = cmd.output().map_err(|e| {
-            error::ErrorKind::SpawnFailed
-                .error()
-                .set_cause(e)
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        let (_remains, (mut items, exit)) = files_parser::files(&data.stdout).map_err(|_| {
-            error::ErrorKind::ParseFailed
-                .error()
-                .set_context(format!("Command: {:?}", cmd))
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `print` command.
+    ///
+    /// Output is read a line, or a piece of binary content, at a time.
+    pub fn run(self) -> Result<Files, error::P4Error> {
+        let cmd = self.to_command();
+        let files = self.file.len();
+        let mut chunks = exec::spawn_files(
+            self.connection,
+            self.settings,
+            cmd,
+            files,
+            files_parser::item,
+        )?;
+        if self.output_to.is_none() {
+            chunks.set_payload(exec::Payload {
+                len: ContentChunk::binary_len,
+                unless: b"text: ",
+                piece: ContentChunk::binary,
+            });
+        }
+        Ok(Files(chunks))
+    }
+
+    /// Run the `print` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<File>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+
+    /// Run the `print` command, grouping the revisions printed for each depot file.
+    ///
+    /// Meant for `all_revs`, e.g. to export a file's history.  Files are in the order they were
+    /// first printed, with their revisions in the order printed, newest first.  Fails like
+    /// `run_checked`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let histories = p4.print("//depot/dir/file").all_revs(true).run_history().unwrap();
+    /// for history in histories {
+    ///     for file in history.revisions {
+    ///         println!("{}#{} @{}", history.depot_file, file.rev, file.change);
+    ///     }
+    /// }
+    /// ```
+    pub fn run_history(self) -> Result<Vec<FileHistory>, error::P4Error> {
+        let mut histories: Vec<FileHistory> = Vec::new();
+        let mut index = collections::HashMap::new();
+        for file in self.run_checked()? {
+            let i = *index.entry(file.depot_file.clone()).or_insert_with(|| {
+                histories.push(FileHistory {
+                    depot_file: file.depot_file.clone(),
+                    revisions: Vec::new(),
+                    non_exhaustive: (),
+                });
+                histories.len() - 1
+            });
+            histories[i].revisions.push(file);
+        }
+        Ok(histories)
+    }
+
+    /// Run the `print` command, writing the content of every file to `writer` as it is read.
+    ///
+    /// Nothing more than a line or a piece of binary content is held in memory.  Text lines
+    /// are written with a `\n` ending, except a last line the file's reported size shows has
+    /// none.  Fails like `run_checked`, or with `ErrorKind::WriteFailed` once writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let mut file = std::fs::File::create("file.bin").unwrap();
+    /// p4.print("//depot/dir/file.bin").run_to_writer(&mut file).unwrap();
+    /// ```
+    pub fn run_to_writer<W: io::Write>(self, writer: &mut W) -> Result<(), error::P4Error> {
+        let mut ends = LineEnds::default();
+        self.run_each(|chunk| match chunk {
+            ContentChunk::FileHeader(file) => {
+                ends = LineEnds::new(&file);
+                Ok(())
+            }
+            ContentChunk::TextLine(line) => ends.line(writer, &line),
+            ContentChunk::BinaryChunk(piece) => writer.write_all(&piece),
+            ContentChunk::FileEnd => ends.end(writer),
+            ContentChunk::__Nonexhaustive => unreachable!("This is a private variant"),
         })?;
-        items.push(exit);
-        Ok(Files(items))
+        writer.flush().map_err(write_failed)
+    }
+
+    /// Run the `print` command, writing each file's content to the writer `sink` opens for it.
+    ///
+    /// `sink` is given the file's details, with empty `content`.  Otherwise like
+    /// `run_to_writer`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// p4.print("//depot/dir/*")
+    ///     .run_to_sinks(|file| {
+    ///         let name = file.depot_file.as_str().rsplit('/').next().unwrap();
+    ///         std::fs::File::create(name)
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn run_to_sinks<F, W>(self, mut sink: F) -> Result<(), error::P4Error>
+    where
+        F: FnMut(&File) -> io::Result<W>,
+        W: io::Write,
+    {
+        let mut writer: Option<W> = None;
+        let mut ends = LineEnds::default();
+        self.run_each(|chunk| match chunk {
+            ContentChunk::FileHeader(file) => {
+                writer = Some(sink(&file)?);
+                ends = LineEnds::new(&file);
+                Ok(())
+            }
+            ContentChunk::TextLine(line) => {
+                ends.line(writer.as_mut().expect("content follows a header"), &line)
+            }
+            ContentChunk::BinaryChunk(piece) => writer
+                .as_mut()
+                .expect("content follows a header")
+                .write_all(&piece),
+            ContentChunk::FileEnd => {
+                let mut writer = writer.take().expect("ends follow a header");
+                ends.end(&mut writer)?;
+                writer.flush()
+            }
+            ContentChunk::__Nonexhaustive => unreachable!("This is a private variant"),
+        })
+    }
+
+    /// Hand each piece of output to `each` as it is read, failing like `run_checked`.
+    fn run_each<F>(self, mut each: F) -> Result<(), error::P4Error>
+    where
+        F: FnMut(ContentChunk) -> io::Result<()>,
+    {
+        let mut rest = Vec::new();
+        for item in self.run()?.chunks() {
+            match item {
+                // Dropping the output stops `p4`.
+                error::Item::Data(chunk) => each(chunk).map_err(write_failed)?,
+                item => rest.push(item),
+            }
+        }
+        exec::collect(rest).map(|_| ())
     }
 }
 
+fn write_failed(e: io::Error) -> error::P4Error {
+    error::ErrorKind::WriteFailed.error().set_cause(e)
+}
+
 pub type FileItem = error::Item<File>;
 
-pub struct Files(Vec<FileItem>);
+pub struct Files(exec::Items<ContentChunk>);
+
+impl Files {
+    /// Each file's details and then its content, a line or a piece of binary content at a time.
+    ///
+    /// Unlike iterating over whole files, no more than a piece of a file is held in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let files = p4.print("//depot/dir/*").run().unwrap();
+    /// let mut size = 0;
+    /// for chunk in files.chunks() {
+    ///     match chunk {
+    ///         p4_cmd::error::Item::Data(p4_cmd::print::ContentChunk::BinaryChunk(piece)) => {
+    ///             size += piece.len();
+    ///         }
+    ///         p4_cmd::error::Item::Data(p4_cmd::print::ContentChunk::FileEnd) => {
+    ///             println!("{} bytes", size);
+    ///             size = 0;
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn chunks(self) -> ContentChunks {
+        ContentChunks {
+            chunks: self.0,
+            in_file: false,
+            next: None,
+        }
+    }
+}
 
 impl IntoIterator for Files {
     type Item = FileItem;
     type IntoIter = FilesIntoIter;
 
     fn into_iter(self) -> FilesIntoIter {
-        FilesIntoIter(self.0.into_iter())
+        FilesIntoIter(self.chunks())
     }
 }
 
 #[derive(Debug)]
-pub struct FilesIntoIter(vec::IntoIter<FileItem>);
+pub struct FilesIntoIter(ContentChunks);
 
 impl Iterator for FilesIntoIter {
     type Item = FileItem;
 
-    #[inline]
     fn next(&mut self) -> Option<FileItem> {
-        self.0.next()
+        let mut file = match self.0.next()? {
+            error::Item::Data(ContentChunk::FileHeader(file)) => file,
+            error::Item::Data(_) => unreachable!("content follows a header"),
+            error::Item::Message(m) => return Some(error::Item::Message(m)),
+            error::Item::Error(e) => return Some(error::Item::Error(e)),
+            error::Item::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        for chunk in &mut self.0 {
+            match chunk {
+                error::Item::Data(ContentChunk::TextLine(line)) => {
+                    let line = parser::lossy(&line);
+                    match file.content {
+                        FileContent::Text(ref mut lines) => lines.push(line),
+                        _ => file.content = FileContent::Text(vec![line]),
+                    }
+                }
+                error::Item::Data(ContentChunk::BinaryChunk(piece)) => match file.content {
+                    FileContent::Binary(ref mut content) => content.extend_from_slice(&piece),
+                    _ => file.content = FileContent::Binary(piece),
+                },
+                _ => break,
+            }
+        }
+        Some(error::Item::Data(file))
+    }
+}
+
+/// The revisions printed for a depot file, see `PrintCommand::run_history`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileHistory {
+    pub depot_file: paths::DepotPath,
+    /// Each revision's details and content.
+    pub revisions: Vec<File>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+pub type ContentChunkItem = error::Item<ContentChunk>;
+
+/// A piece of `print` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ContentChunk {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    /// A file's details, with empty `content`, ahead of its content.
+    FileHeader(File),
+    /// A line of a text file, without its ending, exactly as reported.
+    TextLine(Vec<u8>),
+    /// A piece of a binary file.
+    BinaryChunk(Vec<u8>),
+    /// The file's content is done.
+    FileEnd,
+}
+
+impl ContentChunk {
+    /// A text line, with anything that isn't UTF-8 replaced.
+    pub fn as_text_line(&self) -> Option<borrow::Cow<'_, str>> {
+        match self {
+            ContentChunk::TextLine(line) => Some(String::from_utf8_lossy(line)),
+            _ => None,
+        }
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+    fn binary_len(&self) -> Option<usize> {
+        match self {
+            ContentChunk::FileHeader(file) => Some(file.file_size),
+            _ => None,
+        }
     }
 
-    #[inline]
-    fn count(self) -> usize {
-        self.0.count()
+    fn binary(piece: &[u8]) -> Self {
+        ContentChunk::BinaryChunk(piece.to_vec())
+    }
+}
+
+/// Puts back the line endings `text:` records drop, except a final one the file doesn't have.
+#[derive(Debug, Default)]
+struct LineEnds {
+    /// The file's size, or 0 if it wasn't reported.
+    size: usize,
+    written: usize,
+    /// Whether the last line written is still missing its ending.
+    pending: bool,
+}
+
+impl LineEnds {
+    fn new(file: &File) -> Self {
+        Self {
+            size: file.file_size,
+            ..Default::default()
+        }
+    }
+
+    fn line<W: io::Write>(&mut self, writer: &mut W, line: &[u8]) -> io::Result<()> {
+        if self.pending {
+            writer.write_all(b"\n")?;
+            self.written += 1;
+        }
+        writer.write_all(line)?;
+        self.written += line.len();
+        self.pending = true;
+        Ok(())
+    }
+
+    fn end<W: io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        let ended = self.size == 0 || self.written < self.size;
+        if self.pending && ended {
+            writer.write_all(b"\n")?;
+        }
+        self.pending = false;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ContentChunks {
+    chunks: exec::Items<ContentChunk>,
+    /// Whether a file's content is being read.
+    in_file: bool,
+    /// The item following the last file's content.
+    next: Option<ContentChunkItem>,
+}
+
+impl Iterator for ContentChunks {
+    type Item = ContentChunkItem;
+
+    fn next(&mut self) -> Option<ContentChunkItem> {
+        loop {
+            let item = self.next.take().or_else(|| self.chunks.next());
+            let content = matches!(
+                item,
+                Some(error::Item::Data(ContentChunk::TextLine(_)))
+                    | Some(error::Item::Data(ContentChunk::BinaryChunk(_)))
+            );
+            if content && !self.in_file {
+                // Content without a file to belong to.
+                continue;
+            }
+            if !content && self.in_file {
+                self.in_file = false;
+                self.next = item;
+                return Some(error::Item::Data(ContentChunk::FileEnd));
+            }
+            if let Some(error::Item::Data(ContentChunk::FileHeader(_))) = item {
+                self.in_file = true;
+            }
+            return item;
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FileContent {
     #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __Nonexhaustive,
 
+    /// Lines, with anything that isn't UTF-8 replaced.  `run_to_writer` keeps the exact bytes.
     Text(Vec<String>),
     Binary(Vec<u8>),
 }
@@ -165,15 +518,17 @@ impl FileContent {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct File {
     pub content: FileContent,
-    pub depot_file: String,
+    pub depot_file: paths::DepotPath,
     pub rev: usize,
     pub change: usize,
     pub action: p4::Action,
     pub file_type: p4::FileType,
     pub time: p4::Time,
     pub file_size: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
     non_exhaustive: (),
 }
 
@@ -182,7 +537,7 @@ mod files_parser {
 
     use super::super::parser::*;
 
-    named!(pub file<&[u8], File>,
+    named!(header<&[u8], File>,
         do_parse!(
             depot_file: depot_file >>
             rev: rev >>
@@ -190,55 +545,94 @@ mod files_parser {
             action: action >>
             file_type: file_type >>
             time: time >>
-            file_size: file_size >>
-            content: alt!(
-                map!(many1!(text), texts_to_content) |
-                map!(take!(file_size.size), slice_to_content)
-            ) >>
+            file_size: opt!(file_size) >>
             (
                 File {
-                    content: content,
-                    depot_file: depot_file.path.to_owned(),
+                    content: FileContent::Binary(Vec::new()),
+                    depot_file: paths::DepotPath::from_bytes(depot_file.path),
                     rev: rev.rev,
                     change: change.change,
                     action: action.action.parse().expect("`Unknown` to capture all"),
                     file_type: file_type.ft.parse().expect("`Unknown` to capture all"),
                     time: p4::from_timestamp(time.time),
-                    file_size: file_size.size,
+                    file_size: file_size.map_or(0, |f| f.size),
                     non_exhaustive: (),
                 }
             )
         )
     );
 
-    named!(item<&[u8], FileItem>,
+    named!(pub item<&[u8], error::Item<ContentChunk>>,
         alt!(
-            map!(file, data_to_item) |
+            map!(header, |f| data_to_item(ContentChunk::FileHeader(f))) |
+            map!(text, |t| data_to_item(ContentChunk::TextLine(t))) |
             map!(error, error_to_item) |
             map!(info, info_to_item)
         )
     );
-
-    named!(pub files<&[u8], (Vec<FileItem>, FileItem)>,
-        pair!(
-            many0!(item),
-            map!(exit, exit_to_item)
-        )
-    );
-
-    fn texts_to_content(texts: Vec<String>) -> FileContent {
-        FileContent::Text(texts)
-    }
-
-    fn slice_to_content(s: &[u8]) -> FileContent {
-        FileContent::Binary(s.to_vec())
-    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use std::cell;
+    use std::sync;
+
+    /// A binary file, an empty one and a text file.
+    const BINARY_THEN_TEXT: &[u8] = b"info1: depotFile //depot/dir/file
+info1: rev 3
+info1: change 42
+info1: action edit
+info1: type binary
+info1: time 1527128624
+info1: fileSize 5
+1\x002\n3info1: depotFile //depot/dir/empty
+info1: rev 1
+info1: change 42
+info1: action add
+info1: type binary
+info1: time 1527128624
+info1: fileSize 0
+info1: depotFile //depot/dir/file2
+info1: rev 1
+info1: change 42
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: fileSize 6
+text: Hello
+warning: //depot/dir/missing - no such file(s).
+exit: 0
+";
+
+    /// Appends to the content of the last file in its list.
+    struct Sink<'a>(&'a cell::RefCell<Vec<(String, Vec<u8>)>>);
+
+    impl<'a> io::Write for Sink<'a> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut sinks = self.0.borrow_mut();
+            sinks.last_mut().unwrap().1.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Parse canned output, splitting off the trailing `exit:` record.
+    fn parse(output: &[u8]) -> (Vec<FileItem>, FileItem) {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: output.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut items: Vec<_> = p4.print("//depot/...").run().unwrap().into_iter().collect();
+        let exit = items.pop().expect("output to include an exit record");
+        (items, exit)
+    }
+
     #[test]
     fn print_text_single() {
         let output: &[u8] = br#"info1: depotFile //depot/dir/file
@@ -252,7 +646,7 @@ text: Hello
 text: World
 exit: 0
 "#;
-        let (_remains, (items, exit)) = files_parser::files(output).unwrap();
+        let (items, exit) = parse(output);
         let item = items[0].as_data().unwrap();
         assert_eq!(
             item.content,
@@ -283,7 +677,7 @@ text: Goodbye
 text: World
 exit: 0
 "#;
-        let (_remains, (items, exit)) = files_parser::files(output).unwrap();
+        let (items, exit) = parse(output);
         let first = items[0].as_data().unwrap();
         let last = items[1].as_data().unwrap();
         assert_eq!(
@@ -308,7 +702,7 @@ info1: time 1527128624
 info1: fileSize 5
 1\02\n3exit: 0
 ";
-        let (_remains, (items, exit)) = files_parser::files(output).unwrap();
+        let (items, exit) = parse(output);
         assert_eq!(
             items[0].as_data().unwrap().content,
             FileContent::Binary(b"1\02\n3".to_vec())
@@ -317,17 +711,280 @@ info1: fileSize 5
     }
 
     #[test]
-    fn file_binary() {
-        let output: &[u8] = b"info1: depotFile //depot/dir/file
+    fn print_chunks() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: BINARY_THEN_TEXT.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let chunks: Vec<_> = p4.print("//depot/...").run().unwrap().chunks().collect();
+        let kinds: Vec<_> = chunks
+            .iter()
+            .map(|chunk| match *chunk {
+                error::Item::Data(ContentChunk::FileHeader(ref file)) => {
+                    assert_eq!(file.content, FileContent::Binary(vec![]));
+                    "header"
+                }
+                error::Item::Data(ContentChunk::TextLine(_)) => "text",
+                error::Item::Data(ContentChunk::BinaryChunk(_)) => "binary",
+                error::Item::Data(ContentChunk::FileEnd) => "end",
+                error::Item::Message(_) => "message",
+                error::Item::Error(_) => "exit",
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "header", "binary", "end", "header", "end", "header", "text", "end", "message",
+                "exit"
+            ]
+        );
+    }
+
+    #[test]
+    fn print_output_to() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"info1: depotFile //depot/dir/file
 info1: rev 3
 info1: change 42
 info1: action edit
-info1: type binary
+info1: type binary+x
 info1: time 1527128624
 info1: fileSize 5
-1\02\n3
+exit: 0
+"
+            .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+        let files = p4
+            .print("//depot/dir/file")
+            .output_to(path::PathBuf::from("/tmp/file"))
+            .run_checked()
+            .unwrap();
+        assert_eq!(files[0].file_size, 5);
+        assert_eq!(files[0].content, FileContent::Binary(vec![]));
+
+        let calls = runner.calls.lock().unwrap();
+        let (ref args, _) = calls[0];
+        let print = args.iter().position(|a| a == "print").unwrap();
+        assert_eq!(
+            &args[print..],
+            &["print", "-o", "/tmp/file", "//depot/dir/file"]
+        );
+    }
+
+    #[test]
+    fn print_history() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"info1: depotFile //depot/dir/a
+info1: rev 2
+info1: change 43
+info1: action edit
+info1: type text
+info1: time 1527128624
+info1: fileSize 4
+text: two
+info1: depotFile //depot/dir/a
+info1: rev 1
+info1: change 41
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: fileSize 4
+text: one
+info1: depotFile //depot/dir/b
+info1: rev 1
+info1: change 42
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: fileSize 2
+text: b
+exit: 0
+"
+            .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+        let histories = p4
+            .print("//depot/dir/...")
+            .all_revs(true)
+            .run_history()
+            .unwrap();
+        assert_eq!(histories.len(), 2);
+        assert_eq!(histories[0].depot_file, "//depot/dir/a");
+        let revs: Vec<_> = histories[0]
+            .revisions
+            .iter()
+            .map(|f| (f.rev, f.change))
+            .collect();
+        assert_eq!(revs, [(2, 43), (1, 41)]);
+        assert_eq!(histories[1].depot_file, "//depot/dir/b");
+        assert_eq!(histories[1].revisions.len(), 1);
+
+        let calls = runner.calls.lock().unwrap();
+        let (ref args, _) = calls[0];
+        let print = args.iter().position(|a| a == "print").unwrap();
+        assert_eq!(&args[print..], &["print", "-a", "//depot/dir/..."]);
+    }
+
+    #[test]
+    fn print_to_writer() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: BINARY_THEN_TEXT.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut content = Vec::new();
+        p4.print("//depot/...").run_to_writer(&mut content).unwrap();
+        assert_eq!(content, b"1\x002\n3Hello\n");
+
+        let sinks = cell::RefCell::new(Vec::new());
+        p4.print("//depot/...")
+            .run_to_sinks(|file| {
+                let name = file.depot_file.to_string();
+                sinks.borrow_mut().push((name, Vec::new()));
+                Ok(Sink(&sinks))
+            })
+            .unwrap();
+        let sinks = sinks.into_inner();
+        assert_eq!(
+            sinks[0],
+            ("//depot/dir/file".to_owned(), b"1\x002\n3".to_vec())
+        );
+        assert_eq!(sinks[1], ("//depot/dir/empty".to_owned(), vec![]));
+        assert_eq!(
+            sinks[2],
+            ("//depot/dir/file2".to_owned(), b"Hello\n".to_vec())
+        );
+    }
+
+    #[test]
+    fn print_to_writer_no_final_newline() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: b"info1: depotFile //depot/dir/file
+info1: rev 1
+info1: change 42
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: fileSize 9
+text: Hello
+text: Bye
+info1: depotFile //depot/dir/file2
+info1: rev 1
+info1: change 42
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: fileSize 7
+text: Hello
+text: 
+exit: 0
+"
+            .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut content = Vec::new();
+        p4.print("//depot/...").run_to_writer(&mut content).unwrap();
+        assert_eq!(content, b"Hello\nByeHello\n\n");
+
+        let sinks = cell::RefCell::new(Vec::new());
+        p4.print("//depot/...")
+            .run_to_sinks(|file| {
+                let name = file.depot_file.to_string();
+                sinks.borrow_mut().push((name, Vec::new()));
+                Ok(Sink(&sinks))
+            })
+            .unwrap();
+        let sinks = sinks.into_inner();
+        assert_eq!(sinks[0].1, b"Hello\nBye");
+        assert_eq!(sinks[1].1, b"Hello\n\n");
+    }
+
+    #[test]
+    fn print_to_writer_non_utf8_text() {
+        let output: &[u8] = b"info1: depotFile //depot/dir/latin1.txt
+info1: rev 1
+info1: change 42
+info1: action add
+info1: type text
+info1: time 1527128624
+info1: fileSize 12
+text: caf\xe9
+text: \x82\xa0\x82\xa2
+exit: 0
 ";
-        let (_remains, item) = files_parser::file(output).unwrap();
-        assert_eq!(item.content, FileContent::Binary(b"1\02\n3".to_vec()));
+        let runner = sync::Arc::new(exec::Recorder {
+            output: output.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut content = Vec::new();
+        p4.print("//depot/...").run_to_writer(&mut content).unwrap();
+        assert_eq!(content, b"caf\xe9\n\x82\xa0\x82\xa2\n");
+
+        let chunks: Vec<_> = p4.print("//depot/...").run().unwrap().chunks().collect();
+        let line = chunks[1].as_data().unwrap();
+        assert_eq!(line, &ContentChunk::TextLine(b"caf\xe9".to_vec()));
+        assert_eq!(line.as_text_line().unwrap(), "caf\u{fffd}");
+
+        let (items, _) = parse(output);
+        assert_eq!(
+            items[0].as_data().unwrap().content,
+            FileContent::Text(vec![
+                "caf\u{fffd}".to_owned(),
+                "\u{fffd}\u{fffd}\u{fffd}\u{fffd}".to_owned()
+            ])
+        );
+    }
+
+    #[test]
+    fn print_to_writer_failure() {
+        struct Full;
+
+        impl io::Write for Full {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let runner = sync::Arc::new(exec::Recorder {
+            output: BINARY_THEN_TEXT.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let err = p4
+            .print("//depot/...")
+            .run_to_writer(&mut Full)
+            .unwrap_err();
+        assert_eq!(err.kind(), error::ErrorKind::WriteFailed);
+    }
+
+    #[test]
+    fn print_binary_then_text() {
+        let (items, exit) = parse(BINARY_THEN_TEXT);
+        assert_eq!(
+            items[0].as_data().unwrap().content,
+            FileContent::Binary(b"1\x002\n3".to_vec())
+        );
+        assert_eq!(
+            items[1].as_data().unwrap().content,
+            FileContent::Binary(vec![])
+        );
+        assert_eq!(
+            items[2].as_data().unwrap().content,
+            FileContent::Text(vec!["Hello".to_owned()])
+        );
+        assert!(items[3].as_message().is_some());
+        assert_eq!(items.len(), 4);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
     }
 }
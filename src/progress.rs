@@ -0,0 +1,134 @@
+//! Report progress of long-running commands, like `sync`.
+//!
+//! Requesting progress passes the global `-I` flag, and each progress indicator `p4` reports is
+//! handed to a callback as the command runs.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::sync::mpsc;
+//! use std::thread;
+//!
+//! let (sender, receiver) = mpsc::channel();
+//! let sync = thread::spawn(move || {
+//!     let p4 = p4_cmd::P4::new();
+//!     let files = p4
+//!         .sync("//depot/...")
+//!         .progress(move |progress| {
+//!             let _ = sender.send(progress.clone());
+//!         })
+//!         .run_checked();
+//!     files.map(|files| files.len())
+//! });
+//! for progress in receiver {
+//!     println!("{} {}/{:?}", progress.description, progress.done, progress.total);
+//! }
+//! sync.join().unwrap().unwrap();
+//! ```
+
+use std::fmt;
+use std::sync;
+
+/// A progress indicator reported by `p4`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Progress {
+    /// What is being done, e.g. `Syncing files`.
+    pub description: String,
+    /// How much is done.
+    pub done: u64,
+    /// How much there is to do, if known.
+    pub total: Option<u64>,
+    /// What is being counted, e.g. `files` or `bytes`, if reported.
+    pub units: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Progress {
+    /// Recognize a progress line, like `Syncing files 10/200 files (5%)`.
+    pub(crate) fn from_line(line: &str) -> Option<Self> {
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        let (index, done, total) = tokens
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(i, t)| count(t).map(|(done, total)| (i, done, total)))
+            .next()?;
+        let description = tokens[..index].join(" ");
+        let description = description.trim_end_matches(':');
+        if description.is_empty() {
+            return None;
+        }
+        let units = tokens
+            .get(index + 1)
+            .filter(|t| !t.starts_with('('))
+            .map(|t| (*t).to_owned());
+        Some(Self {
+            description: description.to_owned(),
+            done,
+            total,
+            units,
+            non_exhaustive: (),
+        })
+    }
+}
+
+/// Parse `done` or `done/total`.
+fn count(token: &str) -> Option<(u64, Option<u64>)> {
+    let mut parts = token.splitn(2, '/');
+    let done = parts.next()?.parse().ok()?;
+    match parts.next() {
+        Some(total) => Some((done, Some(total.parse().ok()?))),
+        None => Some((done, None)),
+    }
+}
+
+/// Hands progress to a user's callback.
+#[derive(Clone)]
+pub(crate) struct Reporter(sync::Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl Reporter {
+    pub(crate) fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        Reporter(sync::Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, progress: &Progress) {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for Reporter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Reporter(<fn>)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn progress_from_line() {
+        let progress = Progress::from_line("Syncing files 10/200 files (5%)").unwrap();
+        assert_eq!(progress.description, "Syncing files");
+        assert_eq!(progress.done, 10);
+        assert_eq!(progress.total, Some(200));
+        assert_eq!(progress.units, Some("files".to_owned()));
+
+        let progress = Progress::from_line("Transferring: 4096 bytes").unwrap();
+        assert_eq!(progress.description, "Transferring");
+        assert_eq!(progress.done, 4096);
+        assert_eq!(progress.total, None);
+        assert_eq!(progress.units, Some("bytes".to_owned()));
+
+        let progress = Progress::from_line("Checking 3/4 (75%)").unwrap();
+        assert_eq!(progress.units, None);
+
+        assert_eq!(Progress::from_line("//depot/file.c#1 - added"), None);
+        assert_eq!(Progress::from_line("12/20"), None);
+    }
+}
@@ -0,0 +1,527 @@
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+use view;
+
+/// Display protections defined for a specified user and path
+///
+/// Protects displays the lines from the protections table that apply to
+/// the current user.  The protections table is managed using the protect
+/// command.
+///
+/// If a file argument is specified, only the lines that apply to files
+/// matching the file argument are displayed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let protections = p4.protects().user("alice").file("//depot/...").run().unwrap();
+/// for protection in protections {
+///     println!("{:?}", protection);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProtectsCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    file: Vec<&'f str>,
+
+    all: bool,
+    group: Option<&'f str>,
+    user: Option<&'f str>,
+    host: Option<&'f str>,
+    max_access: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> ProtectsCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            file: vec![],
+            all: false,
+            group: None,
+            user: None,
+            host: None,
+            max_access: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Only display lines that apply to files matching this file argument.
+    /// Can be repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file);
+        self
+    }
+
+    /// The -a flag displays protection lines for all users.  This flag
+    /// requires super access.
+    pub fn all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
+    }
+
+    /// The -g flag displays lines for associated group.  This flag
+    /// requires super access.
+    pub fn group(mut self, group: &'f str) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    /// The -u flag displays protection lines for the specified user.  This
+    /// flag requires super access.
+    pub fn user(mut self, user: &'f str) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// The -h flag displays protection lines for the specified host (IP
+    /// address).
+    pub fn host(mut self, host: &'f str) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// The -m flag displays a single word summary of the maximum access
+    /// level that applies, rather than the protection lines.  Only `mode`
+    /// is set on the resulting `Protection`.
+    pub fn max_access(mut self, max_access: bool) -> Self {
+        self.max_access = max_access;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("protects");
+        if self.all {
+            cmd.arg("-a");
+        }
+        if let Some(group) = self.group {
+            cmd.args(&["-g", group]);
+        }
+        if let Some(user) = self.user {
+            cmd.args(&["-u", user]);
+        }
+        if let Some(host) = self.host {
+            cmd.args(&["-h", host]);
+        }
+        if self.max_access {
+            cmd.arg("-m");
+        }
+        for file in &self.file {
+            cmd.arg(file);
+        }
+        cmd
+    }
+
+    /// Run the `protects` command.
+    pub fn run(self) -> Result<Protections, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Protections(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            protects_parser::item,
+        )?))
+    }
+
+    /// Run the `protects` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Protection>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ProtectionItem = error::Item<Protection>;
+
+pub struct Protections(exec::Items<Protection>);
+
+impl IntoIterator for Protections {
+    type Item = ProtectionItem;
+    type IntoIter = ProtectionsIntoIter;
+
+    fn into_iter(self) -> ProtectionsIntoIter {
+        ProtectionsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ProtectionsIntoIter(exec::Items<Protection>);
+
+impl Iterator for ProtectionsIntoIter {
+    type Item = ProtectionItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ProtectionItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A line from the protections table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Protection {
+    pub mode: Mode,
+    /// Whether `user` names a group.
+    pub is_group: bool,
+    /// The user or group, `None` with `max_access`.
+    pub user: Option<String>,
+    /// The host, `None` with `max_access`.
+    pub host: Option<String>,
+    /// The depot path, `None` with `max_access`.
+    pub depot_file: Option<String>,
+    /// Whether this is an exclusionary (`-//depot/...`) line.
+    pub unmap: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Protection {
+    /// The line as it's written in the protections table, `None` with `max_access`.
+    pub fn to_entry(&self) -> Option<ProtectionEntry> {
+        let mut entry = ProtectionEntry::new(
+            self.mode.clone(),
+            self.is_group,
+            self.user.clone()?,
+            self.host.clone()?,
+            self.depot_file.clone()?,
+        );
+        entry.exclusionary = self.unmap;
+        Some(entry)
+    }
+}
+
+/// A line of the protections table, as edited with `p4 protect`.
+///
+/// # Example
+///
+/// ```rust
+/// use p4_cmd::protects::{Mode, ProtectionEntry};
+///
+/// let entry: ProtectionEntry = "list group dev * -//secret/...".parse().unwrap();
+/// assert_eq!(entry.mode, Mode::List);
+/// assert!(entry.is_group);
+/// assert_eq!(entry.depot_path, "//secret/...");
+/// assert!(entry.exclusionary);
+/// assert_eq!(entry.to_string(), "list group dev * -//secret/...");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProtectionEntry {
+    pub mode: Mode,
+    /// Whether `name` names a group.
+    pub is_group: bool,
+    /// The user or group, `*` for everyone.
+    pub name: String,
+    /// The host or IP address pattern, `*` for any.
+    pub host: String,
+    /// The depot path, without the `-` of exclusionary lines.
+    pub depot_path: String,
+    /// Whether this is an exclusionary (`-//depot/...`) line.
+    pub exclusionary: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl ProtectionEntry {
+    pub fn new(mode: Mode, is_group: bool, name: String, host: String, depot_path: String) -> Self {
+        Self {
+            mode,
+            is_group,
+            name,
+            host,
+            depot_path,
+            exclusionary: false,
+            non_exhaustive: (),
+        }
+    }
+}
+
+impl str::FromStr for ProtectionEntry {
+    type Err = fmt::Error;
+
+    /// Parse a table line, e.g. `write user alice * //depot/...`.  Paths with spaces are quoted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mode, rest) = view::next_word(s).ok_or(fmt::Error)?;
+        let (kind, rest) = view::next_word(rest).ok_or(fmt::Error)?;
+        let (name, rest) = view::next_word(rest).ok_or(fmt::Error)?;
+        let (host, rest) = view::next_word(rest).ok_or(fmt::Error)?;
+        let (path, rest) = view::next_path(rest).ok_or(fmt::Error)?;
+        let is_group = match kind {
+            "user" => false,
+            "group" => true,
+            _ => return Err(fmt::Error),
+        };
+        if !rest.trim().is_empty() {
+            return Err(fmt::Error);
+        }
+        let (exclusionary, depot_path) = match path.strip_prefix('-') {
+            Some(path) => (true, path.to_owned()),
+            None => (false, path),
+        };
+        let mut entry = ProtectionEntry::new(
+            mode.parse()?,
+            is_group,
+            name.to_owned(),
+            host.to_owned(),
+            depot_path,
+        );
+        entry.exclusionary = exclusionary;
+        Ok(entry)
+    }
+}
+
+impl fmt::Display for ProtectionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.is_group { "group" } else { "user" };
+        let exclude = if self.exclusionary { "-" } else { "" };
+        let q = view::quote(&self.depot_path);
+        write!(
+            f,
+            "{} {} {} {} {q}{}{}{q}",
+            self.mode,
+            kind,
+            self.name,
+            self.host,
+            exclude,
+            self.depot_path,
+            q = q
+        )
+    }
+}
+
+/// Access level granted by a protections line.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::protects::Mode::Write.to_string(), "write");
+/// assert_eq!(
+///     "=branch".parse::<p4_cmd::protects::Mode>().unwrap(),
+///     p4_cmd::protects::Mode::BranchRight
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Mode {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    None,
+    List,
+    Read,
+    Open,
+    Write,
+    Admin,
+    Owner,
+    Super,
+    Review,
+    /// `=read`
+    ReadRight,
+    /// `=branch`
+    BranchRight,
+    /// `=open`
+    OpenRight,
+    /// `=write`
+    WriteRight,
+
+    Unknown(String),
+}
+
+impl str::FromStr for Mode {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode = match s {
+            "none" => Mode::None,
+            "list" => Mode::List,
+            "read" => Mode::Read,
+            "open" => Mode::Open,
+            "write" => Mode::Write,
+            "admin" => Mode::Admin,
+            "owner" => Mode::Owner,
+            "super" => Mode::Super,
+            "review" => Mode::Review,
+            "=read" => Mode::ReadRight,
+            "=branch" => Mode::BranchRight,
+            "=open" => Mode::OpenRight,
+            "=write" => Mode::WriteRight,
+            s => Mode::Unknown(s.to_owned()),
+        };
+        Ok(mode)
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            Mode::None => "none",
+            Mode::List => "list",
+            Mode::Read => "read",
+            Mode::Open => "open",
+            Mode::Write => "write",
+            Mode::Admin => "admin",
+            Mode::Owner => "owner",
+            Mode::Super => "super",
+            Mode::Review => "review",
+            Mode::ReadRight => "=read",
+            Mode::BranchRight => "=branch",
+            Mode::OpenRight => "=open",
+            Mode::WriteRight => "=write",
+            Mode::Unknown(ref s) => s.as_str(),
+            Mode::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+mod protects_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_protection((mode, fields): (&str, Vec<Field>)) -> Result<Protection, fmt::Error> {
+        let mut protection = Protection {
+            mode: mode.parse()?,
+            is_group: false,
+            user: None,
+            host: None,
+            depot_file: None,
+            unmap: false,
+            non_exhaustive: (),
+        };
+        for field in fields {
+            match field.key {
+                "user" => protection.user = Some(field.value.to_owned()),
+                "host" => protection.host = Some(field.value.to_owned()),
+                "depotFile" => protection.depot_file = Some(field.value.to_owned()),
+                "isgroup" => protection.is_group = true,
+                "unmap" => protection.unmap = true,
+                _ => (),
+            }
+        }
+        Ok(protection)
+    }
+
+    named!(protection_field<&[u8], Field>,
+        verify!(any_field, |f: Field| f.key != "perm" && f.key != "permMax")
+    );
+
+    named!(protection<&[u8], Protection>,
+        map_res!(
+            pair!(
+                alt!(call!(field, "perm") | call!(field, "permMax")),
+                many0!(protection_field)
+            ),
+            to_protection
+        )
+    );
+
+    named!(pub item<&[u8], ProtectionItem>,
+        alt!(
+            map!(protection, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn protects_lines() {
+        let output: &[u8] = br#"info1: perm write
+info1: host *
+info1: user *
+info1: line 1
+info1: depotFile //...
+info1: perm list
+info1: host *
+info1: user dev
+info1: isgroup 
+info1: line 2
+info1: depotFile //secret/...
+info1: unmap 
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, protects_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.mode, Mode::Write);
+        assert_eq!(first.depot_file, Some("//...".to_owned()));
+        assert!(!first.is_group);
+        assert_eq!(last.mode, Mode::List);
+        assert_eq!(last.user, Some("dev".to_owned()));
+        assert!(last.is_group);
+        assert!(last.unmap);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn protects_max_access() {
+        let output: &[u8] = br#"info1: permMax super
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, protects_parser::item);
+        let max = items[0].as_data().unwrap();
+        assert_eq!(max.mode, Mode::Super);
+        assert_eq!(max.depot_file, None);
+    }
+
+    #[test]
+    fn protection_entry_round_trip() {
+        let line = "write user alice 10.0.0.* //depot/...";
+        let entry: ProtectionEntry = line.parse().unwrap();
+        assert_eq!(entry.name, "alice");
+        assert_eq!(entry.host, "10.0.0.*");
+        assert!(!entry.is_group && !entry.exclusionary);
+        assert_eq!(entry.to_string(), line);
+
+        let line = "=write group dev * \"-//depot/read only/...\"";
+        let entry: ProtectionEntry = line.parse().unwrap();
+        assert_eq!(entry.mode, Mode::WriteRight);
+        assert_eq!(entry.depot_path, "//depot/read only/...");
+        assert!(entry.exclusionary);
+        assert_eq!(entry.to_string(), line);
+
+        assert!("write owner alice * //..."
+            .parse::<ProtectionEntry>()
+            .is_err());
+        assert!("write user alice *".parse::<ProtectionEntry>().is_err());
+
+        let output: &[u8] = br#"info1: perm list
+info1: host *
+info1: user dev
+info1: isgroup 
+info1: line 1
+info1: depotFile //secret/...
+info1: unmap 
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, protects_parser::item);
+        let entry = items[0].as_data().unwrap().to_entry().unwrap();
+        assert_eq!(entry.to_string(), "list group dev * -//secret/...");
+    }
+}
@@ -0,0 +1,176 @@
+use std::borrow;
+use std::process;
+
+use error;
+use exec;
+use fetch;
+use p4;
+
+/// Copy files and history from this server into another one
+///
+/// Pushes the changes matching the remote spec's DepotMap (or the file
+/// argument) from the personal server to the remote server.
+///
+/// Remote specs are read and written like other specs, with `p4.spec("remote")` and
+/// [`Spec::remote_depot_map`](../spec/struct.Spec.html#method.remote_depot_map).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let changes = p4.push().remote("origin").run().unwrap();
+/// for change in changes {
+///     println!("{:?}", change);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PushCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    file: Vec<borrow::Cow<'f, str>>,
+
+    remote: Option<&'f str>,
+    preview: bool,
+    verbose: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> PushCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            file: vec![],
+            remote: None,
+            preview: false,
+            verbose: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Restrict pushing to files matching this file argument.  Can be
+    /// repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// The -r flag specifies the remote spec to push to, instead of
+    /// 'origin'.
+    pub fn remote(mut self, remote: &'f str) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// The -n flag displays what would be pushed without pushing it.
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// The -v flag reports each change as it is pushed.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("push");
+        if let Some(remote) = self.remote {
+            cmd.args(&["-r", remote]);
+        }
+        if self.preview {
+            cmd.arg("-n");
+        }
+        if self.verbose {
+            cmd.arg("-v");
+        }
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `push` command.
+    pub fn run(self) -> Result<fetch::Transfers, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(fetch::Transfers(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            fetch::fetch_parser::item,
+        )?))
+    }
+
+    /// Run the `push` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<fetch::Transfer>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push_to_command() {
+        let p4 = p4::P4::new();
+        let cmd = p4
+            .push()
+            .remote("upstream")
+            .preview(true)
+            .verbose(true)
+            .file("//depot/main/...")
+            .file_at("//depot/main/a@b", p4::RevSpec::Head)
+            .to_command();
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_str().unwrap()).collect();
+        let push = args.iter().position(|&a| a == "push").unwrap();
+        assert_eq!(
+            &args[push..],
+            [
+                "push",
+                "-r",
+                "upstream",
+                "-n",
+                "-v",
+                "//depot/main/...",
+                "//depot/main/a%40b#head"
+            ]
+        );
+    }
+
+    #[test]
+    fn push_output() {
+        let output: &[u8] = br#"info1: change 12
+info1: newChange 40
+info1: files 2
+info1: desc Fix crash
+info: 1 change(s) containing a total of 2 file revision(s) were successfully pushed.
+exit: 0
+"#;
+        let (_, p4) = exec::recorder(output);
+        let transfers: Vec<_> = p4.push().run().unwrap().into_iter().collect();
+        let transfer = transfers[0].as_data().unwrap();
+        assert_eq!(transfer.change, 12);
+        assert_eq!(transfer.new_change, Some(40));
+        assert_eq!(transfer.files, Some(2));
+        assert_eq!(
+            transfers[1].as_message().unwrap().msg,
+            "1 change(s) containing a total of 2 file revision(s) were successfully pushed."
+        );
+        assert_eq!(
+            transfers[2].as_error(),
+            Some(&error::OperationError::new(0))
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Space out commands, e.g. so bulk tooling stays under a server's command limits.
+//!
+//! Every `p4` invocation waits for its turn, including retries and logging in again.  Clones of
+//! a limiter share its schedule, so one limiter can pace several connections.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let limiter = p4_cmd::rate_limit::RateLimiter::per_second(10);
+//! let p4 = p4_cmd::P4::new().set_rate_limiter(Some(limiter));
+//! for dir in &["//depot/a/*", "//depot/b/*", "//depot/c/*"] {
+//!     let files = p4.files(dir).run_checked().unwrap();
+//!     println!("{}: {}", dir, files.len());
+//! }
+//! ```
+
+use std::io;
+use std::process;
+use std::sync;
+use std::thread;
+use std::time;
+
+use runner;
+
+/// Limits how often `p4` is run.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    interval: time::Duration,
+    next: sync::Arc<sync::Mutex<time::Instant>>,
+}
+
+impl RateLimiter {
+    /// Run at most `commands` per second.
+    pub fn per_second(commands: u32) -> Self {
+        Self::min_delay(time::Duration::from_secs(1) / commands.max(1))
+    }
+
+    /// Start commands at least `delay` apart.
+    pub fn min_delay(delay: time::Duration) -> Self {
+        Self {
+            interval: delay,
+            next: sync::Arc::new(sync::Mutex::new(time::Instant::now())),
+        }
+    }
+
+    /// Block until the next command may start.
+    fn wait(&self) {
+        let now = time::Instant::now();
+        let start = {
+            let mut next = self
+                .next
+                .lock()
+                .unwrap_or_else(sync::PoisonError::into_inner);
+            let start = (*next).max(now);
+            *next = start + self.interval;
+            start
+        };
+        if start > now {
+            thread::sleep(start - now);
+        }
+    }
+}
+
+/// Waits for the limiter before each invocation.
+#[derive(Debug)]
+pub(crate) struct Limited {
+    pub(crate) runner: sync::Arc<dyn runner::P4Runner>,
+    pub(crate) limiter: RateLimiter,
+}
+
+impl runner::P4Runner for Limited {
+    fn run(
+        &self,
+        cmd: process::Command,
+        options: runner::RunOptions,
+    ) -> io::Result<Box<dyn io::Read + Send>> {
+        self.limiter.wait();
+        self.runner.run(cmd, options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn limiter_spaces_commands() {
+        let limiter = RateLimiter::min_delay(time::Duration::from_millis(20));
+        let shared = limiter.clone();
+        let start = time::Instant::now();
+        limiter.wait();
+        shared.wait();
+        limiter.wait();
+        assert!(start.elapsed() >= time::Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,254 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+use progress;
+
+/// Run a command this crate doesn't wrap
+///
+/// Runs `p4 <command> <args>...` with the connection's global options,
+/// reporting each tagged record as generic fields.  Records are split
+/// when a field name repeats.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let records = p4.raw_command("changes").args(&["-m", "5"]).run().unwrap();
+/// for record in records {
+///     println!("{:?}", record);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RawCommand<'p, 'c> {
+    connection: &'p p4::P4,
+    command: &'c str,
+
+    args: Vec<&'c str>,
+    input: Option<Vec<u8>>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'c> RawCommand<'p, 'c> {
+    pub fn new(connection: &'p p4::P4, command: &'c str) -> Self {
+        Self {
+            connection,
+            command,
+            args: vec![],
+            input: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Append an argument to the command.
+    pub fn arg(mut self, arg: &'c str) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    /// Append arguments to the command.
+    pub fn args(mut self, args: &[&'c str]) -> Self {
+        self.args.extend(args);
+        self
+    }
+
+    /// Feed `input` to the command's stdin, e.g. a spec form for `-i`.
+    pub fn input(mut self, input: Vec<u8>) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    settings_methods!();
+
+    /// Report progress indicators (`-I`) through `callback` while the command runs.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&progress::Progress) + Send + Sync + 'static,
+    {
+        self.settings.progress = Some(progress::Reporter::new(callback));
+        self
+    }
+
+    /// Parse `-ztag` output (`... <name> <value>`) rather than `-s` output.
+    ///
+    /// Some commands report fields more reliably this way.  Records are split at blank lines, and
+    /// failures are only known from what `p4` writes to stderr.
+    pub fn ztag(mut self, ztag: bool) -> Self {
+        self.settings.format = if ztag {
+            exec::Format::Ztag
+        } else {
+            exec::Format::Tagged
+        };
+        self
+    }
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        if self.settings.progress.is_some() {
+            cmd.arg("-I");
+        }
+        cmd.arg(self.command);
+        for arg in &self.args {
+            cmd.arg(arg);
+        }
+        cmd
+    }
+
+    /// Run the command.
+    pub fn run(self) -> Result<Records, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Records(exec::spawn_with_input(
+            self.connection,
+            self.settings,
+            cmd,
+            self.input,
+            raw_parser::item,
+        )?))
+    }
+
+    /// Run the command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Record>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type RecordItem = error::Item<Record>;
+
+pub struct Records(exec::Items<Record>);
+
+impl IntoIterator for Records {
+    type Item = RecordItem;
+    type IntoIter = RecordsIntoIter;
+
+    fn into_iter(self) -> RecordsIntoIter {
+        RecordsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct RecordsIntoIter(exec::Items<Record>);
+
+impl Iterator for RecordsIntoIter {
+    type Item = RecordItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<RecordItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A tagged record, as reported by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Record {
+    /// The fields, in order.
+    pub fields: Vec<(String, String)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Record {
+    /// Look up the value of the first field named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.0 == key)
+            .map(|f| f.1.as_str())
+    }
+}
+
+mod raw_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_record(fields: Vec<Field>) -> Record {
+        Record {
+            fields: fields
+                .into_iter()
+                .map(|f| (f.key.to_owned(), f.value.to_owned()))
+                .collect(),
+            non_exhaustive: (),
+        }
+    }
+
+    named!(pub item<&[u8], RecordItem>,
+        alt!(
+            map!(map!(record, to_record), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync;
+
+    #[test]
+    fn raw_records() {
+        let output: &[u8] = br#"info1: change 12
+info1: user alice
+info1: change 11
+info1: user bob
+info: Done
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, raw_parser::item);
+        let first = items[0].as_data().unwrap();
+        assert_eq!(first.get("change"), Some("12"));
+        assert_eq!(first.get("user"), Some("alice"));
+        assert_eq!(items[1].as_data().unwrap().get("user"), Some("bob"));
+        assert!(items[2].as_message().is_some());
+        assert_eq!(items.len(), 3);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn raw_ztag_records() {
+        let runner = sync::Arc::new(exec::Recorder {
+            output: br#"... change 12
+... user alice
+
+... change 11
+... user bob
+
+Done
+"#
+            .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+        let items: Vec<_> = p4
+            .raw_command("changes")
+            .ztag(true)
+            .run()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(items[0].as_data().unwrap().get("change"), Some("12"));
+        assert_eq!(items[1].as_data().unwrap().get("user"), Some("bob"));
+        assert!(items[2].as_message().is_some());
+        assert_eq!(items[3].as_error(), Some(&error::OperationError::new(0)));
+        assert_eq!(items.len(), 4);
+
+        let calls = runner.calls.lock().unwrap();
+        assert!(calls[0].0.iter().any(|a| a == "-ztag"));
+    }
+}
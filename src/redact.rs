@@ -0,0 +1,59 @@
+//! Keep passwords out of `Debug` output and error messages.
+
+use std::fmt;
+use std::process;
+
+const REDACTED: &str = "\"<redacted>\"";
+
+/// A value whose `Debug` output is masked.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Secret<T>(pub(crate) T);
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Render `cmd` like its `Debug` output, masking the password passed with `-P`.
+pub(crate) fn command(cmd: &process::Command) -> String {
+    let mut rendered = format!("{:?}", cmd.get_program());
+    let mut password = false;
+    for arg in cmd.get_args() {
+        rendered.push(' ');
+        if password {
+            rendered.push_str(REDACTED);
+        } else {
+            rendered.push_str(&format!("{:?}", arg));
+        }
+        password = arg == "-P";
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use p4;
+
+    #[test]
+    fn command_masks_password() {
+        let mut cmd = process::Command::new("p4");
+        cmd.args(&["-u", "alice", "-P", "secret", "info"]);
+        assert_eq!(
+            command(&cmd),
+            r#""p4" "-u" "alice" "-P" "<redacted>" "info""#
+        );
+    }
+
+    #[test]
+    fn debug_masks_password() {
+        let p4 = p4::P4::new().set_password(Some("secret".to_owned()));
+        assert!(!format!("{:?}", p4).contains("secret"));
+        let files = p4.files("//depot/...").with_password("hunter2");
+        assert!(!format!("{:?}", files).contains("hunter2"));
+        let login = p4.login().password("hunter2");
+        assert!(!format!("{:?}", login).contains("hunter2"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+use unload;
+
+/// Reload an unloaded client, label, or task stream
+///
+/// Reload restores client, label, or task stream metadata that was
+/// previously unloaded to the unload depot with 'p4 unload'.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let reloaded = p4.reload().client("alice_ws").run().unwrap();
+/// for reload in reloaded {
+///     println!("{:?}", reload);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReloadCommand<'p, 's> {
+    connection: &'p p4::P4,
+
+    client: Option<&'s str>,
+    label: Option<&'s str>,
+    force: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 's> ReloadCommand<'p, 's> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            client: None,
+            label: None,
+            force: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -c flag reloads the specified client.
+    pub fn client(mut self, client: &'s str) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// The -l flag reloads the specified label.
+    pub fn label(mut self, label: &'s str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// The -f flag allows an administrator to reload clients or labels
+    /// owned by other users.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("reload");
+        if self.force {
+            cmd.arg("-f");
+        }
+        if let Some(client) = self.client {
+            cmd.args(&["-c", client]);
+        }
+        if let Some(label) = self.label {
+            cmd.args(&["-l", label]);
+        }
+        cmd
+    }
+
+    /// Run the `reload` command.
+    pub fn run(self) -> Result<Reloads, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Reloads(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            reload_parser::item,
+        )?))
+    }
+
+    /// Run the `reload` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Reload>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ReloadItem = error::Item<Reload>;
+
+pub struct Reloads(exec::Items<Reload>);
+
+impl IntoIterator for Reloads {
+    type Item = ReloadItem;
+    type IntoIter = ReloadsIntoIter;
+
+    fn into_iter(self) -> ReloadsIntoIter {
+        ReloadsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReloadsIntoIter(exec::Items<Reload>);
+
+impl Iterator for ReloadsIntoIter {
+    type Item = ReloadItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ReloadItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A spec that was reloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Reload {
+    pub spec_type: unload::SpecType,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod reload_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_reload(i: Info) -> Result<Reload, ()> {
+        let (spec_type, name) = unload::parse_spec_message(i.msg, " reloaded.").ok_or(())?;
+        let reload = Reload {
+            spec_type,
+            name,
+            non_exhaustive: (),
+        };
+        Ok(reload)
+    }
+
+    named!(pub item<&[u8], ReloadItem>,
+        alt!(
+            map!(map_res!(info, to_reload), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reload_client() {
+        let output: &[u8] = br#"info: Client alice_ws reloaded.
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, reload_parser::item);
+        let reload = items[0].as_data().unwrap();
+        assert_eq!(reload.spec_type, unload::SpecType::Client);
+        assert_eq!(reload.name, "alice_ws");
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,208 @@
+//! Re-run commands that fail, e.g. over a flaky network link.
+//!
+//! This complements `P4::set_retries`, which only covers network timeouts within a single `p4`
+//! invocation.  Output is streamed, so a command is only re-run if it fails before reporting
+//! any data.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::time;
+//!
+//! let policy = p4_cmd::retry::RetryPolicy::new(5)
+//!     .set_backoff(time::Duration::from_millis(200))
+//!     .set_max_backoff(time::Duration::from_secs(10));
+//! let p4 = p4_cmd::P4::new().set_retry_policy(Some(policy));
+//! let files = p4.files("//depot/dir/*").run().unwrap();
+//! for file in files {
+//!     println!("{:?}", file);
+//! }
+//! ```
+
+use std::collections::hash_map;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::sync;
+use std::time;
+
+use error;
+
+type Predicate = dyn Fn(&error::OperationError, &[error::Message]) -> bool + Send + Sync;
+
+/// When and how often to re-run a failed command.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    backoff: time::Duration,
+    max_backoff: time::Duration,
+    multiplier: u32,
+    jitter: bool,
+    predicate: Option<sync::Arc<Predicate>>,
+}
+
+impl RetryPolicy {
+    /// Run a command at most `max_attempts` times, including the first.
+    ///
+    /// By default, commands are retried when they time out or can't reach the server, doubling
+    /// the delay between attempts from 1 second up to 1 minute, with jitter.
+    pub fn new(max_attempts: usize) -> Self {
+        Self {
+            max_attempts,
+            backoff: time::Duration::from_secs(1),
+            max_backoff: time::Duration::from_secs(60),
+            multiplier: 2,
+            jitter: true,
+            predicate: None,
+        }
+    }
+
+    /// Delay before the first retry.
+    pub fn set_backoff(mut self, backoff: time::Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Upper bound on the delay between attempts.
+    pub fn set_max_backoff(mut self, max_backoff: time::Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Factor the delay grows by after each attempt.
+    pub fn set_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Randomize each delay to between half and all of its length, so clients that failed
+    /// together don't retry together.
+    pub fn set_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Decide which failures to retry, given the exit status and the messages reported with it.
+    pub fn set_predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&error::OperationError, &[error::Message]) -> bool + Send + Sync + 'static,
+    {
+        self.predicate = Some(sync::Arc::new(predicate));
+        self
+    }
+
+    /// Whether to make another attempt after `attempt` (counting from 1) failed.
+    pub(crate) fn should_retry(
+        &self,
+        attempt: usize,
+        error: &error::OperationError,
+        messages: &[error::Message],
+    ) -> bool {
+        if self.max_attempts <= attempt || error.kind() == Some(error::ErrorKind::Cancelled) {
+            return false;
+        }
+        match self.predicate {
+            Some(ref predicate) => predicate(error, messages),
+            None => is_transient(error, messages),
+        }
+    }
+
+    /// How long to wait after `attempt` (counting from 1) failed.
+    pub(crate) fn delay(&self, attempt: usize) -> time::Duration {
+        let mut delay = self.backoff;
+        for _ in 1..attempt {
+            delay = delay
+                .checked_mul(self.multiplier)
+                .unwrap_or(self.max_backoff);
+            if self.max_backoff <= delay {
+                break;
+            }
+        }
+        let delay = delay.min(self.max_backoff);
+        if self.jitter {
+            let half = delay / 2;
+            half + half.mul_f64(random_fraction())
+        } else {
+            delay
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("predicate", &self.predicate.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
+}
+
+fn is_transient(error: &error::OperationError, messages: &[error::Message]) -> bool {
+    match error.kind() {
+        Some(error::ErrorKind::TimedOut) | Some(error::ErrorKind::ConnectFailed) => true,
+        _ => messages
+            .iter()
+            .any(|m| error::recognize(&m.msg, m.code()) == Some(error::ErrorKind::ConnectFailed)),
+    }
+}
+
+/// A value in `[0, 1)`, good enough for spreading out retries.
+fn random_fraction() -> f64 {
+    let mut hasher = hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delay_backs_off() {
+        let policy = RetryPolicy::new(10)
+            .set_backoff(time::Duration::from_secs(1))
+            .set_max_backoff(time::Duration::from_secs(5))
+            .set_jitter(false);
+        assert_eq!(policy.delay(1), time::Duration::from_secs(1));
+        assert_eq!(policy.delay(2), time::Duration::from_secs(2));
+        assert_eq!(policy.delay(3), time::Duration::from_secs(4));
+        assert_eq!(policy.delay(4), time::Duration::from_secs(5));
+        assert_eq!(policy.delay(50), time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn delay_jitter() {
+        let policy = RetryPolicy::new(10).set_backoff(time::Duration::from_secs(2));
+        let delay = policy.delay(1);
+        assert!(time::Duration::from_secs(1) <= delay);
+        assert!(delay <= time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn should_retry_transient() {
+        let policy = RetryPolicy::new(2);
+        let failed = error::OperationError::new(1);
+        let unreachable = [error::Message::new(
+            error::MessageLevel::Error,
+            "Connect to server failed; check $P4PORT.".to_owned(),
+        )];
+        let denied = [error::Message::new(
+            error::MessageLevel::Error,
+            "You don't have permission for this operation.".to_owned(),
+        )];
+        assert!(policy.should_retry(1, &failed, &unreachable));
+        assert!(!policy.should_retry(2, &failed, &unreachable));
+        assert!(!policy.should_retry(1, &failed, &denied));
+        let timed_out = error::OperationError::stopped(error::ErrorKind::TimedOut);
+        assert!(policy.should_retry(1, &timed_out, &[]));
+    }
+}
@@ -0,0 +1,259 @@
+//! Control how `p4` is run.
+//!
+//! Commands hand their fully built `p4` invocation to a [`P4Runner`](trait.P4Runner.html),
+//! which by default shells out to `p4`.  Applications can substitute their own runner to test
+//! code using this crate without a live Perforce server.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::io;
+//! use std::process;
+//! use std::sync;
+//!
+//! #[derive(Debug)]
+//! struct Canned(&'static [u8]);
+//!
+//! impl p4_cmd::runner::P4Runner for Canned {
+//!     fn run(
+//!         &self,
+//!         _cmd: process::Command,
+//!         _options: p4_cmd::runner::RunOptions,
+//!     ) -> io::Result<Box<dyn io::Read + Send>> {
+//!         Ok(Box::new(self.0))
+//!     }
+//! }
+//!
+//! let runner = Canned(b"info1: key hello\ninfo1: value world\nexit: 0\n");
+//! let p4 = p4_cmd::P4::new().set_runner(Some(sync::Arc::new(runner)));
+//! let keys: Vec<_> = p4.keys().run().unwrap().into_iter().collect();
+//! assert_eq!(keys[0].as_data().unwrap().key, "hello");
+//! ```
+
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::process;
+use std::sync;
+use std::sync::atomic;
+use std::sync::mpsc;
+use std::thread;
+use std::time;
+
+use cancel;
+
+/// How often to check for cancellation while `p4` runs.
+const CANCEL_POLL: time::Duration = time::Duration::from_millis(50);
+
+/// Run a `p4` invocation, producing its tagged (`-s`) output.
+pub trait P4Runner: fmt::Debug + Send + Sync {
+    /// Run `cmd` as described by `options`.
+    ///
+    /// The output is read lazily, so it may be produced while it is being parsed.  A read failing
+    /// with `io::ErrorKind::TimedOut` is reported as `ErrorKind::TimedOut`.
+    fn run(&self, cmd: process::Command, options: RunOptions) -> io::Result<Box<dyn Read + Send>>;
+}
+
+/// How to run a single `p4` invocation.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Content to feed to the command's stdin.
+    pub input: Option<Vec<u8>>,
+    /// How long the command may run before it is killed.
+    pub timeout: Option<time::Duration>,
+    /// Kill the command once this is cancelled.
+    pub cancel: Option<cancel::CancellationToken>,
+    /// Where to write the command's stderr.
+    pub stderr: Stderr,
+    #[allow(dead_code)]
+    pub(crate) non_exhaustive: (),
+}
+
+/// Collects what a command writes to stderr, for reporting why it failed.
+///
+/// Clones share the same buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Stderr(sync::Arc<sync::Mutex<Vec<u8>>>);
+
+impl Stderr {
+    /// Remove and return everything written so far.
+    pub(crate) fn take(&self) -> String {
+        let buffer = match self.0.lock() {
+            Ok(mut buffer) => ::std::mem::take(&mut *buffer),
+            Err(_) => return String::new(),
+        };
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .map_err(|_| io::Error::other("stderr buffer poisoned"))?
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Run commands as `p4` child processes.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ProcessRunner;
+
+impl P4Runner for ProcessRunner {
+    fn run(
+        &self,
+        mut cmd: process::Command,
+        options: RunOptions,
+    ) -> io::Result<Box<dyn Read + Send>> {
+        let stdin = if options.input.is_some() {
+            process::Stdio::piped()
+        } else {
+            process::Stdio::null()
+        };
+        cmd.stdin(stdin)
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped());
+        let mut child = cmd.spawn()?;
+        let mut stderr = child.stderr.take().expect("stderr to be piped");
+        let mut sink = options.stderr;
+        // Drain from a separate thread so `p4` can't block on a full stderr pipe.
+        let stderr = thread::spawn(move || {
+            let _ = io::copy(&mut stderr, &mut sink);
+        });
+        if let (Some(mut stdin), Some(input)) = (child.stdin.take(), options.input) {
+            // Write from a separate thread so `p4` can't block on us reading its output.
+            thread::spawn(move || {
+                // A failed write means `p4` didn't need the input, which its output reports.
+                let _ = stdin.write_all(&input);
+            });
+        }
+        let stdout = child.stdout.take().expect("stdout to be piped");
+        let child = sync::Arc::new(sync::Mutex::new(child));
+        let watchdog = if options.timeout.is_some() || options.cancel.is_some() {
+            Some(Watchdog::start(
+                child.clone(),
+                options.timeout,
+                options.cancel,
+            ))
+        } else {
+            None
+        };
+        Ok(Box::new(ChildOutput {
+            child,
+            stdout,
+            stderr: Some(stderr),
+            watchdog,
+            reaped: false,
+        }))
+    }
+}
+
+/// Output of a `p4` child process, reaping it once the output is exhausted.
+///
+/// Dropping it early kills the process, so abandoning a command's output doesn't leave `p4`
+/// running.
+#[derive(Debug)]
+struct ChildOutput {
+    child: sync::Arc<sync::Mutex<process::Child>>,
+    stdout: process::ChildStdout,
+    stderr: Option<thread::JoinHandle<()>>,
+    watchdog: Option<Watchdog>,
+    reaped: bool,
+}
+
+impl Read for ChildOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() && !self.reaped {
+            if let Ok(mut child) = self.child.lock() {
+                // Its outcome is reported through the `exit:` record.
+                let _ = child.wait();
+            }
+            if let Some(stderr) = self.stderr.take() {
+                // Finish capturing stderr before the output is reported as complete.
+                let _ = stderr.join();
+            }
+            self.reaped = true;
+        }
+        let timed_out = self.watchdog.as_ref().map(Watchdog::fired);
+        if read == 0 && timed_out == Some(true) {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "p4 was killed after exceeding its timeout",
+            ));
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for ChildOutput {
+    fn drop(&mut self) {
+        if !self.reaped {
+            if let Ok(mut child) = self.child.lock() {
+                // Fails only if the process already exited, which the wait reaps.
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// Kills a child process that runs too long or is cancelled.
+///
+/// The watch ends when this is dropped.
+#[derive(Debug)]
+struct Watchdog {
+    _running: mpsc::Sender<()>,
+    fired: sync::Arc<atomic::AtomicBool>,
+}
+
+impl Watchdog {
+    fn start(
+        child: sync::Arc<sync::Mutex<process::Child>>,
+        timeout: Option<time::Duration>,
+        cancel: Option<cancel::CancellationToken>,
+    ) -> Self {
+        let (running, stopped) = mpsc::channel::<()>();
+        let fired = sync::Arc::new(atomic::AtomicBool::new(false));
+        let flag = fired.clone();
+        let deadline = timeout.map(|timeout| time::Instant::now() + timeout);
+        thread::spawn(move || loop {
+            let now = time::Instant::now();
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(now));
+            let wait = match (remaining, cancel.is_some()) {
+                (Some(remaining), true) => remaining.min(CANCEL_POLL),
+                (Some(remaining), false) => remaining,
+                (None, _) => CANCEL_POLL,
+            };
+            if let Err(mpsc::RecvTimeoutError::Disconnected) = stopped.recv_timeout(wait) {
+                return;
+            }
+            let expired = deadline.is_some_and(|deadline| deadline <= time::Instant::now());
+            let cancelled = cancel.as_ref().is_some_and(|c| c.is_cancelled());
+            if expired || cancelled {
+                if let Ok(mut child) = child.lock() {
+                    if let Ok(None) = child.try_wait() {
+                        // Cancellation is reported from the token itself.
+                        flag.store(expired, atomic::Ordering::SeqCst);
+                        let _ = child.kill();
+                    }
+                }
+                return;
+            }
+        });
+        Self {
+            _running: running,
+            fired,
+        }
+    }
+
+    fn fired(&self) -> bool {
+        self.fired.load(atomic::Ordering::SeqCst)
+    }
+}
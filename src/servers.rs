@@ -0,0 +1,372 @@
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Display list of server specifications
+///
+/// Lists the server specs defined in a distributed (commit/edge or
+/// replicated) installation, so tooling can discover the topology.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let servers = p4.servers().run().unwrap();
+/// for server in servers {
+///     println!("{:?}", server);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServersCommand<'p> {
+    connection: &'p p4::P4,
+    settings: exec::Settings,
+}
+
+impl<'p> ServersCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("servers");
+        cmd
+    }
+
+    /// Run the `servers` command.
+    pub fn run(self) -> Result<Servers, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Servers(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            servers_parser::item,
+        )?))
+    }
+
+    /// Run the `servers` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Server>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+/// Display a server specification
+///
+/// Writes the server spec for `server_id` ('p4 server -o').
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let servers = p4.server("edge1").run().unwrap();
+/// for server in servers {
+///     println!("{:?}", server);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServerCommand<'p, 's> {
+    connection: &'p p4::P4,
+    server_id: &'s str,
+    settings: exec::Settings,
+}
+
+impl<'p, 's> ServerCommand<'p, 's> {
+    pub fn new(connection: &'p p4::P4, server_id: &'s str) -> Self {
+        Self {
+            connection,
+            server_id,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&["server", "-o", self.server_id]);
+        cmd
+    }
+
+    /// Run the `server -o` command.
+    pub fn run(self) -> Result<Servers, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Servers(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            servers_parser::item,
+        )?))
+    }
+
+    /// Run the `server -o` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Server>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type ServerItem = error::Item<Server>;
+
+pub struct Servers(exec::Items<Server>);
+
+impl IntoIterator for Servers {
+    type Item = ServerItem;
+    type IntoIter = ServersIntoIter;
+
+    fn into_iter(self) -> ServersIntoIter {
+        ServersIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ServersIntoIter(exec::Items<Server>);
+
+impl Iterator for ServersIntoIter {
+    type Item = ServerItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<ServerItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A server spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Server {
+    pub server_id: String,
+    pub server_type: ServerType,
+    pub services: Services,
+    /// The P4PORT clients use to reach the server.
+    pub address: Option<String>,
+    /// The P4NAME used to select configurables.
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// The kind of server process.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::servers::ServerType::Proxy.to_string(), "proxy");
+/// assert_eq!(
+///     "server".parse::<p4_cmd::servers::ServerType>().unwrap(),
+///     p4_cmd::servers::ServerType::Server
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ServerType {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Server,
+    Proxy,
+    Broker,
+    Connector,
+
+    Unknown(String),
+}
+
+impl str::FromStr for ServerType {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let server_type = match s {
+            "server" => ServerType::Server,
+            "proxy" => ServerType::Proxy,
+            "broker" => ServerType::Broker,
+            "connector" => ServerType::Connector,
+            s => ServerType::Unknown(s.to_owned()),
+        };
+        Ok(server_type)
+    }
+}
+
+impl fmt::Display for ServerType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            ServerType::Server => "server",
+            ServerType::Proxy => "proxy",
+            ServerType::Broker => "broker",
+            ServerType::Connector => "connector",
+            ServerType::Unknown(ref s) => s.as_str(),
+            ServerType::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// The role a server plays in the topology.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::servers::Services::EdgeServer.to_string(), "edge-server");
+/// assert_eq!(
+///     "forwarding-replica".parse::<p4_cmd::servers::Services>().unwrap(),
+///     p4_cmd::servers::Services::ForwardingReplica
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Services {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Standard,
+    Replica,
+    ForwardingReplica,
+    BuildServer,
+    CommitServer,
+    EdgeServer,
+    StandbyReplica,
+    ForwardingStandby,
+    ReadOnly,
+
+    Unknown(String),
+}
+
+impl str::FromStr for Services {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let services = match s {
+            "standard" => Services::Standard,
+            "replica" => Services::Replica,
+            "forwarding-replica" => Services::ForwardingReplica,
+            "build-server" => Services::BuildServer,
+            "commit-server" => Services::CommitServer,
+            "edge-server" => Services::EdgeServer,
+            "standby" => Services::StandbyReplica,
+            "forwarding-standby" => Services::ForwardingStandby,
+            "readonly" => Services::ReadOnly,
+            s => Services::Unknown(s.to_owned()),
+        };
+        Ok(services)
+    }
+}
+
+impl fmt::Display for Services {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            Services::Standard => "standard",
+            Services::Replica => "replica",
+            Services::ForwardingReplica => "forwarding-replica",
+            Services::BuildServer => "build-server",
+            Services::CommitServer => "commit-server",
+            Services::EdgeServer => "edge-server",
+            Services::StandbyReplica => "standby",
+            Services::ForwardingStandby => "forwarding-standby",
+            Services::ReadOnly => "readonly",
+            Services::Unknown(ref s) => s.as_str(),
+            Services::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+mod servers_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_server(fields: Vec<Field>) -> Result<Server, fmt::Error> {
+        let mut server_id = None;
+        let mut server_type = None;
+        let mut services = None;
+        let mut address = None;
+        let mut name = None;
+        let mut description = None;
+        for field in fields {
+            match field.key {
+                "ServerID" => server_id = Some(field.value.to_owned()),
+                "Type" => server_type = Some(field.value.parse()?),
+                "Services" => services = Some(field.value.parse()?),
+                "Address" => address = Some(field.value.to_owned()),
+                "Name" => name = Some(field.value.to_owned()),
+                "Description" => description = Some(field.value.to_owned()),
+                _ => (),
+            }
+        }
+        let server = Server {
+            server_id: server_id.ok_or(fmt::Error)?,
+            server_type: server_type.ok_or(fmt::Error)?,
+            services: services.ok_or(fmt::Error)?,
+            address,
+            name,
+            description,
+            non_exhaustive: (),
+        };
+        Ok(server)
+    }
+
+    named!(pub item<&[u8], ServerItem>,
+        alt!(
+            map!(map_res!(record, to_server), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn servers_topology() {
+        let output: &[u8] = br#"info1: ServerID commit
+info1: Type server
+info1: Name commit
+info1: Address ssl:commit:1666
+info1: Services commit-server
+info1: Description Commit server
+info1: ServerID edge1
+info1: Type server
+info1: Services edge-server
+info1: Description Edge server
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, servers_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.server_id, "commit");
+        assert_eq!(first.server_type, ServerType::Server);
+        assert_eq!(first.services, Services::CommitServer);
+        assert_eq!(first.address, Some("ssl:commit:1666".to_owned()));
+        assert_eq!(last.server_id, "edge1");
+        assert_eq!(last.services, Services::EdgeServer);
+        assert_eq!(last.address, None);
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
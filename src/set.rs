@@ -0,0 +1,287 @@
+use std::fmt;
+use std::path;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Display Perforce variables
+///
+/// Lists the Perforce variables in effect (from the environment, P4CONFIG
+/// files, the P4ENVIRO file, or the Windows registry) along with where each
+/// value came from.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let settings = p4.set().run().unwrap();
+/// for setting in settings {
+///     println!("{:?}", setting);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SetCommand<'p> {
+    connection: &'p p4::P4,
+
+    quiet: bool,
+    settings: exec::Settings,
+}
+
+impl<'p> SetCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            quiet: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -q flag reduces the output to only the variables and their
+    /// values.  It does not show where the value is set, leaving `source`
+    /// as `None`.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("set");
+        if self.quiet {
+            cmd.arg("-q");
+        }
+        cmd
+    }
+
+    /// Run the `set` command.
+    pub fn run(self) -> Result<Settings, error::P4Error> {
+        let cmd = self.to_command();
+        let items = exec::spawn(self.connection, self.settings, cmd, set_parser::item)?;
+        Ok(Settings(items, !self.quiet))
+    }
+
+    /// Run the `set` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Setting>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type SettingItem = error::Item<Setting>;
+
+pub struct Settings(exec::Items<Setting>, bool);
+
+impl IntoIterator for Settings {
+    type Item = SettingItem;
+    type IntoIter = SettingsIntoIter;
+
+    fn into_iter(self) -> SettingsIntoIter {
+        SettingsIntoIter(self.0, self.1)
+    }
+}
+
+#[derive(Debug)]
+pub struct SettingsIntoIter(exec::Items<Setting>, bool);
+
+impl Iterator for SettingsIntoIter {
+    type Item = SettingItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<SettingItem> {
+        let mut item = self.0.next();
+        if self.1 {
+            // Values from the environment are the only ones without an annotation.
+            if let Some(error::Item::Data(ref mut setting)) = item {
+                if setting.source.is_none() {
+                    setting.source = Some(Source::Environment);
+                }
+            }
+        }
+        item
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A Perforce variable in effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+    /// Where the value came from, `None` when run with `quiet`.
+    pub source: Option<Source>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// Where a Perforce variable was set.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::set::Source::Enviro.to_string(), "enviro");
+/// assert_eq!(
+///     "config '/home/alice/ws/.p4config'".parse::<p4_cmd::set::Source>().unwrap(),
+///     p4_cmd::set::Source::Config("/home/alice/ws/.p4config".into())
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Source {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    /// The process environment.
+    Environment,
+    /// A P4CONFIG file.
+    Config(path::PathBuf),
+    /// The P4ENVIRO file.
+    Enviro,
+    /// The user's registry (`p4 set`).
+    Registry,
+    /// The system registry (`p4 set -s`).
+    SystemRegistry,
+
+    Unknown(String),
+}
+
+impl str::FromStr for Source {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = match s {
+            "enviro" => Source::Enviro,
+            "set" => Source::Registry,
+            "set -s" => Source::SystemRegistry,
+            s if s.starts_with("config '") && s.ends_with('\'') => {
+                Source::Config(path::PathBuf::from(&s[8..s.len() - 1]))
+            }
+            s => Source::Unknown(s.to_owned()),
+        };
+        Ok(source)
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Environment => write!(f, "environment"),
+            Source::Config(ref path) => write!(f, "config '{}'", path.display()),
+            Source::Enviro => write!(f, "enviro"),
+            Source::Registry => write!(f, "set"),
+            Source::SystemRegistry => write!(f, "set -s"),
+            Source::Unknown(ref s) => write!(f, "{}", s),
+            Source::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+}
+
+mod set_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// e.g. `P4CLIENT=alice_ws (config '/home/alice/ws/.p4config')`
+    fn to_setting(line: &str) -> Result<Setting, fmt::Error> {
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().ok_or(fmt::Error)?;
+        let rest = parts.next().ok_or(fmt::Error)?;
+        if name.is_empty() || name.contains(' ') {
+            return Err(fmt::Error);
+        }
+        let (value, source) = match rest.find(" (") {
+            Some(i) if rest.ends_with(')') => {
+                let source = rest[i + 2..rest.len() - 1].parse()?;
+                (&rest[0..i], Some(source))
+            }
+            _ => (rest, None),
+        };
+        let setting = Setting {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            source,
+            non_exhaustive: (),
+        };
+        Ok(setting)
+    }
+
+    fn to_setting_from_info(i: Info) -> Result<Setting, fmt::Error> {
+        to_setting(i.msg)
+    }
+
+    fn to_setting_from_bytes(input: &[u8]) -> Result<Setting, fmt::Error> {
+        let line = str::from_utf8(input).map_err(|_| fmt::Error)?;
+        to_setting(line)
+    }
+
+    named!(bare_setting<&[u8], Setting>,
+        map_res!(
+            terminated!(take_till1!(|c| c == b'\n' || c == b'\r'), newline),
+            to_setting_from_bytes
+        )
+    );
+
+    named!(pub item<&[u8], SettingItem>,
+        alt!(
+            map!(map_res!(info, to_setting_from_info), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item) |
+            map!(bare_setting, data_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_sources() {
+        let output: &[u8] = br#"info: P4CLIENT=alice_ws (config '/home/alice/ws/.p4config')
+info: P4CONFIG=.p4config (enviro)
+info: P4PORT=perforce:1666
+info: P4USER=alice (set)
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, set_parser::item);
+        let client = items[0].as_data().unwrap();
+        assert_eq!(client.name, "P4CLIENT");
+        assert_eq!(client.value, "alice_ws");
+        assert_eq!(
+            client.source,
+            Some(Source::Config(path::PathBuf::from(
+                "/home/alice/ws/.p4config"
+            )))
+        );
+        assert_eq!(items[1].as_data().unwrap().source, Some(Source::Enviro));
+        assert_eq!(items[2].as_data().unwrap().source, None);
+        assert_eq!(items[3].as_data().unwrap().source, Some(Source::Registry));
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn set_untagged() {
+        let output: &[u8] = br#"P4PORT=perforce:1666
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, set_parser::item);
+        assert_eq!(items[0].as_data().unwrap().value, "perforce:1666");
+    }
+}
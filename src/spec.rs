@@ -0,0 +1,852 @@
+//! Spec forms, like those of clients, labels, branches, changes, jobs, streams and remotes.
+//!
+//! A [`Spec`](struct.Spec.html) is read from `p4 <kind> -o` and written back with `p4 <kind> -i`.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let p4 = p4_cmd::P4::new();
+//! let mut spec = p4.spec("client").name("alice-ws").run_checked().unwrap().remove(0);
+//! spec.set("Root", p4_cmd::spec::SpecValue::Line("/home/alice/ws".to_owned()));
+//! let form = spec.to_string();
+//! let saved = p4
+//!     .raw_command("client")
+//!     .arg("-i")
+//!     .input(form.into_bytes())
+//!     .run_checked()
+//!     .unwrap();
+//! ```
+
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+use view;
+
+/// Write a spec form
+///
+/// Writes the `kind` spec, e.g. `client`, to be edited and written back
+/// with 'p4 <kind> -i' ('p4 <kind> -o').
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let specs = p4.spec("label").name("rel-1.0").run().unwrap();
+/// for spec in specs {
+///     println!("{:?}", spec);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpecCommand<'p, 's> {
+    connection: &'p p4::P4,
+    kind: &'s str,
+
+    name: Option<&'s str>,
+    settings: exec::Settings,
+}
+
+impl<'p, 's> SpecCommand<'p, 's> {
+    pub fn new(connection: &'p p4::P4, kind: &'s str) -> Self {
+        Self {
+            connection,
+            kind,
+            name: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The spec to write, rather than the default for `kind`, e.g. the
+    /// current client.
+    pub fn name(mut self, name: &'s str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&[self.kind, "-o"]);
+        if let Some(name) = self.name {
+            cmd.arg(name);
+        }
+        cmd
+    }
+
+    /// Run the `<kind> -o` command.
+    pub fn run(self) -> Result<Specs, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Specs(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            spec_parser::item,
+        )?))
+    }
+
+    /// Run the `<kind> -o` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Spec>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type SpecItem = error::Item<Spec>;
+
+pub struct Specs(exec::Items<Spec>);
+
+impl IntoIterator for Specs {
+    type Item = SpecItem;
+    type IntoIter = SpecsIntoIter;
+
+    fn into_iter(self) -> SpecsIntoIter {
+        SpecsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct SpecsIntoIter(exec::Items<Spec>);
+
+impl Iterator for SpecsIntoIter {
+    type Item = SpecItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<SpecItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// The value of a spec field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SpecValue {
+    /// A value on the field's line, e.g. `Owner:` or `Root:`.
+    Line(String),
+    /// A value on the indented lines after the field, e.g. `View:` or `Description:`.
+    Lines(Vec<String>),
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+impl SpecValue {
+    /// The value as a date, like `Update:` and `Access:`.
+    pub fn to_date(&self) -> Option<p4::ServerDate> {
+        match self {
+            SpecValue::Line(ref line) => line.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value, with `Lines` joined by newlines.
+    pub fn to_text(&self) -> String {
+        match self {
+            SpecValue::Line(ref line) => line.clone(),
+            SpecValue::Lines(ref lines) => lines.join("\n"),
+            SpecValue::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+}
+
+/// A spec form, with its fields in order.
+///
+/// Parses and writes the form text of `p4 <kind> -o` and `p4 <kind> -i`, skipping `#` comments.
+///
+/// # Example
+///
+/// ```rust
+/// use p4_cmd::spec::{Spec, SpecValue};
+///
+/// let spec: Spec = "Label:\trel-1.0\n\nView:\n\t//depot/rel/...\n".parse().unwrap();
+/// assert_eq!(spec.get("Label"), Some(&SpecValue::Line("rel-1.0".to_owned())));
+/// assert_eq!(spec.to_string(), "Label:\trel-1.0\n\nView:\n\t//depot/rel/...\n\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Spec {
+    /// The fields, in order.
+    pub fields: Vec<(String, SpecValue)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Spec {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Gather tagged fields, like those `-o` reports, into a spec.
+    ///
+    /// Numbered fields, like `View0` and `View1`, are gathered into the lines of `View`.  Only
+    /// fields numbered from `0` up are gathered, so a field like `Build64` keeps its name.
+    pub fn from_fields<I>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let fields: Vec<_> = fields.into_iter().collect();
+        let is_list = |stem: &str| {
+            let mut indices: Vec<_> = fields
+                .iter()
+                .filter_map(|f| split_index(&f.0))
+                .filter(|&(s, _)| s == stem)
+                .map(|(_, index)| index)
+                .collect();
+            indices.sort();
+            indices.iter().enumerate().all(|(i, &index)| i == index)
+        };
+
+        let mut spec = Spec::new();
+        for (key, value) in fields.iter().cloned() {
+            let name = match split_index(&key) {
+                Some((stem, _)) if is_list(stem) => stem,
+                _ => {
+                    let value = if value.contains('\n') {
+                        SpecValue::Lines(value.lines().map(str::to_owned).collect())
+                    } else {
+                        SpecValue::Line(value)
+                    };
+                    spec.fields.push((key, value));
+                    continue;
+                }
+            };
+            match spec.fields.iter_mut().find(|f| f.0 == name) {
+                Some(&mut (_, SpecValue::Lines(ref mut lines))) => lines.push(value),
+                _ => spec
+                    .fields
+                    .push((name.to_owned(), SpecValue::Lines(vec![value]))),
+            }
+        }
+        spec
+    }
+
+    /// Look up the value of the field named `name`.
+    pub fn get(&self, name: &str) -> Option<&SpecValue> {
+        self.fields.iter().find(|f| f.0 == name).map(|f| &f.1)
+    }
+
+    /// Replace the value of the field named `name`, adding it if it's missing.
+    pub fn set(&mut self, name: &str, value: SpecValue) {
+        match self.fields.iter_mut().find(|f| f.0 == name) {
+            Some(field) => field.1 = value,
+            None => self.fields.push((name.to_owned(), value)),
+        }
+    }
+
+    /// Remove the field named `name`, returning its value.
+    pub fn remove(&mut self, name: &str) -> Option<SpecValue> {
+        let index = self.fields.iter().position(|f| f.0 == name)?;
+        Some(self.fields.remove(index).1)
+    }
+
+    /// The client's `Options:`, `None` unless the field is there and parses.
+    pub fn client_options(&self) -> Option<ClientOptions> {
+        match self.get("Options") {
+            Some(SpecValue::Line(line)) => line.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Replace the client's `Options:`.
+    pub fn set_client_options(&mut self, options: &ClientOptions) {
+        self.set("Options", SpecValue::Line(options.to_string()));
+    }
+
+    /// The stream's `Paths:`, `None` unless the field is there and every line parses.
+    pub fn stream_paths(&self) -> Option<Vec<StreamPath>> {
+        self.parse_lines("Paths")
+    }
+
+    /// Replace the stream's `Paths:`.
+    pub fn set_stream_paths(&mut self, paths: &[StreamPath]) {
+        self.set_lines("Paths", paths);
+    }
+
+    /// The stream's `Remapped:`, each line mapping a path in the stream's view to another.
+    pub fn stream_remapped(&self) -> Option<Vec<view::Mapping>> {
+        self.parse_lines("Remapped")
+    }
+
+    /// Replace the stream's `Remapped:`.
+    pub fn set_stream_remapped(&mut self, remapped: &[view::Mapping]) {
+        self.set_lines("Remapped", remapped);
+    }
+
+    /// The stream's `Ignored:`, paths and file extensions left out of the stream's view.
+    pub fn stream_ignored(&self) -> Option<Vec<String>> {
+        self.parse_lines("Ignored")
+    }
+
+    /// Replace the stream's `Ignored:`.
+    pub fn set_stream_ignored(&mut self, ignored: &[String]) {
+        self.set_lines("Ignored", ignored);
+    }
+
+    /// The remote's `DepotMap:`, mapping this server's depot paths (left) to the remote's
+    /// (right), as used by `fetch`, `push` and `clone_server`.
+    pub fn remote_depot_map(&self) -> Option<Vec<view::Mapping>> {
+        self.parse_lines("DepotMap")
+    }
+
+    /// Replace the remote's `DepotMap:`.
+    pub fn set_remote_depot_map(&mut self, depot_map: &[view::Mapping]) {
+        self.set_lines("DepotMap", depot_map);
+    }
+
+    fn parse_lines<T: str::FromStr>(&self, name: &str) -> Option<Vec<T>> {
+        match self.get(name) {
+            Some(SpecValue::Lines(lines)) => lines
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.parse().ok())
+                .collect(),
+            Some(SpecValue::Line(line)) => line.parse().ok().map(|l| vec![l]),
+            _ => None,
+        }
+    }
+
+    fn set_lines<T: fmt::Display>(&mut self, name: &str, lines: &[T]) {
+        let lines = lines.iter().map(ToString::to_string).collect();
+        self.set(name, SpecValue::Lines(lines));
+    }
+}
+
+/// Split a numbered field, like `View1`, into its name and index.
+fn split_index(key: &str) -> Option<(&str, usize)> {
+    let stem = key.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &key[stem.len()..];
+    if stem.is_empty() || digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return None;
+    }
+    digits.parse().ok().map(|index| (stem, index))
+}
+
+impl str::FromStr for Spec {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut spec = Spec::new();
+        let mut lines = s.lines().peekable();
+        while let Some(line) = lines.next() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let colon = line.find(':').ok_or(fmt::Error)?;
+            let name = &line[..colon];
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(fmt::Error);
+            }
+            let value = line[colon + 1..].trim();
+            if !value.is_empty() {
+                spec.fields
+                    .push((name.to_owned(), SpecValue::Line(value.to_owned())));
+                continue;
+            }
+
+            let mut block = Vec::new();
+            while let Some(&next) = lines.peek() {
+                if next.trim().is_empty() {
+                    block.push(String::new());
+                } else if next.starts_with(char::is_whitespace) {
+                    let next = next.strip_prefix('\t').unwrap_or_else(|| next.trim_start());
+                    block.push(next.to_owned());
+                } else {
+                    break;
+                }
+                lines.next();
+            }
+            while block.last().is_some_and(String::is_empty) {
+                block.pop();
+            }
+            spec.fields.push((name.to_owned(), SpecValue::Lines(block)));
+        }
+        Ok(spec)
+    }
+}
+
+impl fmt::Display for Spec {
+    /// Write the form for `-i`, separating fields with blank lines.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.fields {
+            match value {
+                SpecValue::Line(ref line) => writeln!(f, "{}:\t{}", name, line)?,
+                SpecValue::Lines(ref lines) => {
+                    writeln!(f, "{}:", name)?;
+                    for line in lines {
+                        writeln!(f, "\t{}", line)?;
+                    }
+                }
+                SpecValue::__Nonexhaustive => unreachable!("This is a private variant"),
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// A client's `Options:`, e.g. `noallwrite noclobber nocompress unlocked nomodtime normdir`.
+///
+/// The default is the server's default, every option off.  Options added by newer servers are
+/// kept as they are, so they survive a round trip.
+///
+/// # Example
+///
+/// ```rust
+/// use p4_cmd::spec::ClientOptions;
+///
+/// let mut options: ClientOptions = "noallwrite clobber nocompress unlocked nomodtime normdir"
+///     .parse()
+///     .unwrap();
+/// assert!(options.clobber);
+/// options.rmdir = true;
+/// assert_eq!(
+///     options.to_string(),
+///     "noallwrite clobber nocompress unlocked nomodtime rmdir"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClientOptions {
+    /// Leave all files writable, `allwrite`.
+    pub allwrite: bool,
+    /// Let `sync` overwrite writable files that aren't opened, `clobber`.
+    pub clobber: bool,
+    /// Compress data sent between client and server, `compress`.
+    pub compress: bool,
+    /// Only let the owner use, edit or delete the client, `locked`.
+    pub locked: bool,
+    /// Set files' modification times to when they were submitted, `modtime`.
+    pub modtime: bool,
+    /// Delete directories left empty by removing files, `rmdir`.
+    pub rmdir: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    unknown: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl ClientOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Each option with the words that turn it on and off.
+    fn flags(&self) -> [(bool, &'static str, &'static str); 6] {
+        [
+            (self.allwrite, "allwrite", "noallwrite"),
+            (self.clobber, "clobber", "noclobber"),
+            (self.compress, "compress", "nocompress"),
+            (self.locked, "locked", "unlocked"),
+            (self.modtime, "modtime", "nomodtime"),
+            (self.rmdir, "rmdir", "normdir"),
+        ]
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "allwrite" => Some(&mut self.allwrite),
+            "clobber" => Some(&mut self.clobber),
+            "compress" => Some(&mut self.compress),
+            "locked" => Some(&mut self.locked),
+            "modtime" => Some(&mut self.modtime),
+            "rmdir" => Some(&mut self.rmdir),
+            _ => None,
+        }
+    }
+}
+
+impl str::FromStr for ClientOptions {
+    type Err = fmt::Error;
+
+    /// Fails if an option is given more than once, e.g. `clobber noclobber`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = ClientOptions::new();
+        let mut seen = Vec::new();
+        for word in s.split_whitespace() {
+            let (name, on) = match word {
+                "unlocked" => ("locked", false),
+                _ => match word.strip_prefix("no") {
+                    Some(name) if options.flag_mut(name).is_some() => (name, false),
+                    _ => (word, true),
+                },
+            };
+            match options.flag_mut(name) {
+                Some(flag) => *flag = on,
+                None => {
+                    options.unknown.push(word.to_owned());
+                    continue;
+                }
+            }
+            if seen.contains(&name) {
+                return Err(fmt::Error);
+            }
+            seen.push(name);
+        }
+        Ok(options)
+    }
+}
+
+impl fmt::Display for ClientOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self.flags();
+        let words = flags
+            .iter()
+            .map(|&(on, yes, no)| if on { yes } else { no })
+            .chain(self.unknown.iter().map(String::as_str));
+        for (i, word) in words.enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(word)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a stream's `Paths:` line brings files into the stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StreamPathKind {
+    /// Files are synced, submitted and integrated to and from the parent, `share`.
+    Share,
+    /// Files are synced and submitted but not integrated, `isolate`.
+    Isolate,
+    /// Files are synced from another depot path but not submitted, `import`.
+    Import,
+    /// Files are synced from another depot path and can be submitted, `import+`.
+    ImportPlus,
+    /// Files are left out of the stream, `exclude`.
+    Exclude,
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+impl StreamPathKind {
+    fn keyword(self) -> &'static str {
+        match self {
+            StreamPathKind::Share => "share",
+            StreamPathKind::Isolate => "isolate",
+            StreamPathKind::Import => "import",
+            StreamPathKind::ImportPlus => "import+",
+            StreamPathKind::Exclude => "exclude",
+            StreamPathKind::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+}
+
+/// A line of a stream's `Paths:`, e.g. `import lib/... //depot/lib/...@42`.
+///
+/// # Example
+///
+/// ```rust
+/// use p4_cmd::spec::{StreamPath, StreamPathKind};
+///
+/// let path: StreamPath = "import+ lib/... //depot/lib/...".parse().unwrap();
+/// assert_eq!(path.kind, StreamPathKind::ImportPlus);
+/// assert_eq!(path.view_path, "lib/...");
+/// assert_eq!(path.depot_path, Some("//depot/lib/...".to_owned()));
+/// assert_eq!(path.to_string(), "import+ lib/... //depot/lib/...");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StreamPath {
+    pub kind: StreamPathKind,
+    /// The path, relative to the stream's root.
+    pub view_path: String,
+    /// Where files are imported from, possibly with a revision.  Only imports have one, and
+    /// without one they're imported from the parent's depot path.
+    pub depot_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl StreamPath {
+    pub fn new(kind: StreamPathKind, view_path: String) -> Self {
+        Self {
+            kind,
+            view_path,
+            depot_path: None,
+            non_exhaustive: (),
+        }
+    }
+
+    /// An import from `depot_path`.
+    pub fn import(view_path: String, depot_path: String) -> Self {
+        let mut path = Self::new(StreamPathKind::Import, view_path);
+        path.depot_path = Some(depot_path);
+        path
+    }
+}
+
+impl str::FromStr for StreamPath {
+    type Err = fmt::Error;
+
+    /// Parse a `Paths:` line.  Paths with spaces are quoted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start();
+        let end = s.find(char::is_whitespace).ok_or(fmt::Error)?;
+        let kind = match &s[..end] {
+            "share" => StreamPathKind::Share,
+            "isolate" => StreamPathKind::Isolate,
+            "import" => StreamPathKind::Import,
+            "import+" => StreamPathKind::ImportPlus,
+            "exclude" => StreamPathKind::Exclude,
+            _ => return Err(fmt::Error),
+        };
+        let (view_path, rest) = view::next_path(&s[end..]).ok_or(fmt::Error)?;
+        let depot_path = match view::next_path(rest) {
+            Some((depot_path, rest)) if rest.trim().is_empty() => Some(depot_path),
+            Some(_) => return Err(fmt::Error),
+            None => None,
+        };
+        let imported = matches!(kind, StreamPathKind::Import | StreamPathKind::ImportPlus);
+        if depot_path.is_some() && !imported {
+            return Err(fmt::Error);
+        }
+        let mut path = StreamPath::new(kind, view_path);
+        path.depot_path = depot_path;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for StreamPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let q = view::quote(&self.view_path);
+        write!(f, "{} {q}{}{q}", self.kind.keyword(), self.view_path, q = q)?;
+        if let Some(ref depot_path) = self.depot_path {
+            let q = view::quote(depot_path);
+            write!(f, " {q}{}{q}", depot_path, q = q)?;
+        }
+        Ok(())
+    }
+}
+
+mod spec_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_spec(fields: Vec<Field>) -> Spec {
+        Spec::from_fields(
+            fields
+                .into_iter()
+                .map(|f| (f.key.to_owned(), f.value.to_owned())),
+        )
+    }
+
+    named!(pub item<&[u8], SpecItem>,
+        alt!(
+            map!(map!(record, to_spec), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FORM: &str = "# A Perforce Client Specification.
+#
+#  Client:      The client name.
+
+Client:\talice-ws
+
+Owner:\talice
+
+Description:
+\tCreated by alice.
+
+\tSecond paragraph.
+
+Root:\t/home/alice/ws
+
+View:
+\t//depot/main/... //alice-ws/main/...
+\t-//depot/main/tmp/... //alice-ws/main/tmp/...
+
+";
+
+    #[test]
+    fn spec_form_round_trip() {
+        let spec: Spec = FORM.parse().unwrap();
+        assert_eq!(spec.fields.len(), 5);
+        assert_eq!(
+            spec.get("Description"),
+            Some(&SpecValue::Lines(vec![
+                "Created by alice.".to_owned(),
+                "".to_owned(),
+                "Second paragraph.".to_owned(),
+            ]))
+        );
+        assert_eq!(
+            spec.get("View").unwrap().to_text(),
+            "//depot/main/... //alice-ws/main/...\n-//depot/main/tmp/... //alice-ws/main/tmp/..."
+        );
+        let form = spec.to_string();
+        assert!(form.starts_with("Client:\talice-ws\n\nOwner:\talice\n\n"));
+        assert_eq!(form.parse::<Spec>().unwrap(), spec);
+        assert!("\tindented".parse::<Spec>().is_err());
+    }
+
+    #[test]
+    fn spec_from_tagged() {
+        let output: &[u8] = br#"info1: Client alice-ws
+info1: Owner alice
+info1: Update 2018/08/13 10:11:12
+info1: View0 //depot/main/... //alice-ws/main/...
+info1: View1 -//depot/main/tmp/... //alice-ws/main/tmp/...
+exit: 0
+"#;
+        let (items, _) = exec::parse(output, spec_parser::item);
+        let mut spec = items[0].as_data().unwrap().clone();
+        assert_eq!(
+            spec.get("Owner"),
+            Some(&SpecValue::Line("alice".to_owned()))
+        );
+        assert_eq!(
+            spec.get("Update").and_then(SpecValue::to_date),
+            Some("2018/08/13 10:11:12".parse().unwrap())
+        );
+        assert_eq!(
+            spec.get("View"),
+            Some(&SpecValue::Lines(vec![
+                "//depot/main/... //alice-ws/main/...".to_owned(),
+                "-//depot/main/tmp/... //alice-ws/main/tmp/...".to_owned(),
+            ]))
+        );
+        spec.set("Owner", SpecValue::Line("bob".to_owned()));
+        assert_eq!(
+            spec.remove("Client"),
+            Some(SpecValue::Line("alice-ws".to_owned()))
+        );
+        assert!(spec.to_string().starts_with(
+            "Owner:\tbob\n\nUpdate:\t2018/08/13 10:11:12\n\nView:\n\t//depot/main/..."
+        ));
+    }
+
+    #[test]
+    fn spec_from_tagged_keeps_numbered_names() {
+        let spec = Spec::from_fields(vec![
+            ("Job".to_owned(), "job000001".to_owned()),
+            ("Build64".to_owned(), "yes".to_owned()),
+            ("Files0".to_owned(), "//depot/a".to_owned()),
+            ("Arch2".to_owned(), "x86".to_owned()),
+            ("Arch3".to_owned(), "arm".to_owned()),
+        ]);
+        assert_eq!(
+            spec.get("Build64"),
+            Some(&SpecValue::Line("yes".to_owned()))
+        );
+        assert_eq!(
+            spec.get("Files"),
+            Some(&SpecValue::Lines(vec!["//depot/a".to_owned()]))
+        );
+        assert_eq!(spec.get("Arch"), None);
+        assert_eq!(spec.get("Arch3"), Some(&SpecValue::Line("arm".to_owned())));
+    }
+
+    #[test]
+    fn client_options_round_trip() {
+        let line = "allwrite noclobber compress unlocked modtime normdir";
+        let options: ClientOptions = line.parse().unwrap();
+        assert!(options.allwrite && options.compress && options.modtime);
+        assert!(!options.clobber && !options.locked && !options.rmdir);
+        assert_eq!(options.to_string(), line);
+
+        let options: ClientOptions = "locked noaltsync".parse().unwrap();
+        assert!(options.locked);
+        assert_eq!(
+            options.to_string(),
+            "noallwrite noclobber nocompress locked nomodtime normdir noaltsync"
+        );
+        assert!("clobber noclobber".parse::<ClientOptions>().is_err());
+
+        let mut spec: Spec = FORM.parse().unwrap();
+        assert_eq!(spec.client_options(), None);
+        spec.set_client_options(&options);
+        assert_eq!(spec.client_options(), Some(options));
+    }
+
+    #[test]
+    fn stream_paths_round_trip() {
+        let form = "Stream:\t//streams/dev
+
+Paths:
+\tshare ...
+\tisolate bin/...
+\timport lib/... //depot/lib/...@42
+\texclude \"tmp files/...\"
+
+Remapped:
+\tsrc/... source/...
+
+Ignored:
+\t.o
+";
+        let mut spec: Spec = form.parse().unwrap();
+        let paths = spec.stream_paths().unwrap();
+        assert_eq!(paths.len(), 4);
+        assert_eq!(
+            paths[1],
+            StreamPath::new(StreamPathKind::Isolate, "bin/...".to_owned())
+        );
+        assert_eq!(
+            paths[2],
+            StreamPath::import("lib/...".to_owned(), "//depot/lib/...@42".to_owned())
+        );
+        assert_eq!(paths[3].view_path, "tmp files/...");
+        assert_eq!(spec.stream_remapped().unwrap()[0].right, "source/...");
+        assert_eq!(spec.stream_ignored().unwrap(), vec![".o".to_owned()]);
+
+        spec.set_stream_paths(&paths);
+        assert_eq!(
+            spec.to_string().parse::<Spec>().unwrap(),
+            form.parse().unwrap()
+        );
+        assert!("share a/... //depot/a/...".parse::<StreamPath>().is_err());
+        assert!("branch a/...".parse::<StreamPath>().is_err());
+    }
+
+    #[test]
+    fn remote_depot_map_round_trip() {
+        let form = "RemoteID:\torigin
+
+Address:\tperforce:1666
+
+DepotMap:
+\t//depot/main/... //depot/main/...
+\t-//depot/main/tmp/... //depot/main/tmp/...
+";
+        let mut spec: Spec = form.parse().unwrap();
+        let map = spec.remote_depot_map().unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[0].left, "//depot/main/...");
+        assert_eq!(map[1].right, "//depot/main/tmp/...");
+
+        spec.set_remote_depot_map(&map);
+        assert_eq!(
+            spec.to_string().parse::<Spec>().unwrap(),
+            form.parse().unwrap()
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Adapt command output into a `futures::Stream`.
+//!
+//! This is gated behind the `futures` feature.  Commands are run as blocking child processes, so
+//! the output is bridged to async code through a channel fed by a worker thread:
+//! - The channel is bounded; the worker stops reading `p4`'s output while the stream is not being
+//!   polled.
+//! - Dropping the stream cancels the worker at its next record, killing `p4` if it hasn't
+//!   finished.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! extern crate futures;
+//! extern crate p4_cmd;
+//!
+//! use futures::StreamExt;
+//!
+//! let p4 = p4_cmd::P4::new();
+//! let files = p4.files("//depot/dir/*").run().unwrap();
+//! let files = p4_cmd::stream::stream(files);
+//! futures::executor::block_on(files.for_each(|file| {
+//!     println!("{:?}", file);
+//!     futures::future::ready(())
+//! }));
+//! ```
+
+use std::pin;
+use std::task;
+use std::thread;
+
+use futures::channel::mpsc;
+use futures::executor;
+use futures::SinkExt;
+use futures::Stream;
+
+/// Records buffered ahead of the consumer.
+const BUFFER: usize = 16;
+
+/// Move `items` to a worker thread, streaming them back as they are produced.
+pub fn stream<I>(items: I) -> ItemStream<I::Item>
+where
+    I: IntoIterator,
+    I::IntoIter: Send + 'static,
+    I::Item: Send + 'static,
+{
+    let (mut sender, receiver) = mpsc::channel(BUFFER);
+    let items = items.into_iter();
+    thread::spawn(move || {
+        for item in items {
+            if executor::block_on(sender.send(item)).is_err() {
+                // The stream was dropped.
+                break;
+            }
+        }
+    });
+    ItemStream { receiver }
+}
+
+/// Command output produced by a worker thread.
+///
+/// See [`stream`](fn.stream.html).
+#[derive(Debug)]
+pub struct ItemStream<T> {
+    receiver: mpsc::Receiver<T>,
+}
+
+impl<T> Stream for ItemStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: pin::Pin<&mut Self>, cx: &mut task::Context) -> task::Poll<Option<T>> {
+        pin::Pin::new(&mut self.receiver).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.receiver.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::StreamExt;
+
+    #[test]
+    fn stream_preserves_order() {
+        let items: Vec<usize> = (0..(BUFFER * 3)).collect();
+        let streamed: Vec<usize> = executor::block_on(stream(items.clone()).collect());
+        assert_eq!(streamed, items);
+    }
+
+    #[test]
+    fn stream_cancels_on_drop() {
+        let mut items = stream(0..);
+        assert_eq!(executor::block_on(items.next()), Some(0));
+        drop(items);
+    }
+}
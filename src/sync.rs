@@ -1,8 +1,13 @@
-use std::path;
-use std::vec;
+use std::borrow;
+use std::process;
+use std::str;
 
 use error;
+use exec;
+use info;
 use p4;
+use paths;
+use progress;
 
 /// Synchronize the client with its view of the depot
 ///
@@ -46,7 +51,8 @@ use p4;
 #[derive(Debug, Clone)]
 pub struct SyncCommand<'p, 'f> {
     connection: &'p p4::P4,
-    file: Vec<&'f str>,
+    file: Vec<borrow::Cow<'f, str>>,
+    batch_files: bool,
 
     force: bool,
     preview: bool,
@@ -55,13 +61,19 @@ pub struct SyncCommand<'p, 'f> {
     verify: bool,
     max_files: Option<usize>,
     parallel: Option<usize>,
+    quiet: bool,
+    reopen_moved: bool,
+    use_stream_views: bool,
+    estimate: bool,
+    settings: exec::Settings,
 }
 
 impl<'p, 'f> SyncCommand<'p, 'f> {
     pub fn new(connection: &'p p4::P4, file: &'f str) -> Self {
         Self {
             connection: connection,
-            file: vec![file],
+            file: vec![file.into()],
+            batch_files: false,
             force: false,
             preview: false,
             server_only: false,
@@ -69,11 +81,29 @@ impl<'p, 'f> SyncCommand<'p, 'f> {
             verify: false,
             max_files: None,
             parallel: None,
+            quiet: false,
+            reopen_moved: false,
+            use_stream_views: false,
+            estimate: false,
+            settings: exec::Settings::default(),
         }
     }
 
-    pub fn file(mut self, dir: &'f str) -> Self {
-        self.file.push(dir);
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// Pass the file arguments on stdin (the global `-x -` option) rather
+    /// than the command line, avoiding OS limits on very long file lists.
+    pub fn batch_files(mut self, batch_files: bool) -> Self {
+        self.batch_files = batch_files;
         self
     }
 
@@ -155,14 +185,57 @@ impl<'p, 'f> SyncCommand<'p, 'f> {
     /// unsetting the net.parallel.threads configurable. A user may override
     /// the configured auto parallel sync options on the command line, or may
     /// disable it via 'p4 sync --parallel=0'.
+    ///
+    /// Servers older than 2014.1 don't support this, failing with
+    /// `ErrorKind::UnsupportedByServer` when the server's version is known.
     pub fn parallel(mut self, parallel: usize) -> Self {
         self.parallel = Some(parallel);
         self
     }
 
-    /// Run the `sync` command.
-    pub fn run(self) -> Result<Files, error::P4Error> {
-        let mut cmd = self.connection.connect_with_retries(None);
+    /// The -q flag suppresses normal output messages. Messages regarding
+    /// errors or exceptional conditions are not suppressed.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// The -r flag allows open files which are mapped to new locations
+    /// in the depot to be reopened accordingly.
+    pub fn reopen_moved(mut self, reopen_moved: bool) -> Self {
+        self.reopen_moved = reopen_moved;
+        self
+    }
+
+    /// The --use-stream-views flag syncs a stream client through its
+    /// stream's views.
+    pub fn use_stream_views(mut self, use_stream_views: bool) -> Self {
+        self.use_stream_views = use_stream_views;
+        self
+    }
+
+    settings_methods!();
+
+    /// Report progress indicators (`-I`) through `callback` while the command runs.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&progress::Progress) + Send + Sync + 'static,
+    {
+        self.settings.progress = Some(progress::Reporter::new(callback));
+        self
+    }
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    ///
+    /// With `batch_files`, the files are fed over stdin rather than being arguments.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        if self.batch_files {
+            cmd.args(&["-x", "-"]);
+        }
+        if self.settings.progress.is_some() {
+            cmd.arg("-I");
+        }
         cmd.arg("sync");
         if self.force {
             cmd.arg("-f");
@@ -179,6 +252,18 @@ impl<'p, 'f> SyncCommand<'p, 'f> {
         if self.verify {
             cmd.arg("-s");
         }
+        if self.quiet {
+            cmd.arg("-q");
+        }
+        if self.reopen_moved {
+            cmd.arg("-r");
+        }
+        if self.estimate {
+            cmd.arg("-N");
+        }
+        if self.use_stream_views {
+            cmd.arg("--use-stream-views");
+        }
         if let Some(max_files) = self.max_files {
             let max_files = format!("{}", max_files);
             cmd.args(&["-m", &max_files]);
@@ -187,63 +272,171 @@ impl<'p, 'f> SyncCommand<'p, 'f> {
             let parallel = format!("{}", parallel);
             cmd.args(&["--parallel", &parallel]);
         }
-        for file in self.file {
-            cmd.arg(file);
+        if !self.batch_files {
+            for file in &self.file {
+                cmd.arg(file.as_ref());
+            }
         }
-        let data = cmd.output().map_err(|e| {
-            error::ErrorKind::SpawnFailed
-                .error()
-                .set_cause(e)
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        let (_remains, (mut items, exit)) = files_parser::files(&data.stdout).map_err(|_| {
+        cmd
+    }
+
+    /// Run the `sync` command.
+    pub fn run(self) -> Result<Files, error::P4Error> {
+        Ok(Files(self.spawn(files_parser::item)?))
+    }
+
+    /// Run the `sync` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<File>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+
+    /// Run the `sync` command, collecting its output and the totals for the whole sync.
+    ///
+    /// Fails like `run_checked`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let (files, summary) = p4.sync("//depot/...").run_summary().unwrap();
+    /// if let Some(summary) = summary {
+    ///     println!("{} of {} files", files.len(), summary.total_file_count);
+    /// }
+    /// ```
+    pub fn run_summary(self) -> Result<(Vec<File>, Option<SyncSummary>), error::P4Error> {
+        let mut files = self.run()?.into_iter();
+        let collected = exec::collect(files.by_ref())?;
+        Ok((collected, files.summary))
+    }
+
+    /// Run the `sync` command with -N, estimating how much it would transfer without updating
+    /// the workspace.
+    ///
+    /// Fails with the reported messages if the command reports an error, or with
+    /// `ErrorKind::ParseFailed` if no estimate was reported.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// let p4 = p4_cmd::P4::new();
+    /// let estimate = p4.sync("//depot/...").run_estimate().unwrap();
+    /// println!("{} bytes to add", estimate.bytes_added);
+    /// ```
+    pub fn run_estimate(mut self) -> Result<SyncEstimate, error::P4Error> {
+        self.estimate = true;
+        let mut estimate = None;
+        let mut rest = Vec::new();
+        for item in self.spawn(files_parser::estimate_item)? {
+            match item {
+                error::Item::Data(e) if estimate.is_none() => estimate = Some(e),
+                // Without tagged output, the estimate is a message.
+                error::Item::Message(ref m) if estimate.is_none() => {
+                    estimate = SyncEstimate::from_message(m);
+                    if estimate.is_none() {
+                        rest.push(item);
+                    }
+                }
+                item => rest.push(item),
+            }
+        }
+        exec::collect(rest)?;
+        estimate.ok_or_else(|| {
             error::ErrorKind::ParseFailed
                 .error()
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        items.push(exit);
-        Ok(Files(items))
+                .set_context("No network estimate reported")
+        })
+    }
+
+    fn spawn<T>(self, parser: exec::ItemParser<T>) -> Result<exec::Items<T>, error::P4Error> {
+        if self.parallel.is_some() {
+            let since = info::ServerVersion::new(2014, 1);
+            self.connection
+                .require(&self.settings, "--parallel", since)?;
+        }
+        let cmd = self.to_command();
+        if self.batch_files {
+            let input = exec::arg_file(&self.file);
+            return exec::spawn_with_input(
+                self.connection,
+                self.settings,
+                cmd,
+                Some(input),
+                parser,
+            );
+        }
+        let files = self.file.len();
+        exec::spawn_files(self.connection, self.settings, cmd, files, parser)
     }
 }
 
 pub type FileItem = error::Item<File>;
 
-pub struct Files(Vec<FileItem>);
+pub struct Files(exec::Items<Record>);
 
 impl IntoIterator for Files {
     type Item = FileItem;
     type IntoIter = FilesIntoIter;
 
     fn into_iter(self) -> FilesIntoIter {
-        FilesIntoIter(self.0.into_iter())
+        FilesIntoIter {
+            records: self.0,
+            summary: None,
+        }
     }
 }
 
 #[derive(Debug)]
-pub struct FilesIntoIter(vec::IntoIter<FileItem>);
+pub struct FilesIntoIter {
+    records: exec::Items<Record>,
+    summary: Option<SyncSummary>,
+}
+
+impl FilesIntoIter {
+    /// Totals for the whole sync, reported along with the first file.
+    ///
+    /// When a long file list was split across several runs, this sums their totals so far.
+    pub fn summary(&self) -> Option<&SyncSummary> {
+        self.summary.as_ref()
+    }
+}
 
 impl Iterator for FilesIntoIter {
     type Item = FileItem;
 
     #[inline]
     fn next(&mut self) -> Option<FileItem> {
-        self.0.next()
+        let record = match self.records.next()? {
+            error::Item::Data(record) => record,
+            error::Item::Message(m) => return Some(error::Item::Message(m)),
+            error::Item::Error(e) => return Some(error::Item::Error(e)),
+            error::Item::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        if let Some(summary) = record.summary {
+            self.summary = Some(match self.summary.take() {
+                Some(total) => SyncSummary {
+                    total_file_size: total.total_file_size + summary.total_file_size,
+                    total_file_count: total.total_file_count + summary.total_file_count,
+                    ..summary
+                },
+                None => summary,
+            });
+        }
+        Some(error::Item::Data(record.file))
     }
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
-    }
-
-    #[inline]
-    fn count(self) -> usize {
-        self.0.count()
+        self.records.size_hint()
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FileContent {
     #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __Nonexhaustive,
 
     Text(Vec<String>),
@@ -267,12 +460,130 @@ impl FileContent {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct File {
-    pub depot_file: String,
-    pub client_file: path::PathBuf,
+    pub depot_file: paths::DepotPath,
+    pub client_file: paths::LocalPath,
     pub rev: usize,
     pub action: p4::Action,
     pub file_size: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// A file as reported, with the sync's totals when they were reported along with it.
+#[derive(Debug)]
+struct Record {
+    file: File,
+    summary: Option<SyncSummary>,
+}
+
+/// A message `sync` commonly reports about files it left alone.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let files = p4.sync("//depot/dir/...").run().unwrap();
+/// for item in files {
+///     let warning = item
+///         .as_message()
+///         .and_then(p4_cmd::sync::SyncWarning::from_message);
+///     if let Some(p4_cmd::sync::SyncWarning::CantClobber(path)) = warning {
+///         println!("{} is writable", path);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SyncWarning {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    /// The files given, e.g. `//depot/dir/...`, are already synced.
+    UpToDate(String),
+    /// No files match the files given.
+    NoSuchFiles(String),
+    /// A writable workspace file wasn't overwritten, for clients with `noclobber`.
+    CantClobber(String),
+    /// The opened file has to be resolved against the revision synced.
+    MustResolve(String),
+}
+
+impl SyncWarning {
+    /// Recognize `message`, `None` if it isn't one of these.
+    pub fn from_message(message: &error::Message) -> Option<Self> {
+        let msg = message.text().trim_end();
+        if let Some(path) = msg.strip_suffix(" - file(s) up-to-date.") {
+            Some(SyncWarning::UpToDate(path.to_owned()))
+        } else if let Some(path) = msg.strip_suffix(" - no such file(s).") {
+            Some(SyncWarning::NoSuchFiles(path.to_owned()))
+        } else if let Some(path) = msg.strip_prefix("Can't clobber writable file ") {
+            Some(SyncWarning::CantClobber(path.to_owned()))
+        } else {
+            msg.find(" - must resolve ")
+                .map(|i| SyncWarning::MustResolve(msg[..i].to_owned()))
+        }
+    }
+}
+
+/// What a sync would transfer, see `SyncCommand::run_estimate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SyncEstimate {
+    pub files_added: usize,
+    pub files_updated: usize,
+    pub files_deleted: usize,
+    pub bytes_added: u64,
+    pub bytes_updated: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl SyncEstimate {
+    /// Parse `Server network estimates: files added/updated/deleted=1/2/3, bytes
+    /// added/updated=4/5`.
+    fn from_message(message: &error::Message) -> Option<Self> {
+        fn counts<T: str::FromStr>(msg: &str, name: &str) -> Option<Vec<T>> {
+            let start = msg.find(name)? + name.len();
+            let counts = msg[start..].split([',', ' ']).next()?;
+            counts.split('/').map(|c| c.trim().parse().ok()).collect()
+        }
+
+        let msg = message.text();
+        if !msg.starts_with("Server network estimates:") {
+            return None;
+        }
+        let files = counts::<usize>(msg, "files added/updated/deleted=")?;
+        let bytes = counts::<u64>(msg, "bytes added/updated=")?;
+        match (files.as_slice(), bytes.as_slice()) {
+            (&[files_added, files_updated, files_deleted], &[bytes_added, bytes_updated]) => {
+                Some(Self {
+                    files_added,
+                    files_updated,
+                    files_deleted,
+                    bytes_added,
+                    bytes_updated,
+                    non_exhaustive: (),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Totals for a sync.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SyncSummary {
+    /// Bytes to be transferred.
+    pub total_file_size: usize,
+    /// Files to be updated.
+    pub total_file_count: usize,
+    /// The changelist the client is being synced to.
+    pub change: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
     non_exhaustive: (),
 }
 
@@ -281,28 +592,47 @@ mod files_parser {
 
     use super::super::parser::*;
 
-    named!(pub file<&[u8], File>,
+    named!(pub file<&[u8], Record>,
         do_parse!(
             depot_file: depot_file >>
             client_file: client_file >>
             rev: rev >>
             action: action >>
             file_size: file_size >>
-            _ignore: opt!(delimited!(ignore_info1, ignore_info1, change)) >>
+            summary: opt!(summary) >>
             (
-                File {
-                    depot_file: depot_file.path.to_owned(),
-                    client_file: path::PathBuf::from(client_file.path),
-                    rev: rev.rev,
-                    action: action.action.parse().expect("`Unknown` to capture all"),
-                    file_size: file_size.size,
+                Record {
+                    file: File {
+                        depot_file: paths::DepotPath::from_bytes(depot_file.path),
+                        client_file: paths::LocalPath::from_bytes(client_file.path),
+                        rev: rev.rev,
+                        action: action.action.parse().expect("`Unknown` to capture all"),
+                        file_size: file_size.size,
+                        non_exhaustive: (),
+                    },
+                    summary,
+                }
+            )
+        )
+    );
+
+    named!(summary<&[u8], SyncSummary>,
+        do_parse!(
+            total_file_size: map_res!(call!(field, "totalFileSize"), str::parse) >>
+            total_file_count: map_res!(call!(field, "totalFileCount"), str::parse) >>
+            change: change >>
+            (
+                SyncSummary {
+                    total_file_size,
+                    total_file_count,
+                    change: change.change,
                     non_exhaustive: (),
                 }
             )
         )
     );
 
-    named!(item<&[u8], FileItem>,
+    named!(pub item<&[u8], error::Item<Record>>,
         alt!(
             map!(file, data_to_item) |
             map!(error, error_to_item) |
@@ -310,10 +640,31 @@ mod files_parser {
         )
     );
 
-    named!(pub files<&[u8], (Vec<FileItem>, FileItem)>,
-        pair!(
-            many0!(item),
-            map!(exit, exit_to_item)
+    named!(estimate<&[u8], SyncEstimate>,
+        do_parse!(
+            files_added: map_res!(call!(field, "fileAdds"), str::parse) >>
+            files_updated: map_res!(call!(field, "fileUpdates"), str::parse) >>
+            files_deleted: map_res!(call!(field, "fileDeletes"), str::parse) >>
+            bytes_added: map_res!(call!(field, "bytesAdded"), str::parse) >>
+            bytes_updated: map_res!(call!(field, "bytesUpdated"), str::parse) >>
+            (
+                SyncEstimate {
+                    files_added,
+                    files_updated,
+                    files_deleted,
+                    bytes_added,
+                    bytes_updated,
+                    non_exhaustive: (),
+                }
+            )
+        )
+    );
+
+    named!(pub estimate_item<&[u8], error::Item<SyncEstimate>>,
+        alt!(
+            map!(estimate, data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
         )
     );
 }
@@ -322,6 +673,13 @@ mod files_parser {
 mod test {
     use super::*;
 
+    #[test]
+    fn sync_parallel_needs_2014_1() {
+        let p4 = p4::P4::new().set_server_version(Some(info::ServerVersion::new(2013, 3)));
+        let e = p4.sync("//depot/...").parallel(4).run().err().unwrap();
+        assert_eq!(e.kind(), error::ErrorKind::UnsupportedByServer);
+    }
+
     #[test]
     fn sync_single() {
         let output: &[u8] = br#"info1: depotFile //depot/dir/file
@@ -334,9 +692,10 @@ info1: totalFileCount 24
 info1: change 25662947
 exit: 0
 "#;
-        let (_remains, (items, exit)) = files_parser::files(output).unwrap();
+        let (items, exit) = exec::parse(output, files_parser::item);
         let first = items[0].as_data().unwrap();
-        assert_eq!(first.depot_file, "//depot/dir/file");
+        assert_eq!(first.file.depot_file, "//depot/dir/file");
+        assert_eq!(first.summary.as_ref().unwrap().total_file_count, 24);
         assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
     }
 
@@ -357,11 +716,132 @@ info1: action added
 info1: fileSize 729154
 exit: 0
 "#;
-        let (_remains, (items, exit)) = files_parser::files(output).unwrap();
+        let (items, exit) = exec::parse(output, files_parser::item);
         let first = items[0].as_data().unwrap();
         let last = items[1].as_data().unwrap();
-        assert_eq!(first.depot_file, "//depot/dir/file");
-        assert_eq!(last.depot_file, "//depot/dir/file1");
+        assert_eq!(first.file.depot_file, "//depot/dir/file");
+        assert_eq!(last.file.depot_file, "//depot/dir/file1");
+        assert!(last.summary.is_none());
         assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
     }
+
+    #[test]
+    fn sync_warnings() {
+        let output: &[u8] = b"warning: //depot/a/... - file(s) up-to-date.
+warning: //depot/b - no such file(s).
+error: Can't clobber writable file /home/user/ws/c
+info: //ws/d - must resolve #4 before submitting
+info: //depot/e#2 - updating /home/user/ws/e
+exit: 1
+";
+        let (items, _) = exec::parse(output, files_parser::item);
+        let warnings: Vec<_> = items
+            .iter()
+            .map(|i| SyncWarning::from_message(i.as_message().unwrap()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                Some(SyncWarning::UpToDate("//depot/a/...".to_owned())),
+                Some(SyncWarning::NoSuchFiles("//depot/b".to_owned())),
+                Some(SyncWarning::CantClobber("/home/user/ws/c".to_owned())),
+                Some(SyncWarning::MustResolve("//ws/d".to_owned())),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_estimate() {
+        let runner = ::std::sync::Arc::new(exec::Recorder {
+            output: b"info: Server network estimates: files added/updated/deleted=3/2/1, \
+bytes added/updated=12345/678\nexit: 0\n"
+                .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner.clone()));
+        let estimate = p4
+            .sync("//depot/...")
+            .quiet(true)
+            .reopen_moved(true)
+            .use_stream_views(true)
+            .run_estimate()
+            .unwrap();
+        assert_eq!(
+            (
+                estimate.files_added,
+                estimate.files_updated,
+                estimate.files_deleted
+            ),
+            (3, 2, 1)
+        );
+        assert_eq!((estimate.bytes_added, estimate.bytes_updated), (12345, 678));
+
+        let calls = runner.calls.lock().unwrap();
+        let (ref args, _) = calls[0];
+        let sync = args.iter().position(|a| a == "sync").unwrap();
+        assert_eq!(
+            &args[sync..],
+            &[
+                "sync",
+                "-q",
+                "-r",
+                "-N",
+                "--use-stream-views",
+                "//depot/..."
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_estimate_tagged() {
+        let runner = ::std::sync::Arc::new(exec::Recorder {
+            output: b"info1: fileAdds 3\ninfo1: fileUpdates 2\ninfo1: fileDeletes 1\n\
+info1: bytesAdded 12345\ninfo1: bytesUpdated 678\nexit: 0\n"
+                .to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let estimate = p4.sync("//depot/...").run_estimate().unwrap();
+        assert_eq!(
+            (
+                estimate.files_added,
+                estimate.files_updated,
+                estimate.files_deleted
+            ),
+            (3, 2, 1)
+        );
+        assert_eq!((estimate.bytes_added, estimate.bytes_updated), (12345, 678));
+    }
+
+    #[test]
+    fn sync_summary() {
+        let output = br#"info1: depotFile //depot/dir/file
+info1: clientFile /home/user/depot/dir/file
+info1: rev 1
+info1: action added
+info1: fileSize 1016
+info1: totalFileSize 865153
+info1: totalFileCount 24
+info1: change 25662947
+exit: 0
+"#;
+        let runner = ::std::sync::Arc::new(exec::Recorder {
+            output: output.to_vec(),
+            ..Default::default()
+        });
+        let p4 = p4::P4::new().set_runner(Some(runner));
+        let mut files = p4.sync("//depot/dir/...").run().unwrap().into_iter();
+        assert_eq!(files.summary(), None);
+        let first = files.next().unwrap();
+        assert_eq!(first.as_data().unwrap().file_size, 1016);
+        let summary = files.summary().unwrap();
+        assert_eq!(summary.total_file_size, 865153);
+        assert_eq!(summary.total_file_count, 24);
+        assert_eq!(summary.change, 25662947);
+
+        let (files, summary) = p4.sync("//depot/dir/...").run_summary().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(summary.unwrap().total_file_count, 24);
+    }
 }
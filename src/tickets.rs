@@ -0,0 +1,193 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Display list of session tickets for this user
+///
+/// Lists all the tickets stored in the user's ticket file, so callers can
+/// detect which servers they are already authenticated to.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let tickets = p4.tickets().run().unwrap();
+/// for ticket in tickets {
+///     println!("{:?}", ticket);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TicketsCommand<'p> {
+    connection: &'p p4::P4,
+    settings: exec::Settings,
+}
+
+impl<'p> TicketsCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("tickets");
+        cmd
+    }
+
+    /// Run the `tickets` command.
+    pub fn run(self) -> Result<Tickets, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Tickets(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            tickets_parser::item,
+        )?))
+    }
+
+    /// Run the `tickets` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Ticket>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type TicketItem = error::Item<Ticket>;
+
+pub struct Tickets(exec::Items<Ticket>);
+
+impl IntoIterator for Tickets {
+    type Item = TicketItem;
+    type IntoIter = TicketsIntoIter;
+
+    fn into_iter(self) -> TicketsIntoIter {
+        TicketsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct TicketsIntoIter(exec::Items<Ticket>);
+
+impl Iterator for TicketsIntoIter {
+    type Item = TicketItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<TicketItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A stored session ticket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ticket {
+    /// The server address (or server ID) the ticket is for.
+    pub host: String,
+    pub user: String,
+    pub ticket: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+mod tickets_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    named!(ticket<&[u8], Ticket>,
+        do_parse!(
+            host: call!(field, "Host") >>
+            user: call!(field, "User") >>
+            ticket: call!(field, "Ticket") >>
+            (
+                Ticket {
+                    host: host.to_owned(),
+                    user: user.to_owned(),
+                    ticket: ticket.to_owned(),
+                    non_exhaustive: (),
+                }
+            )
+        )
+    );
+
+    /// Untagged output, e.g. `info: localhost:1666 (alice) 2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C`
+    fn to_ticket(i: Info) -> Result<Ticket, ()> {
+        let mut parts = i.msg.split(' ');
+        let host = parts.next().ok_or(())?;
+        let user = parts.next().ok_or(())?;
+        let ticket = parts.next().ok_or(())?;
+        if parts.next().is_some() || !user.starts_with('(') || !user.ends_with(')') {
+            return Err(());
+        }
+        let ticket = Ticket {
+            host: host.to_owned(),
+            user: user[1..user.len() - 1].to_owned(),
+            ticket: ticket.to_owned(),
+            non_exhaustive: (),
+        };
+        Ok(ticket)
+    }
+
+    named!(pub item<&[u8], TicketItem>,
+        alt!(
+            map!(ticket, data_to_item) |
+            map!(map_res!(info, to_ticket), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tickets_tagged() {
+        let output: &[u8] = br#"info1: Host localhost:1666
+info1: User alice
+info1: Ticket 2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C
+info1: Host ssl:perforce:1666
+info1: User bob
+info1: Ticket 8C1F5D4CB2B5EBFBBBB55BD81BDDBA2A
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, tickets_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.host, "localhost:1666");
+        assert_eq!(first.user, "alice");
+        assert_eq!(last.host, "ssl:perforce:1666");
+        assert_eq!(last.ticket, "8C1F5D4CB2B5EBFBBBB55BD81BDDBA2A");
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn tickets_untagged() {
+        let output: &[u8] = br#"info: localhost:1666 (alice) 2A1F5D4CB2B5EBFBBBB55BD81BDDBA8C
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, tickets_parser::item);
+        let first = items[0].as_data().unwrap();
+        assert_eq!(first.host, "localhost:1666");
+        assert_eq!(first.user, "alice");
+    }
+}
@@ -0,0 +1,138 @@
+//! Instrument each command with a `tracing` span, with the `tracing` feature.
+//!
+//! The span is named `p4` and records the command name, how many files it was given and the
+//! server address.  Events mark when `p4` is spawned, when its first output arrives, and when it
+//! completes.
+
+use std::process;
+
+use error;
+
+/// Global options taking a value, which comes in the next argument.
+const VALUE_OPTIONS: &[&str] = &[
+    "-c", "-C", "-d", "-H", "-L", "-p", "-P", "-Q", "-r", "-u", "-v", "-x", "-Z",
+];
+
+/// The command name and how many files it was given, from `p4`'s arguments.
+///
+/// Files are counted as the command's non-option arguments plus any lines fed to `-x -`.
+pub(crate) fn describe(cmd: &process::Command, input: Option<&[u8]>) -> (String, usize) {
+    let mut args = cmd.get_args().map(|a| a.to_string_lossy());
+    let mut command = String::new();
+    while let Some(arg) = args.next() {
+        if VALUE_OPTIONS.contains(&arg.as_ref()) {
+            args.next();
+        } else if !arg.starts_with('-') {
+            command = arg.into_owned();
+            break;
+        }
+    }
+    let listed = input.map_or(0, |input| {
+        input
+            .split(|&b| b == b'\n')
+            .filter(|l| !l.is_empty())
+            .count()
+    });
+    (
+        command,
+        args.filter(|a| !a.starts_with('-')).count() + listed,
+    )
+}
+
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+pub(crate) struct Trace {
+    span: tracing::Span,
+    received: bool,
+}
+
+#[cfg(feature = "tracing")]
+impl Trace {
+    pub(crate) fn new(command: &str, files: usize, server: Option<&str>) -> Self {
+        let span = tracing::info_span!(
+            "p4",
+            command,
+            files = files as u64,
+            server = server.unwrap_or("")
+        );
+        Self {
+            span,
+            received: false,
+        }
+    }
+
+    /// For commands run on behalf of another, like logging in again.
+    pub(crate) fn none() -> Self {
+        Self {
+            span: tracing::Span::none(),
+            received: false,
+        }
+    }
+
+    pub(crate) fn spawned(&mut self, attempt: usize) {
+        self.received = false;
+        tracing::debug!(parent: &self.span, attempt = attempt as u64, "spawned p4");
+    }
+
+    pub(crate) fn received(&mut self) {
+        if !self.received {
+            self.received = true;
+            tracing::debug!(parent: &self.span, "received first output");
+        }
+    }
+
+    pub(crate) fn completed(&self, error: &error::OperationError) {
+        tracing::debug!(
+            parent: &self.span,
+            code = error.code(),
+            kind = ?error.kind(),
+            "completed"
+        );
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug)]
+pub(crate) struct Trace;
+
+#[cfg(not(feature = "tracing"))]
+impl Trace {
+    pub(crate) fn new(_command: &str, _files: usize, _server: Option<&str>) -> Self {
+        Trace
+    }
+
+    pub(crate) fn none() -> Self {
+        Trace
+    }
+
+    pub(crate) fn spawned(&mut self, _attempt: usize) {}
+
+    pub(crate) fn received(&mut self) {}
+
+    pub(crate) fn completed(&self, _error: &error::OperationError) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn describe_command() {
+        let mut cmd = process::Command::new("p4");
+        cmd.args(&[
+            "-Gs",
+            "-C",
+            "utf8",
+            "-p",
+            "perforce:1666",
+            "-x",
+            "-",
+            "files",
+            "-a",
+        ]);
+        cmd.args(&["//depot/a/...", "//depot/b/..."]);
+        let (command, files) = describe(&cmd, Some(b"//depot/c\n//depot/d\n"));
+        assert_eq!(command, "files");
+        assert_eq!(files, 4);
+    }
+}
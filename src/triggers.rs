@@ -0,0 +1,382 @@
+//! The triggers table, as edited with `p4 triggers`.
+//!
+//! [`TriggersCommand`](struct.TriggersCommand.html) reads the table's lines as
+//! [`TriggerEntry`](struct.TriggerEntry.html)s, which also parse and write lines on their own,
+//! e.g. to check a table before it's saved.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use p4_cmd::triggers::{TriggerEntry, TriggerType};
+//!
+//! let entry: TriggerEntry = "check change-submit //depot/... \"/usr/bin/check %change%\""
+//!     .parse()
+//!     .unwrap();
+//! assert_eq!(entry.trigger_type, TriggerType::ChangeSubmit);
+//! assert_eq!(entry.path, "//depot/...");
+//! assert_eq!(entry.command, "/usr/bin/check %change%");
+//! assert_eq!(
+//!     entry.to_string(),
+//!     "check change-submit //depot/... \"/usr/bin/check %change%\""
+//! );
+//! ```
+
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+use view;
+
+/// Write the triggers table
+///
+/// Writes the lines of the triggers table, which is edited with
+/// 'p4 triggers -i' ('p4 triggers -o').  This command requires super
+/// access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let triggers = p4.triggers().run().unwrap();
+/// for trigger in triggers {
+///     println!("{:?}", trigger);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TriggersCommand<'p> {
+    connection: &'p p4::P4,
+    settings: exec::Settings,
+}
+
+impl<'p> TriggersCommand<'p> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&["triggers", "-o"]);
+        cmd
+    }
+
+    /// Run the `triggers -o` command.
+    pub fn run(self) -> Result<Triggers, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Triggers(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            triggers_parser::item,
+        )?))
+    }
+
+    /// Run the `triggers -o` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<TriggerEntry>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type TriggerItem = error::Item<TriggerEntry>;
+
+pub struct Triggers(exec::Items<TriggerEntry>);
+
+impl IntoIterator for Triggers {
+    type Item = TriggerItem;
+    type IntoIter = TriggersIntoIter;
+
+    fn into_iter(self) -> TriggersIntoIter {
+        TriggersIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct TriggersIntoIter(exec::Items<TriggerEntry>);
+
+impl Iterator for TriggersIntoIter {
+    type Item = TriggerItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<TriggerItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// When a trigger runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TriggerType {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    /// `change-submit`, before a changelist's files are transferred.
+    ChangeSubmit,
+    /// `change-content`, after a changelist's files are transferred.
+    ChangeContent,
+    /// `change-commit`, after a changelist is submitted.
+    ChangeCommit,
+    /// `change-failed`, after a submit fails.
+    ChangeFailed,
+    /// `shelve-submit`, before files are shelved.
+    ShelveSubmit,
+    /// `shelve-commit`, after files are shelved.
+    ShelveCommit,
+    /// `shelve-delete`, before shelved files are discarded.
+    ShelveDelete,
+    /// `fix-add`, before a fix is added.
+    FixAdd,
+    /// `fix-delete`, before a fix is removed.
+    FixDelete,
+    /// `form-in`, when a spec form is read by the server.
+    FormIn,
+    /// `form-out`, when a spec form is generated.
+    FormOut,
+    /// `form-save`, before a spec form is saved.
+    FormSave,
+    /// `form-commit`, after a spec form is saved.
+    FormCommit,
+    /// `form-delete`, before a spec form is deleted.
+    FormDelete,
+    /// `auth-check`, to check a password.
+    AuthCheck,
+    /// `auth-check-sso`, to check single sign-on credentials.
+    AuthCheckSso,
+    /// `auth-set`, to change a password.
+    AuthSet,
+    /// `service-check`, to check a service user's password.
+    ServiceCheck,
+    /// `archive`, to read and write files of `+X` types.
+    Archive,
+    /// `command`, before or after a command.
+    Command,
+
+    Unknown(String),
+}
+
+impl str::FromStr for TriggerType {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trigger_type = match s {
+            "change-submit" => TriggerType::ChangeSubmit,
+            "change-content" => TriggerType::ChangeContent,
+            "change-commit" => TriggerType::ChangeCommit,
+            "change-failed" => TriggerType::ChangeFailed,
+            "shelve-submit" => TriggerType::ShelveSubmit,
+            "shelve-commit" => TriggerType::ShelveCommit,
+            "shelve-delete" => TriggerType::ShelveDelete,
+            "fix-add" => TriggerType::FixAdd,
+            "fix-delete" => TriggerType::FixDelete,
+            "form-in" => TriggerType::FormIn,
+            "form-out" => TriggerType::FormOut,
+            "form-save" => TriggerType::FormSave,
+            "form-commit" => TriggerType::FormCommit,
+            "form-delete" => TriggerType::FormDelete,
+            "auth-check" => TriggerType::AuthCheck,
+            "auth-check-sso" => TriggerType::AuthCheckSso,
+            "auth-set" => TriggerType::AuthSet,
+            "service-check" => TriggerType::ServiceCheck,
+            "archive" => TriggerType::Archive,
+            "command" => TriggerType::Command,
+            "" => return Err(fmt::Error),
+            s => TriggerType::Unknown(s.to_owned()),
+        };
+        Ok(trigger_type)
+    }
+}
+
+impl fmt::Display for TriggerType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            TriggerType::ChangeSubmit => "change-submit",
+            TriggerType::ChangeContent => "change-content",
+            TriggerType::ChangeCommit => "change-commit",
+            TriggerType::ChangeFailed => "change-failed",
+            TriggerType::ShelveSubmit => "shelve-submit",
+            TriggerType::ShelveCommit => "shelve-commit",
+            TriggerType::ShelveDelete => "shelve-delete",
+            TriggerType::FixAdd => "fix-add",
+            TriggerType::FixDelete => "fix-delete",
+            TriggerType::FormIn => "form-in",
+            TriggerType::FormOut => "form-out",
+            TriggerType::FormSave => "form-save",
+            TriggerType::FormCommit => "form-commit",
+            TriggerType::FormDelete => "form-delete",
+            TriggerType::AuthCheck => "auth-check",
+            TriggerType::AuthCheckSso => "auth-check-sso",
+            TriggerType::AuthSet => "auth-set",
+            TriggerType::ServiceCheck => "service-check",
+            TriggerType::Archive => "archive",
+            TriggerType::Command => "command",
+            TriggerType::Unknown(ref s) => s.as_str(),
+            TriggerType::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// A line of the triggers table, e.g. `check change-submit //depot/... "/usr/bin/check %change%"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TriggerEntry {
+    pub name: String,
+    pub trigger_type: TriggerType,
+    /// The depot path the trigger applies to, or for `form-*` triggers the form type and for
+    /// `command` triggers the commands, e.g. `pre-user-submit`.
+    pub path: String,
+    /// The command to run, with `%variables%` unexpanded.
+    pub command: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl TriggerEntry {
+    pub fn new(name: String, trigger_type: TriggerType, path: String, command: String) -> Self {
+        Self {
+            name,
+            trigger_type,
+            path,
+            command,
+            non_exhaustive: (),
+        }
+    }
+}
+
+impl str::FromStr for TriggerEntry {
+    type Err = fmt::Error;
+
+    /// Parse a table line.  The path is quoted if it has spaces, and the command if it has
+    /// arguments; quotes inside the command are kept.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, rest) = view::next_word(s).ok_or(fmt::Error)?;
+        let (trigger_type, rest) = view::next_word(rest).ok_or(fmt::Error)?;
+        let (path, rest) = view::next_path(rest).ok_or(fmt::Error)?;
+        let rest = rest.trim();
+        let command = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.strip_suffix('"').ok_or(fmt::Error)?,
+            None => rest,
+        };
+        if command.is_empty() {
+            return Err(fmt::Error);
+        }
+        Ok(TriggerEntry::new(
+            name.to_owned(),
+            trigger_type.parse()?,
+            path,
+            command.to_owned(),
+        ))
+    }
+}
+
+impl fmt::Display for TriggerEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let q = view::quote(&self.path);
+        write!(
+            f,
+            "{} {} {q}{}{q} ",
+            self.name,
+            self.trigger_type,
+            self.path,
+            q = q
+        )?;
+        let q = if self.command.contains(char::is_whitespace) || self.command.starts_with('"') {
+            "\""
+        } else {
+            ""
+        };
+        write!(f, "{q}{}{q}", self.command, q = q)
+    }
+}
+
+mod triggers_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// A `Triggers<n>` field, one per line of the table.
+    fn to_entry(field: Field) -> Result<TriggerEntry, fmt::Error> {
+        let index = field.key.strip_prefix("Triggers").ok_or(fmt::Error)?;
+        if index.is_empty() || !index.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(fmt::Error);
+        }
+        field.value.parse()
+    }
+
+    named!(pub item<&[u8], TriggerItem>,
+        alt!(
+            map!(map_res!(any_field, to_entry), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn triggers_tagged() {
+        let output: &[u8] =
+            br#"info1: Triggers0 check change-submit //depot/... "/usr/bin/check %change%"
+info1: Triggers1 jobs form-save job /p4/jobs.py
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, triggers_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.name, "check");
+        assert_eq!(first.trigger_type, TriggerType::ChangeSubmit);
+        assert_eq!(first.command, "/usr/bin/check %change%");
+        assert_eq!(last.trigger_type, TriggerType::FormSave);
+        assert_eq!(last.path, "job");
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn trigger_entry_round_trip() {
+        let line = "jobs form-save job \"python3 /p4/jobs.py \"%formfile%\"\"";
+        let entry: TriggerEntry = line.parse().unwrap();
+        assert_eq!(entry.trigger_type, TriggerType::FormSave);
+        assert_eq!(entry.path, "job");
+        assert_eq!(entry.command, "python3 /p4/jobs.py \"%formfile%\"");
+        assert_eq!(entry.to_string(), line);
+
+        let line = "audit custom-type \"//depot/a b/...\" /p4/audit";
+        let entry: TriggerEntry = line.parse().unwrap();
+        assert_eq!(
+            entry.trigger_type,
+            TriggerType::Unknown("custom-type".to_owned())
+        );
+        assert_eq!(entry.path, "//depot/a b/...");
+        assert_eq!(entry.to_string(), line);
+
+        assert!("check change-submit //depot/..."
+            .parse::<TriggerEntry>()
+            .is_err());
+        assert!("check change-submit //depot/... \"/bin/x"
+            .parse::<TriggerEntry>()
+            .is_err());
+    }
+}
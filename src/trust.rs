@@ -0,0 +1,273 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+
+/// Establish trust of an SSL connection
+///
+/// Establish trust of an SSL connection.  This client command manages the
+/// p4 trust file.  This file contains fingerprints of the keys received on
+/// ssl connections.  When an SSL connection is made, this file is examined
+/// to determine if the SSL connection has been used before and if the key
+/// is the same as a previously seen key for that connection.  Establishing
+/// trust with a connection prevents undetected communication interception
+/// (man-in-the-middle) attacks.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let fingerprints = p4.trust().list(true).run().unwrap();
+/// for fingerprint in fingerprints {
+///     println!("{:?}", fingerprint);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrustCommand<'p, 'f> {
+    connection: &'p p4::P4,
+
+    list: bool,
+    accept: bool,
+    refuse: bool,
+    delete: bool,
+    force: bool,
+    replacement: bool,
+    fingerprint: Option<&'f str>,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> TrustCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            list: false,
+            accept: false,
+            refuse: false,
+            delete: false,
+            force: false,
+            replacement: false,
+            fingerprint: None,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -l flag lists existing known fingerprints.
+    pub fn list(mut self, list: bool) -> Self {
+        self.list = list;
+        self
+    }
+
+    /// The -y flag will cause prompts to be automatically accepted.
+    pub fn accept(mut self, accept: bool) -> Self {
+        self.accept = accept;
+        self
+    }
+
+    /// The -n flag will cause prompts to be automatically refused.
+    pub fn refuse(mut self, refuse: bool) -> Self {
+        self.refuse = refuse;
+        self
+    }
+
+    /// The -d flag will remove an existing trusted fingerprint of a
+    /// connection.
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// The -f flag will force the replacement of a mismatched fingerprint.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// The -r flag specifies that a replacement fingerprint is to be
+    /// affected.  Replacement fingerprints can be used in anticipation
+    /// of a server replacing its key.
+    pub fn replacement(mut self, replacement: bool) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// The -i flag will install the specified fingerprint.
+    pub fn fingerprint(mut self, fingerprint: &'f str) -> Self {
+        self.fingerprint = Some(fingerprint);
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("trust");
+        if self.list {
+            cmd.arg("-l");
+        }
+        if self.accept {
+            cmd.arg("-y");
+        }
+        if self.refuse {
+            cmd.arg("-n");
+        }
+        if self.delete {
+            cmd.arg("-d");
+        }
+        if self.force {
+            cmd.arg("-f");
+        }
+        if self.replacement {
+            cmd.arg("-r");
+        }
+        if let Some(fingerprint) = self.fingerprint {
+            cmd.args(&["-i", fingerprint]);
+        }
+        cmd
+    }
+
+    /// Run the `trust` command.
+    pub fn run(self) -> Result<Fingerprints, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Fingerprints(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            trust_parser::item,
+        )?))
+    }
+
+    /// Run the `trust` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Fingerprint>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type FingerprintItem = error::Item<Fingerprint>;
+
+pub struct Fingerprints(exec::Items<Fingerprint>);
+
+impl IntoIterator for Fingerprints {
+    type Item = FingerprintItem;
+    type IntoIter = FingerprintsIntoIter;
+
+    fn into_iter(self) -> FingerprintsIntoIter {
+        FingerprintsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct FingerprintsIntoIter(exec::Items<Fingerprint>);
+
+impl Iterator for FingerprintsIntoIter {
+    type Item = FingerprintItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<FingerprintItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A server key fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fingerprint {
+    /// The address the fingerprint is trusted for, when listed.
+    pub address: Option<String>,
+    /// Colon-separated hex digest of the server's key.
+    pub fingerprint: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+fn is_fingerprint(s: &str) -> bool {
+    !s.is_empty()
+        && s.split(':')
+            .all(|b| b.len() == 2 && b.bytes().all(|c| c.is_ascii_hexdigit()))
+}
+
+mod trust_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    /// e.g. `info: 10.0.0.1:1666 AB:CD:...` when listing or
+    /// `info: That fingerprint is AB:CD:...` when prompting.
+    fn to_fingerprint(i: Info) -> Result<Fingerprint, ()> {
+        let msg = i.msg.trim();
+        let mut parts = msg.rsplitn(2, ' ');
+        let fingerprint = parts.next().ok_or(())?;
+        if !is_fingerprint(fingerprint) {
+            return Err(());
+        }
+        let address = parts.next().map(str::trim).filter(|a| !a.contains(' '));
+        let fingerprint = Fingerprint {
+            address: address.map(str::to_owned),
+            fingerprint: fingerprint.to_owned(),
+            non_exhaustive: (),
+        };
+        Ok(fingerprint)
+    }
+
+    named!(pub item<&[u8], FingerprintItem>,
+        alt!(
+            map!(map_res!(info, to_fingerprint), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trust_list() {
+        let output: &[u8] =
+            br#"info: 10.0.0.1:1666 A8:5D:89:7C:1F:5D:4C:B2:B5:EB:FB:BB:B5:5B:D8:1B:DD:BA:8C:2A
+info: 10.0.0.2:1666 B8:5D:89:7C:1F:5D:4C:B2:B5:EB:FB:BB:B5:5B:D8:1B:DD:BA:8C:2A
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, trust_parser::item);
+        let first = items[0].as_data().unwrap();
+        assert_eq!(first.address, Some("10.0.0.1:1666".to_owned()));
+        assert_eq!(
+            first.fingerprint,
+            "A8:5D:89:7C:1F:5D:4C:B2:B5:EB:FB:BB:B5:5B:D8:1B:DD:BA:8C:2A"
+        );
+        assert!(items[1].as_data().is_some());
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+
+    #[test]
+    fn trust_accept() {
+        let output: &[u8] = br#"info: The fingerprint of the server of your P4PORT setting
+info: 'ssl:perforce:1666' (10.0.0.1:1666) is not known.
+info: That fingerprint is A8:5D:89:7C:1F:5D:4C:B2:B5:EB:FB:BB:B5:5B:D8:1B:DD:BA:8C:2A
+info: Added trust for P4PORT 'ssl:perforce:1666' (10.0.0.1:1666)
+exit: 0
+"#;
+        let (items, _exit) = exec::parse(output, trust_parser::item);
+        let fingerprint = items[2].as_data().unwrap();
+        assert_eq!(fingerprint.address, None);
+        assert_eq!(
+            fingerprint.fingerprint,
+            "A8:5D:89:7C:1F:5D:4C:B2:B5:EB:FB:BB:B5:5B:D8:1B:DD:BA:8C:2A"
+        );
+        assert!(items[3].as_message().is_some());
+    }
+}
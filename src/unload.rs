@@ -0,0 +1,297 @@
+use std::fmt;
+use std::process;
+use std::str;
+
+use error;
+use exec;
+use p4;
+
+/// Unload a client, label, or task stream to the unload depot
+///
+/// Unload transfers infrequently-used metadata from the versioning engine
+/// tables to an unload depot.  The metadata can later be restored with
+/// 'p4 reload'.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let unloaded = p4.unload().client("alice_ws").run().unwrap();
+/// for unload in unloaded {
+///     println!("{:?}", unload);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnloadCommand<'p, 's> {
+    connection: &'p p4::P4,
+
+    client: Option<&'s str>,
+    label: Option<&'s str>,
+    all: bool,
+    date: Option<&'s str>,
+    user: Option<&'s str>,
+    force: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 's> UnloadCommand<'p, 's> {
+    pub fn new(connection: &'p p4::P4) -> Self {
+        Self {
+            connection,
+            client: None,
+            label: None,
+            all: false,
+            date: None,
+            user: None,
+            force: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -c flag unloads the specified client.
+    pub fn client(mut self, client: &'s str) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// The -l flag unloads the specified label.
+    pub fn label(mut self, label: &'s str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// The -a flag unloads all clients and labels that have not been
+    /// accessed since `date`.  Combine with `client` or `label` to
+    /// restrict it to clients (-ac) or labels (-al).
+    pub fn all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
+    }
+
+    /// The -d date flag restricts `all` to clients and labels older than
+    /// the date.
+    pub fn date(mut self, date: &'s str) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// The -u user flag restricts `all` to clients and labels owned by the
+    /// user.
+    pub fn user(mut self, user: &'s str) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// The -f flag allows an administrator to unload clients or labels
+    /// owned by other users.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.arg("unload");
+        if self.force {
+            cmd.arg("-f");
+        }
+        if self.all {
+            match (self.client.is_some(), self.label.is_some()) {
+                (true, false) => cmd.arg("-ac"),
+                (false, true) => cmd.arg("-al"),
+                _ => cmd.arg("-a"),
+            };
+            if let Some(date) = self.date {
+                cmd.args(&["-d", date]);
+            }
+            if let Some(user) = self.user {
+                cmd.args(&["-u", user]);
+            }
+        } else {
+            if let Some(client) = self.client {
+                cmd.args(&["-c", client]);
+            }
+            if let Some(label) = self.label {
+                cmd.args(&["-l", label]);
+            }
+        }
+        cmd
+    }
+
+    /// Run the `unload` command.
+    pub fn run(self) -> Result<Unloads, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Unloads(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            unload_parser::item,
+        )?))
+    }
+
+    /// Run the `unload` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Unload>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type UnloadItem = error::Item<Unload>;
+
+pub struct Unloads(exec::Items<Unload>);
+
+impl IntoIterator for Unloads {
+    type Item = UnloadItem;
+    type IntoIter = UnloadsIntoIter;
+
+    fn into_iter(self) -> UnloadsIntoIter {
+        UnloadsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnloadsIntoIter(exec::Items<Unload>);
+
+impl Iterator for UnloadsIntoIter {
+    type Item = UnloadItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<UnloadItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A spec that was unloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Unload {
+    pub spec_type: SpecType,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+/// The kind of spec moved to or from the unload depot.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(p4_cmd::unload::SpecType::Client.to_string(), "Client");
+/// assert_eq!(
+///     "Label".parse::<p4_cmd::unload::SpecType>().unwrap(),
+///     p4_cmd::unload::SpecType::Label
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SpecType {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Client,
+    Label,
+    Stream,
+
+    Unknown(String),
+}
+
+impl str::FromStr for SpecType {
+    type Err = fmt::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec_type = match s {
+            "Client" => SpecType::Client,
+            "Label" => SpecType::Label,
+            "Stream" => SpecType::Stream,
+            s => SpecType::Unknown(s.to_owned()),
+        };
+        Ok(spec_type)
+    }
+}
+
+impl fmt::Display for SpecType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            SpecType::Client => "Client",
+            SpecType::Label => "Label",
+            SpecType::Stream => "Stream",
+            SpecType::Unknown(ref s) => s.as_str(),
+            SpecType::__Nonexhaustive => unreachable!("This is a private variant"),
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// e.g. `Client alice_ws unloaded.`
+pub(crate) fn parse_spec_message(msg: &str, suffix: &str) -> Option<(SpecType, String)> {
+    let msg = msg.trim_end();
+    if !msg.ends_with(suffix) {
+        return None;
+    }
+    let (spec_type, name) = msg[0..msg.len() - suffix.len()].split_once(' ')?;
+    if name.is_empty() || name.contains(' ') {
+        return None;
+    }
+    let spec_type = spec_type.parse().ok()?;
+    Some((spec_type, name.to_owned()))
+}
+
+mod unload_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_unload(i: Info) -> Result<Unload, ()> {
+        let (spec_type, name) = parse_spec_message(i.msg, " unloaded.").ok_or(())?;
+        let unload = Unload {
+            spec_type,
+            name,
+            non_exhaustive: (),
+        };
+        Ok(unload)
+    }
+
+    named!(pub item<&[u8], UnloadItem>,
+        alt!(
+            map!(map_res!(info, to_unload), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unload_all() {
+        let output: &[u8] = br#"info: Client alice_ws unloaded.
+info: Label rel-1.0 unloaded.
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, unload_parser::item);
+        let first = items[0].as_data().unwrap();
+        let last = items[1].as_data().unwrap();
+        assert_eq!(first.spec_type, SpecType::Client);
+        assert_eq!(first.name, "alice_ws");
+        assert_eq!(last.spec_type, SpecType::Label);
+        assert_eq!(last.name, "rel-1.0");
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
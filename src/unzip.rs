@@ -0,0 +1,130 @@
+use std::process;
+
+use error;
+use exec;
+use p4;
+use zip;
+
+/// Import files from a zip file created by p4 zip
+///
+/// Imports the revisions, changelists, and (optionally) archive content
+/// packaged by 'p4 zip' on another server.
+///
+/// Requires admin access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let files = p4.unzip("release.zip").archives(true).run().unwrap();
+/// for file in files {
+///     println!("{:?}", file);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnzipCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    input: &'f str,
+
+    force: bool,
+    preview: bool,
+    archives: bool,
+    exclude_integrations: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> UnzipCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, input: &'f str) -> Self {
+        Self {
+            connection,
+            input,
+            force: false,
+            preview: false,
+            archives: false,
+            exclude_integrations: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// The -f flag bypasses checks that the imported changes don't
+    /// conflict with existing ones.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// The -n flag displays what would be imported without importing it.
+    pub fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// The -A flag imports the archive content of the files.
+    pub fn archives(mut self, archives: bool) -> Self {
+        self.archives = archives;
+        self
+    }
+
+    /// The -I flag excludes integration records for the files.
+    pub fn exclude_integrations(mut self, exclude_integrations: bool) -> Self {
+        self.exclude_integrations = exclude_integrations;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&["unzip", "-i", self.input]);
+        if self.force {
+            cmd.arg("-f");
+        }
+        if self.preview {
+            cmd.arg("-n");
+        }
+        if self.archives {
+            cmd.arg("-A");
+        }
+        if self.exclude_integrations {
+            cmd.arg("-I");
+        }
+        cmd
+    }
+
+    /// Run the `unzip` command.
+    pub fn run(self) -> Result<zip::Revisions, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(zip::Revisions(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            zip::zip_parser::item,
+        )?))
+    }
+
+    /// Run the `unzip` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<zip::Revision>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unzip_preview() {
+        let output: &[u8] = br#"info1: depotFile //depot/rel/a.txt
+info1: rev 2
+info1: change 42
+info1: action edit
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, zip::zip_parser::item);
+        assert_eq!(items[0].as_data().unwrap().depot_file, "//depot/rel/a.txt");
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
@@ -0,0 +1,516 @@
+//! Client and branch views, mapping paths on one side to paths on the other.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let view: p4_cmd::view::ViewMap = "
+//!     //depot/main/... //ws/main/...
+//!     -//depot/main/secret/... //ws/main/secret/...
+//!     \"//depot/main/read me.txt\" \"//ws/README.txt\"
+//! ".parse().unwrap();
+//! assert_eq!(view.translate("//depot/main/a/b.c"), Some("//ws/main/a/b.c".to_owned()));
+//! assert_eq!(view.translate("//depot/main/secret/key"), None);
+//! assert_eq!(view.translate_back("//ws/README.txt"), Some("//depot/main/read me.txt".to_owned()));
+//! ```
+
+use std::fmt;
+use std::iter;
+use std::str;
+
+/// How a mapping affects the lines before it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MappingKind {
+    /// Map the paths, replacing earlier mappings of them.
+    Include,
+    /// Unmap the paths, `-`.
+    Exclude,
+    /// Map the paths on top of earlier mappings, `+`.
+    Overlay,
+    /// Map the paths in addition to earlier mappings, `&`.
+    Ditto,
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+}
+
+impl MappingKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            MappingKind::Include => "",
+            MappingKind::Exclude => "-",
+            MappingKind::Overlay => "+",
+            MappingKind::Ditto => "&",
+            MappingKind::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+}
+
+/// One line of a view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Mapping {
+    pub kind: MappingKind,
+    /// The depot side.
+    pub left: String,
+    /// The client side, or the target of a branch.
+    pub right: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Mapping {
+    pub fn new(kind: MappingKind, left: String, right: String) -> Self {
+        Self {
+            kind,
+            left,
+            right,
+            non_exhaustive: (),
+        }
+    }
+
+    /// The side mapped from and the side mapped to.
+    fn sides(&self, from_left: bool) -> (&str, &str) {
+        if from_left {
+            (&self.left, &self.right)
+        } else {
+            (&self.right, &self.left)
+        }
+    }
+}
+
+/// Split off the next whitespace-separated word of `line`.
+pub(crate) fn next_word(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    let end = line.find(char::is_whitespace).unwrap_or(line.len());
+    Some(line.split_at(end))
+}
+
+/// Split off the next path of a view line, unquoting it.
+///
+/// Returns the path, with any `-`/`+`/`&` prefix, and the rest of the line.
+pub(crate) fn next_path(line: &str) -> Option<(String, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    let prefix_len = if line.starts_with(['-', '+', '&']) {
+        1
+    } else {
+        0
+    };
+    let (prefix, rest) = line.split_at(prefix_len);
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        let path = format!("{}{}", prefix, &quoted[..end]);
+        return Some((path, &quoted[end + 1..]));
+    }
+    let end = line.find(char::is_whitespace).unwrap_or(line.len());
+    Some((line[..end].to_owned(), &line[end..]))
+}
+
+/// The quote for `path` in a view line, for paths with spaces.
+pub(crate) fn quote(path: &str) -> &'static str {
+    if path.contains(char::is_whitespace) {
+        "\""
+    } else {
+        ""
+    }
+}
+
+impl str::FromStr for Mapping {
+    type Err = fmt::Error;
+
+    /// Parse a view line, e.g. `-//depot/a/... //ws/a/...`.  Paths with spaces are quoted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (left, rest) = next_path(s).ok_or(fmt::Error)?;
+        let (right, rest) = next_path(rest).ok_or(fmt::Error)?;
+        if !rest.trim().is_empty() {
+            return Err(fmt::Error);
+        }
+        let kind = match left.chars().next() {
+            Some('-') => MappingKind::Exclude,
+            Some('+') => MappingKind::Overlay,
+            Some('&') => MappingKind::Ditto,
+            _ => MappingKind::Include,
+        };
+        let left = left[kind.prefix().len()..].to_owned();
+        if left.is_empty() || right.is_empty() {
+            return Err(fmt::Error);
+        }
+        Ok(Mapping::new(kind, left, right))
+    }
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{q}{}{}{q} ",
+            self.kind.prefix(),
+            self.left,
+            q = quote(&self.left)
+        )?;
+        write!(f, "{q}{}{q}", self.right, q = quote(&self.right))
+    }
+}
+
+/// The lines of a client or branch view.
+///
+/// Later lines take precedence over earlier ones, on both sides: a later line mapping onto a path
+/// unmaps it from earlier lines, unless it is an overlay or ditto.  Paths are compared
+/// case-sensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ViewMap {
+    mappings: Vec<Mapping>,
+}
+
+impl ViewMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a line, taking precedence over the lines before it.
+    pub fn push(&mut self, mapping: Mapping) {
+        self.mappings.push(mapping);
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// Map a path on the left, e.g. a depot path, to the right, e.g. a client path.
+    ///
+    /// Returns `None` if `path` isn't mapped.
+    pub fn translate(&self, path: &str) -> Option<String> {
+        self.map(path, true)
+    }
+
+    /// Map a path on the right, e.g. a client path, to the left, e.g. a depot path.
+    ///
+    /// Returns `None` if `path` isn't mapped.
+    pub fn translate_back(&self, path: &str) -> Option<String> {
+        self.map(path, false)
+    }
+
+    fn map(&self, path: &str, from_left: bool) -> Option<String> {
+        for (i, mapping) in self.mappings.iter().enumerate().rev() {
+            let (from, to) = mapping.sides(from_left);
+            let mut captures = Vec::new();
+            if !matches(&tokenize(from), path, &mut captures) {
+                continue;
+            }
+            if mapping.kind == MappingKind::Exclude {
+                return None;
+            }
+            let mapped = substitute(&tokenize(to), &captures)?;
+            // A later line mapping onto the same path takes it over, unless it is `+` or `&`.
+            let taken = self.mappings[i + 1..].iter().any(|later| {
+                let takes_over = matches!(later.kind, MappingKind::Include | MappingKind::Exclude);
+                let (_, later_to) = later.sides(from_left);
+                takes_over && matches(&tokenize(later_to), &mapped, &mut Vec::new())
+            });
+            if taken {
+                return None;
+            }
+            return Some(mapped);
+        }
+        None
+    }
+}
+
+impl str::FromStr for ViewMap {
+    type Err = fmt::Error;
+
+    /// Parse one mapping per line, skipping blank lines.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mappings = s
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { mappings })
+    }
+}
+
+impl fmt::Display for ViewMap {
+    /// One mapping per line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for mapping in &self.mappings {
+            writeln!(f, "{}", mapping)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Wildcard {
+    /// `...`, matching across directories.
+    Dots,
+    /// `*`, matching within a directory.
+    Star,
+    /// `%%n`, matching within a directory.
+    Positional(u8),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Literal(&'a str),
+    Wildcard(Wildcard),
+}
+
+fn tokenize(pattern: &str) -> Vec<Token<'_>> {
+    let bytes = pattern.as_bytes();
+    let mut tokens = Vec::new();
+    let mut literal = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let wildcard = if bytes[i..].starts_with(b"...") {
+            Some((Wildcard::Dots, 3))
+        } else if bytes[i] == b'*' {
+            Some((Wildcard::Star, 1))
+        } else if bytes[i..].starts_with(b"%%") && bytes.get(i + 2).is_some_and(u8::is_ascii_digit)
+        {
+            Some((Wildcard::Positional(bytes[i + 2] - b'0'), 3))
+        } else {
+            None
+        };
+        match wildcard {
+            Some((wildcard, len)) => {
+                if literal < i {
+                    tokens.push(Token::Literal(&pattern[literal..i]));
+                }
+                tokens.push(Token::Wildcard(wildcard));
+                i += len;
+                literal = i;
+            }
+            None => i += 1,
+        }
+    }
+    if literal < bytes.len() {
+        tokens.push(Token::Literal(&pattern[literal..]));
+    }
+    tokens
+}
+
+/// Match `path` against `tokens`, recording what each wildcard matched.
+fn matches<'p>(tokens: &[Token], path: &'p str, captures: &mut Vec<(Wildcard, &'p str)>) -> bool {
+    let mut failed = vec![false; (tokens.len() + 1) * (path.len() + 1)];
+    matches_from(tokens, 0, path, 0, captures, &mut failed)
+}
+
+/// Match `path[start..]` against `tokens[token..]`.
+///
+/// `failed` remembers which of these didn't match, so each is only tried once rather than
+/// backtracking exponentially over several wildcards.
+fn matches_from<'p>(
+    tokens: &[Token],
+    token: usize,
+    path: &'p str,
+    start: usize,
+    captures: &mut Vec<(Wildcard, &'p str)>,
+    failed: &mut [bool],
+) -> bool {
+    let state = token * (path.len() + 1) + start;
+    if failed[state] {
+        return false;
+    }
+    let rest = &path[start..];
+    let matched = match tokens.get(token) {
+        None => rest.is_empty(),
+        Some(&Token::Literal(literal)) => {
+            rest.starts_with(literal)
+                && matches_from(
+                    tokens,
+                    token + 1,
+                    path,
+                    start + literal.len(),
+                    captures,
+                    failed,
+                )
+        }
+        Some(&Token::Wildcard(wildcard)) => {
+            let limit = if wildcard == Wildcard::Dots {
+                rest.len()
+            } else {
+                rest.find('/').unwrap_or(rest.len())
+            };
+            let ends = rest[..limit]
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(iter::once(limit));
+            let mut matched = false;
+            for end in ends.rev() {
+                captures.push((wildcard, &rest[..end]));
+                if matches_from(tokens, token + 1, path, start + end, captures, failed) {
+                    matched = true;
+                    break;
+                }
+                captures.pop();
+            }
+            matched
+        }
+    };
+    if !matched {
+        failed[state] = true;
+    }
+    matched
+}
+
+/// Fill in `tokens`' wildcards with what the corresponding wildcards matched.
+///
+/// `...` and `*` correspond in order, `%%n` by number.
+fn substitute(tokens: &[Token], captures: &[(Wildcard, &str)]) -> Option<String> {
+    let mut path = String::new();
+    let mut dots = 0;
+    let mut stars = 0;
+    for token in tokens {
+        match *token {
+            Token::Literal(literal) => path.push_str(literal),
+            Token::Wildcard(wildcard) => {
+                let nth = match wildcard {
+                    Wildcard::Dots => {
+                        dots += 1;
+                        dots
+                    }
+                    Wildcard::Star => {
+                        stars += 1;
+                        stars
+                    }
+                    Wildcard::Positional(_) => 1,
+                };
+                let (_, matched) = captures
+                    .iter()
+                    .filter(|&&(captured, _)| captured == wildcard)
+                    .nth(nth - 1)?;
+                path.push_str(matched);
+            }
+        }
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VIEW: &str = r#"//depot/main/... //ws/main/...
+-//depot/main/secret/... //ws/main/secret/...
++//depot/overlay/... //ws/main/...
+"//depot/with space/..." "//ws/with space/..."
+"-//depot/with space/tmp/..." "//ws/with space/tmp/..."
+//depot/src/%%1/%%2.c //ws/by-name/%%2/%%1.c
+//depot/*.txt //ws/docs/*.txt
+"#;
+
+    #[test]
+    fn view_round_trip() {
+        let view: ViewMap = VIEW.parse().unwrap();
+        assert_eq!(view.mappings().len(), 7);
+        assert_eq!(view.mappings()[1].kind, MappingKind::Exclude);
+        assert_eq!(view.mappings()[2].kind, MappingKind::Overlay);
+        assert_eq!(view.mappings()[3].left, "//depot/with space/...");
+        assert_eq!(view.mappings()[4].kind, MappingKind::Exclude);
+        assert_eq!(view.to_string(), VIEW);
+        assert!("//depot/a/...".parse::<ViewMap>().is_err());
+    }
+
+    #[test]
+    fn view_translate() {
+        let view: ViewMap = VIEW.parse().unwrap();
+        assert_eq!(
+            view.translate("//depot/main/a/b.c"),
+            Some("//ws/main/a/b.c".to_owned())
+        );
+        assert_eq!(view.translate("//depot/main/secret/key"), None);
+        assert_eq!(view.translate("//depot/other/a"), None);
+        assert_eq!(
+            view.translate("//depot/main/café"),
+            Some("//ws/main/café".to_owned())
+        );
+        assert_eq!(
+            view.translate("//depot/with space/a"),
+            Some("//ws/with space/a".to_owned())
+        );
+        assert_eq!(view.translate("//depot/with space/tmp/a"), None);
+        assert_eq!(
+            view.translate("//depot/src/lib/util.c"),
+            Some("//ws/by-name/util/lib.c".to_owned())
+        );
+        assert_eq!(view.translate("//depot/src/lib/sub/util.c"), None);
+        assert_eq!(
+            view.translate("//depot/notes.txt"),
+            Some("//ws/docs/notes.txt".to_owned())
+        );
+    }
+
+    #[test]
+    fn view_translate_back() {
+        let view: ViewMap = VIEW.parse().unwrap();
+        // The overlay is later, so takes precedence.
+        assert_eq!(
+            view.translate_back("//ws/main/a/b.c"),
+            Some("//depot/overlay/a/b.c".to_owned())
+        );
+        assert_eq!(
+            view.translate_back("//ws/by-name/util/lib.c"),
+            Some("//depot/src/lib/util.c".to_owned())
+        );
+        assert_eq!(
+            view.translate_back("//ws/main/secret/key"),
+            Some("//depot/overlay/secret/key".to_owned())
+        );
+        assert_eq!(view.translate_back("//ws/with space/tmp/a"), None);
+    }
+
+    #[test]
+    fn view_later_line_takes_over_target() {
+        let view: ViewMap = "//depot/a/... //ws/x/...\n//depot/b/... //ws/x/...\n"
+            .parse()
+            .unwrap();
+        assert_eq!(view.translate("//depot/a/f"), None);
+        assert_eq!(view.translate("//depot/b/f"), Some("//ws/x/f".to_owned()));
+        assert_eq!(
+            view.translate_back("//ws/x/f"),
+            Some("//depot/b/f".to_owned())
+        );
+
+        let view: ViewMap = "//ws/x/... //depot/a/...\n//ws/y/... //depot/a/...\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            view.translate_back("//depot/a/f"),
+            Some("//ws/y/f".to_owned())
+        );
+        assert_eq!(view.translate("//ws/x/f"), None);
+
+        // Overlays map on top of earlier lines rather than replacing them.
+        let view: ViewMap = "//depot/a/... //ws/x/...\n+//depot/b/... //ws/x/...\n"
+            .parse()
+            .unwrap();
+        assert_eq!(view.translate("//depot/a/f"), Some("//ws/x/f".to_owned()));
+        assert_eq!(
+            view.translate_back("//ws/x/f"),
+            Some("//depot/b/f".to_owned())
+        );
+    }
+
+    #[test]
+    fn view_many_wildcards() {
+        let view: ViewMap = "//depot/.../.../.../.../*.c //ws/.../.../.../.../*.c\n"
+            .parse()
+            .unwrap();
+        let deep = format!("//depot/{}file.h", "d/".repeat(200));
+        assert_eq!(view.translate(&deep), None);
+        let deep = format!("//depot/{}file.c", "d/".repeat(200));
+        assert_eq!(
+            view.translate(&deep),
+            Some(format!("//ws/{}file.c", "d/".repeat(200)))
+        );
+    }
+}
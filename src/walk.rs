@@ -0,0 +1,349 @@
+//! Walk a depot subtree, like `walkdir` walks a local one.
+//!
+//! The tree is listed a level at a time, with a `dirs` and a `files` command per level, so even
+//! large trees take few commands.  Within a level, each directory's subdirectories are yielded
+//! followed by its files.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let p4 = p4_cmd::P4::new();
+//! for entry in p4_cmd::walk::DepotWalk::new(&p4, "//depot/dir").max_depth(2).run() {
+//!     let entry = entry.unwrap();
+//!     match entry.kind {
+//!         p4_cmd::walk::EntryKind::Directory(ref dir) => println!("{}/", dir.dir),
+//!         p4_cmd::walk::EntryKind::File(ref file) => println!("{}#{}", file.depot_file, file.rev),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use std::collections;
+use std::mem;
+
+use bulk;
+use dirs;
+use error;
+use exec;
+use files;
+use p4;
+use paths;
+
+/// Walk the directories and files under a depot path.
+#[derive(Debug, Clone)]
+pub struct DepotWalk<'p> {
+    connection: &'p p4::P4,
+    root: String,
+
+    max_depth: Option<usize>,
+    workers: usize,
+    include_deleted: bool,
+    files: bool,
+}
+
+impl<'p> DepotWalk<'p> {
+    pub fn new(connection: &'p p4::P4, root: &str) -> Self {
+        let root = root.trim_end_matches("...").trim_end_matches('/');
+        Self {
+            connection,
+            // The depot root `//` lists as `//*`, like `//depot` does as `//depot/*`.
+            root: if root.is_empty() { "/" } else { root }.to_owned(),
+            max_depth: None,
+            workers: 1,
+            include_deleted: false,
+            files: true,
+        }
+    }
+
+    /// Walk no more than `max_depth` levels below the root, `1` being the root's contents.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// List each level with up to `workers` concurrent `dirs` and `files` commands.
+    pub fn parallel(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Include deleted, purged and archived files, and directories containing only those.
+    pub fn include_deleted(mut self, include_deleted: bool) -> Self {
+        self.include_deleted = include_deleted;
+        self
+    }
+
+    /// Only list directories, for `dirs::DirsWalk`.
+    pub(crate) fn dirs_only(mut self) -> Self {
+        self.files = false;
+        self
+    }
+
+    /// Start walking, a level being listed once the one above has been yielded.
+    pub fn run(self) -> DepotWalkIter<'p> {
+        let level = vec![self.root.clone()];
+        DepotWalkIter {
+            walk: self,
+            level,
+            depth: 0,
+            found: collections::VecDeque::new(),
+        }
+    }
+
+    /// Walk the whole tree, collecting every entry.
+    ///
+    /// Fails on the first level whose `dirs` or `files` reports an error.
+    pub fn run_checked(self) -> Result<Vec<Entry>, error::P4Error> {
+        self.run().collect()
+    }
+
+    /// List the contents of `parents`, grouped by parent.
+    fn list(&self, parents: &[String], depth: usize) -> Result<Vec<Entry>, error::P4Error> {
+        let patterns: Vec<_> = parents.iter().map(|p| format!("{}/*", p)).collect();
+        let include_deleted = self.include_deleted;
+        let found_dirs = bulk::chunked(
+            self.connection,
+            patterns.clone(),
+            self.workers,
+            move |p4, patterns| dirs::list(p4, patterns, include_deleted),
+        )?;
+        let found_files = if self.files {
+            bulk::chunked(
+                self.connection,
+                patterns,
+                self.workers,
+                move |p4, patterns| list_files(p4, patterns, include_deleted),
+            )?
+        } else {
+            Vec::new()
+        };
+
+        let mut children: collections::HashMap<String, Vec<EntryKind>> =
+            collections::HashMap::new();
+        let dirs = found_dirs
+            .into_iter()
+            .map(|d| (parent(&d.dir), EntryKind::Directory(d)));
+        let files = found_files
+            .into_iter()
+            .map(|f| (parent(&f.depot_file), EntryKind::File(f)));
+        for (parent, kind) in dirs.chain(files) {
+            children.entry(parent).or_default().push(kind);
+        }
+        let entries = parents
+            .iter()
+            .filter_map(|p| children.remove(p))
+            .flatten()
+            .map(|kind| Entry {
+                depth,
+                kind,
+                non_exhaustive: (),
+            })
+            .collect();
+        Ok(entries)
+    }
+}
+
+/// The directory `path` is in.
+fn parent(path: &paths::DepotPath) -> String {
+    let path = path.as_str();
+    path.rfind('/').map_or(path, |i| &path[..i]).to_owned()
+}
+
+/// Run `files` for `patterns`, a directory without files not being a failure.
+fn list_files(
+    connection: &p4::P4,
+    patterns: &[String],
+    include_deleted: bool,
+) -> Result<Vec<files::File>, error::P4Error> {
+    let mut cmd =
+        files::FilesCommand::new(connection, &patterns[0]).syncable_only(!include_deleted);
+    for pattern in &patterns[1..] {
+        cmd = cmd.file(pattern);
+    }
+    exec::collect_found(cmd.run()?)
+}
+
+/// A directory or file found by a [`DepotWalk`](struct.DepotWalk.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Entry {
+    /// How many levels below the root, `1` being the root's contents.
+    pub depth: usize,
+    pub kind: EntryKind,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+impl Entry {
+    pub fn path(&self) -> &paths::DepotPath {
+        match self.kind {
+            EntryKind::Directory(ref dir) => &dir.dir,
+            EntryKind::File(ref file) => &file.depot_file,
+            EntryKind::__Nonexhaustive => unreachable!("This is a private variant"),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, EntryKind::Directory(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EntryKind {
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    __Nonexhaustive,
+
+    Directory(dirs::Dir),
+    /// The file's head revision.
+    File(files::File),
+}
+
+/// Entries from a [`DepotWalk`](struct.DepotWalk.html), level by level.
+#[derive(Debug)]
+pub struct DepotWalkIter<'p> {
+    walk: DepotWalk<'p>,
+    /// Directories whose contents are the next level.
+    level: Vec<String>,
+    depth: usize,
+    found: collections::VecDeque<Entry>,
+}
+
+impl<'p> Iterator for DepotWalkIter<'p> {
+    type Item = Result<Entry, error::P4Error>;
+
+    fn next(&mut self) -> Option<Result<Entry, error::P4Error>> {
+        while self.found.is_empty() {
+            let deeper = self.walk.max_depth.is_none_or(|max| self.depth < max);
+            if self.level.is_empty() || !deeper {
+                return None;
+            }
+            self.depth += 1;
+            let parents = mem::take(&mut self.level);
+            match self.walk.list(&parents, self.depth) {
+                Ok(entries) => self.found.extend(entries),
+                Err(e) => return Some(Err(e)),
+            }
+            self.level = self
+                .found
+                .iter()
+                .filter(|e| e.is_dir())
+                .map(|e| e.path().to_string())
+                .collect();
+        }
+        self.found.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    use std::io;
+    use std::process;
+    use std::sync;
+
+    use runner;
+
+    /// Answers `dirs` and `files` from a list of paths, directories ending with `/`.
+    #[derive(Debug)]
+    pub(crate) struct Tree(pub(crate) &'static [&'static str]);
+
+    impl runner::P4Runner for Tree {
+        fn run(
+            &self,
+            cmd: process::Command,
+            _options: runner::RunOptions,
+        ) -> io::Result<Box<dyn io::Read + Send>> {
+            let args: Vec<_> = cmd
+                .get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect();
+            let command = args
+                .iter()
+                .position(|a| a == "dirs" || a == "files")
+                .unwrap();
+            let dirs = args[command] == "dirs";
+            let mut output = String::new();
+            let mut unmatched = false;
+            for pattern in args[command + 1..].iter().filter(|a| !a.starts_with('-')) {
+                let parent = pattern.trim_end_matches("/*");
+                let children: Vec<_> = self
+                    .0
+                    .iter()
+                    .filter(|p| p.ends_with('/') == dirs)
+                    .map(|p| p.trim_end_matches('/'))
+                    .filter(|p| p.rsplit_once('/').unwrap().0 == parent)
+                    .collect();
+                if children.is_empty() {
+                    unmatched = true;
+                    output.push_str(&format!("error: {} - no such file(s).\n", pattern));
+                }
+                for child in children {
+                    if dirs {
+                        output.push_str(&format!("info1: dir {}\n", child));
+                    } else {
+                        output.push_str(&format!(
+                            "info1: depotFile {}\ninfo1: rev 1\ninfo1: change 1\n\
+                             info1: action add\ninfo1: type text\ninfo1: time 1527128318\n",
+                            child
+                        ));
+                    }
+                }
+            }
+            output.push_str(if unmatched { "exit: 1\n" } else { "exit: 0\n" });
+            Ok(Box::new(io::Cursor::new(output.into_bytes())))
+        }
+    }
+
+    const TREE: &[&str] = &[
+        "//depot/a/",
+        "//depot/b/",
+        "//depot/top.txt",
+        "//depot/a/x/",
+        "//depot/a/a.txt",
+        "//depot/b/b.txt",
+        "//depot/a/x/x.txt",
+    ];
+
+    fn walk(walk: DepotWalk) -> Vec<(usize, String)> {
+        walk.run_checked()
+            .unwrap()
+            .into_iter()
+            .map(|e| {
+                let slash = if e.is_dir() { "/" } else { "" };
+                (e.depth, format!("{}{}", e.path(), slash))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn walk_levels() {
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(Tree(TREE))));
+        let expected: Vec<_> = [1, 1, 1, 2, 2, 2, 3]
+            .iter()
+            .zip(TREE)
+            .map(|(&depth, &path)| (depth, path.to_owned()))
+            .collect();
+        assert_eq!(walk(DepotWalk::new(&p4, "//depot/...")), expected);
+        assert_eq!(walk(DepotWalk::new(&p4, "//depot").parallel(3)), expected);
+        assert_eq!(
+            walk(DepotWalk::new(&p4, "//depot").max_depth(1)),
+            &expected[..3]
+        );
+    }
+
+    #[test]
+    fn walk_depot_root() {
+        const ROOT: &[&str] = &["//depot/", "//spec/", "//depot/a.txt"];
+        let p4 = p4::P4::new().set_runner(Some(sync::Arc::new(Tree(ROOT))));
+        let expected = [
+            (1, "//depot/".to_owned()),
+            (1, "//spec/".to_owned()),
+            (2, "//depot/a.txt".to_owned()),
+        ];
+        assert_eq!(walk(DepotWalk::new(&p4, "//...")), expected);
+        assert_eq!(walk(DepotWalk::new(&p4, "//")), expected);
+    }
+}
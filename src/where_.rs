@@ -1,8 +1,9 @@
-use std::path;
-use std::vec;
+use std::process;
 
 use error;
+use exec;
 use p4;
+use paths;
 
 /// Show how file names are mapped by the client view
 ///
@@ -17,6 +18,10 @@ use p4;
 /// Note that 'p4 where' does not determine where any real files reside.
 /// It only displays the locations that are mapped by the client view.
 ///
+/// A file matched by several lines of the view gets a `File` per line, in
+/// view order, including lines that exclude it.  The last one wins: the file
+/// is only in the client if the last isn't [`unmapped`](struct.File.html#structfield.unmapped).
+///
 /// # Examples
 ///
 /// ```rust,no_run
@@ -30,6 +35,7 @@ use p4;
 pub struct WhereCommand<'p, 'f> {
     connection: &'p p4::P4,
     file: Vec<&'f str>,
+    settings: exec::Settings,
 }
 
 impl<'p, 'f> WhereCommand<'p, 'f> {
@@ -37,6 +43,7 @@ impl<'p, 'f> WhereCommand<'p, 'f> {
         Self {
             connection,
             file: vec![],
+            settings: exec::Settings::default(),
         }
     }
 
@@ -46,44 +53,61 @@ impl<'p, 'f> WhereCommand<'p, 'f> {
         self
     }
 
-    /// Run the `where` command.
-    pub fn run(self) -> Result<Files, error::P4Error> {
-        let mut cmd = self.connection.connect_with_retries(None);
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
         cmd.arg("where");
-        for file in self.file {
+        for file in &self.file {
             cmd.arg(file);
         }
-        let data = cmd.output().map_err(|e| {
-            error::ErrorKind::SpawnFailed
-                .error()
-                .set_cause(e)
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        let (_remains, (mut items, exit)) = where_parser::where_(&data.stdout).map_err(|_| {
-            error::ErrorKind::ParseFailed
-                .error()
-                .set_context(format!("Command: {:?}", cmd))
-        })?;
-        items.push(exit);
-        Ok(Files(items))
+        cmd
+    }
+
+    /// Run the `where` command.
+    pub fn run(self) -> Result<Files, error::P4Error> {
+        let cmd = self.to_command();
+        let files = self.file.len();
+        Ok(Files(exec::spawn_files(
+            self.connection,
+            self.settings,
+            cmd,
+            files,
+            where_parser::item,
+        )?))
+    }
+
+    /// Run the `where` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<File>, error::P4Error> {
+        exec::collect(self.run()?)
     }
 }
 
+/// The mapping that wins for a single file, `None` if the client view doesn't include it.
+pub(crate) fn mapping(connection: &p4::P4, file: &str) -> Result<Option<File>, error::P4Error> {
+    let files = WhereCommand::new(connection).file(file).run()?;
+    let last = exec::collect_unmatched(files, " - file(s) not in client view.")?.pop();
+    Ok(last.filter(|f| !f.unmapped))
+}
+
 pub type FileItem = error::Item<File>;
 
-pub struct Files(Vec<FileItem>);
+pub struct Files(exec::Items<File>);
 
 impl IntoIterator for Files {
     type Item = FileItem;
     type IntoIter = FilesIntoIter;
 
     fn into_iter(self) -> FilesIntoIter {
-        FilesIntoIter(self.0.into_iter())
+        FilesIntoIter(self.0)
     }
 }
 
 #[derive(Debug)]
-pub struct FilesIntoIter(vec::IntoIter<FileItem>);
+pub struct FilesIntoIter(exec::Items<File>);
 
 impl Iterator for FilesIntoIter {
     type Item = FileItem;
@@ -105,10 +129,15 @@ impl Iterator for FilesIntoIter {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct File {
-    pub depot_file: String,
-    pub client_file: String,
-    pub path: path::PathBuf,
+    pub depot_file: paths::DepotPath,
+    pub client_file: paths::ClientPath,
+    pub path: paths::LocalPath,
+    /// The mapping is an exclusion, a `-//depot/...` view line, so the file isn't in the client
+    /// unless a later mapping includes it again.
+    pub unmapped: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     non_exhaustive: (),
 }
 
@@ -117,34 +146,173 @@ mod where_parser {
 
     use super::super::parser::*;
 
+    // Tagged output marks exclusions with a valueless `unmap` field.
+    named!(unmap<&[u8], ()>,
+        value!((), tuple!(field_tag, tag!(b"unmap"), opt!(tag!(b" ")), newline))
+    );
+
+    /// Split the `-` untagged output prefixes exclusions with from a path.
+    fn exclusion(path: &[u8]) -> (bool, &[u8]) {
+        match path.split_first() {
+            Some((b'-', rest)) => (true, rest),
+            _ => (false, path),
+        }
+    }
+
     named!(file<&[u8], File>,
         do_parse!(
+            unmap: opt!(unmap) >>
             depot_file: depot_file >>
             client_file: client_file >>
             path: path >>
-            (
+            ({
+                let (excluded, depot_file) = exclusion(depot_file.path);
+                let (_, client_file) = exclusion(client_file.path);
                 File {
-                    depot_file: depot_file.path.to_owned(),
-                    client_file: client_file.path.to_owned(),
-                    path: path::PathBuf::from(path.path),
+                    depot_file: paths::DepotPath::from_bytes(depot_file),
+                    client_file: paths::ClientPath::from_bytes(client_file),
+                    path: paths::LocalPath::from_bytes(path.path),
+                    unmapped: unmap.is_some() || excluded,
                     non_exhaustive: (),
                 }
-            )
+            })
         )
     );
 
-    named!(item<&[u8], FileItem>,
+    named!(pub item<&[u8], FileItem>,
         alt!(
             map!(file, data_to_item) |
             map!(error, error_to_item) |
             map!(info, info_to_item)
         )
     );
+}
 
-    named!(pub where_<&[u8], (Vec<FileItem>, FileItem)>,
-        pair!(
-            many0!(item),
-            map!(exit, exit_to_item)
-        )
-    );
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path;
+
+    #[test]
+    fn where_non_utf8_paths() {
+        let output: &[u8] = b"info1: depotFile //depot/caf\xe9
+info1: clientFile //ws/caf\xe9
+info1: path /home/user/ws/caf\xe9
+exit: 0
+";
+        let (items, _) = exec::parse(output, where_parser::item);
+        let file = items[0].as_data().unwrap();
+        assert_eq!(file.depot_file, "//depot/caf\u{fffd}");
+        assert_eq!(file.depot_file.as_bytes(), b"//depot/caf\xe9");
+        assert_eq!(file.client_file.as_bytes(), b"//ws/caf\xe9");
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            assert_eq!(
+                file.path.as_path().as_os_str().as_bytes(),
+                b"/home/user/ws/caf\xe9"
+            );
+        }
+    }
+
+    #[test]
+    fn where_utf8_paths() {
+        let output: &[u8] = b"info1: depotFile //depot/a
+info1: clientFile //ws/a
+info1: path /home/user/ws/a
+exit: 0
+";
+        let (items, _) = exec::parse(output, where_parser::item);
+        let file = items[0].as_data().unwrap();
+        assert_eq!(file.depot_file.as_bytes(), b"//depot/a");
+        assert_eq!(file.client_file, "//ws/a");
+        assert!(!file.unmapped);
+    }
+
+    #[test]
+    fn where_unmapped() {
+        let output: &[u8] = b"info1: depotFile //depot/a
+info1: clientFile //ws/a
+info1: path /home/user/ws/a
+info1: unmap
+info1: depotFile //depot/a
+info1: clientFile //ws/a
+info1: path /home/user/ws/a
+info1: depotFile -//depot/b
+info1: clientFile -//ws/b
+info1: path /home/user/ws/b
+exit: 0
+";
+        let (items, _) = exec::parse(output, where_parser::item);
+        let files: Vec<_> = items.iter().map(|i| i.as_data().unwrap()).collect();
+        assert_eq!(files.len(), 3);
+        assert!(!files[0].unmapped);
+        assert!(files[1].unmapped);
+        assert_eq!(files[1].depot_file, "//depot/a");
+        assert!(files[2].unmapped);
+        assert_eq!(files[2].depot_file, "//depot/b");
+        assert_eq!(files[2].client_file, "//ws/b");
+    }
+
+    #[test]
+    fn where_translate() {
+        let output: &[u8] = b"info1: depotFile //depot/a%40b
+info1: clientFile //ws/a%40b
+info1: path /home/user/ws/a@b
+exit: 0
+";
+        let (runner, p4) = exec::recorder(output);
+        assert_eq!(
+            p4.depot_to_local("//depot/a@b").unwrap(),
+            Some(path::PathBuf::from("/home/user/ws/a@b"))
+        );
+        assert_eq!(
+            p4.local_to_depot("/home/user/ws/a@b").unwrap().unwrap(),
+            "//depot/a%40b"
+        );
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(calls[0].0.last().unwrap(), "//depot/a%40b");
+        assert_eq!(calls[1].0.last().unwrap(), "/home/user/ws/a%40b");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn where_translate_non_utf8() {
+        use std::ffi;
+        use std::os::unix::ffi::OsStrExt;
+
+        let (runner, p4) = exec::recorder(b"exit: 0\n");
+        let path = path::Path::new(ffi::OsStr::from_bytes(b"/home/user/ws/caf\xe9"));
+        let err = p4.local_to_depot(path).unwrap_err();
+        assert_eq!(err.kind(), error::ErrorKind::InvalidArgument);
+        assert!(runner.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn where_translate_unmapped() {
+        let output: &[u8] = b"info1: depotFile //depot/a
+info1: clientFile //ws/a
+info1: path /home/user/ws/a
+info1: unmap
+info1: depotFile //depot/a
+info1: clientFile //ws/a
+info1: path /home/user/ws/a
+exit: 0
+";
+        let (_, p4) = exec::recorder(output);
+        assert_eq!(p4.depot_to_local("//depot/a").unwrap(), None);
+
+        let output: &[u8] = b"error: //depot/b - file(s) not in client view.
+exit: 1
+";
+        let (_, p4) = exec::recorder(output);
+        assert_eq!(p4.depot_to_local("//depot/b").unwrap(), None);
+
+        let output: &[u8] = b"error: Client 'ws' unknown - use 'client' command to create it.
+exit: 1
+";
+        let (_, p4) = exec::recorder(output);
+        assert!(p4.depot_to_local("//depot/b").is_err());
+    }
 }
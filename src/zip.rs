@@ -0,0 +1,250 @@
+use std::borrow;
+use std::fmt;
+use std::process;
+
+use error;
+use exec;
+use p4;
+use paths;
+
+/// Package a set of files and their history for use by p4 unzip
+///
+/// Writes the specified revisions, along with their changelists and
+/// (optionally) archive content, to a zip file that another server can
+/// import with 'p4 unzip', e.g. to move content across an air gap.
+///
+/// Requires admin access.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let p4 = p4_cmd::P4::new();
+/// let files = p4.zip("release.zip").file("//depot/rel/...").run().unwrap();
+/// for file in files {
+///     println!("{:?}", file);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZipCommand<'p, 'f> {
+    connection: &'p p4::P4,
+    output: &'f str,
+    file: Vec<borrow::Cow<'f, str>>,
+
+    change: Option<usize>,
+    remote: Option<&'f str>,
+    archives: bool,
+    exclude_integrations: bool,
+    settings: exec::Settings,
+}
+
+impl<'p, 'f> ZipCommand<'p, 'f> {
+    pub fn new(connection: &'p p4::P4, output: &'f str) -> Self {
+        Self {
+            connection,
+            output,
+            file: vec![],
+            change: None,
+            remote: None,
+            archives: false,
+            exclude_integrations: false,
+            settings: exec::Settings::default(),
+        }
+    }
+
+    /// Revisions to package.  Can be repeated.
+    pub fn file(mut self, file: &'f str) -> Self {
+        self.file.push(file.into());
+        self
+    }
+
+    /// `file` at `rev`, escaping any `@`, `#`, `%` or `*` in `file`.  Can be repeated.
+    pub fn file_at(mut self, file: &str, rev: p4::RevSpec) -> Self {
+        self.file.push(p4::file_at(file, &rev).into());
+        self
+    }
+
+    /// The -c flag packages the files submitted in the changelist, instead
+    /// of a file argument.
+    pub fn change(mut self, change: usize) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    /// The -r flag specifies a remote spec whose DepotMap is applied to
+    /// the files.
+    pub fn remote(mut self, remote: &'f str) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// The -A flag includes the archive content of the files.
+    pub fn archives(mut self, archives: bool) -> Self {
+        self.archives = archives;
+        self
+    }
+
+    /// The -I flag excludes integration records for the files.
+    pub fn exclude_integrations(mut self, exclude_integrations: bool) -> Self {
+        self.exclude_integrations = exclude_integrations;
+        self
+    }
+
+    settings_methods!();
+
+    /// The `p4` invocation `run` executes, e.g. for logging or testing how arguments are built.
+    pub fn to_command(&self) -> process::Command {
+        let mut cmd = self.connection.connect(&self.settings);
+        cmd.args(&["zip", "-o", self.output]);
+        if let Some(remote) = self.remote {
+            cmd.args(&["-r", remote]);
+        }
+        if self.archives {
+            cmd.arg("-A");
+        }
+        if self.exclude_integrations {
+            cmd.arg("-I");
+        }
+        if let Some(change) = self.change {
+            let change = format!("{}", change);
+            cmd.args(&["-c", &change]);
+        }
+        for file in &self.file {
+            cmd.arg(file.as_ref());
+        }
+        cmd
+    }
+
+    /// Run the `zip` command.
+    pub fn run(self) -> Result<Revisions, error::P4Error> {
+        let cmd = self.to_command();
+        Ok(Revisions(exec::spawn(
+            self.connection,
+            self.settings,
+            cmd,
+            zip_parser::item,
+        )?))
+    }
+
+    /// Run the `zip` command, collecting its output.
+    ///
+    /// Fails with the reported messages if the command reports an error.
+    pub fn run_checked(self) -> Result<Vec<Revision>, error::P4Error> {
+        exec::collect(self.run()?)
+    }
+}
+
+pub type RevisionItem = error::Item<Revision>;
+
+pub struct Revisions(pub(crate) exec::Items<Revision>);
+
+impl IntoIterator for Revisions {
+    type Item = RevisionItem;
+    type IntoIter = RevisionsIntoIter;
+
+    fn into_iter(self) -> RevisionsIntoIter {
+        RevisionsIntoIter(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct RevisionsIntoIter(exec::Items<Revision>);
+
+impl Iterator for RevisionsIntoIter {
+    type Item = RevisionItem;
+
+    #[inline]
+    fn next(&mut self) -> Option<RevisionItem> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.count()
+    }
+}
+
+/// A file revision packaged into, or imported from, a zip file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Revision {
+    pub depot_file: paths::DepotPath,
+    pub rev: Option<usize>,
+    pub change: Option<usize>,
+    pub action: Option<p4::Action>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    non_exhaustive: (),
+}
+
+pub(crate) mod zip_parser {
+    use super::*;
+
+    use super::super::parser::*;
+
+    fn to_revision(fields: Vec<Field>) -> Result<Revision, fmt::Error> {
+        let mut depot_file = None;
+        let mut rev = None;
+        let mut change = None;
+        let mut action = None;
+        for field in fields {
+            match field.key {
+                "depotFile" => {
+                    depot_file = Some(paths::DepotPath::from_bytes(field.value.as_bytes()))
+                }
+                "rev" => rev = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                "change" => change = Some(field.value.parse().map_err(|_| fmt::Error)?),
+                "action" => action = Some(field.value.parse()?),
+                _ => (),
+            }
+        }
+        let revision = Revision {
+            depot_file: depot_file.ok_or(fmt::Error)?,
+            rev,
+            change,
+            action,
+            non_exhaustive: (),
+        };
+        Ok(revision)
+    }
+
+    named!(pub item<&[u8], RevisionItem>,
+        alt!(
+            map!(map_res!(record, to_revision), data_to_item) |
+            map!(error, error_to_item) |
+            map!(info, info_to_item)
+        )
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zip_files() {
+        let output: &[u8] = br#"info1: depotFile //depot/rel/a.txt
+info1: rev 2
+info1: change 42
+info1: action edit
+info1: depotFile //depot/rel/b.txt
+info1: rev 1
+info1: change 40
+info1: action add
+info: Zipped 2 files from 2 changes.
+exit: 0
+"#;
+        let (items, exit) = exec::parse(output, zip_parser::item);
+        let first = items[0].as_data().unwrap();
+        assert_eq!(first.depot_file, "//depot/rel/a.txt");
+        assert_eq!(first.rev, Some(2));
+        assert_eq!(first.change, Some(42));
+        assert_eq!(first.action, Some(p4::Action::Edit));
+        assert_eq!(items[1].as_data().unwrap().action, Some(p4::Action::Add));
+        assert!(items[2].as_message().is_some());
+        assert_eq!(exit.as_error(), Some(&error::OperationError::new(0)));
+    }
+}
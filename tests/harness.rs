@@ -0,0 +1,75 @@
+//! Run with a throwaway server, e.g. `P4D=/usr/local/bin/p4d cargo test --features it-harness`.
+#![cfg(feature = "it-harness")]
+
+extern crate p4_cmd;
+
+use p4_cmd::harness;
+
+fn server() -> Option<harness::Server> {
+    let server = harness::Server::from_env().unwrap();
+    if server.is_none() {
+        eprintln!("Skipping, neither P4D nor P4D_IMAGE is set");
+    }
+    server
+}
+
+#[test]
+fn files_lists_submitted() {
+    let server = match server() {
+        Some(server) => server,
+        None => return,
+    };
+    server.submit("dir/file", b"Hello World\n").unwrap();
+
+    let p4 = server.connection();
+    let files: Vec<_> = p4
+        .files("//depot/dir/*")
+        .run()
+        .unwrap()
+        .into_iter()
+        .filter_map(|i| i.as_data().cloned())
+        .collect();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].depot_file, "//depot/dir/file");
+    assert_eq!(files[0].rev, 1);
+}
+
+#[test]
+fn dirs_lists_submitted() {
+    let server = match server() {
+        Some(server) => server,
+        None => return,
+    };
+    server.submit("dir/file", b"Hello World\n").unwrap();
+
+    let p4 = server.connection();
+    let dirs: Vec<_> = p4
+        .dirs("//depot/*")
+        .run()
+        .unwrap()
+        .into_iter()
+        .filter_map(|i| i.as_data().cloned())
+        .collect();
+    assert_eq!(dirs.len(), 1);
+    assert_eq!(dirs[0].dir, "//depot/dir");
+}
+
+#[test]
+fn where_maps_to_client() {
+    let server = match server() {
+        Some(server) => server,
+        None => return,
+    };
+
+    let p4 = server.connection();
+    let files: Vec<_> = p4
+        .where_()
+        .file("//depot/dir/file")
+        .run()
+        .unwrap()
+        .into_iter()
+        .filter_map(|i| i.as_data().cloned())
+        .collect();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, server.client_root().join("dir").join("file"));
+}